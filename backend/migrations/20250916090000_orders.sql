@@ -0,0 +1,43 @@
+CREATE TABLE orders (
+    id TEXT PRIMARY KEY,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    status TEXT NOT NULL,
+    customer_name TEXT NOT NULL,
+    customer_email TEXT NOT NULL,
+    customer_phone TEXT NOT NULL,
+    shipping_line1 TEXT NOT NULL,
+    shipping_line2 TEXT,
+    shipping_city TEXT NOT NULL,
+    shipping_state TEXT NOT NULL,
+    shipping_postal_code TEXT NOT NULL,
+    shipping_country TEXT NOT NULL,
+    special_instructions TEXT,
+    shipping_option TEXT NOT NULL,
+    payment_method TEXT NOT NULL,
+    payment_reference TEXT,
+    items_subtotal NUMERIC(10,2) NOT NULL,
+    shipping NUMERIC(10,2) NOT NULL,
+    tax NUMERIC(10,2) NOT NULL,
+    grand_total NUMERIC(10,2) NOT NULL,
+    currency TEXT NOT NULL,
+    estimated_delivery_min DATE NOT NULL,
+    estimated_delivery_max DATE NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX orders_user_id_idx ON orders (user_id);
+CREATE INDEX orders_status_created_at_idx ON orders (status, created_at);
+
+CREATE TABLE order_items (
+    id BIGSERIAL PRIMARY KEY,
+    order_id TEXT NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    size TEXT NOT NULL,
+    finish TEXT NOT NULL,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    image_ids TEXT[] NOT NULL,
+    unit_price NUMERIC(10,2) NOT NULL,
+    line_total NUMERIC(10,2) NOT NULL
+);
+
+CREATE INDEX order_items_order_id_idx ON order_items (order_id);
@@ -0,0 +1,6 @@
+-- One counter row per day; order IDs are ord_YYYYMMDD_NNNN where NNNN is the
+-- counter value, incremented atomically with INSERT ... ON CONFLICT.
+CREATE TABLE order_number_sequences (
+    day DATE PRIMARY KEY,
+    last_value INTEGER NOT NULL
+);
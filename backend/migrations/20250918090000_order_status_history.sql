@@ -0,0 +1,19 @@
+-- "processing" was used for credit card orders before statuses were typed
+UPDATE orders SET status = 'pending_payment' WHERE status = 'processing';
+
+ALTER TABLE orders ADD CONSTRAINT orders_status_check CHECK (status IN (
+    'pending_payment', 'paid', 'in_production', 'printed',
+    'shipped', 'delivered', 'cancelled', 'refunded'
+));
+
+CREATE TABLE order_status_history (
+    id BIGSERIAL PRIMARY KEY,
+    order_id TEXT NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    from_status TEXT,
+    to_status TEXT NOT NULL,
+    changed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    note TEXT,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX order_status_history_order_id_idx ON order_status_history (order_id);
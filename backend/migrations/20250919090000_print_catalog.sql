@@ -0,0 +1,34 @@
+CREATE TABLE print_sizes (
+    id TEXT PRIMARY KEY,
+    description TEXT NOT NULL,
+    price NUMERIC(10,2) NOT NULL CHECK (price >= 0),
+    currency TEXT NOT NULL DEFAULT 'USD',
+    width_in REAL NOT NULL CHECK (width_in > 0),
+    height_in REAL NOT NULL CHECK (height_in > 0),
+    weight_lbs REAL NOT NULL CHECK (weight_lbs > 0),
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    sort_order INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE print_finishes (
+    id TEXT PRIMARY KEY,
+    description TEXT NOT NULL,
+    premium NUMERIC(10,2) NOT NULL CHECK (premium >= 0),
+    currency TEXT NOT NULL DEFAULT 'USD',
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    sort_order INTEGER NOT NULL DEFAULT 0
+);
+
+-- Seed with the prices that used to be hard-coded in order pricing
+INSERT INTO print_sizes (id, description, price, width_in, height_in, weight_lbs, sort_order) VALUES
+    ('4x6', '4" x 6" print', 1.50, 4, 6, 0.02, 10),
+    ('5x7', '5" x 7" print', 2.00, 5, 7, 0.03, 20),
+    ('8x10', '8" x 10" print', 4.00, 8, 10, 0.06, 30),
+    ('11x14', '11" x 14" print', 8.00, 11, 14, 0.12, 40);
+
+INSERT INTO print_finishes (id, description, premium, sort_order) VALUES
+    ('glossy', 'Glossy photo paper', 0.00, 10),
+    ('matte', 'Matte photo paper', 0.25, 20),
+    ('metallic', 'Metallic photo paper', 0.50, 30);
@@ -0,0 +1,14 @@
+CREATE TABLE images (
+    id TEXT PRIMARY KEY,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    -- Order the image belongs to; NULL while it's staged for an order not yet placed
+    order_id TEXT REFERENCES orders(id) ON DELETE SET NULL,
+    storage_key TEXT NOT NULL UNIQUE,
+    original_filename TEXT,
+    content_type TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL CHECK (size_bytes > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX images_user_id_idx ON images (user_id);
+CREATE INDEX images_order_id_idx ON images (order_id);
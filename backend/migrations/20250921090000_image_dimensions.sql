@@ -0,0 +1,4 @@
+-- Pixel dimensions read at upload time; NULL for images uploaded before they were recorded
+ALTER TABLE images
+    ADD COLUMN width_px INTEGER CHECK (width_px > 0),
+    ADD COLUMN height_px INTEGER CHECK (height_px > 0);
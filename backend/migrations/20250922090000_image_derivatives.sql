@@ -0,0 +1,16 @@
+-- Thumbnails and print-size crop previews rendered from an uploaded image
+CREATE TABLE image_derivatives (
+    id BIGSERIAL PRIMARY KEY,
+    image_id TEXT NOT NULL REFERENCES images(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL CHECK (kind IN ('thumbnail', 'preview')),
+    -- Print size a preview is cropped for; NULL for thumbnails
+    size_id TEXT,
+    storage_key TEXT NOT NULL,
+    width_px INTEGER NOT NULL,
+    height_px INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK ((kind = 'preview') = (size_id IS NOT NULL))
+);
+
+CREATE UNIQUE INDEX image_derivatives_unique_idx
+    ON image_derivatives (image_id, kind, COALESCE(size_id, ''));
@@ -0,0 +1,21 @@
+-- Payments taken through a provider (PayPal, ...), one row per attempt
+CREATE TABLE payments (
+    id BIGSERIAL PRIMARY KEY,
+    order_id TEXT NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    -- Payment method of the provider, e.g. 'paypal'
+    provider TEXT NOT NULL,
+    -- The provider's payment ID (PayPal order ID)
+    intent_id TEXT NOT NULL,
+    -- The provider's capture ID, set once the money is taken
+    capture_id TEXT,
+    status TEXT NOT NULL CHECK (status IN (
+        'pending', 'approved', 'completed', 'cancelled', 'failed', 'refunded'
+    )),
+    amount NUMERIC(10,2) NOT NULL,
+    currency TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (provider, intent_id)
+);
+
+CREATE INDEX payments_order_id_idx ON payments (order_id, created_at);
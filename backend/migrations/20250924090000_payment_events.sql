@@ -0,0 +1,21 @@
+-- Payments can be disputed by the customer (chargebacks)
+ALTER TABLE payments DROP CONSTRAINT payments_status_check;
+ALTER TABLE payments ADD CONSTRAINT payments_status_check CHECK (status IN (
+    'pending', 'approved', 'completed', 'cancelled', 'failed', 'refunded', 'disputed'
+));
+
+-- Webhook events received from payment providers, kept verbatim for replay
+CREATE TABLE payment_events (
+    id BIGSERIAL PRIMARY KEY,
+    -- Payment method of the provider that sent it, e.g. 'paypal'
+    provider TEXT NOT NULL,
+    -- The provider's event ID; redeliveries reuse it
+    event_id TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    -- The request body exactly as received
+    payload TEXT NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    -- Set once the event has been applied to the payment and order
+    processed_at TIMESTAMPTZ,
+    UNIQUE (provider, event_id)
+);
@@ -0,0 +1,29 @@
+-- Money returned to the customer so far, kept up to date with every refund
+ALTER TABLE orders ADD COLUMN amount_refunded NUMERIC(10,2) NOT NULL DEFAULT 0;
+
+-- Refunds issued by admins through the payment provider
+CREATE TABLE refunds (
+    id BIGSERIAL PRIMARY KEY,
+    order_id TEXT NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    payment_id BIGINT NOT NULL REFERENCES payments(id) ON DELETE CASCADE,
+    -- The provider's ID for the refund
+    provider_refund_id TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('pending', 'completed', 'failed', 'cancelled')),
+    amount NUMERIC(10,2) NOT NULL CHECK (amount > 0),
+    currency TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    -- Admin who issued the refund
+    refunded_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX refunds_order_id_idx ON refunds (order_id, created_at);
+
+-- Line items a refund covers, when it was issued per item
+CREATE TABLE refund_items (
+    refund_id BIGINT NOT NULL REFERENCES refunds(id) ON DELETE CASCADE,
+    order_item_id BIGINT NOT NULL REFERENCES order_items(id) ON DELETE CASCADE,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    amount NUMERIC(10,2) NOT NULL,
+    PRIMARY KEY (refund_id, order_item_id)
+);
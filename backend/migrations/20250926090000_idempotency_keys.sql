@@ -0,0 +1,16 @@
+-- Idempotency-Key headers seen on order and payment requests, per user
+CREATE TABLE idempotency_keys (
+    user_id TEXT NOT NULL,
+    key TEXT NOT NULL,
+    -- SHA-256 of the method, path and body of the first request with the key
+    request_hash TEXT NOT NULL,
+    -- The first response, replayed on retries; NULL while it's being handled
+    response_status INTEGER,
+    response_body BYTEA,
+    -- SHA-256 of response_body
+    response_hash TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, key)
+);
+
+CREATE INDEX idempotency_keys_created_at_idx ON idempotency_keys (created_at);
@@ -0,0 +1,28 @@
+-- Tracking number of the order's current shipping label
+ALTER TABLE orders ADD COLUMN tracking_number TEXT;
+
+-- Labels bought from carriers; voided labels are kept for the record
+CREATE TABLE shipping_labels (
+    id BIGSERIAL PRIMARY KEY,
+    order_id TEXT NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    carrier TEXT NOT NULL,
+    service_code TEXT NOT NULL,
+    tracking_number TEXT NOT NULL,
+    format TEXT NOT NULL CHECK (format IN ('pdf', 'gif', 'zpl')),
+    -- Object storage key of the label file
+    storage_key TEXT NOT NULL,
+    -- What the carrier charged, when it said
+    price NUMERIC(10,2),
+    currency TEXT,
+    -- Admin who bought the label
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    voided_at TIMESTAMPTZ,
+    UNIQUE (carrier, tracking_number),
+    CHECK ((price IS NULL) = (currency IS NULL))
+);
+
+-- An order has at most one label that hasn't been voided
+CREATE UNIQUE INDEX shipping_labels_active_order_idx ON shipping_labels (order_id)
+    WHERE voided_at IS NULL;
+CREATE INDEX shipping_labels_order_id_idx ON shipping_labels (order_id, created_at);
@@ -0,0 +1,6 @@
+-- Business days needed to produce a print size before it can ship
+ALTER TABLE print_sizes
+    ADD COLUMN production_days INTEGER NOT NULL DEFAULT 1 CHECK (production_days >= 0);
+
+-- Larger prints take longer to print and dry
+UPDATE print_sizes SET production_days = 2 WHERE id IN ('8x10', '11x14');
@@ -0,0 +1,8 @@
+-- Harmonized System code prints of a size are declared under at customs;
+-- 4911.91 covers photographs printed on paper
+ALTER TABLE print_sizes
+    ADD COLUMN hs_code TEXT NOT NULL DEFAULT '491191' CHECK (hs_code ~ '^[0-9]{6,10}$');
+
+-- Order items keep the code they were sold under, like their price
+ALTER TABLE order_items
+    ADD COLUMN hs_code TEXT NOT NULL DEFAULT '491191';
@@ -0,0 +1,29 @@
+-- Print labs that produce orders and ship them
+CREATE TABLE fulfillment_locations (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    -- Contact printed on labels as the shipper
+    contact_name TEXT NOT NULL,
+    company TEXT,
+    phone TEXT,
+    line1 TEXT NOT NULL,
+    line2 TEXT,
+    city TEXT NOT NULL,
+    state TEXT NOT NULL,
+    postal_code TEXT NOT NULL,
+    country TEXT NOT NULL CHECK (country ~ '^[A-Z]{2}$'),
+    -- Destinations the lab is preferred for: countries (`US`) or states (`US-CA`)
+    regions TEXT[] NOT NULL DEFAULT '{}',
+    -- Print sizes the lab can produce, empty for every size
+    size_ids TEXT[] NOT NULL DEFAULT '{}',
+    -- Lower goes first when the routing rules leave a tie
+    priority INTEGER NOT NULL DEFAULT 0,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- Location that produces and ships the order; orders placed before locations
+-- existed ship from the first active one
+ALTER TABLE orders ADD COLUMN fulfillment_location_id TEXT REFERENCES fulfillment_locations(id);
+CREATE INDEX orders_fulfillment_location_id_idx ON orders (fulfillment_location_id);
@@ -0,0 +1,16 @@
+-- Labs customers can collect their prints from
+ALTER TABLE fulfillment_locations ADD COLUMN pickup BOOLEAN NOT NULL DEFAULT FALSE;
+-- Opening hours shown to customers, e.g. `Mon-Fri 9:00-18:00, Sat 10:00-14:00`
+ALTER TABLE fulfillment_locations ADD COLUMN pickup_hours TEXT;
+
+-- Orders are either shipped or collected from their fulfillment location
+ALTER TABLE orders ADD COLUMN fulfillment_method TEXT NOT NULL DEFAULT 'ship'
+    CHECK (fulfillment_method IN ('ship', 'pickup'));
+-- Code the customer shows staff to collect a pickup order
+ALTER TABLE orders ADD COLUMN pickup_code TEXT;
+
+ALTER TABLE orders DROP CONSTRAINT orders_status_check;
+ALTER TABLE orders ADD CONSTRAINT orders_status_check CHECK (status IN (
+    'pending_payment', 'paid', 'in_production', 'printed', 'ready_for_pickup',
+    'shipped', 'delivered', 'cancelled', 'refunded'
+));
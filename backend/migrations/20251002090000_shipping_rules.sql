@@ -0,0 +1,23 @@
+-- Adjustments applied to carrier quotes, in position order
+CREATE TABLE shipping_rules (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    -- Lower applies first; later rules see the price earlier ones set
+    position INTEGER NOT NULL DEFAULT 0,
+    kind TEXT NOT NULL CHECK (kind IN ('free_shipping', 'flat_rate', 'adjust', 'hide')),
+    -- Shipping options the rule applies to, empty for every option
+    shipping_options TEXT[] NOT NULL DEFAULT '{}',
+    -- Destinations the rule applies to: countries (`US`) or states (`US-CA`),
+    -- empty for everywhere
+    regions TEXT[] NOT NULL DEFAULT '{}',
+    -- Only applies when the prints cost at least this much
+    min_subtotal NUMERIC(12, 2),
+    -- Price of a flat rate, or a fixed amount added by an adjustment
+    amount NUMERIC(12, 2),
+    -- Percentage added by an adjustment, in basis points; negative for discounts
+    basis_points INTEGER,
+    currency TEXT NOT NULL DEFAULT 'USD',
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
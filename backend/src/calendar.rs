@@ -0,0 +1,171 @@
+//! Business days
+//!
+//! Production and carrier transit times are counted in business days: the
+//! working days of the week, less holidays. The same calendar is used for
+//! both, since carriers don't deliver on the holidays we close for.
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use std::{collections::BTreeSet, env};
+
+/// Working days of the week and holidays
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BusinessCalendar {
+    working_days: Vec<Weekday>,
+    holidays: BTreeSet<NaiveDate>,
+}
+
+impl Default for BusinessCalendar {
+    /// Monday to Friday, without holidays
+    fn default() -> Self {
+        BusinessCalendar {
+            working_days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            holidays: BTreeSet::new(),
+        }
+    }
+}
+
+impl BusinessCalendar {
+    /// Create a calendar, failing if no day of the week is a working day
+    pub fn new(
+        working_days: Vec<Weekday>,
+        holidays: impl IntoIterator<Item = NaiveDate>,
+    ) -> Result<Self, String> {
+        if working_days.is_empty() {
+            return Err("At least one working day is required".to_string());
+        }
+        Ok(BusinessCalendar {
+            working_days,
+            holidays: holidays.into_iter().collect(),
+        })
+    }
+
+    /// Create a BusinessCalendar from environment variables
+    ///
+    /// # Environment Variables
+    ///
+    /// - `BUSINESS_DAYS`: Comma-separated working days, e.g. `mon,tue,wed,thu,fri`
+    ///   (optional, defaults to Monday to Friday)
+    /// - `HOLIDAYS`: Comma-separated `YYYY-MM-DD` dates nothing ships or
+    ///   arrives on (optional)
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = BusinessCalendar::default();
+        let working_days = match env::var("BUSINESS_DAYS") {
+            Ok(value) => list(&value)
+                .map(|day| {
+                    day.parse::<Weekday>()
+                        .map_err(|_| format!("Invalid BUSINESS_DAYS day: {}", day))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            Err(_) => defaults.working_days,
+        };
+        let holidays = match env::var("HOLIDAYS") {
+            Ok(value) => list(&value)
+                .map(|date| {
+                    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                        .map_err(|_| format!("Invalid HOLIDAYS date: {}", date))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            Err(_) => Vec::new(),
+        };
+
+        BusinessCalendar::new(working_days, holidays)
+    }
+
+    /// Whether work happens and packages move on a date
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        self.working_days.contains(&date.weekday()) && !self.holidays.contains(&date)
+    }
+
+    /// The date itself if it's a business day, or else the next one
+    pub fn next_business_day(&self, date: NaiveDate) -> NaiveDate {
+        let mut date = date;
+        while !self.is_business_day(date) {
+            date = date.succ_opt().unwrap_or(date);
+        }
+        date
+    }
+
+    /// The business day `days` business days after a date
+    pub fn add_business_days(&self, date: NaiveDate, days: u32) -> NaiveDate {
+        let mut date = self.next_business_day(date);
+        for _ in 0..days {
+            date = self.next_business_day(date.succ_opt().unwrap_or(date));
+        }
+        date
+    }
+}
+
+/// Non-empty, trimmed items of a comma-separated list
+fn list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_add_business_days_skips_weekends_and_holidays() {
+        // Thanksgiving 2025 is Thursday the 27th
+        let calendar = BusinessCalendar::new(
+            BusinessCalendar::default().working_days,
+            [date(2025, 11, 27)],
+        )
+        .unwrap();
+
+        // Wednesday + 1 skips the holiday, + 2 skips the weekend too
+        assert_eq!(
+            calendar.add_business_days(date(2025, 11, 26), 1),
+            date(2025, 11, 28)
+        );
+        assert_eq!(
+            calendar.add_business_days(date(2025, 11, 26), 2),
+            date(2025, 12, 1)
+        );
+        // A weekend start counts from Monday
+        assert_eq!(
+            calendar.add_business_days(date(2025, 11, 29), 0),
+            date(2025, 12, 1)
+        );
+        assert_eq!(
+            calendar.add_business_days(date(2025, 11, 29), 1),
+            date(2025, 12, 2)
+        );
+    }
+
+    #[test]
+    fn test_saturday_working_day() {
+        let calendar = BusinessCalendar::new(
+            vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+                Weekday::Sat,
+            ],
+            [],
+        )
+        .unwrap();
+
+        assert!(calendar.is_business_day(date(2025, 11, 29)));
+        assert_eq!(
+            calendar.add_business_days(date(2025, 11, 28), 1),
+            date(2025, 11, 29)
+        );
+        assert!(BusinessCalendar::new(Vec::new(), []).is_err());
+    }
+}
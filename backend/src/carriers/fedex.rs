@@ -0,0 +1,945 @@
+//! FedEx REST API integration
+//!
+//! Rates are requested without a service type so FedEx quotes every service
+//! available for the shipment in one call, billed to the configured account.
+
+use async_trait::async_trait;
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+use super::{
+    AddressCandidate, AddressClassification, AddressValidation, Carrier, CarrierError,
+    CarrierService, LabelFormat, LabelRequest, Shipment, ShipmentAddress, ShippingLabel,
+    ShippingQuote, TokenCache, TokenResponse, TrackingEvent, TrackingInfo, TrackingStatus,
+    decimal_money, event_location, join_package_labels, parse_timestamp,
+};
+use crate::{
+    customs::CommercialInvoice,
+    money::Money,
+    types::{AddressValidationResult, PackageDimensions},
+};
+use rust_decimal::prelude::ToPrimitive;
+
+/// FedEx sandbox API base URL
+pub const SANDBOX_API_URL: &str = "https://apis-sandbox.fedex.com";
+
+/// FedEx services we sell, keyed by service type
+const SERVICES: &[CarrierService] = &[
+    CarrierService {
+        shipping_option: "FedEx_Ground",
+        code: "FEDEX_GROUND",
+        description: "FedEx Ground",
+        transit_days: (1, 5),
+    },
+    CarrierService {
+        shipping_option: "FedEx_HomeDelivery",
+        code: "GROUND_HOME_DELIVERY",
+        description: "FedEx Home Delivery",
+        transit_days: (1, 5),
+    },
+    CarrierService {
+        shipping_option: "FedEx_ExpressSaver",
+        code: "FEDEX_EXPRESS_SAVER",
+        description: "FedEx Express Saver",
+        transit_days: (3, 3),
+    },
+    CarrierService {
+        shipping_option: "FedEx_2Day",
+        code: "FEDEX_2_DAY",
+        description: "FedEx 2Day",
+        transit_days: (2, 2),
+    },
+    CarrierService {
+        shipping_option: "FedEx_StandardOvernight",
+        code: "STANDARD_OVERNIGHT",
+        description: "FedEx Standard Overnight",
+        transit_days: (1, 1),
+    },
+    CarrierService {
+        shipping_option: "FedEx_PriorityOvernight",
+        code: "PRIORITY_OVERNIGHT",
+        description: "FedEx Priority Overnight",
+        transit_days: (1, 1),
+    },
+];
+
+/// FedEx API project settings
+#[derive(Debug, Clone)]
+pub struct FedExConfig {
+    /// FedEx API base URL (sandbox or `https://apis.fedex.com`)
+    pub api_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Shipping account rates and labels are billed to
+    pub account_number: String,
+}
+
+/// Carrier backed by the FedEx REST APIs
+#[derive(Debug)]
+pub struct FedExCarrier {
+    config: FedExConfig,
+    client: reqwest::Client,
+    token: TokenCache,
+}
+
+/// FedEx wraps every response body in `output`
+#[derive(Debug, Deserialize)]
+struct Output<T> {
+    output: T,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResolveOutput {
+    #[serde(default)]
+    resolved_addresses: Vec<ResolvedAddress>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResolvedAddress {
+    #[serde(default)]
+    street_lines_token: Vec<String>,
+    city: Option<String>,
+    state_or_province_code: Option<String>,
+    postal_code: Option<String>,
+    country_code: Option<String>,
+    classification: Option<String>,
+    #[serde(default)]
+    attributes: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RateOutput {
+    #[serde(default)]
+    rate_reply_details: Vec<RateReplyDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RateReplyDetail {
+    service_type: String,
+    #[serde(default)]
+    rated_shipment_details: Vec<RatedShipmentDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RatedShipmentDetail {
+    total_net_charge: f64,
+    currency: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ShipOutput {
+    transaction_shipments: Vec<TransactionShipment>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionShipment {
+    master_tracking_number: String,
+    #[serde(default)]
+    piece_responses: Vec<PieceResponse>,
+    completed_shipment_detail: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PieceResponse {
+    #[serde(default)]
+    package_documents: Vec<PackageDocument>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PackageDocument {
+    encoded_label: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CancelOutput {
+    cancelled_shipment: bool,
+    message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrackOutput {
+    complete_track_results: Vec<CompleteTrackResult>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompleteTrackResult {
+    tracking_number: String,
+    track_results: Vec<TrackResult>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrackResult {
+    latest_status_detail: Option<StatusDetail>,
+    #[serde(default)]
+    date_and_times: Vec<DateAndTime>,
+    #[serde(default)]
+    scan_events: Vec<ScanEvent>,
+    error: Option<FedExError>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StatusDetail {
+    code: Option<String>,
+    derived_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DateAndTime {
+    #[serde(rename = "type")]
+    date_type: String,
+    date_time: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanEvent {
+    date: String,
+    event_description: String,
+    derived_status_code: Option<String>,
+    event_type: Option<String>,
+    scan_location: Option<ScanLocation>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanLocation {
+    city: Option<String>,
+    state_or_province_code: Option<String>,
+    country_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    #[serde(default)]
+    errors: Vec<FedExError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FedExError {
+    code: String,
+    message: Option<String>,
+}
+
+impl FedExCarrier {
+    pub fn new(config: FedExConfig) -> Self {
+        FedExCarrier {
+            config,
+            client: reqwest::Client::new(),
+            token: TokenCache::default(),
+        }
+    }
+
+    /// Get a cached access token, fetching a new one when it's about to expire
+    async fn access_token(&self) -> Result<String, CarrierError> {
+        self.token
+            .get(async {
+                let response = self
+                    .client
+                    .post(format!("{}/oauth/token", self.config.api_url))
+                    .form(&[
+                        ("grant_type", "client_credentials"),
+                        ("client_id", &self.config.client_id),
+                        ("client_secret", &self.config.client_secret),
+                    ])
+                    .send()
+                    .await?;
+                let token: TokenResponse = Self::parse_response(response).await?;
+                Ok((token.access_token, Duration::from_secs(token.expires_in)))
+            })
+            .await
+    }
+
+    /// Send an authenticated JSON request and decode the `output` of the response
+    async fn send<T: DeserializeOwned>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: &Value,
+    ) -> Result<T, CarrierError> {
+        let response = self
+            .client
+            .request(method, format!("{}{}", self.config.api_url, path))
+            .bearer_auth(self.access_token().await?)
+            .header("X-locale", "en_US")
+            .json(body)
+            .send()
+            .await?;
+        let body: Output<T> = Self::parse_response(response).await?;
+        Ok(body.output)
+    }
+
+    /// Decode a successful response, or turn a FedEx error body into a `CarrierError`
+    async fn parse_response<T: DeserializeOwned>(
+        response: reqwest::Response,
+    ) -> Result<T, CarrierError> {
+        let status = response.status();
+        let text = response.text().await?;
+        if status.is_success() {
+            return serde_json::from_str(&text).map_err(|e| CarrierError::Parse(e.to_string()));
+        }
+
+        let error = serde_json::from_str::<ErrorResponse>(&text)
+            .ok()
+            .and_then(|e| e.errors.into_iter().next());
+        let code = error.as_ref().map(|e| e.code.clone());
+        let message = error.and_then(|e| e.message).unwrap_or(text);
+        Err(match (status.as_u16(), code.as_deref()) {
+            (404, _) | (_, Some("TRACKING.TRACKINGNUMBER.NOTFOUND")) => {
+                CarrierError::NotFound(message)
+            }
+            (status, _) => CarrierError::Api(format!("FedEx error ({}): {}", status, message)),
+        })
+    }
+}
+
+/// Address as FedEx takes it
+fn fedex_address(address: &ShipmentAddress) -> Value {
+    let mut street_lines = vec![address.line1.clone()];
+    street_lines.extend(address.line2.clone());
+    json!({
+        "streetLines": street_lines,
+        "city": address.city,
+        "stateOrProvinceCode": address.state,
+        "postalCode": address.postal_code,
+        "countryCode": address.country,
+    })
+}
+
+/// Contact and address of a label party; FedEx requires a phone number
+fn fedex_party(address: &ShipmentAddress) -> Result<Value, CarrierError> {
+    let phone = address.phone.as_ref().ok_or_else(|| {
+        CarrierError::Config(format!(
+            "FedEx labels need a phone number for {}",
+            address.name
+        ))
+    })?;
+    Ok(json!({
+        "contact": {
+            "personName": address.name,
+            "companyName": address.company,
+            "phoneNumber": phone,
+        },
+        "address": fedex_address(address),
+    }))
+}
+
+/// Package line item; FedEx takes whole inches
+fn package_line_item(package: &PackageDimensions) -> Value {
+    json!({
+        "weight": { "units": "LB", "value": package.weight },
+        "dimensions": {
+            "length": package.length.ceil() as u32,
+            "width": package.width.ceil() as u32,
+            "height": package.height.ceil() as u32,
+            "units": "IN",
+        },
+    })
+}
+
+/// An amount as FedEx takes it, a number and a currency
+fn fedex_money(money: &Money) -> Value {
+    json!({
+        "amount": money.to_decimal().to_f64(),
+        "currency": money.currency(),
+    })
+}
+
+/// Commodities of a shipment abroad; duties are left to the recipient
+fn customs_clearance_detail(invoice: &CommercialInvoice) -> Result<Value, CarrierError> {
+    let commodities = invoice
+        .items
+        .iter()
+        .map(|item| {
+            Ok(json!({
+                "description": item.description,
+                "countryOfManufacture": invoice.origin_country,
+                "harmonizedCode": item.hs_code,
+                "quantity": item.quantity,
+                "quantityUnits": "PCS",
+                "unitPrice": fedex_money(&item.unit_value),
+                "customsValue": fedex_money(&item.value()?),
+                "weight": { "units": "LB", "value": item.unit_weight_lbs * item.quantity as f32 },
+            }))
+        })
+        .collect::<Result<Vec<_>, CarrierError>>()?;
+    Ok(json!({
+        "dutiesPayment": { "paymentType": "RECIPIENT" },
+        "totalCustomsValue": fedex_money(&invoice.total_value),
+        "commodities": commodities,
+    }))
+}
+
+/// Map a FedEx status code, such as `DL` for delivered
+fn tracking_status(code: &str) -> TrackingStatus {
+    match code {
+        "OC" | "LP" => TrackingStatus::PreTransit,
+        "PU" | "IT" | "AR" | "DP" | "AF" | "FD" | "PL" | "CC" => TrackingStatus::InTransit,
+        "OD" => TrackingStatus::OutForDelivery,
+        "DL" => TrackingStatus::Delivered,
+        "DE" | "SE" | "DY" | "CA" | "RS" | "HL" => TrackingStatus::Exception,
+        _ => TrackingStatus::Unknown,
+    }
+}
+
+#[async_trait]
+impl Carrier for FedExCarrier {
+    fn name(&self) -> &str {
+        "fedex"
+    }
+
+    fn services(&self) -> &[CarrierService] {
+        SERVICES
+    }
+
+    async fn validate_address(
+        &self,
+        address: &ShipmentAddress,
+    ) -> Result<AddressValidation, CarrierError> {
+        let output: ResolveOutput = self
+            .send(
+                reqwest::Method::POST,
+                "/address/v1/addresses/resolve",
+                &json!({ "addressesToValidate": [{ "address": fedex_address(address) }] }),
+            )
+            .await?;
+        let Some(resolved) = output.resolved_addresses.into_iter().next() else {
+            return Ok(AddressValidation {
+                result: AddressValidationResult::NoCandidates,
+                classification: AddressClassification::Unknown,
+                candidates: Vec::new(),
+            });
+        };
+
+        // Attributes are strings, e.g. `"DPV": "true"`
+        let attribute = |name: &str| resolved.attributes[name].as_str() == Some("true");
+        let result = if attribute("DPV") {
+            AddressValidationResult::Valid
+        } else if attribute("Resolved") {
+            AddressValidationResult::Ambiguous
+        } else {
+            AddressValidationResult::Invalid
+        };
+        let classification = match resolved.classification.as_deref() {
+            Some("RESIDENTIAL") => AddressClassification::Residential,
+            Some("BUSINESS") => AddressClassification::Commercial,
+            _ => AddressClassification::Unknown,
+        };
+        let mut lines = resolved.street_lines_token.into_iter();
+        let candidate = ShipmentAddress {
+            name: address.name.clone(),
+            company: address.company.clone(),
+            phone: address.phone.clone(),
+            line1: lines.next().unwrap_or_else(|| address.line1.clone()),
+            line2: lines.next(),
+            city: resolved.city.unwrap_or_else(|| address.city.clone()),
+            state: resolved
+                .state_or_province_code
+                .unwrap_or_else(|| address.state.clone()),
+            postal_code: resolved
+                .postal_code
+                .unwrap_or_else(|| address.postal_code.clone()),
+            country: resolved
+                .country_code
+                .unwrap_or_else(|| address.country.clone()),
+        };
+        Ok(AddressValidation {
+            result,
+            classification,
+            candidates: vec![AddressCandidate {
+                address: candidate,
+                classification,
+            }],
+        })
+    }
+
+    async fn rate(&self, shipment: &Shipment) -> Result<Vec<ShippingQuote>, CarrierError> {
+        let mut body = json!({
+            "accountNumber": { "value": self.config.account_number },
+            "requestedShipment": {
+                "shipper": { "address": fedex_address(&shipment.ship_from) },
+                "recipient": { "address": fedex_address(&shipment.ship_to) },
+                "shipDateStamp": shipment.ship_date.to_string(),
+                "pickupType": "DROPOFF_AT_FEDEX_LOCATION",
+                "rateRequestType": ["ACCOUNT"],
+                "requestedPackageLineItems": shipment
+                    .packages
+                    .iter()
+                    .map(package_line_item)
+                    .collect::<Vec<_>>(),
+            },
+        });
+        if let Some(invoice) = &shipment.customs {
+            body["requestedShipment"]["customsClearanceDetail"] =
+                customs_clearance_detail(invoice)?;
+        }
+        let output: RateOutput = self
+            .send(reqwest::Method::POST, "/rate/v1/rates/quotes", &body)
+            .await?;
+
+        let mut quotes = Vec::new();
+        for detail in output.rate_reply_details {
+            let Some(service) = SERVICES.iter().find(|s| s.code == detail.service_type) else {
+                tracing::debug!("Skipping unknown FedEx service {}", detail.service_type);
+                continue;
+            };
+            let Some(rated) = detail.rated_shipment_details.first() else {
+                continue;
+            };
+            let price = decimal_money(rated.total_net_charge, &rated.currency)?;
+            quotes.push(ShippingQuote::new(self.name(), service, price, None));
+        }
+        quotes.sort_by_key(|quote| quote.price.amount_minor());
+        Ok(quotes)
+    }
+
+    async fn create_label(&self, request: &LabelRequest) -> Result<ShippingLabel, CarrierError> {
+        let (image_type, stock_type) = match request.format {
+            LabelFormat::Pdf => ("PDF", "PAPER_4X6"),
+            LabelFormat::Zpl => ("ZPLII", "STOCK_4X6"),
+            LabelFormat::Gif => {
+                return Err(CarrierError::Unsupported(
+                    "FedEx labels can't be printed as GIF".to_string(),
+                ));
+            }
+        };
+        request.check_format()?;
+        let shipment = &request.shipment;
+        let packages: Vec<Value> = shipment
+            .packages
+            .iter()
+            .enumerate()
+            .map(|(index, package)| {
+                let mut package = package_line_item(package);
+                package["sequenceNumber"] = json!(index + 1);
+                package["customerReferences"] = json!([
+                    { "customerReferenceType": "CUSTOMER_REFERENCE", "value": request.reference }
+                ]);
+                package
+            })
+            .collect();
+        let mut body = json!({
+            "labelResponseOptions": "LABEL",
+            "accountNumber": { "value": self.config.account_number },
+            "requestedShipment": {
+                "shipper": fedex_party(&shipment.ship_from)?,
+                "recipients": [fedex_party(&shipment.ship_to)?],
+                "serviceType": request.service_code,
+                "shipDatestamp": shipment.ship_date.to_string(),
+                "packagingType": "YOUR_PACKAGING",
+                "pickupType": "DROPOFF_AT_FEDEX_LOCATION",
+                "shippingChargesPayment": { "paymentType": "SENDER" },
+                "labelSpecification": {
+                    "imageType": image_type,
+                    "labelStockType": stock_type,
+                },
+                "totalPackageCount": packages.len(),
+                "requestedPackageLineItems": packages,
+            },
+        });
+        if let Some(invoice) = &shipment.customs {
+            let mut detail = customs_clearance_detail(invoice)?;
+            detail["commercialInvoice"] = json!({
+                "shipmentPurpose": "SOLD",
+                "customerReferences": [
+                    { "customerReferenceType": "INVOICE_NUMBER", "value": invoice.invoice_number }
+                ],
+            });
+            body["requestedShipment"]["customsClearanceDetail"] = detail;
+        }
+        let output: ShipOutput = self
+            .send(reqwest::Method::POST, "/ship/v1/shipments", &body)
+            .await?;
+
+        let shipment = output
+            .transaction_shipments
+            .into_iter()
+            .next()
+            .ok_or_else(|| CarrierError::Parse("No shipment in response".to_string()))?;
+        // One label per package, in package order
+        let labels = shipment
+            .piece_responses
+            .iter()
+            .filter_map(|piece| {
+                piece
+                    .package_documents
+                    .iter()
+                    .find_map(|document| document.encoded_label.as_deref())
+            })
+            .map(|encoded| {
+                general_purpose::STANDARD
+                    .decode(encoded)
+                    .map_err(|e| CarrierError::Parse(e.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let rate = shipment
+            .completed_shipment_detail
+            .as_ref()
+            .map(|detail| &detail["shipmentRating"]["shipmentRateDetails"][0]);
+        let price =
+            match rate.map(|rate| (rate["totalNetCharge"].as_f64(), rate["currency"].as_str())) {
+                Some((Some(amount), Some(currency))) => Some(decimal_money(amount, currency)?),
+                _ => None,
+            };
+
+        Ok(ShippingLabel {
+            tracking_number: shipment.master_tracking_number,
+            format: request.format,
+            data: join_package_labels(request.format, labels)?,
+            price,
+        })
+    }
+
+    async fn track(&self, tracking_number: &str) -> Result<TrackingInfo, CarrierError> {
+        let output: TrackOutput = self
+            .send(
+                reqwest::Method::POST,
+                "/track/v1/trackingnumbers",
+                &json!({
+                    "includeDetailedScans": true,
+                    "trackingInfo": [{ "trackingNumberInfo": { "trackingNumber": tracking_number } }],
+                }),
+            )
+            .await?;
+        let complete = output
+            .complete_track_results
+            .into_iter()
+            .next()
+            .ok_or_else(|| CarrierError::NotFound(tracking_number.to_string()))?;
+        let result = complete
+            .track_results
+            .into_iter()
+            .next()
+            .ok_or_else(|| CarrierError::NotFound(tracking_number.to_string()))?;
+        // Unknown tracking numbers come back as a result carrying an error
+        if let Some(error) = result.error {
+            return Err(match error.code.as_str() {
+                "TRACKING.TRACKINGNUMBER.NOTFOUND" => {
+                    CarrierError::NotFound(tracking_number.to_string())
+                }
+                _ => CarrierError::Api(error.message.unwrap_or(error.code)),
+            });
+        }
+
+        let status = result
+            .latest_status_detail
+            .as_ref()
+            .and_then(|detail| detail.derived_code.as_deref().or(detail.code.as_deref()))
+            .map(tracking_status)
+            .unwrap_or(TrackingStatus::Unknown);
+        let events = result
+            .scan_events
+            .iter()
+            .filter_map(|event| {
+                let code = event
+                    .derived_status_code
+                    .as_deref()
+                    .or(event.event_type.as_deref())
+                    .unwrap_or_default();
+                let location = event.scan_location.as_ref();
+                Some(TrackingEvent {
+                    status: tracking_status(code),
+                    description: event.event_description.clone(),
+                    location: event_location(&[
+                        location.and_then(|l| l.city.as_deref()),
+                        location.and_then(|l| l.state_or_province_code.as_deref()),
+                        location.and_then(|l| l.country_code.as_deref()),
+                    ]),
+                    timestamp: parse_timestamp(&event.date)?,
+                })
+            })
+            .collect();
+        let estimated_delivery = result
+            .date_and_times
+            .iter()
+            .find(|date| date.date_type == "ESTIMATED_DELIVERY")
+            .and_then(|date| parse_timestamp(&date.date_time))
+            .map(|timestamp| timestamp.date_naive());
+
+        Ok(TrackingInfo {
+            tracking_number: complete.tracking_number,
+            status,
+            estimated_delivery,
+            events,
+        })
+    }
+
+    async fn void_label(&self, tracking_number: &str) -> Result<(), CarrierError> {
+        let output: CancelOutput = self
+            .send(
+                reqwest::Method::PUT,
+                "/ship/v1/shipments/cancel",
+                &json!({
+                    "accountNumber": { "value": self.config.account_number },
+                    "trackingNumber": tracking_number,
+                    "deletionControl": "DELETE_ALL_PACKAGES",
+                    "emailShipment": false,
+                }),
+            )
+            .await?;
+        if !output.cancelled_shipment {
+            return Err(CarrierError::Api(output.message.unwrap_or_else(|| {
+                format!("FedEx didn't cancel shipment {}", tracking_number)
+            })));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        money::Money,
+        test_support::{shipment_address, spawn_stub_server},
+    };
+    use axum::{
+        Json, Router,
+        http::{HeaderMap, StatusCode},
+        routing::{post, put},
+    };
+    use chrono::NaiveDate;
+
+    type StubResult = Result<Json<Value>, (StatusCode, Json<Value>)>;
+
+    fn authorized(headers: &HeaderMap) -> Result<(), (StatusCode, Json<Value>)> {
+        match headers.get("authorization").and_then(|v| v.to_str().ok()) {
+            Some("Bearer fedex-token") => Ok(()),
+            _ => Err((
+                StatusCode::UNAUTHORIZED,
+                Json(
+                    json!({ "errors": [{ "code": "NOT.AUTHORIZED.ERROR", "message": "Unauthorized" }] }),
+                ),
+            )),
+        }
+    }
+
+    async fn token() -> Json<Value> {
+        Json(json!({ "access_token": "fedex-token", "token_type": "bearer", "expires_in": 3599 }))
+    }
+
+    async fn rates(headers: HeaderMap, Json(body): Json<Value>) -> StubResult {
+        authorized(&headers)?;
+        let shipment = &body["requestedShipment"];
+        assert_eq!(body["accountNumber"]["value"], "740561073");
+        assert_eq!(
+            shipment["requestedPackageLineItems"][0]["dimensions"]["height"],
+            2
+        );
+        let detail = |service: &str, charge: f64| {
+            json!({
+                "serviceType": service,
+                "ratedShipmentDetails": [{ "rateType": "ACCOUNT", "totalNetCharge": charge, "currency": "USD" }]
+            })
+        };
+        Ok(Json(json!({ "output": { "rateReplyDetails": [
+            detail("PRIORITY_OVERNIGHT", 61.02),
+            detail("FEDEX_GROUND", 12.1),
+            detail("FIRST_OVERNIGHT", 98.4),
+        ] } })))
+    }
+
+    async fn resolve(headers: HeaderMap) -> StubResult {
+        authorized(&headers)?;
+        Ok(Json(json!({ "output": { "resolvedAddresses": [{
+            "streetLinesToken": ["123 MAIN ST"],
+            "city": "AUSTIN",
+            "stateOrProvinceCode": "TX",
+            "postalCode": "78701-3021",
+            "countryCode": "US",
+            "classification": "BUSINESS",
+            "attributes": { "Resolved": "true", "DPV": "false" }
+        }] } })))
+    }
+
+    async fn ship(headers: HeaderMap, Json(body): Json<Value>) -> StubResult {
+        authorized(&headers)?;
+        let shipment = &body["requestedShipment"];
+        assert_eq!(shipment["serviceType"], "FEDEX_GROUND");
+        assert_eq!(
+            shipment["recipients"][0]["contact"]["phoneNumber"],
+            "5552345678"
+        );
+        Ok(Json(json!({ "output": { "transactionShipments": [{
+            "masterTrackingNumber": "794953555571",
+            "pieceResponses": [{
+                "trackingNumber": "794953555571",
+                "packageDocuments": [{
+                    "contentType": "LABEL",
+                    "docType": "PDF",
+                    "encodedLabel": general_purpose::STANDARD.encode(b"%PDF-1.4 label")
+                }]
+            }],
+            "completedShipmentDetail": { "shipmentRating": { "shipmentRateDetails": [
+                { "totalNetCharge": 12.1, "currency": "USD" }
+            ] } }
+        }] } })))
+    }
+
+    async fn cancel(headers: HeaderMap, Json(body): Json<Value>) -> StubResult {
+        authorized(&headers)?;
+        let cancelled = body["trackingNumber"] == "794953555571";
+        Ok(Json(
+            json!({ "output": { "cancelledShipment": cancelled } }),
+        ))
+    }
+
+    async fn track(headers: HeaderMap, Json(body): Json<Value>) -> StubResult {
+        authorized(&headers)?;
+        let tracking_number =
+            body["trackingInfo"][0]["trackingNumberInfo"]["trackingNumber"].clone();
+        if tracking_number != "794953555571" {
+            return Ok(Json(json!({ "output": { "completeTrackResults": [{
+                "trackingNumber": tracking_number,
+                "trackResults": [{ "error": {
+                    "code": "TRACKING.TRACKINGNUMBER.NOTFOUND",
+                    "message": "Tracking number cannot be found."
+                } }]
+            }] } })));
+        }
+        Ok(Json(json!({ "output": { "completeTrackResults": [{
+            "trackingNumber": tracking_number,
+            "trackResults": [{
+                "latestStatusDetail": { "code": "DL", "derivedCode": "DL", "description": "Delivered" },
+                "dateAndTimes": [{ "type": "ACTUAL_DELIVERY", "dateTime": "2025-10-02T14:31:00-05:00" }],
+                "scanEvents": [
+                    {
+                        "date": "2025-10-02T14:31:00-05:00",
+                        "eventType": "DL",
+                        "eventDescription": "Delivered",
+                        "derivedStatusCode": "DL",
+                        "scanLocation": { "city": "AUSTIN", "stateOrProvinceCode": "TX", "countryCode": "US" }
+                    },
+                    {
+                        "date": "2025-09-30T18:02:00-06:00",
+                        "eventType": "PU",
+                        "eventDescription": "Picked up",
+                        "derivedStatusCode": "PU",
+                        "scanLocation": { "city": "DENVER", "stateOrProvinceCode": "CO", "countryCode": "US" }
+                    }
+                ]
+            }]
+        }] } })))
+    }
+
+    async fn stub_carrier() -> FedExCarrier {
+        let router = Router::new()
+            .route("/oauth/token", post(token))
+            .route("/rate/v1/rates/quotes", post(rates))
+            .route("/address/v1/addresses/resolve", post(resolve))
+            .route("/ship/v1/shipments", post(ship))
+            .route("/ship/v1/shipments/cancel", put(cancel))
+            .route("/track/v1/trackingnumbers", post(track));
+        FedExCarrier::new(FedExConfig {
+            api_url: spawn_stub_server(router).await,
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            account_number: "740561073".to_string(),
+        })
+    }
+
+    fn address(name: &str, city: &str, state: &str, postal_code: &str) -> ShipmentAddress {
+        ShipmentAddress {
+            name: name.to_string(),
+            phone: Some("5552345678".to_string()),
+            city: city.to_string(),
+            state: state.to_string(),
+            postal_code: postal_code.to_string(),
+            ..shipment_address()
+        }
+    }
+
+    fn shipment() -> Shipment {
+        Shipment {
+            ship_from: address("Sushi Prints", "Denver", "CO", "80202"),
+            ship_to: address("Jane Doe", "Austin", "TX", "78701"),
+            packages: vec![PackageDimensions {
+                length: 8.0,
+                width: 6.0,
+                height: 1.1,
+                weight: 0.7,
+            }],
+            ship_date: NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(),
+            customs: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate() {
+        let carrier = stub_carrier().await;
+
+        let quotes = carrier.rate(&shipment()).await.unwrap();
+        let options: Vec<&str> = quotes.iter().map(|q| q.shipping_option.as_str()).collect();
+        assert_eq!(options, ["FedEx_Ground", "FedEx_PriorityOvernight"]);
+        assert_eq!(quotes[0].price, Money::from_minor(1210, "USD"));
+        assert_eq!(quotes[0].carrier, "fedex");
+    }
+
+    #[tokio::test]
+    async fn test_validate_address() {
+        let carrier = stub_carrier().await;
+
+        let validation = carrier.validate_address(&shipment().ship_to).await.unwrap();
+        assert_eq!(validation.result, AddressValidationResult::Ambiguous);
+        assert_eq!(validation.classification, AddressClassification::Commercial);
+        assert_eq!(validation.candidates[0].address.line1, "123 MAIN ST");
+        assert_eq!(validation.candidates[0].address.name, "Jane Doe");
+    }
+
+    #[tokio::test]
+    async fn test_label_lifecycle() {
+        let carrier = stub_carrier().await;
+        let mut request = LabelRequest {
+            shipment: shipment(),
+            service_code: "FEDEX_GROUND".to_string(),
+            format: LabelFormat::Pdf,
+            reference: "ord_20250930_0001".to_string(),
+        };
+
+        let label = carrier.create_label(&request).await.unwrap();
+        assert_eq!(label.tracking_number, "794953555571");
+        assert_eq!(label.data, b"%PDF-1.4 label");
+        assert_eq!(label.price, Some(Money::from_minor(1210, "USD")));
+        carrier.void_label(&label.tracking_number).await.unwrap();
+        assert!(carrier.void_label("000000000000").await.is_err());
+
+        // Both parties need a phone number
+        request.shipment.ship_to.phone = None;
+        let err = carrier.create_label(&request).await.unwrap_err();
+        assert!(matches!(err, CarrierError::Config(_)));
+    }
+
+    #[tokio::test]
+    async fn test_track() {
+        let carrier = stub_carrier().await;
+
+        let tracking = carrier.track("794953555571").await.unwrap();
+        assert_eq!(tracking.status, TrackingStatus::Delivered);
+        assert_eq!(tracking.estimated_delivery, None);
+        assert_eq!(tracking.events.len(), 2);
+        assert_eq!(
+            tracking.events[0].location.as_deref(),
+            Some("AUSTIN, TX, US")
+        );
+        assert_eq!(tracking.events[1].status, TrackingStatus::InTransit);
+
+        let err = carrier.track("000000000000").await.unwrap_err();
+        assert!(matches!(err, CarrierError::NotFound(_)));
+    }
+}
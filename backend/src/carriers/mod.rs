@@ -0,0 +1,606 @@
+//! Shipping carriers
+//!
+//! Every carrier implements `Carrier`, so quoting, labels and tracking don't
+//! care who moves the package. Carriers are registered in `Carriers` under a
+//! short name (`ups`, `usps`, `fedex`), and every service they offer maps to
+//! a `shipping_option` customers pick at checkout, such as `UPS_Ground`.
+//!
+//! UPS is always enabled; USPS and FedEx are enabled by their credentials.
+
+pub mod fedex;
+pub mod ups;
+pub mod usps;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fmt,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+use crate::{
+    UpsClient,
+    customs::CommercialInvoice,
+    models::ship_from::ShipFrom,
+    money::{Money, MoneyError},
+    types::{AddressValidationResult, PackageDimensions},
+};
+
+/// Refresh carrier OAuth tokens this long before they expire
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Errors returned by carriers
+#[derive(Debug)]
+pub enum CarrierError {
+    /// Missing or invalid carrier configuration
+    Config(String),
+    /// The carrier rejected the request
+    Api(String),
+    /// No shipment exists with the given tracking number
+    NotFound(String),
+    /// The carrier doesn't offer the operation
+    Unsupported(String),
+    /// Unexpected response body
+    Parse(String),
+    /// Connection failure
+    Network(String),
+}
+
+impl fmt::Display for CarrierError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CarrierError::Config(msg) => write!(f, "Carrier configuration error: {}", msg),
+            CarrierError::Api(msg) => write!(f, "Carrier error: {}", msg),
+            CarrierError::NotFound(id) => write!(f, "Shipment not found: {}", id),
+            CarrierError::Unsupported(msg) => write!(f, "{}", msg),
+            CarrierError::Parse(msg) => write!(f, "Unexpected carrier response: {}", msg),
+            CarrierError::Network(msg) => write!(f, "Carrier unreachable: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CarrierError {}
+
+impl From<reqwest::Error> for CarrierError {
+    fn from(err: reqwest::Error) -> Self {
+        CarrierError::Network(err.to_string())
+    }
+}
+
+impl From<MoneyError> for CarrierError {
+    fn from(err: MoneyError) -> Self {
+        CarrierError::Parse(err.to_string())
+    }
+}
+
+/// A postal address with the contact carriers print on labels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShipmentAddress {
+    /// Person or business receiving or sending the package
+    pub name: String,
+    pub company: Option<String>,
+    pub phone: Option<String>,
+    pub line1: String,
+    pub line2: Option<String>,
+    pub city: String,
+    /// State or province code
+    pub state: String,
+    pub postal_code: String,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+}
+
+impl ShipmentAddress {
+    /// How closely a region matches the address: 2 for its state, such as
+    /// `US-CA`, 1 for its country, such as `US`, and 0 for neither
+    pub fn region_match(&self, region: &str) -> u8 {
+        let country = self.country.to_ascii_uppercase();
+        if region == country {
+            1
+        } else if region == format!("{}-{}", country, self.state.to_ascii_uppercase()) {
+            2
+        } else {
+            0
+        }
+    }
+}
+
+impl From<ShipFrom> for ShipmentAddress {
+    fn from(ship_from: ShipFrom) -> Self {
+        let from = ship_from.from;
+        let mut lines = from.address_line.into_iter();
+        ShipmentAddress {
+            name: from.consignee_name,
+            company: Some(from.building_name).filter(|name| !name.is_empty()),
+            phone: ship_from.phone,
+            line1: lines.next().unwrap_or_default(),
+            line2: lines.next(),
+            city: from.political_division2,
+            state: from.political_division1,
+            postal_code: from.postcode_primary_low.unwrap_or_default(),
+            country: from.country_code,
+        }
+    }
+}
+
+/// Whether an address is a home or a business
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressClassification {
+    Residential,
+    Commercial,
+    Unknown,
+}
+
+/// An address the carrier suggests in place of the one given
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AddressCandidate {
+    pub address: ShipmentAddress,
+    pub classification: AddressClassification,
+}
+
+/// Result of validating an address with a carrier
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddressValidation {
+    pub result: AddressValidationResult,
+    /// Classification of the address as given, when the carrier knows it
+    pub classification: AddressClassification,
+    /// Corrected or alternative addresses, best match first
+    pub candidates: Vec<AddressCandidate>,
+}
+
+/// Packages to move between two addresses
+#[derive(Debug, Clone)]
+pub struct Shipment {
+    pub ship_from: ShipmentAddress,
+    pub ship_to: ShipmentAddress,
+    /// At least one package
+    pub packages: Vec<PackageDimensions>,
+    /// Business day the packages are handed to the carrier
+    pub ship_date: NaiveDate,
+    /// Customs paperwork, for shipments that cross a border
+    pub customs: Option<CommercialInvoice>,
+}
+
+/// A service a carrier offers, and the checkout option it's sold as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CarrierService {
+    /// Value customers send as `shipping_option`, e.g. `UPS_Ground`
+    pub shipping_option: &'static str,
+    /// The carrier's own code for the service
+    pub code: &'static str,
+    pub description: &'static str,
+    /// Usual business days in transit (min, max), when the carrier doesn't say
+    pub transit_days: (u32, u32),
+}
+
+/// Price and transit time of one shipping service
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShippingQuote {
+    /// Carrier name, e.g. `ups`
+    pub carrier: String,
+    /// Value to send as `shipping_option` when placing the order
+    pub shipping_option: String,
+    /// The carrier's service code, e.g. `03` for UPS Ground
+    pub service_code: String,
+    pub description: String,
+    pub price: Money,
+    /// Fewest business days in transit
+    pub transit_days_min: u32,
+    /// Most business days in transit
+    pub transit_days_max: u32,
+    /// Whether the carrier guarantees the transit time
+    pub guaranteed: bool,
+    /// Arrival date the carrier estimates for the shipment's ship date
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arrival_date: Option<NaiveDate>,
+    /// Duties and taxes the carrier estimates the recipient will owe, on top
+    /// of the price
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duties_and_taxes: Option<Money>,
+}
+
+impl ShippingQuote {
+    /// Quote a service at a price, in its usual or a guaranteed transit time
+    pub fn new(
+        carrier: &str,
+        service: &CarrierService,
+        price: Money,
+        guaranteed_days: Option<u32>,
+    ) -> Self {
+        let (transit_days_min, transit_days_max) = match guaranteed_days {
+            Some(days) => (days, days),
+            None => service.transit_days,
+        };
+        ShippingQuote {
+            carrier: carrier.to_string(),
+            shipping_option: service.shipping_option.to_string(),
+            service_code: service.code.to_string(),
+            description: service.description.to_string(),
+            price,
+            transit_days_min,
+            transit_days_max,
+            guaranteed: guaranteed_days.is_some(),
+            arrival_date: None,
+            duties_and_taxes: None,
+        }
+    }
+}
+
+/// Image format of a shipping label
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum LabelFormat {
+    Pdf,
+    Gif,
+    /// Zebra thermal printer commands
+    Zpl,
+}
+
+impl LabelFormat {
+    /// MIME type of the label file
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            LabelFormat::Pdf => "application/pdf",
+            LabelFormat::Gif => "image/gif",
+            LabelFormat::Zpl => "application/x-zpl",
+        }
+    }
+
+    /// File extension of the label file
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LabelFormat::Pdf => "pdf",
+            LabelFormat::Gif => "gif",
+            LabelFormat::Zpl => "zpl",
+        }
+    }
+}
+
+/// A label to buy for a shipment
+#[derive(Debug, Clone)]
+pub struct LabelRequest {
+    pub shipment: Shipment,
+    /// The carrier's code for the service to ship with
+    pub service_code: String,
+    pub format: LabelFormat,
+    /// Our reference printed on the label, usually the order ID
+    pub reference: String,
+}
+
+impl LabelRequest {
+    /// Fail unless every package's label can go in one file of the requested
+    /// format, so nothing is bought that can't be stored
+    pub fn check_format(&self) -> Result<(), CarrierError> {
+        if self.shipment.packages.len() > 1 && self.format != LabelFormat::Zpl {
+            return Err(CarrierError::Unsupported(
+                "Labels for shipments of several packages can only be printed as ZPL".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Join the labels of a shipment's packages into one file
+///
+/// Thermal printers print concatenated ZPL labels one after another; other
+/// formats only ever hold a single label, see `LabelRequest::check_format`.
+pub fn join_package_labels(
+    format: LabelFormat,
+    labels: Vec<Vec<u8>>,
+) -> Result<Vec<u8>, CarrierError> {
+    match (format, labels.len()) {
+        (_, 0) => Err(CarrierError::Parse("No label in response".to_string())),
+        (_, 1) => Ok(labels.into_iter().next().unwrap_or_default()),
+        (LabelFormat::Zpl, _) => Ok(labels.concat()),
+        (_, count) => Err(CarrierError::Unsupported(format!(
+            "Can't join {} {} labels into one file",
+            count,
+            format.extension()
+        ))),
+    }
+}
+
+/// A purchased shipping label
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShippingLabel {
+    pub tracking_number: String,
+    pub format: LabelFormat,
+    /// The label file
+    pub data: Vec<u8>,
+    /// What the carrier charged, when it says
+    pub price: Option<Money>,
+}
+
+/// Where a shipment is, normalized across carriers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackingStatus {
+    /// Label created, not yet handed to the carrier
+    PreTransit,
+    InTransit,
+    OutForDelivery,
+    Delivered,
+    /// Delayed, returned or otherwise needs attention
+    Exception,
+    Unknown,
+}
+
+/// A single scan or status update
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrackingEvent {
+    pub status: TrackingStatus,
+    pub description: String,
+    /// City, state and country, when the carrier gives them
+    pub location: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Current status and history of a shipment
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrackingInfo {
+    pub tracking_number: String,
+    pub status: TrackingStatus,
+    pub estimated_delivery: Option<NaiveDate>,
+    /// Events, newest first
+    pub events: Vec<TrackingEvent>,
+}
+
+/// A company that moves packages
+#[async_trait]
+pub trait Carrier: Send + Sync + fmt::Debug {
+    /// Short name this carrier is registered under, such as `ups`
+    fn name(&self) -> &str;
+
+    /// Services the carrier can quote and ship
+    fn services(&self) -> &[CarrierService];
+
+    /// Check an address and suggest corrections
+    async fn validate_address(
+        &self,
+        address: &ShipmentAddress,
+    ) -> Result<AddressValidation, CarrierError>;
+
+    /// Quote every service available for a shipment, cheapest first
+    async fn rate(&self, shipment: &Shipment) -> Result<Vec<ShippingQuote>, CarrierError>;
+
+    /// Buy a label
+    async fn create_label(&self, request: &LabelRequest) -> Result<ShippingLabel, CarrierError>;
+
+    /// Current status of a shipment
+    async fn track(&self, tracking_number: &str) -> Result<TrackingInfo, CarrierError>;
+
+    /// Cancel an unused label
+    async fn void_label(&self, tracking_number: &str) -> Result<(), CarrierError>;
+}
+
+/// The configured carriers, looked up by name
+#[derive(Debug, Clone, Default)]
+pub struct Carriers {
+    carriers: Vec<Arc<dyn Carrier>>,
+}
+
+impl Carriers {
+    pub fn new(carriers: Vec<Arc<dyn Carrier>>) -> Self {
+        Carriers { carriers }
+    }
+
+    /// Carrier registered under a name, if it's configured
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Carrier>> {
+        self.carriers.iter().find(|c| c.name() == name)
+    }
+
+    /// All configured carriers
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Carrier>> {
+        self.carriers.iter()
+    }
+
+    /// Names of the configured carriers
+    pub fn names(&self) -> Vec<&str> {
+        self.carriers.iter().map(|c| c.name()).collect()
+    }
+
+    /// Carrier and service behind a checkout `shipping_option`
+    pub fn find_service(
+        &self,
+        shipping_option: &str,
+    ) -> Option<(&Arc<dyn Carrier>, &CarrierService)> {
+        self.carriers.iter().find_map(|carrier| {
+            carrier
+                .services()
+                .iter()
+                .find(|service| service.shipping_option == shipping_option)
+                .map(|service| (carrier, service))
+        })
+    }
+}
+
+/// Carrier settings beyond UPS
+#[derive(Debug, Clone)]
+pub struct CarrierConfig {
+    /// USPS credentials; USPS is disabled without them
+    pub usps: Option<usps::UspsConfig>,
+    /// FedEx credentials; FedEx is disabled without them
+    pub fedex: Option<fedex::FedExConfig>,
+}
+
+impl CarrierConfig {
+    /// Load carrier configuration from environment variables
+    ///
+    /// # Environment Variables
+    ///
+    /// - `USPS_API_URL`: USPS API base URL (optional, defaults to the test environment)
+    /// - `USPS_CLIENT_ID` / `USPS_CLIENT_SECRET`: USPS API app credentials
+    /// - `USPS_CRID` / `USPS_MID` / `USPS_ACCOUNT_NUMBER`: USPS business account that
+    ///   pays for labels (optional; needed to buy labels)
+    /// - `FEDEX_API_URL`: FedEx API base URL (optional, defaults to the sandbox)
+    /// - `FEDEX_CLIENT_ID` / `FEDEX_CLIENT_SECRET`: FedEx API project credentials
+    /// - `FEDEX_ACCOUNT_NUMBER`: FedEx shipping account
+    pub fn from_env() -> Result<Self, String> {
+        let usps = match env::var("USPS_CLIENT_ID") {
+            Ok(client_id) => Some(usps::UspsConfig {
+                api_url: env::var("USPS_API_URL")
+                    .unwrap_or_else(|_| usps::TEST_API_URL.to_string()),
+                client_id,
+                client_secret: env::var("USPS_CLIENT_SECRET")
+                    .map_err(|_| "USPS_CLIENT_SECRET not set")?,
+                payment_account: match env::var("USPS_CRID") {
+                    Ok(crid) => Some(usps::UspsPaymentAccount {
+                        crid,
+                        mid: env::var("USPS_MID").map_err(|_| "USPS_MID not set")?,
+                        account_number: env::var("USPS_ACCOUNT_NUMBER")
+                            .map_err(|_| "USPS_ACCOUNT_NUMBER not set")?,
+                    }),
+                    Err(_) => None,
+                },
+            }),
+            Err(_) => None,
+        };
+        let fedex = match env::var("FEDEX_CLIENT_ID") {
+            Ok(client_id) => Some(fedex::FedExConfig {
+                api_url: env::var("FEDEX_API_URL")
+                    .unwrap_or_else(|_| fedex::SANDBOX_API_URL.to_string()),
+                client_id,
+                client_secret: env::var("FEDEX_CLIENT_SECRET")
+                    .map_err(|_| "FEDEX_CLIENT_SECRET not set")?,
+                account_number: env::var("FEDEX_ACCOUNT_NUMBER")
+                    .map_err(|_| "FEDEX_ACCOUNT_NUMBER not set")?,
+            }),
+            Err(_) => None,
+        };
+        Ok(CarrierConfig { usps, fedex })
+    }
+
+    /// Build the configured carriers, UPS first
+    pub fn build(self, ups: UpsClient) -> Carriers {
+        let mut carriers: Vec<Arc<dyn Carrier>> = vec![Arc::new(ups)];
+        if let Some(config) = self.usps {
+            carriers.push(Arc::new(usps::UspsCarrier::new(config)));
+        }
+        if let Some(config) = self.fedex {
+            carriers.push(Arc::new(fedex::FedExCarrier::new(config)));
+        }
+        Carriers::new(carriers)
+    }
+}
+
+/// OAuth client-credentials token shared by a carrier's requests
+#[derive(Debug, Default)]
+struct TokenCache {
+    token: Mutex<Option<(String, Instant)>>, // access token, refresh deadline
+}
+
+impl TokenCache {
+    /// Get the cached token, or `fetch` a new one with its lifetime
+    ///
+    /// The lock is held while fetching, so concurrent callers share one refresh.
+    async fn get<F>(&self, fetch: F) -> Result<String, CarrierError>
+    where
+        F: Future<Output = Result<(String, Duration), CarrierError>>,
+    {
+        let mut token = self.token.lock().await;
+        if let Some((access_token, refresh_at)) = token.as_ref()
+            && Instant::now() < *refresh_at
+        {
+            return Ok(access_token.clone());
+        }
+
+        let (access_token, lifetime) = fetch.await?;
+        let refresh_at = Instant::now() + lifetime.saturating_sub(TOKEN_EXPIRY_MARGIN);
+        *token = Some((access_token.clone(), refresh_at));
+        Ok(access_token)
+    }
+}
+
+/// Body of an OAuth token response
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+/// Convert a decimal amount from a JSON number, as USPS and FedEx send prices
+fn decimal_money(amount: f64, currency: &str) -> Result<Money, CarrierError> {
+    Ok(Money::parse(&format!("{:.2}", amount), currency)?)
+}
+
+/// Parse a carrier timestamp, treating ones without an offset as UTC
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+                .ok()
+                .map(|timestamp| timestamp.and_utc())
+        })
+}
+
+/// Join the parts of an event location that are present
+fn event_location(parts: &[Option<&str>]) -> Option<String> {
+    let parts: Vec<&str> = parts
+        .iter()
+        .flatten()
+        .copied()
+        .filter(|part| !part.is_empty())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        let expected = DateTime::parse_from_rfc3339("2025-09-30T14:05:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(parse_timestamp("2025-09-30T09:05:00-05:00"), Some(expected));
+        assert_eq!(parse_timestamp("2025-09-30T14:05:00"), Some(expected));
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn test_find_service() {
+        let ups = UpsClient::new(crate::UpsConfig::new(
+            "http://localhost".to_string(),
+            "client".to_string(),
+            "secret".to_string(),
+            "A1B2C3".to_string(),
+        ));
+        let carriers = CarrierConfig {
+            usps: None,
+            fedex: None,
+        }
+        .build(ups);
+
+        let (carrier, service) = carriers.find_service("UPS_2Day").unwrap();
+        assert_eq!(carrier.name(), "ups");
+        assert_eq!(service.code, "02");
+        // USPS isn't configured
+        assert!(carriers.find_service("USPS_Priority").is_none());
+    }
+
+    #[test]
+    fn test_join_package_labels() {
+        let labels = vec![b"^XA1^XZ".to_vec(), b"^XA2^XZ".to_vec()];
+        assert_eq!(
+            join_package_labels(LabelFormat::Zpl, labels.clone()).unwrap(),
+            b"^XA1^XZ^XA2^XZ"
+        );
+        assert!(matches!(
+            join_package_labels(LabelFormat::Gif, labels),
+            Err(CarrierError::Unsupported(_))
+        ));
+        assert_eq!(
+            join_package_labels(LabelFormat::Gif, vec![b"GIF89a".to_vec()]).unwrap(),
+            b"GIF89a"
+        );
+    }
+}
@@ -0,0 +1,1092 @@
+//! UPS as a `Carrier`
+//!
+//! `UpsClient` talks to the UPS REST APIs; this module maps its requests and
+//! responses to the carrier-neutral types.
+
+use async_trait::async_trait;
+use base64::{Engine as _, engine::general_purpose};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+use super::{
+    AddressCandidate, AddressClassification, AddressValidation, Carrier, CarrierError,
+    CarrierService, LabelFormat, LabelRequest, Shipment, ShipmentAddress, ShippingLabel,
+    ShippingQuote, TrackingEvent, TrackingInfo, TrackingStatus, event_location,
+    join_package_labels,
+};
+use crate::{
+    UpsClient,
+    customs::CommercialInvoice,
+    error::UpsError,
+    models::{
+        address::Address,
+        ups_api_response::UPSApiResponse,
+        ups_rate_request::{
+            BillShipper, Dimensions, InvoiceLineTotal, PackageWeight, PackagingType,
+            PaymentDetails, RateAddress, Service, ShipmentCharge, TransactionReference,
+            UnitOfMeasurement,
+        },
+        ups_rate_response::UPSRateResponse,
+        ups_request::AddressKeyFormat,
+        ups_response::{AddressClassification as UpsClassification, AddressKeyFormatCandidate},
+        ups_ship_request::{
+            FormContacts, InternationalForms, LabelImageFormat, LabelSpecification, LabelStockSize,
+            Phone, Product, ProductUnit, ProductUnitOfMeasurement, ReferenceNumber, ShipPackage,
+            ShipParty, ShipRequestInfo, ShipShipment, ShipmentRequest, ShipmentServiceOptions,
+            UPSShipRequest,
+        },
+        ups_track_response::{TrackActivity, UPSTrackResponse},
+    },
+    money::Money,
+    types::{RateRequestOptions, ShippingRateRequest, UpsServiceCode},
+};
+
+/// UPS services we sell, keyed by UPS service code
+const SERVICES: &[CarrierService] = &[
+    CarrierService {
+        shipping_option: "UPS_Ground",
+        code: "03",
+        description: "UPS Ground",
+        transit_days: (1, 5),
+    },
+    CarrierService {
+        shipping_option: "UPS_3DaySelect",
+        code: "12",
+        description: "UPS 3 Day Select",
+        transit_days: (3, 3),
+    },
+    CarrierService {
+        shipping_option: "UPS_2Day",
+        code: "02",
+        description: "UPS 2nd Day Air",
+        transit_days: (2, 2),
+    },
+    CarrierService {
+        shipping_option: "UPS_2DayAM",
+        code: "59",
+        description: "UPS 2nd Day Air A.M.",
+        transit_days: (2, 2),
+    },
+    CarrierService {
+        shipping_option: "UPS_NextDayAirSaver",
+        code: "13",
+        description: "UPS Next Day Air Saver",
+        transit_days: (1, 1),
+    },
+    CarrierService {
+        shipping_option: "UPS_Overnight",
+        code: "01",
+        description: "UPS Next Day Air",
+        transit_days: (1, 1),
+    },
+    CarrierService {
+        shipping_option: "UPS_NextDayAirEarly",
+        code: "14",
+        description: "UPS Next Day Air Early",
+        transit_days: (1, 1),
+    },
+    CarrierService {
+        shipping_option: "UPS_Express",
+        code: "07",
+        description: "UPS Express",
+        transit_days: (1, 3),
+    },
+];
+
+impl From<UpsError> for CarrierError {
+    fn from(err: UpsError) -> Self {
+        match err {
+            UpsError::Config(msg) => CarrierError::Config(msg),
+            UpsError::Auth(msg) | UpsError::Api(msg) | UpsError::Validation(msg) => {
+                CarrierError::Api(msg)
+            }
+            UpsError::Parse(msg) => CarrierError::Parse(msg),
+            UpsError::Network(msg) => CarrierError::Network(msg),
+        }
+    }
+}
+
+/// Convert an address to the UPS address validation format
+pub fn key_format(address: &ShipmentAddress) -> AddressKeyFormat {
+    let mut address_line = vec![address.line1.clone()];
+    address_line.extend(address.line2.clone());
+    AddressKeyFormat {
+        consignee_name: address.name.clone(),
+        building_name: address.company.clone().unwrap_or_default(),
+        address_line,
+        region: format!("{},{},{}", address.city, address.state, address.postal_code),
+        political_division2: address.city.clone(),
+        political_division1: address.state.clone(),
+        postcode_primary_low: Some(address.postal_code.clone()),
+        postcode_extended_low: String::new(),
+        urbanization: None,
+        country_code: address.country.clone(),
+    }
+}
+
+/// Convert an address to the ship-to form the UPS rate request takes
+fn rate_address(address: &ShipmentAddress) -> Address {
+    Address {
+        address: address.line1.clone(),
+        city: address.city.clone(),
+        state: address.state.clone(),
+        postal_code: address.postal_code.clone(),
+        country: address.country.clone(),
+    }
+}
+
+/// Convert an address to the form UPS shipments take
+fn ship_address(address: &ShipmentAddress) -> RateAddress {
+    let mut address_line = vec![address.line1.clone()];
+    address_line.extend(address.line2.clone());
+    RateAddress {
+        address_line,
+        city: address.city.clone(),
+        state_province_code: address.state.clone(),
+        postal_code: address.postal_code.clone(),
+        country_code: address.country.clone(),
+    }
+}
+
+/// A shipment party; businesses ship under their company name, attention of the contact
+fn ship_party(address: &ShipmentAddress, shipper_number: Option<&str>) -> ShipParty {
+    let (name, attention_name) = match &address.company {
+        Some(company) => (company.clone(), Some(address.name.clone())),
+        None => (address.name.clone(), None),
+    };
+    ShipParty {
+        name,
+        attention_name,
+        shipper_number: shipper_number.map(str::to_string),
+        phone: address.phone.clone().map(|number| Phone { number }),
+        address: ship_address(address),
+    }
+}
+
+/// Build a Ship API request for a single-package label
+fn ship_request(
+    request: &LabelRequest,
+    shipper_number: &str,
+) -> Result<UPSShipRequest, CarrierError> {
+    let (code, label_stock_size) = match request.format {
+        LabelFormat::Gif => ("GIF", None),
+        LabelFormat::Zpl => (
+            "ZPL",
+            Some(LabelStockSize {
+                height: "6".to_string(),
+                width: "4".to_string(),
+            }),
+        ),
+        LabelFormat::Pdf => {
+            return Err(CarrierError::Unsupported(
+                "UPS labels can only be printed as GIF or ZPL".to_string(),
+            ));
+        }
+    };
+    let service = SERVICES
+        .iter()
+        .find(|service| service.code == request.service_code)
+        .ok_or_else(|| {
+            CarrierError::Unsupported(format!("Unknown UPS service {}", request.service_code))
+        })?;
+    let shipment = &request.shipment;
+
+    Ok(UPSShipRequest {
+        shipment_request: ShipmentRequest {
+            request: ShipRequestInfo {
+                request_option: "nonvalidate".to_string(),
+                transaction_reference: TransactionReference {
+                    customer_context: request.reference.clone(),
+                },
+            },
+            shipment: ShipShipment {
+                description: "Photo prints".to_string(),
+                shipper: ship_party(&shipment.ship_from, Some(shipper_number)),
+                ship_to: ship_party(&shipment.ship_to, None),
+                ship_from: ship_party(&shipment.ship_from, None),
+                payment_information: PaymentDetails {
+                    shipment_charge: vec![ShipmentCharge {
+                        charge_type: "01".to_string(), // Transportation
+                        bill_shipper: BillShipper {
+                            account_number: shipper_number.to_string(),
+                        },
+                    }],
+                },
+                service: Service {
+                    code: service.code.to_string(),
+                    description: service.description.to_string(),
+                },
+                invoice_line_total: shipment.customs.as_ref().map(|invoice| InvoiceLineTotal {
+                    currency_code: invoice.currency.clone(),
+                    monetary_value: invoice.total_value.to_decimal().to_string(),
+                }),
+                shipment_service_options: shipment.customs.as_ref().map(|invoice| {
+                    ShipmentServiceOptions {
+                        international_forms: Some(international_forms(invoice, &shipment.ship_to)),
+                    }
+                }),
+                package: shipment
+                    .packages
+                    .iter()
+                    .map(|package| ShipPackage {
+                        packaging: PackagingType {
+                            code: "02".to_string(), // Customer Supplied Package
+                            description: "Customer Supplied Package".to_string(),
+                        },
+                        dimensions: Dimensions {
+                            unit_of_measurement: UnitOfMeasurement {
+                                code: "IN".to_string(),
+                                description: "Inches".to_string(),
+                            },
+                            length: package.length.to_string(),
+                            width: package.width.to_string(),
+                            height: package.height.to_string(),
+                        },
+                        package_weight: PackageWeight {
+                            unit_of_measurement: UnitOfMeasurement {
+                                code: "LBS".to_string(),
+                                description: "Pounds".to_string(),
+                            },
+                            weight: package.weight.to_string(),
+                        },
+                        reference_number: vec![ReferenceNumber {
+                            value: request.reference.clone(),
+                        }],
+                    })
+                    .collect(),
+            },
+            label_specification: LabelSpecification {
+                label_image_format: LabelImageFormat {
+                    code: code.to_string(),
+                },
+                label_stock_size,
+            },
+        },
+    })
+}
+
+/// A paperless commercial invoice, sold to the recipient
+fn international_forms(
+    invoice: &CommercialInvoice,
+    ship_to: &ShipmentAddress,
+) -> InternationalForms {
+    InternationalForms {
+        form_type: vec!["01".to_string()], // Invoice
+        invoice_number: invoice.invoice_number.clone(),
+        invoice_date: invoice.invoice_date.format("%Y%m%d").to_string(),
+        reason_for_export: invoice.reason.clone(),
+        currency_code: invoice.currency.clone(),
+        contacts: FormContacts {
+            sold_to: ship_party(ship_to, None),
+        },
+        product: invoice
+            .items
+            .iter()
+            .map(|item| Product {
+                description: vec![item.description.chars().take(35).collect()],
+                unit: ProductUnit {
+                    number: item.quantity.to_string(),
+                    value: item.unit_value.to_decimal().to_string(),
+                    unit_of_measurement: ProductUnitOfMeasurement {
+                        code: "PCS".to_string(),
+                    },
+                },
+                commodity_code: item.hs_code.clone(),
+                origin_country_code: invoice.origin_country.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// Map a UPS activity status type, such as `D` for delivered
+fn tracking_status(status_type: &str) -> TrackingStatus {
+    match status_type {
+        "M" | "MV" => TrackingStatus::PreTransit,
+        "P" | "I" => TrackingStatus::InTransit,
+        "O" => TrackingStatus::OutForDelivery,
+        "D" => TrackingStatus::Delivered,
+        "X" | "RS" => TrackingStatus::Exception,
+        _ => TrackingStatus::Unknown,
+    }
+}
+
+/// When a scan happened, from its UTC time or else its local time
+fn activity_timestamp(activity: &TrackActivity) -> Option<DateTime<Utc>> {
+    if let (Some(date), Some(time)) = (&activity.gmt_date, &activity.gmt_time)
+        && let Ok(timestamp) =
+            NaiveDateTime::parse_from_str(&format!("{}{}", date, time), "%Y%m%d%H:%M:%S")
+    {
+        return Some(timestamp.and_utc());
+    }
+    NaiveDateTime::parse_from_str(
+        &format!("{}{}", activity.date, activity.time),
+        "%Y%m%d%H%M%S",
+    )
+    .ok()
+    .map(|timestamp| timestamp.and_utc())
+}
+
+/// Turn a UPS Track response into tracking info for its first package
+pub fn tracking_from_response(response: UPSTrackResponse) -> Result<TrackingInfo, CarrierError> {
+    let package = response
+        .track_response
+        .shipment
+        .into_iter()
+        .flat_map(|shipment| shipment.package)
+        .next()
+        .ok_or_else(|| CarrierError::Parse("No package in tracking response".to_string()))?;
+
+    let events: Vec<TrackingEvent> = package
+        .activity
+        .iter()
+        .filter_map(|activity| {
+            let address = activity
+                .location
+                .as_ref()
+                .and_then(|location| location.address.as_ref());
+            Some(TrackingEvent {
+                status: tracking_status(&activity.status.status_type),
+                description: activity.status.description.clone(),
+                location: event_location(&[
+                    address.and_then(|a| a.city.as_deref()),
+                    address.and_then(|a| a.state_province.as_deref()),
+                    address.and_then(|a| a.country_code.as_deref()),
+                ]),
+                timestamp: activity_timestamp(activity)?,
+            })
+        })
+        .collect();
+    let delivery_date = |date_type: &str| {
+        package
+            .delivery_date
+            .iter()
+            .find(|date| date.date_type == date_type)
+            .and_then(|date| NaiveDate::parse_from_str(&date.date, "%Y%m%d").ok())
+    };
+    let status = if delivery_date("DEL").is_some() {
+        TrackingStatus::Delivered
+    } else {
+        events
+            .first()
+            .map(|event| event.status)
+            .unwrap_or(TrackingStatus::Unknown)
+    };
+    let estimated_delivery = match status {
+        TrackingStatus::Delivered => None,
+        _ => delivery_date("RDD").or_else(|| delivery_date("SDD")),
+    };
+
+    Ok(TrackingInfo {
+        tracking_number: package.tracking_number,
+        status,
+        estimated_delivery,
+        events,
+    })
+}
+
+/// Map a UPS address classification code
+fn classification(classification: Option<&UpsClassification>) -> AddressClassification {
+    match classification.map(|c| c.code.as_str()) {
+        Some("1") => AddressClassification::Commercial,
+        Some("2") => AddressClassification::Residential,
+        _ => AddressClassification::Unknown,
+    }
+}
+
+/// Turn a UPS candidate into an address, keeping the name of the one given
+fn candidate_address(
+    candidate: &AddressKeyFormatCandidate,
+    given: &ShipmentAddress,
+) -> ShipmentAddress {
+    let mut lines = candidate
+        .address_line
+        .clone()
+        .unwrap_or_default()
+        .into_iter();
+    let postal_code = match (
+        &candidate.postcode_primary_low,
+        &candidate.postcode_extended_low,
+    ) {
+        (Some(primary), Some(extended)) if !extended.is_empty() => {
+            format!("{}-{}", primary, extended)
+        }
+        (Some(primary), _) => primary.clone(),
+        (None, _) => given.postal_code.clone(),
+    };
+    ShipmentAddress {
+        name: given.name.clone(),
+        company: given.company.clone(),
+        phone: given.phone.clone(),
+        line1: lines.next().unwrap_or_default(),
+        line2: lines.next(),
+        city: candidate
+            .political_division2
+            .clone()
+            .unwrap_or_else(|| given.city.clone()),
+        state: candidate
+            .political_division1
+            .clone()
+            .unwrap_or_else(|| given.state.clone()),
+        postal_code,
+        country: candidate
+            .country_code
+            .clone()
+            .unwrap_or_else(|| given.country.clone()),
+    }
+}
+
+/// Turn a UPS Shop response into quotes, cheapest first
+///
+/// Services we don't sell are skipped.
+pub fn quotes_from_rates(response: &UPSRateResponse) -> Result<Vec<ShippingQuote>, CarrierError> {
+    let mut quotes = Vec::new();
+
+    for shipment in &response.rate_response.rated_shipment {
+        let Some(service) = SERVICES.iter().find(|s| s.code == shipment.service.code) else {
+            tracing::debug!("Skipping unknown UPS service {}", shipment.service.code);
+            continue;
+        };
+        let guaranteed_days = shipment
+            .guaranteed_delivery
+            .as_ref()
+            .and_then(|delivery| delivery.business_days_in_transit.parse::<u32>().ok());
+        let price = shipment.total_charges.amount()?;
+        let mut quote = ShippingQuote::new("ups", service, price, guaranteed_days);
+        if let Some(transit) = &shipment.time_in_transit {
+            if let (None, Some(days)) = (guaranteed_days, transit.business_days()) {
+                quote.transit_days_min = days;
+                quote.transit_days_max = days;
+            }
+            quote.arrival_date = transit.arrival_date();
+        }
+        // Only returned for shipments abroad, in the currency of the charges
+        if let Some(taxes) = shipment.tax_charges.as_ref().filter(|t| !t.is_empty()) {
+            let currency = &shipment.total_charges.currency_code;
+            let amounts = taxes
+                .iter()
+                .map(|tax| Money::parse(&tax.monetary_value, currency))
+                .collect::<Result<Vec<_>, _>>()?;
+            quote.duties_and_taxes = Some(Money::sum(&amounts, currency)?);
+        }
+        quotes.push(quote);
+    }
+
+    quotes.sort_by_key(|quote| quote.price.amount_minor());
+    Ok(quotes)
+}
+
+#[async_trait]
+impl Carrier for UpsClient {
+    fn name(&self) -> &str {
+        "ups"
+    }
+
+    fn services(&self) -> &[CarrierService] {
+        SERVICES
+    }
+
+    async fn validate_address(
+        &self,
+        address: &ShipmentAddress,
+    ) -> Result<AddressValidation, CarrierError> {
+        let (response, result) = UpsClient::validate_address(self, &key_format(address)).await?;
+        let body = match response {
+            UPSApiResponse::Success(response) => response.xav_response,
+            UPSApiResponse::Error(error) => {
+                let messages: Vec<String> = error
+                    .response
+                    .errors
+                    .into_iter()
+                    .map(|e| format!("{} ({})", e.message, e.code))
+                    .collect();
+                return Err(CarrierError::Api(messages.join("; ")));
+            }
+        };
+
+        let candidates = body
+            .candidate
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|candidate| {
+                let key_format = candidate.address_key_format.as_ref()?;
+                Some(AddressCandidate {
+                    address: candidate_address(key_format, address),
+                    classification: classification(candidate.address_classification.as_ref()),
+                })
+            })
+            .collect();
+        Ok(AddressValidation {
+            result,
+            classification: classification(body.address_classification.as_ref()),
+            candidates,
+        })
+    }
+
+    async fn rate(&self, shipment: &Shipment) -> Result<Vec<ShippingQuote>, CarrierError> {
+        let request = ShippingRateRequest {
+            ship_from: &key_format(&shipment.ship_from),
+            ship_to: &rate_address(&shipment.ship_to),
+            customer_name: &shipment.ship_to.name,
+            // Prices every service along with its time in transit from the ship date
+            request_option: RateRequestOptions::ShopTimeInTransit,
+            // The code is required but ignored when shopping
+            service_code: UpsServiceCode::Ground,
+            packages: shipment.packages.clone(),
+            pickup_date: Some(shipment.ship_date),
+            customs: shipment.customs.as_ref(),
+        };
+        let response = self.get_shipping_rates(&request).await?;
+        quotes_from_rates(&response)
+    }
+
+    /// Every package gets its own label; they're joined into one file, and
+    /// the shipment identification number, which is also the lead package's
+    /// tracking number, is what the label is tracked and voided by
+    async fn create_label(&self, request: &LabelRequest) -> Result<ShippingLabel, CarrierError> {
+        request.check_format()?;
+        let body = ship_request(request, self.shipper_number())?;
+        let results = self
+            .create_shipment(&body)
+            .await?
+            .shipment_response
+            .shipment_results;
+
+        let labels = results
+            .package_results
+            .into_iter()
+            .map(|package| {
+                let image = package
+                    .shipping_label
+                    .ok_or_else(|| CarrierError::Parse("No label in shipment".to_string()))?;
+                general_purpose::STANDARD
+                    .decode(image.graphic_image)
+                    .map_err(|e| CarrierError::Parse(e.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let price = match &results.shipment_charges {
+            Some(charges) => Some(charges.total_charges.amount()?),
+            None => None,
+        };
+
+        Ok(ShippingLabel {
+            tracking_number: results.shipment_identification_number,
+            format: request.format,
+            data: join_package_labels(request.format, labels)?,
+            price,
+        })
+    }
+
+    async fn track(&self, tracking_number: &str) -> Result<TrackingInfo, CarrierError> {
+        match self.track_shipment(tracking_number).await? {
+            Some(response) => tracking_from_response(response),
+            None => Err(CarrierError::NotFound(tracking_number.to_string())),
+        }
+    }
+
+    /// Voiding the shipment identification number voids every package
+    async fn void_label(&self, tracking_number: &str) -> Result<(), CarrierError> {
+        let response = self.void_shipment(tracking_number).await?;
+        let status = response.void_shipment_response.summary_result.status;
+        if status.code != "1" {
+            return Err(CarrierError::Api(format!(
+                "UPS didn't void shipment {}: {}",
+                tracking_number, status.description
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        UpsConfig,
+        customs::CustomsItem,
+        money::Money,
+        test_support::{shipment_address, spawn_stub_server},
+        types::{AddressValidationResult, PackageDimensions},
+    };
+    use axum::{
+        Json, Router,
+        extract::Path,
+        http::StatusCode,
+        routing::{delete, get, post},
+    };
+    use serde_json::{Value, json};
+
+    fn rated_shipment(code: &str, total: &str, guaranteed_days: Option<&str>) -> Value {
+        let charges = json!({ "CurrencyCode": "USD", "MonetaryValue": total });
+        let mut shipment = json!({
+            "Service": { "Code": code },
+            "TransportationCharges": charges,
+            "TotalCharges": charges,
+            "RatedPackage": [{ "TransportationCharges": charges, "TotalCharges": charges, "Weight": "1.0" }]
+        });
+        if let Some(days) = guaranteed_days {
+            shipment["GuaranteedDelivery"] =
+                json!({ "BusinessDaysInTransit": days, "DeliveryByTime": "10:30 A.M." });
+        }
+        shipment
+    }
+
+    fn shop_response() -> Value {
+        json!({ "RateResponse": {
+            "Response": { "ResponseStatus": { "Code": "1", "Description": "Success" } },
+            "RatedShipment": [
+                rated_shipment("01", "48.12", Some("1")),
+                rated_shipment("03", "11.87", None),
+                rated_shipment("96", "150.00", None),
+                rated_shipment("02", "24.50", Some("2")),
+            ]
+        } })
+    }
+
+    fn address(name: &str, city: &str, state: &str, postal_code: &str) -> ShipmentAddress {
+        ShipmentAddress {
+            name: name.to_string(),
+            city: city.to_string(),
+            state: state.to_string(),
+            postal_code: postal_code.to_string(),
+            ..shipment_address()
+        }
+    }
+
+    async fn stub_client(router: Router) -> UpsClient {
+        let router = router.route(
+            "/security/v1/oauth/token",
+            post(|| async { Json(json!({ "access_token": "ups-token", "expires_in": "14399" })) }),
+        );
+        UpsClient::new(UpsConfig::new(
+            spawn_stub_server(router).await,
+            "client".to_string(),
+            "secret".to_string(),
+            "A1B2C3".to_string(),
+        ))
+    }
+
+    #[test]
+    fn test_quotes_from_rates() {
+        let response: UPSRateResponse = serde_json::from_value(shop_response()).unwrap();
+        let quotes = quotes_from_rates(&response).unwrap();
+
+        // Unknown services are dropped and the rest sorted by price
+        let options: Vec<&str> = quotes.iter().map(|q| q.shipping_option.as_str()).collect();
+        assert_eq!(options, ["UPS_Ground", "UPS_2Day", "UPS_Overnight"]);
+
+        assert_eq!(quotes[0].carrier, "ups");
+        assert_eq!(quotes[0].price, Money::from_minor(1187, "USD"));
+        assert_eq!(
+            (quotes[0].transit_days_min, quotes[0].transit_days_max),
+            (1, 5)
+        );
+        assert!(!quotes[0].guaranteed);
+        assert_eq!(
+            (quotes[1].transit_days_min, quotes[1].transit_days_max),
+            (2, 2)
+        );
+        assert!(quotes[1].guaranteed);
+    }
+
+    #[tokio::test]
+    async fn test_rate_shops_time_in_transit() {
+        let client = stub_client(Router::new().route(
+            "/api/rating/v2409/ShopTimeInTransit",
+            post(|Json(body): Json<Value>| async move {
+                let shipment = &body["RateRequest"]["Shipment"];
+                assert_eq!(shipment["ShipTo"]["Name"], "Jane Doe");
+                assert_eq!(shipment["NumOfPieces"], "2");
+                assert_eq!(shipment["Package"][0]["Dimensions"]["Length"], "8");
+                assert_eq!(shipment["Package"][1]["PackageWeight"]["Weight"], "4.5");
+                assert_eq!(shipment["ShipmentTotalWeight"]["Weight"], "5.2");
+                assert_eq!(
+                    shipment["DeliveryTimeInformation"]["Pickup"]["Date"],
+                    "20251124"
+                );
+
+                let mut response = shop_response();
+                response["RateResponse"]["RatedShipment"][1]["TimeInTransit"] = json!({
+                    "PickupDate": "20251124",
+                    "ServiceSummary": { "EstimatedArrival": {
+                        "Arrival": { "Date": "20251127", "Time": "233000" },
+                        "BusinessDaysInTransit": "3"
+                    } }
+                });
+                Json(response)
+            }),
+        ))
+        .await;
+
+        let shipment = Shipment {
+            ship_from: address("Sushi Prints", "Denver", "CO", "80202"),
+            ship_to: address("Jane Doe", "Austin", "TX", "78701"),
+            packages: vec![
+                PackageDimensions {
+                    length: 8.0,
+                    width: 6.0,
+                    height: 1.1,
+                    weight: 0.7,
+                },
+                PackageDimensions {
+                    length: 16.0,
+                    width: 13.0,
+                    height: 4.0,
+                    weight: 4.5,
+                },
+            ],
+            ship_date: NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(),
+            customs: None,
+        };
+        let quotes = Carrier::rate(&client, &shipment).await.unwrap();
+        assert_eq!(quotes.len(), 3);
+        assert_eq!(quotes[0].service_code, "03");
+        // Ground's usual 1-5 days narrow to what UPS says for this route
+        assert_eq!(
+            (quotes[0].transit_days_min, quotes[0].transit_days_max),
+            (3, 3)
+        );
+        assert_eq!(
+            quotes[0].arrival_date,
+            NaiveDate::from_ymd_opt(2025, 11, 27)
+        );
+        assert_eq!(quotes[1].arrival_date, None);
+    }
+
+    fn customs_invoice() -> CommercialInvoice {
+        let item = CustomsItem {
+            description: "Photographic prints, 8x10 matte".to_string(),
+            hs_code: "491191".to_string(),
+            quantity: 4,
+            unit_value: Money::from_minor(625, "USD"),
+            unit_weight_lbs: 0.06,
+        };
+        let date = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+        CommercialInvoice::new("ord_1", date, "USD", "US", vec![item]).unwrap()
+    }
+
+    fn abroad(shipment: &mut Shipment) {
+        shipment.ship_to = address("Jane Doe", "Toronto", "ON", "M5V 2T6");
+        shipment.ship_to.country = "CA".to_string();
+        shipment.customs = Some(customs_invoice());
+    }
+
+    #[tokio::test]
+    async fn test_rate_abroad_estimates_duties_and_taxes() {
+        let client = stub_client(Router::new().route(
+            "/api/rating/v2409/ShopTimeInTransit",
+            post(|Json(body): Json<Value>| async move {
+                let shipment = &body["RateRequest"]["Shipment"];
+                assert_eq!(shipment["InvoiceLineTotal"]["MonetaryValue"], "25.00");
+                assert_eq!(shipment["TaxInformationIndicator"], "");
+
+                let mut response = shop_response();
+                response["RateResponse"]["RatedShipment"][1]["TaxCharges"] = json!([
+                    { "Type": "GST", "MonetaryValue": "1.25" },
+                    { "Type": "DUTY", "MonetaryValue": "3.10" }
+                ]);
+                Json(response)
+            }),
+        ))
+        .await;
+
+        let mut shipment = Shipment {
+            ship_from: address("Sushi Prints", "Denver", "CO", "80202"),
+            ship_to: address("Jane Doe", "Austin", "TX", "78701"),
+            packages: vec![PackageDimensions {
+                length: 11.0,
+                width: 9.0,
+                height: 1.0,
+                weight: 0.6,
+            }],
+            ship_date: NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(),
+            customs: None,
+        };
+        abroad(&mut shipment);
+        let quotes = Carrier::rate(&client, &shipment).await.unwrap();
+        assert_eq!(quotes[0].service_code, "03");
+        assert_eq!(
+            quotes[0].duties_and_taxes,
+            Some(Money::from_minor(435, "USD"))
+        );
+        assert_eq!(quotes[1].duties_and_taxes, None);
+    }
+
+    #[test]
+    fn test_ship_request_files_commercial_invoice() {
+        let mut request = LabelRequest {
+            shipment: Shipment {
+                ship_from: address("Sushi Prints", "Denver", "CO", "80202"),
+                ship_to: address("Jane Doe", "Austin", "TX", "78701"),
+                packages: vec![PackageDimensions {
+                    length: 11.0,
+                    width: 9.0,
+                    height: 1.0,
+                    weight: 0.6,
+                }],
+                ship_date: NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(),
+                customs: None,
+            },
+            service_code: "07".to_string(),
+            format: LabelFormat::Gif,
+            reference: "ord_1".to_string(),
+        };
+        let domestic = ship_request(&request, "A1B2C3").unwrap();
+        assert!(
+            domestic
+                .shipment_request
+                .shipment
+                .shipment_service_options
+                .is_none()
+        );
+
+        abroad(&mut request.shipment);
+        let shipment = ship_request(&request, "A1B2C3")
+            .unwrap()
+            .shipment_request
+            .shipment;
+        assert_eq!(shipment.invoice_line_total.unwrap().monetary_value, "25.00");
+        let forms = shipment
+            .shipment_service_options
+            .and_then(|options| options.international_forms)
+            .expect("Forms should be filed");
+        assert_eq!(forms.form_type, ["01"]);
+        assert_eq!(forms.invoice_date, "20251124");
+        assert_eq!(forms.contacts.sold_to.address.country_code, "CA");
+        assert_eq!(forms.product[0].commodity_code, "491191");
+        assert_eq!(forms.product[0].unit.number, "4");
+        assert_eq!(forms.product[0].unit.value, "6.25");
+    }
+
+    #[tokio::test]
+    async fn test_validate_address_candidates() {
+        let client = stub_client(Router::new().route(
+            "/api/addressvalidation/v2/1",
+            post(|| async {
+                Json(json!({ "XAVResponse": {
+                    "Response": { "ResponseStatus": { "Code": "1", "Description": "Success" } },
+                    "AmbiguousAddressIndicator": "",
+                    "AddressClassification": { "Code": "0", "Description": "Unknown" },
+                    "Candidate": [{
+                        "AddressClassification": { "Code": "2", "Description": "Residential" },
+                        "AddressKeyFormat": {
+                            "AddressLine": ["123 MAIN ST"],
+                            "PoliticalDivision2": "DENVER",
+                            "PoliticalDivision1": "CO",
+                            "PostcodePrimaryLow": "80202",
+                            "PostcodeExtendedLow": "1234",
+                            "CountryCode": "US"
+                        }
+                    }]
+                } }))
+            }),
+        ))
+        .await;
+
+        let given = address("Jane Doe", "Denver", "CO", "80202");
+        let validation = Carrier::validate_address(&client, &given).await.unwrap();
+        assert_eq!(validation.result, AddressValidationResult::Ambiguous);
+        assert_eq!(validation.classification, AddressClassification::Unknown);
+        assert_eq!(validation.candidates.len(), 1);
+
+        let candidate = &validation.candidates[0];
+        assert_eq!(candidate.classification, AddressClassification::Residential);
+        assert_eq!(candidate.address.name, "Jane Doe");
+        assert_eq!(candidate.address.line1, "123 MAIN ST");
+        assert_eq!(candidate.address.postal_code, "80202-1234");
+    }
+
+    async fn ship(Json(body): Json<Value>) -> Json<Value> {
+        let request = &body["ShipmentRequest"];
+        let shipment = &request["Shipment"];
+        assert_eq!(shipment["Shipper"]["ShipperNumber"], "A1B2C3");
+        assert_eq!(shipment["ShipTo"]["Phone"]["Number"], "5552345678");
+        assert_eq!(shipment["Service"]["Code"], "03");
+        assert_eq!(
+            shipment["Package"][0]["ReferenceNumber"][0]["Value"],
+            "ord_1"
+        );
+        assert_eq!(
+            request["LabelSpecification"]["LabelImageFormat"]["Code"],
+            "ZPL"
+        );
+        assert_eq!(
+            request["LabelSpecification"]["LabelStockSize"]["Height"],
+            "6"
+        );
+        // A single package comes back as an object rather than an array
+        Json(json!({ "ShipmentResponse": {
+            "Response": { "ResponseStatus": { "Code": "1", "Description": "Success" } },
+            "ShipmentResults": {
+                "ShipmentCharges": { "TotalCharges": { "CurrencyCode": "USD", "MonetaryValue": "11.87" } },
+                "ShipmentIdentificationNumber": "1ZA1B2C30312345678",
+                "PackageResults": {
+                    "TrackingNumber": "1ZA1B2C30312345678",
+                    "ShippingLabel": {
+                        "ImageFormat": { "Code": "ZPL" },
+                        "GraphicImage": general_purpose::STANDARD.encode("^XA^FDlabel^FS^XZ")
+                    }
+                }
+            }
+        } }))
+    }
+
+    async fn void(Path(shipment_id): Path<String>) -> (StatusCode, Json<Value>) {
+        if shipment_id != "1ZA1B2C30312345678" {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "response": { "errors": [
+                    { "code": "190117", "message": "Shipment not found within the void period" }
+                ] } })),
+            );
+        }
+        (
+            StatusCode::OK,
+            Json(json!({ "VoidShipmentResponse": {
+                "Response": { "ResponseStatus": { "Code": "1", "Description": "Success" } },
+                "SummaryResult": { "Status": { "Code": "1", "Description": "Voided" } }
+            } })),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_label_lifecycle() {
+        let client = stub_client(
+            Router::new()
+                .route("/api/shipments/v2409/ship", post(ship))
+                .route(
+                    "/api/shipments/v2409/void/cancel/{shipment_id}",
+                    delete(void),
+                ),
+        )
+        .await;
+
+        let mut ship_to = address("Jane Doe", "Austin", "TX", "78701");
+        ship_to.phone = Some("5552345678".to_string());
+        let mut request = LabelRequest {
+            shipment: Shipment {
+                ship_from: address("Sushi Prints", "Denver", "CO", "80202"),
+                ship_to,
+                packages: vec![PackageDimensions {
+                    length: 8.0,
+                    width: 6.0,
+                    height: 1.1,
+                    weight: 0.7,
+                }],
+                ship_date: NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(),
+                customs: None,
+            },
+            service_code: "03".to_string(),
+            format: LabelFormat::Zpl,
+            reference: "ord_1".to_string(),
+        };
+
+        let label = client.create_label(&request).await.unwrap();
+        assert_eq!(label.tracking_number, "1ZA1B2C30312345678");
+        assert_eq!(label.data, b"^XA^FDlabel^FS^XZ");
+        assert_eq!(label.price, Some(Money::from_minor(1187, "USD")));
+
+        client.void_label(&label.tracking_number).await.unwrap();
+        let err = client.void_label("1Z0000").await.unwrap_err();
+        assert!(err.to_string().contains("190117"));
+
+        // UPS doesn't print PDF labels
+        request.format = LabelFormat::Pdf;
+        let err = client.create_label(&request).await.unwrap_err();
+        assert!(matches!(err, CarrierError::Unsupported(_)));
+    }
+
+    fn activity(status_type: &str, description: &str, city: &str, gmt: (&str, &str)) -> Value {
+        json!({
+            "location": { "address": { "city": city, "stateProvince": "TX", "countryCode": "US" } },
+            "status": { "type": status_type, "description": description, "code": "XX" },
+            "date": gmt.0,
+            "time": "000000",
+            "gmtDate": gmt.0,
+            "gmtTime": gmt.1
+        })
+    }
+
+    async fn track(Path(tracking_number): Path<String>) -> (StatusCode, Json<Value>) {
+        if tracking_number != "1ZA1B2C30312345678" {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(json!({ "response": { "errors": [
+                    { "code": "TW0001", "message": "Tracking Information Not Found" }
+                ] } })),
+            );
+        }
+        (
+            StatusCode::OK,
+            Json(json!({ "trackResponse": { "shipment": [{
+                "inquiryNumber": tracking_number,
+                "package": [{
+                    "trackingNumber": tracking_number,
+                    "deliveryDate": [{ "type": "SDD", "date": "20251003" }],
+                    "currentStatus": { "code": "072", "description": "Out For Delivery Today" },
+                    "activity": [
+                        activity("O", "Out For Delivery Today", "Austin", ("20251003", "13:02:00")),
+                        activity("I", "Arrived at Facility", "Austin", ("20251002", "22:40:00")),
+                        activity("M", "Shipper created a label", "", ("20250930", "18:15:00")),
+                    ]
+                }]
+            }] } })),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_track() {
+        let client =
+            stub_client(Router::new().route("/api/track/v1/details/{tracking_number}", get(track)))
+                .await;
+
+        let tracking = client.track("1ZA1B2C30312345678").await.unwrap();
+        assert_eq!(tracking.status, TrackingStatus::OutForDelivery);
+        assert_eq!(
+            tracking.estimated_delivery,
+            NaiveDate::from_ymd_opt(2025, 10, 3)
+        );
+        assert_eq!(tracking.events.len(), 3);
+        assert_eq!(
+            tracking.events[0].location.as_deref(),
+            Some("Austin, TX, US")
+        );
+        assert_eq!(
+            tracking.events[0].timestamp.to_rfc3339(),
+            "2025-10-03T13:02:00+00:00"
+        );
+        // A location without a city keeps the rest
+        assert_eq!(tracking.events[2].location.as_deref(), Some("TX, US"));
+        assert_eq!(tracking.events[2].status, TrackingStatus::PreTransit);
+
+        let err = client.track("1Z0000").await.unwrap_err();
+        assert!(matches!(err, CarrierError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_tracking_delivered() {
+        let response: UPSTrackResponse = serde_json::from_value(json!({ "trackResponse": {
+            "shipment": [{
+                "inquiryNumber": "1ZA1B2C30312345678",
+                "package": [{
+                    "trackingNumber": "1ZA1B2C30312345678",
+                    "deliveryDate": [{ "type": "DEL", "date": "20251003" }],
+                    "activity": [{
+                        "status": { "type": "D", "description": "DELIVERED", "code": "KB" },
+                        "date": "20251003",
+                        "time": "143100"
+                    }]
+                }]
+            }]
+        } }))
+        .unwrap();
+
+        let tracking = tracking_from_response(response).unwrap();
+        assert_eq!(tracking.status, TrackingStatus::Delivered);
+        assert_eq!(tracking.estimated_delivery, None);
+        // Without a UTC time the local time is used
+        assert_eq!(
+            tracking.events[0].timestamp.to_rfc3339(),
+            "2025-10-03T14:31:00+00:00"
+        );
+        assert_eq!(tracking.events[0].location, None);
+    }
+}
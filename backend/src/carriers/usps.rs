@@ -0,0 +1,832 @@
+//! USPS APIs v3 integration
+//!
+//! USPS only ships domestically here: other destinations get no quotes.
+//! Prices come from the Domestic Prices API. Labels are paid for by the
+//! configured business account, through a payment authorization token
+//! requested alongside each label.
+
+use async_trait::async_trait;
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+use super::{
+    AddressCandidate, AddressClassification, AddressValidation, Carrier, CarrierError,
+    CarrierService, LabelFormat, LabelRequest, Shipment, ShipmentAddress, ShippingLabel,
+    ShippingQuote, TokenCache, TokenResponse, TrackingEvent, TrackingInfo, TrackingStatus,
+    decimal_money, event_location, parse_timestamp,
+};
+use crate::types::AddressValidationResult;
+
+/// USPS test environment API base URL
+pub const TEST_API_URL: &str = "https://apis-tem.usps.com";
+
+/// USPS services we sell, keyed by mail class
+const SERVICES: &[CarrierService] = &[
+    CarrierService {
+        shipping_option: "USPS_GroundAdvantage",
+        code: "USPS_GROUND_ADVANTAGE",
+        description: "USPS Ground Advantage",
+        transit_days: (2, 5),
+    },
+    CarrierService {
+        shipping_option: "USPS_Priority",
+        code: "PRIORITY_MAIL",
+        description: "USPS Priority Mail",
+        transit_days: (1, 3),
+    },
+    CarrierService {
+        shipping_option: "USPS_PriorityExpress",
+        code: "PRIORITY_MAIL_EXPRESS",
+        description: "USPS Priority Mail Express",
+        transit_days: (1, 2),
+    },
+];
+
+/// USPS API app settings
+#[derive(Debug, Clone)]
+pub struct UspsConfig {
+    /// USPS API base URL (test environment or `https://apis.usps.com`)
+    pub api_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Business account that pays for labels; labels can't be bought without it
+    pub payment_account: Option<UspsPaymentAccount>,
+}
+
+/// USPS business account identifiers
+#[derive(Debug, Clone)]
+pub struct UspsPaymentAccount {
+    /// Customer registration ID
+    pub crid: String,
+    /// Mailer ID
+    pub mid: String,
+    /// Enterprise Payment System account number
+    pub account_number: String,
+}
+
+/// Carrier backed by the USPS REST APIs
+#[derive(Debug)]
+pub struct UspsCarrier {
+    config: UspsConfig,
+    client: reqwest::Client,
+    token: TokenCache,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddressResponse {
+    firm: Option<String>,
+    address: UspsAddress,
+    additional_info: Option<AdditionalInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UspsAddress {
+    street_address: String,
+    secondary_address: Option<String>,
+    city: String,
+    state: String,
+    #[serde(rename = "ZIPCode")]
+    zip_code: String,
+    #[serde(rename = "ZIPPlus4")]
+    zip_plus4: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdditionalInfo {
+    /// `Y` confirmed, `S`/`D` missing or unknown secondary address, `N` not deliverable
+    #[serde(rename = "DPVConfirmation")]
+    dpv_confirmation: Option<String>,
+    business: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RatesResponse {
+    rate_options: Vec<RateOption>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RateOption {
+    total_base_price: f64,
+    rates: Vec<Rate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rate {
+    mail_class: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PaymentAuthorizationResponse {
+    payment_authorization_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LabelResponse {
+    label_metadata: LabelMetadata,
+    label_image: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LabelMetadata {
+    tracking_number: String,
+    postage: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrackingResponse {
+    tracking_number: String,
+    status_category: Option<String>,
+    expected_delivery_time_stamp: Option<String>,
+    #[serde(default)]
+    tracking_events: Vec<UspsTrackingEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UspsTrackingEvent {
+    event_type: String,
+    event_timestamp: String,
+    event_city: Option<String>,
+    event_state: Option<String>,
+    event_country: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: UspsErrorBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct UspsErrorBody {
+    message: Option<String>,
+    #[serde(default)]
+    errors: Vec<UspsErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UspsErrorDetail {
+    detail: Option<String>,
+}
+
+impl UspsCarrier {
+    pub fn new(config: UspsConfig) -> Self {
+        UspsCarrier {
+            config,
+            client: reqwest::Client::new(),
+            token: TokenCache::default(),
+        }
+    }
+
+    /// Get a cached access token, fetching a new one when it's about to expire
+    async fn access_token(&self) -> Result<String, CarrierError> {
+        self.token
+            .get(async {
+                let response = self
+                    .client
+                    .post(format!("{}/oauth2/v3/token", self.config.api_url))
+                    .json(&json!({
+                        "grant_type": "client_credentials",
+                        "client_id": self.config.client_id,
+                        "client_secret": self.config.client_secret,
+                    }))
+                    .send()
+                    .await?;
+                let token: TokenResponse = Self::parse_response(response).await?;
+                Ok((token.access_token, Duration::from_secs(token.expires_in)))
+            })
+            .await
+    }
+
+    /// Authorize the business account to pay for a label
+    async fn payment_token(&self) -> Result<String, CarrierError> {
+        let account = self.config.payment_account.as_ref().ok_or_else(|| {
+            CarrierError::Config(
+                "USPS labels need USPS_CRID, USPS_MID and USPS_ACCOUNT_NUMBER".to_string(),
+            )
+        })?;
+        let response = self
+            .client
+            .post(format!(
+                "{}/payments/v3/payment-authorization",
+                self.config.api_url
+            ))
+            .bearer_auth(self.access_token().await?)
+            .json(&json!({
+                "roles": [
+                    {
+                        "roleName": "PAYER",
+                        "CRID": account.crid,
+                        "accountType": "EPS",
+                        "accountNumber": account.account_number,
+                    },
+                    {
+                        "roleName": "LABEL_OWNER",
+                        "CRID": account.crid,
+                        "MID": account.mid,
+                        "manifestMID": account.mid,
+                    },
+                ]
+            }))
+            .send()
+            .await?;
+        let authorization: PaymentAuthorizationResponse = Self::parse_response(response).await?;
+        Ok(authorization.payment_authorization_token)
+    }
+
+    /// Decode a successful response, or turn a USPS error body into a `CarrierError`
+    async fn parse_response<T: DeserializeOwned>(
+        response: reqwest::Response,
+    ) -> Result<T, CarrierError> {
+        let status = response.status();
+        let text = response.text().await?;
+        if status.is_success() {
+            return serde_json::from_str(&text).map_err(|e| CarrierError::Parse(e.to_string()));
+        }
+
+        let error = serde_json::from_str::<ErrorResponse>(&text)
+            .ok()
+            .map(|e| e.error);
+        let detail = error
+            .as_ref()
+            .and_then(|e| e.errors.first())
+            .and_then(|e| e.detail.clone());
+        let message = error.and_then(|e| e.message).unwrap_or(text);
+        let message = match detail {
+            Some(detail) => format!("{} ({})", message, detail),
+            None => message,
+        };
+        Err(match status.as_u16() {
+            404 => CarrierError::NotFound(message),
+            status => CarrierError::Api(format!("USPS error ({}): {}", status, message)),
+        })
+    }
+}
+
+/// USPS takes five-digit ZIP codes
+fn zip5(postal_code: &str) -> &str {
+    postal_code.get(..5).unwrap_or(postal_code)
+}
+
+/// Address as the Labels API takes it
+fn label_address(address: &ShipmentAddress) -> Value {
+    json!({
+        "firstName": address.name,
+        "firm": address.company,
+        "streetAddress": address.line1,
+        "secondaryAddress": address.line2,
+        "city": address.city,
+        "state": address.state,
+        "ZIPCode": zip5(&address.postal_code),
+        "phone": address.phone,
+    })
+}
+
+/// Map USPS tracking status text, which is also used for event types
+fn tracking_status(text: &str) -> TrackingStatus {
+    let text = text.to_lowercase();
+    if text.contains("out for delivery") {
+        TrackingStatus::OutForDelivery
+    } else if text.contains("delivered") {
+        TrackingStatus::Delivered
+    } else if text.contains("label created") || text.contains("pre-shipment") {
+        TrackingStatus::PreTransit
+    } else if text.contains("alert") || text.contains("undeliverable") || text.contains("return") {
+        TrackingStatus::Exception
+    } else if text.is_empty() {
+        TrackingStatus::Unknown
+    } else {
+        TrackingStatus::InTransit
+    }
+}
+
+#[async_trait]
+impl Carrier for UspsCarrier {
+    fn name(&self) -> &str {
+        "usps"
+    }
+
+    fn services(&self) -> &[CarrierService] {
+        SERVICES
+    }
+
+    async fn validate_address(
+        &self,
+        address: &ShipmentAddress,
+    ) -> Result<AddressValidation, CarrierError> {
+        if address.country != "US" {
+            return Err(CarrierError::Unsupported(
+                "USPS only validates US addresses".to_string(),
+            ));
+        }
+        let mut query = vec![
+            ("streetAddress", address.line1.as_str()),
+            ("city", address.city.as_str()),
+            ("state", address.state.as_str()),
+            ("ZIPCode", zip5(&address.postal_code)),
+        ];
+        if let Some(line2) = &address.line2 {
+            query.push(("secondaryAddress", line2));
+        }
+        let response = self
+            .client
+            .get(format!("{}/addresses/v3/address", self.config.api_url))
+            .bearer_auth(self.access_token().await?)
+            .query(&query)
+            .send()
+            .await?;
+        let found: AddressResponse = match Self::parse_response(response).await {
+            Ok(found) => found,
+            Err(CarrierError::NotFound(_)) => {
+                return Ok(AddressValidation {
+                    result: AddressValidationResult::NoCandidates,
+                    classification: AddressClassification::Unknown,
+                    candidates: Vec::new(),
+                });
+            }
+            Err(err) => return Err(err),
+        };
+
+        let info = found.additional_info.as_ref();
+        let result = match info.and_then(|i| i.dpv_confirmation.as_deref()) {
+            Some("Y") => AddressValidationResult::Valid,
+            Some("S" | "D") => AddressValidationResult::Ambiguous,
+            _ => AddressValidationResult::Invalid,
+        };
+        let classification = match info.and_then(|i| i.business.as_deref()) {
+            Some("Y") => AddressClassification::Commercial,
+            Some("N") => AddressClassification::Residential,
+            _ => AddressClassification::Unknown,
+        };
+        let standardized = found.address;
+        let postal_code = match standardized.zip_plus4.filter(|plus4| !plus4.is_empty()) {
+            Some(plus4) => format!("{}-{}", standardized.zip_code, plus4),
+            None => standardized.zip_code,
+        };
+        let candidate = ShipmentAddress {
+            name: address.name.clone(),
+            company: found.firm.or_else(|| address.company.clone()),
+            phone: address.phone.clone(),
+            line1: standardized.street_address,
+            line2: standardized
+                .secondary_address
+                .filter(|line| !line.is_empty()),
+            city: standardized.city,
+            state: standardized.state,
+            postal_code,
+            country: "US".to_string(),
+        };
+        Ok(AddressValidation {
+            result,
+            classification,
+            candidates: vec![AddressCandidate {
+                address: candidate,
+                classification,
+            }],
+        })
+    }
+
+    async fn rate(&self, shipment: &Shipment) -> Result<Vec<ShippingQuote>, CarrierError> {
+        // USPS labels are one package each, see `create_label`
+        let [package] = shipment.packages.as_slice() else {
+            return Ok(Vec::new());
+        };
+        if shipment.ship_to.country != "US" {
+            return Ok(Vec::new());
+        }
+        let mut body = json!({
+            "originZIPCode": zip5(&shipment.ship_from.postal_code),
+            "destinationZIPCode": zip5(&shipment.ship_to.postal_code),
+            "weight": package.weight,
+            "length": package.length,
+            "width": package.width,
+            "height": package.height,
+            "mailClasses": SERVICES.iter().map(|s| s.code).collect::<Vec<_>>(),
+            "priceType": "RETAIL",
+            "mailingDate": shipment.ship_date.to_string(),
+        });
+        if let Some(account) = &self.config.payment_account {
+            body["priceType"] = json!("COMMERCIAL");
+            body["accountType"] = json!("EPS");
+            body["accountNumber"] = json!(account.account_number);
+        }
+        let response = self
+            .client
+            .post(format!(
+                "{}/prices/v3/total-rates/search",
+                self.config.api_url
+            ))
+            .bearer_auth(self.access_token().await?)
+            .json(&body)
+            .send()
+            .await?;
+        let rates: RatesResponse = Self::parse_response(response).await?;
+
+        // USPS can offer a class several ways; keep the cheapest of each
+        let mut quotes: Vec<ShippingQuote> = Vec::new();
+        for option in rates.rate_options {
+            let Some(rate) = option.rates.first() else {
+                continue;
+            };
+            let Some(service) = SERVICES.iter().find(|s| s.code == rate.mail_class) else {
+                continue;
+            };
+            let quote = ShippingQuote::new(
+                self.name(),
+                service,
+                decimal_money(option.total_base_price, "USD")?,
+                None,
+            );
+            match quotes
+                .iter_mut()
+                .find(|q| q.service_code == quote.service_code)
+            {
+                Some(existing) if existing.price.amount_minor() <= quote.price.amount_minor() => {}
+                Some(existing) => *existing = quote,
+                None => quotes.push(quote),
+            }
+        }
+        quotes.sort_by_key(|quote| quote.price.amount_minor());
+        Ok(quotes)
+    }
+
+    async fn create_label(&self, request: &LabelRequest) -> Result<ShippingLabel, CarrierError> {
+        let image_type = match request.format {
+            LabelFormat::Pdf => "PDF",
+            LabelFormat::Zpl => "ZPL203DPI",
+            LabelFormat::Gif => {
+                return Err(CarrierError::Unsupported(
+                    "USPS labels can't be printed as GIF".to_string(),
+                ));
+            }
+        };
+        let [package] = request.shipment.packages.as_slice() else {
+            return Err(CarrierError::Unsupported(
+                "USPS labels can only be bought for a single package".to_string(),
+            ));
+        };
+        let payment_token = self.payment_token().await?;
+        let response = self
+            .client
+            .post(format!("{}/labels/v3/label", self.config.api_url))
+            .bearer_auth(self.access_token().await?)
+            .header("X-Payment-Authorization-Token", payment_token)
+            .header("Accept", "application/vnd.usps.labels+json")
+            .json(&json!({
+                "imageInfo": { "imageType": image_type, "labelType": "4X6LABEL" },
+                "toAddress": label_address(&request.shipment.ship_to),
+                "fromAddress": label_address(&request.shipment.ship_from),
+                "packageDescription": {
+                    "mailClass": request.service_code,
+                    "rateIndicator": "SP",
+                    "weightUOM": "lb",
+                    "weight": package.weight,
+                    "dimensionsUOM": "in",
+                    "length": package.length,
+                    "width": package.width,
+                    "height": package.height,
+                    "processingCategory": "MACHINABLE",
+                    "mailingDate": request.shipment.ship_date.to_string(),
+                    "destinationEntryFacilityType": "NONE",
+                    "customerReference": [{ "referenceNumber": request.reference }],
+                },
+            }))
+            .send()
+            .await?;
+        let label: LabelResponse = Self::parse_response(response).await?;
+
+        Ok(ShippingLabel {
+            tracking_number: label.label_metadata.tracking_number,
+            format: request.format,
+            data: general_purpose::STANDARD
+                .decode(&label.label_image)
+                .map_err(|e| CarrierError::Parse(e.to_string()))?,
+            price: label
+                .label_metadata
+                .postage
+                .map(|postage| decimal_money(postage, "USD"))
+                .transpose()?,
+        })
+    }
+
+    async fn track(&self, tracking_number: &str) -> Result<TrackingInfo, CarrierError> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/tracking/v3/tracking/{}",
+                self.config.api_url, tracking_number
+            ))
+            .bearer_auth(self.access_token().await?)
+            .query(&[("expand", "DETAIL")])
+            .send()
+            .await?;
+        let tracking: TrackingResponse = Self::parse_response(response).await?;
+
+        let events = tracking
+            .tracking_events
+            .iter()
+            .filter_map(|event| {
+                Some(TrackingEvent {
+                    status: tracking_status(&event.event_type),
+                    description: event.event_type.clone(),
+                    location: event_location(&[
+                        event.event_city.as_deref(),
+                        event.event_state.as_deref(),
+                        event.event_country.as_deref(),
+                    ]),
+                    timestamp: parse_timestamp(&event.event_timestamp)?,
+                })
+            })
+            .collect();
+        Ok(TrackingInfo {
+            tracking_number: tracking.tracking_number,
+            status: tracking_status(tracking.status_category.as_deref().unwrap_or_default()),
+            estimated_delivery: tracking
+                .expected_delivery_time_stamp
+                .as_deref()
+                .and_then(parse_timestamp)
+                .map(|timestamp| timestamp.date_naive()),
+            events,
+        })
+    }
+
+    async fn void_label(&self, tracking_number: &str) -> Result<(), CarrierError> {
+        let payment_token = self.payment_token().await?;
+        let response = self
+            .client
+            .delete(format!(
+                "{}/labels/v3/label/{}",
+                self.config.api_url, tracking_number
+            ))
+            .bearer_auth(self.access_token().await?)
+            .header("X-Payment-Authorization-Token", payment_token)
+            .send()
+            .await?;
+        let _: Value = Self::parse_response(response).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        money::Money,
+        test_support::{shipment_address, spawn_stub_server},
+        types::PackageDimensions,
+    };
+    use axum::{
+        Json, Router,
+        extract::{Path, State},
+        http::{HeaderMap, StatusCode},
+        routing::{get, post},
+    };
+    use chrono::NaiveDate;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    #[derive(Debug, Default)]
+    struct StubUsps {
+        token_requests: AtomicUsize,
+    }
+
+    type Stub = State<Arc<StubUsps>>;
+    type StubResult = Result<Json<Value>, (StatusCode, Json<Value>)>;
+
+    fn authorized(headers: &HeaderMap) -> Result<(), (StatusCode, Json<Value>)> {
+        match headers.get("authorization").and_then(|v| v.to_str().ok()) {
+            Some("Bearer usps-token") => Ok(()),
+            _ => Err((
+                StatusCode::UNAUTHORIZED,
+                Json(json!({ "error": { "code": "401", "message": "Unauthorized" } })),
+            )),
+        }
+    }
+
+    async fn token(State(stub): Stub) -> Json<Value> {
+        stub.token_requests.fetch_add(1, Ordering::SeqCst);
+        Json(json!({ "access_token": "usps-token", "token_type": "Bearer", "expires_in": 28799 }))
+    }
+
+    async fn rates(headers: HeaderMap, Json(body): Json<Value>) -> StubResult {
+        authorized(&headers)?;
+        assert_eq!(body["originZIPCode"], "80202");
+        assert_eq!(body["destinationZIPCode"], "78701");
+        assert_eq!(body["priceType"], "COMMERCIAL");
+        let option = |class: &str, price: f64| json!({ "totalBasePrice": price, "rates": [{ "mailClass": class, "price": price }] });
+        Ok(Json(json!({ "rateOptions": [
+            option("PRIORITY_MAIL", 10.4),
+            option("USPS_GROUND_ADVANTAGE", 6.85),
+            option("PRIORITY_MAIL", 9.35),
+            option("PARCEL_SELECT", 5.0),
+        ] })))
+    }
+
+    async fn address(headers: HeaderMap) -> StubResult {
+        authorized(&headers)?;
+        Ok(Json(json!({
+            "firm": null,
+            "address": {
+                "streetAddress": "123 MAIN ST",
+                "secondaryAddress": "",
+                "city": "AUSTIN",
+                "state": "TX",
+                "ZIPCode": "78701",
+                "ZIPPlus4": "3021"
+            },
+            "additionalInfo": { "DPVConfirmation": "Y", "business": "N" }
+        })))
+    }
+
+    async fn payment_authorization(headers: HeaderMap, Json(body): Json<Value>) -> StubResult {
+        authorized(&headers)?;
+        assert_eq!(body["roles"][0]["accountNumber"], "1000123456");
+        Ok(Json(json!({ "paymentAuthorizationToken": "pay-token" })))
+    }
+
+    async fn label(headers: HeaderMap, Json(body): Json<Value>) -> StubResult {
+        authorized(&headers)?;
+        assert_eq!(headers["x-payment-authorization-token"], "pay-token");
+        assert_eq!(body["packageDescription"]["mailClass"], "PRIORITY_MAIL");
+        assert_eq!(body["imageInfo"]["imageType"], "PDF");
+        Ok(Json(json!({
+            "labelMetadata": { "trackingNumber": "9405511899223197428490", "postage": 9.35 },
+            "labelImage": general_purpose::STANDARD.encode(b"%PDF-1.7 label")
+        })))
+    }
+
+    async fn void(headers: HeaderMap, Path(tracking_number): Path<String>) -> StubResult {
+        authorized(&headers)?;
+        assert_eq!(headers["x-payment-authorization-token"], "pay-token");
+        assert_eq!(tracking_number, "9405511899223197428490");
+        Ok(Json(
+            json!({ "trackingNumber": tracking_number, "status": "CANCELED" }),
+        ))
+    }
+
+    async fn tracking(headers: HeaderMap, Path(tracking_number): Path<String>) -> StubResult {
+        authorized(&headers)?;
+        if tracking_number != "9405511899223197428490" {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(json!({ "error": { "code": "404", "message": "Tracking number not found" } })),
+            ));
+        }
+        Ok(Json(json!({
+            "trackingNumber": tracking_number,
+            "statusCategory": "Out for Delivery",
+            "expectedDeliveryTimeStamp": "2025-10-02T20:00:00Z",
+            "trackingEvents": [
+                {
+                    "eventType": "Out for Delivery",
+                    "eventTimestamp": "2025-10-02T08:12:00",
+                    "eventCity": "AUSTIN",
+                    "eventState": "TX",
+                    "eventCountry": ""
+                },
+                {
+                    "eventType": "Shipping Label Created, USPS Awaiting Item",
+                    "eventTimestamp": "2025-09-30T16:40:00",
+                    "eventCity": "DENVER",
+                    "eventState": "CO"
+                }
+            ]
+        })))
+    }
+
+    async fn stub_carrier() -> (UspsCarrier, Arc<StubUsps>) {
+        let stub = Arc::new(StubUsps::default());
+        let router = Router::new()
+            .route("/oauth2/v3/token", post(token))
+            .route("/prices/v3/total-rates/search", post(rates))
+            .route("/addresses/v3/address", get(address))
+            .route(
+                "/payments/v3/payment-authorization",
+                post(payment_authorization),
+            )
+            .route("/labels/v3/label", post(label))
+            .route(
+                "/labels/v3/label/{tracking_number}",
+                axum::routing::delete(void),
+            )
+            .route("/tracking/v3/tracking/{tracking_number}", get(tracking))
+            .with_state(stub.clone());
+        let carrier = UspsCarrier::new(UspsConfig {
+            api_url: spawn_stub_server(router).await,
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            payment_account: Some(UspsPaymentAccount {
+                crid: "56982563".to_string(),
+                mid: "903412345".to_string(),
+                account_number: "1000123456".to_string(),
+            }),
+        });
+        (carrier, stub)
+    }
+
+    fn shipment(country: &str) -> Shipment {
+        let address = |name: &str, city: &str, state: &str, postal_code: &str| ShipmentAddress {
+            name: name.to_string(),
+            city: city.to_string(),
+            state: state.to_string(),
+            postal_code: postal_code.to_string(),
+            country: country.to_string(),
+            ..shipment_address()
+        };
+        Shipment {
+            ship_from: address("Sushi Prints", "Denver", "CO", "80202-1234"),
+            ship_to: address("Jane Doe", "Austin", "TX", "78701"),
+            packages: vec![PackageDimensions {
+                length: 8.0,
+                width: 6.0,
+                height: 1.1,
+                weight: 0.7,
+            }],
+            ship_date: NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(),
+            customs: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate() {
+        let (carrier, stub) = stub_carrier().await;
+
+        let quotes = carrier.rate(&shipment("US")).await.unwrap();
+        let options: Vec<&str> = quotes.iter().map(|q| q.shipping_option.as_str()).collect();
+        assert_eq!(options, ["USPS_GroundAdvantage", "USPS_Priority"]);
+        // The cheaper of the two Priority Mail options wins
+        assert_eq!(quotes[1].price, Money::from_minor(935, "USD"));
+        assert_eq!(quotes[1].carrier, "usps");
+
+        // USPS isn't asked about international destinations
+        assert!(carrier.rate(&shipment("CA")).await.unwrap().is_empty());
+        assert_eq!(stub.token_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_validate_address() {
+        let (carrier, _) = stub_carrier().await;
+
+        let given = shipment("US").ship_to;
+        let validation = carrier.validate_address(&given).await.unwrap();
+        assert_eq!(validation.result, AddressValidationResult::Valid);
+        assert_eq!(
+            validation.classification,
+            AddressClassification::Residential
+        );
+        let candidate = &validation.candidates[0].address;
+        assert_eq!(candidate.line1, "123 MAIN ST");
+        assert_eq!(candidate.line2, None);
+        assert_eq!(candidate.postal_code, "78701-3021");
+    }
+
+    #[tokio::test]
+    async fn test_label_lifecycle() {
+        let (carrier, _) = stub_carrier().await;
+
+        let label = carrier
+            .create_label(&LabelRequest {
+                shipment: shipment("US"),
+                service_code: "PRIORITY_MAIL".to_string(),
+                format: LabelFormat::Pdf,
+                reference: "ord_20250930_0001".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(label.tracking_number, "9405511899223197428490");
+        assert_eq!(label.data, b"%PDF-1.7 label");
+        assert_eq!(label.price, Some(Money::from_minor(935, "USD")));
+
+        carrier.void_label(&label.tracking_number).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_track() {
+        let (carrier, _) = stub_carrier().await;
+
+        let tracking = carrier.track("9405511899223197428490").await.unwrap();
+        assert_eq!(tracking.status, TrackingStatus::OutForDelivery);
+        assert_eq!(
+            tracking.estimated_delivery,
+            Some(chrono::NaiveDate::from_ymd_opt(2025, 10, 2).unwrap())
+        );
+        assert_eq!(tracking.events.len(), 2);
+        assert_eq!(tracking.events[0].location.as_deref(), Some("AUSTIN, TX"));
+        assert_eq!(tracking.events[1].status, TrackingStatus::PreTransit);
+
+        let err = carrier.track("9400000000000000000000").await.unwrap_err();
+        assert!(matches!(err, CarrierError::NotFound(_)));
+    }
+}
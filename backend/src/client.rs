@@ -4,22 +4,41 @@ use crate::{
     config::UpsConfig,
     error::{Result, UpsError},
     models::{
-        address::Address,
         ups_api_response::UPSApiResponse,
+        ups_error::UPSErrorResponse,
         ups_rate_request::*,
         ups_rate_response::UPSRateResponse,
         ups_request::{AddressKeyFormat, UPSAddressValidationRequest, XAVRequest},
+        ups_ship_request::UPSShipRequest,
+        ups_ship_response::{UPSShipResponse, UPSVoidResponse},
+        ups_track_response::UPSTrackResponse,
     },
-    types::{AddressValidationResult, PackageDimensions, ShippingRateRequest, UpsServiceCode},
+    types::{AddressValidationResult, ShippingRateRequest},
 };
 use base64::{Engine as _, engine::general_purpose};
+use reqwest::StatusCode;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+/// Refresh the OAuth token this long before UPS says it expires
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// Token lifetime to assume when the OAuth response has no `expires_in`
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(60 * 60);
 
 /// Main UPS API client
+///
+/// Clones share the OAuth token cache, so the token is fetched once and
+/// refreshed shortly before it expires.
 #[derive(Debug, Clone)]
 pub struct UpsClient {
     config: UpsConfig,
     client: reqwest::Client,
     debug: bool,
+    token: Arc<Mutex<Option<(String, Instant)>>>, // access token, refresh deadline
 }
 
 impl UpsClient {
@@ -29,6 +48,7 @@ impl UpsClient {
             config,
             client: reqwest::Client::new(),
             debug: false,
+            token: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -38,8 +58,59 @@ impl UpsClient {
         self
     }
 
-    /// Get OAuth access token from UPS API
+    /// UPS account number shipments are billed to
+    pub fn shipper_number(&self) -> &str {
+        &self.config.merchant_id
+    }
+
+    /// Get the OAuth access token, fetching a new one when the cached token is about to expire
+    ///
+    /// The cache lock is held during the fetch, so concurrent callers wait for
+    /// a single refresh instead of each requesting their own token.
     pub async fn get_access_token(&self) -> Result<String> {
+        let mut token = self.token.lock().await;
+        if let Some((access_token, refresh_at)) = token.as_ref()
+            && Instant::now() < *refresh_at
+        {
+            return Ok(access_token.clone());
+        }
+
+        let (access_token, lifetime) = self.fetch_access_token().await?;
+        let refresh_at = Instant::now() + lifetime.saturating_sub(TOKEN_EXPIRY_MARGIN);
+        *token = Some((access_token.clone(), refresh_at));
+        Ok(access_token)
+    }
+
+    /// Forget the cached token if it is still `rejected`, so the next call fetches a new one
+    ///
+    /// A token another caller already replaced is left alone.
+    async fn invalidate_token(&self, rejected: &str) {
+        let mut token = self.token.lock().await;
+        if token.as_ref().is_some_and(|(cached, _)| cached == rejected) {
+            *token = None;
+        }
+    }
+
+    /// Send a request built with the current access token, retrying once with
+    /// a new token if UPS answers 401
+    async fn send_authorized(
+        &self,
+        build: impl Fn(&str) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response> {
+        let access_token = self.get_access_token().await?;
+        let response = build(&access_token).send().await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        tracing::warn!("UPS rejected the access token, fetching a new one");
+        self.invalidate_token(&access_token).await;
+        let access_token = self.get_access_token().await?;
+        Ok(build(&access_token).send().await?)
+    }
+
+    /// Request a new OAuth access token from UPS API, returning it with its lifetime
+    async fn fetch_access_token(&self) -> Result<(String, Duration)> {
         if self.debug {
             tracing::info!("\n=== Getting OAuth Token ===");
         }
@@ -88,26 +159,30 @@ impl UpsClient {
             .as_str()
             .ok_or_else(|| UpsError::Parse("No access token in response".to_string()))?;
 
+        // UPS sends `expires_in` as a string of seconds
+        let expires_in = &oauth_json["expires_in"];
+        let lifetime = expires_in
+            .as_u64()
+            .or_else(|| expires_in.as_str().and_then(|s| s.parse().ok()))
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TOKEN_LIFETIME);
+
         if self.debug {
             tracing::info!("OAuth Token obtained successfully");
             tracing::info!(
                 "Token type: {}",
                 oauth_json["token_type"].as_str().unwrap_or("unknown")
             );
-            tracing::info!(
-                "Expires in: {} seconds",
-                oauth_json["expires_in"].as_u64().unwrap_or(0)
-            );
+            tracing::info!("Expires in: {} seconds", lifetime.as_secs());
         }
 
-        Ok(access_token.to_string())
+        Ok((access_token.to_string(), lifetime))
     }
 
     /// Validate an address using UPS Address Validation API
     pub async fn validate_address(
         &self,
         address: &AddressKeyFormat,
-        access_token: &str,
     ) -> Result<(UPSApiResponse, AddressValidationResult)> {
         if self.debug {
             tracing::info!("\n=== Validating Address ===");
@@ -130,14 +205,15 @@ impl UpsClient {
         }
 
         let response = self
-            .client
-            .post(&validation_url)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", access_token))
-            .header("transId", "address-validation-request")
-            .header("transactionSrc", "ups-api-client")
-            .json(&body)
-            .send()
+            .send_authorized(|access_token| {
+                self.client
+                    .post(&validation_url)
+                    .header("Content-Type", "application/json")
+                    .header("Authorization", format!("Bearer {}", access_token))
+                    .header("transId", "address-validation-request")
+                    .header("transactionSrc", "ups-api-client")
+                    .json(&body)
+            })
             .await?;
 
         let response_text = response.text().await?;
@@ -158,7 +234,6 @@ impl UpsClient {
     pub async fn get_shipping_rates(
         &self,
         request: &ShippingRateRequest<'_>,
-        access_token: &str,
     ) -> Result<UPSRateResponse> {
         if self.debug {
             tracing::info!("\n=== Getting Shipping Rate ===");
@@ -170,13 +245,7 @@ impl UpsClient {
             request.request_option.as_str()
         );
 
-        let rate_request = self.create_rate_request(
-            request.ship_from,
-            request.ship_to,
-            request.customer_name,
-            request.service_code,
-            request.dimensions.clone(),
-        )?;
+        let rate_request = self.create_rate_request(request)?;
 
         if self.debug {
             tracing::info!("=== DEBUG: Rate Request ===");
@@ -187,14 +256,15 @@ impl UpsClient {
         }
 
         let response = self
-            .client
-            .post(&rate_url)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", access_token))
-            .header("transId", "rate-request")
-            .header("transactionSrc", "ups-api-client")
-            .json(&rate_request)
-            .send()
+            .send_authorized(|access_token| {
+                self.client
+                    .post(&rate_url)
+                    .header("Content-Type", "application/json")
+                    .header("Authorization", format!("Bearer {}", access_token))
+                    .header("transId", "rate-request")
+                    .header("transactionSrc", "ups-api-client")
+                    .json(&rate_request)
+            })
             .await?;
 
         let response_text = response.text().await?;
@@ -214,15 +284,147 @@ impl UpsClient {
         Ok(rate_response)
     }
 
+    /// Buy a shipment and its labels using UPS Shipping API
+    pub async fn create_shipment(&self, request: &UPSShipRequest) -> Result<UPSShipResponse> {
+        if self.debug {
+            tracing::info!("\n=== Creating Shipment ===");
+        }
+
+        let ship_url = format!("{}/api/shipments/v2409/ship", self.config.api_url);
+
+        if self.debug {
+            tracing::info!("=== DEBUG: Ship Request ===");
+            tracing::info!("URL: {}", ship_url);
+            tracing::info!("Request Body:");
+            tracing::info!("{}", serde_json::to_string_pretty(request)?);
+            tracing::info!("=== END DEBUG: Ship Request ===\n");
+        }
+
+        let response = self
+            .send_authorized(|access_token| {
+                self.client
+                    .post(&ship_url)
+                    .header("Content-Type", "application/json")
+                    .header("Authorization", format!("Bearer {}", access_token))
+                    .header("transId", "ship-request")
+                    .header("transactionSrc", "ups-api-client")
+                    .json(request)
+            })
+            .await?;
+
+        let status = response.status();
+        let response_text = response.text().await?;
+
+        if self.debug {
+            tracing::info!("=== DEBUG: Ship Raw Response ===");
+            tracing::info!("{}", response_text);
+            tracing::info!("=== END DEBUG: Ship Raw Response ===\n");
+        }
+
+        if !status.is_success() {
+            return Err(api_error("Ship API", &response_text));
+        }
+        Ok(serde_json::from_str(&response_text)?)
+    }
+
+    /// Void a shipment that hasn't been handed to UPS, by its shipment identification number
+    pub async fn void_shipment(&self, shipment_id: &str) -> Result<UPSVoidResponse> {
+        if self.debug {
+            tracing::info!("\n=== Voiding Shipment {} ===", shipment_id);
+        }
+
+        let void_url = format!(
+            "{}/api/shipments/v2409/void/cancel/{}",
+            self.config.api_url, shipment_id
+        );
+
+        let response = self
+            .send_authorized(|access_token| {
+                self.client
+                    .delete(&void_url)
+                    .header("Authorization", format!("Bearer {}", access_token))
+                    .header("transId", "void-request")
+                    .header("transactionSrc", "ups-api-client")
+            })
+            .await?;
+
+        let status = response.status();
+        let response_text = response.text().await?;
+
+        if self.debug {
+            tracing::info!("=== DEBUG: Void Raw Response ===");
+            tracing::info!("{}", response_text);
+            tracing::info!("=== END DEBUG: Void Raw Response ===\n");
+        }
+
+        if !status.is_success() {
+            return Err(api_error("Void API", &response_text));
+        }
+        Ok(serde_json::from_str(&response_text)?)
+    }
+
+    /// Track a package using UPS Tracking API
+    ///
+    /// Returns `None` if UPS has no information for the tracking number,
+    /// which is normal until the package is first scanned.
+    pub async fn track_shipment(&self, tracking_number: &str) -> Result<Option<UPSTrackResponse>> {
+        if self.debug {
+            tracing::info!("\n=== Tracking {} ===", tracking_number);
+        }
+
+        let track_url = format!(
+            "{}/api/track/v1/details/{}",
+            self.config.api_url, tracking_number
+        );
+
+        let response = self
+            .send_authorized(|access_token| {
+                self.client
+                    .get(&track_url)
+                    .query(&[("locale", "en_US"), ("returnSignature", "false")])
+                    .header("Authorization", format!("Bearer {}", access_token))
+                    .header("transId", "track-request")
+                    .header("transactionSrc", "ups-api-client")
+            })
+            .await?;
+
+        let status = response.status();
+        let response_text = response.text().await?;
+
+        if self.debug {
+            tracing::info!("=== DEBUG: Track Raw Response ===");
+            tracing::info!("{}", response_text);
+            tracing::info!("=== END DEBUG: Track Raw Response ===\n");
+        }
+
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(api_error("Track API", &response_text));
+        }
+
+        let track_response: UPSTrackResponse = serde_json::from_str(&response_text)?;
+        let has_package = track_response
+            .track_response
+            .shipment
+            .iter()
+            .any(|shipment| !shipment.package.is_empty());
+        Ok(has_package.then_some(track_response))
+    }
+
     /// Create a rate request from address and shipment details
-    fn create_rate_request(
-        &self,
-        ship_from: &AddressKeyFormat,
-        ship_to: &Address,
-        customer_name: &str,
-        service_code: UpsServiceCode,
-        dimensions: PackageDimensions,
-    ) -> Result<UPSRateRequest> {
+    fn create_rate_request(&self, request: &ShippingRateRequest<'_>) -> Result<UPSRateRequest> {
+        let ShippingRateRequest {
+            ship_from,
+            ship_to,
+            customer_name,
+            service_code,
+            packages,
+            pickup_date,
+            customs,
+            ..
+        } = request;
         // Convert AddressKeyFormat to RateAddress for ship from
         let ship_from_address = RateAddress {
             address_line: ship_from.address_line.clone(),
@@ -274,34 +476,60 @@ impl UpsClient {
                         code: service_code.code().to_string(),
                         description: service_code.description().to_string(),
                     },
-                    num_of_pieces: "1".to_string(),
-                    package: Package {
-                        simple_rate: None,
-                        packaging_type: PackagingType {
-                            code: "02".to_string(), // Customer Supplied Package
-                            description: "Customer Supplied Package".to_string(),
+                    num_of_pieces: packages.len().to_string(),
+                    // Declared value, and the taxes UPS charges on it, for shipments abroad
+                    invoice_line_total: customs.map(|invoice| InvoiceLineTotal {
+                        currency_code: invoice.currency.clone(),
+                        monetary_value: invoice.total_value.to_decimal().to_string(),
+                    }),
+                    tax_information_indicator: customs.map(|_| String::new()),
+                    shipment_total_weight: pickup_date.map(|_| PackageWeight {
+                        unit_of_measurement: UnitOfMeasurement {
+                            code: "LBS".to_string(),
+                            description: "Pounds".to_string(),
                         },
-                        dimensions: Dimensions {
-                            unit_of_measurement: UnitOfMeasurement {
-                                code: "IN".to_string(),
-                                description: "Inches".to_string(),
-                            },
-                            length: dimensions.length.to_string(),
-                            width: dimensions.width.to_string(),
-                            height: dimensions.height.to_string(),
+                        weight: packages
+                            .iter()
+                            .map(|dimensions| dimensions.weight)
+                            .sum::<f32>()
+                            .to_string(),
+                    }),
+                    delivery_time_information: pickup_date.map(|date| DeliveryTimeInformation {
+                        package_bill_type: "03".to_string(), // Non-Document
+                        pickup: Pickup {
+                            date: date.format("%Y%m%d").to_string(),
                         },
-                        package_weight: PackageWeight {
-                            unit_of_measurement: UnitOfMeasurement {
-                                code: "LBS".to_string(),
-                                description: "Pounds".to_string(),
+                    }),
+                    package: packages
+                        .iter()
+                        .map(|dimensions| Package {
+                            simple_rate: None,
+                            packaging_type: PackagingType {
+                                code: "02".to_string(), // Customer Supplied Package
+                                description: "Customer Supplied Package".to_string(),
                             },
-                            // Note: UPS will calculate billing weight as the greater of:
-                            // 1. This actual weight
-                            // 2. Dimensional weight: (L×W×H)÷139
-                            // 3. Minimum billing weight (typically 4.0 lbs)
-                            weight: dimensions.weight.to_string(),
-                        },
-                    },
+                            dimensions: Dimensions {
+                                unit_of_measurement: UnitOfMeasurement {
+                                    code: "IN".to_string(),
+                                    description: "Inches".to_string(),
+                                },
+                                length: dimensions.length.to_string(),
+                                width: dimensions.width.to_string(),
+                                height: dimensions.height.to_string(),
+                            },
+                            package_weight: PackageWeight {
+                                unit_of_measurement: UnitOfMeasurement {
+                                    code: "LBS".to_string(),
+                                    description: "Pounds".to_string(),
+                                },
+                                // Note: UPS will calculate billing weight as the greater of:
+                                // 1. This actual weight
+                                // 2. Dimensional weight: (L×W×H)÷139
+                                // 3. Minimum billing weight (typically 4.0 lbs)
+                                weight: dimensions.weight.to_string(),
+                            },
+                        })
+                        .collect(),
                 },
             },
         })
@@ -329,3 +557,149 @@ impl UpsClient {
         }
     }
 }
+
+/// Turn a UPS error body into an `UpsError::Api` listing its messages
+fn api_error(api: &str, body: &str) -> UpsError {
+    match serde_json::from_str::<UPSErrorResponse>(body) {
+        Ok(error) => {
+            let messages: Vec<String> = error
+                .response
+                .errors
+                .iter()
+                .map(|e| format!("{} ({})", e.message, e.code))
+                .collect();
+            UpsError::Api(format!("{} error: {}", api, messages.join("; ")))
+        }
+        Err(_) => UpsError::Api(format!("{} error: {}", api, body)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::spawn_stub_server;
+    use axum::{
+        Json, Router,
+        extract::State,
+        http::{HeaderMap, StatusCode},
+        routing::post,
+    };
+    use serde_json::{Value, json};
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+    /// Stand-in for the UPS API that only accepts the most recent token
+    #[derive(Debug)]
+    struct StubUps {
+        token_requests: AtomicUsize,
+        /// Lifetime of issued tokens, in seconds
+        expires_in: AtomicU64,
+    }
+
+    type Stub = State<Arc<StubUps>>;
+
+    async fn token(State(stub): Stub) -> Json<Value> {
+        let issued = stub.token_requests.fetch_add(1, Ordering::SeqCst) + 1;
+        // Slow enough that concurrent callers overlap
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Json(json!({
+            "token_type": "Bearer",
+            "access_token": format!("ups-token-{}", issued),
+            "expires_in": stub.expires_in.load(Ordering::SeqCst).to_string(),
+        }))
+    }
+
+    async fn validate(State(stub): Stub, headers: HeaderMap) -> (StatusCode, Json<Value>) {
+        let current = format!(
+            "Bearer ups-token-{}",
+            stub.token_requests.load(Ordering::SeqCst)
+        );
+        if headers.get("authorization").and_then(|v| v.to_str().ok()) != Some(current.as_str()) {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(json!({ "response": { "errors": [
+                    { "code": "250002", "message": "Invalid Authentication Information." }
+                ] } })),
+            );
+        }
+        (
+            StatusCode::OK,
+            Json(json!({ "XAVResponse": {
+                "Response": { "ResponseStatus": { "Code": "1", "Description": "Success" } },
+                "ValidAddressIndicator": ""
+            } })),
+        )
+    }
+
+    async fn stub_client(expires_in: u64) -> (UpsClient, Arc<StubUps>) {
+        let stub = Arc::new(StubUps {
+            token_requests: AtomicUsize::new(0),
+            expires_in: AtomicU64::new(expires_in),
+        });
+        let router = Router::new()
+            .route("/security/v1/oauth/token", post(token))
+            .route("/api/addressvalidation/v2/1", post(validate))
+            .with_state(stub.clone());
+        let api_url = spawn_stub_server(router).await;
+        let config = UpsConfig::new(
+            api_url,
+            "client".to_string(),
+            "secret".to_string(),
+            "A1B2C3".to_string(),
+        );
+        (UpsClient::new(config), stub)
+    }
+
+    fn address() -> AddressKeyFormat {
+        AddressKeyFormat {
+            consignee_name: "Sushi Prints".to_string(),
+            building_name: String::new(),
+            address_line: vec!["26601 Aliso Creek Road".to_string()],
+            region: String::new(),
+            political_division2: "Aliso Viejo".to_string(),
+            political_division1: "CA".to_string(),
+            postcode_primary_low: Some("92656".to_string()),
+            postcode_extended_low: String::new(),
+            urbanization: None,
+            country_code: "US".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_is_cached_and_refreshes_are_coalesced() {
+        let (client, stub) = stub_client(14399).await;
+
+        let calls = (0..8).map(|_| {
+            let client = client.clone();
+            tokio::spawn(async move { client.get_access_token().await.unwrap() })
+        });
+        for call in calls {
+            assert_eq!(call.await.unwrap(), "ups-token-1");
+        }
+        assert_eq!(client.get_access_token().await.unwrap(), "ups-token-1");
+        assert_eq!(stub.token_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_token_is_refreshed_before_expiry() {
+        // A token that expires within the margin is never reused
+        let (client, stub) = stub_client(60).await;
+
+        assert_eq!(client.get_access_token().await.unwrap(), "ups-token-1");
+        assert_eq!(client.get_access_token().await.unwrap(), "ups-token-2");
+        assert_eq!(stub.token_requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_request_is_retried_with_new_token() {
+        let (client, stub) = stub_client(14399).await;
+        assert_eq!(client.get_access_token().await.unwrap(), "ups-token-1");
+
+        // UPS revokes the cached token early
+        stub.token_requests.fetch_add(1, Ordering::SeqCst);
+
+        let (_, result) = client.validate_address(&address()).await.unwrap();
+        assert_eq!(result, AddressValidationResult::Valid);
+        assert_eq!(stub.token_requests.load(Ordering::SeqCst), 3);
+        assert_eq!(client.get_access_token().await.unwrap(), "ups-token-3");
+    }
+}
@@ -70,3 +70,49 @@ impl UpsConfig {
         tracing::info!("UPS Merchant ID: {}", "*".repeat(self.merchant_id.len()));
     }
 }
+
+/// Print-quality check settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrintQualityConfig {
+    /// Lowest effective DPI accepted without a warning
+    pub min_dpi: f32,
+    /// Reject orders below `min_dpi` instead of warning
+    pub strict: bool,
+}
+
+impl Default for PrintQualityConfig {
+    fn default() -> Self {
+        PrintQualityConfig {
+            min_dpi: 150.0,
+            strict: false,
+        }
+    }
+}
+
+impl PrintQualityConfig {
+    /// Create a PrintQualityConfig from environment variables
+    ///
+    /// # Environment Variables
+    ///
+    /// - `PRINT_MIN_DPI`: Minimum effective DPI (optional, defaults to 150)
+    /// - `PRINT_QUALITY_STRICT`: `true` to reject low-resolution prints (optional)
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = PrintQualityConfig::default();
+        let min_dpi = match env::var("PRINT_MIN_DPI") {
+            Ok(value) => value
+                .parse::<f32>()
+                .ok()
+                .filter(|dpi| dpi.is_finite() && *dpi > 0.0)
+                .ok_or_else(|| format!("Invalid PRINT_MIN_DPI: {}", value))?,
+            Err(_) => defaults.min_dpi,
+        };
+        let strict = match env::var("PRINT_QUALITY_STRICT") {
+            Ok(value) => value
+                .parse::<bool>()
+                .map_err(|_| format!("Invalid PRINT_QUALITY_STRICT: {}", value))?,
+            Err(_) => defaults.strict,
+        };
+
+        Ok(PrintQualityConfig { min_dpi, strict })
+    }
+}
@@ -0,0 +1,210 @@
+//! Customs paperwork for shipments that cross a border
+//!
+//! Prints are declared at the price the customer pays for them, under the HS
+//! code of their catalog size, as made in the country of the lab they ship
+//! from. The same commercial invoice is sent to the carrier when rating and
+//! when buying the label, and can be downloaded to pack with the order.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::{
+    carriers::ShipmentAddress,
+    models::{print_catalog::PrintSize, print_order::PrintOrder},
+    money::{Money, MoneyError},
+};
+
+/// HS code of photographs printed on paper
+pub const DEFAULT_HS_CODE: &str = "491191";
+
+/// Prints are always exported as sold goods
+const EXPORT_REASON: &str = "SALE";
+
+/// One line of a commercial invoice
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CustomsItem {
+    pub description: String,
+    /// Harmonized System code, 6 to 10 digits
+    pub hs_code: String,
+    pub quantity: i64,
+    /// Declared value of a single unit
+    pub unit_value: Money,
+    /// Weight of a single unit in pounds
+    pub unit_weight_lbs: f32,
+}
+
+impl CustomsItem {
+    /// A number of prints of a catalog size, declared at `unit_value` each
+    pub fn prints(
+        size: &PrintSize,
+        finish: Option<&str>,
+        quantity: i64,
+        unit_value: Money,
+    ) -> Self {
+        CustomsItem {
+            description: print_description(&size.size_id, finish),
+            hs_code: size.hs_code.clone(),
+            quantity,
+            unit_value,
+            unit_weight_lbs: size.weight_lbs,
+        }
+    }
+
+    /// Declared value of the whole line
+    pub fn value(&self) -> Result<Money, MoneyError> {
+        self.unit_value.checked_mul(self.quantity)
+    }
+}
+
+/// What a shipment contains and what it's worth, for customs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommercialInvoice {
+    /// The order ID, or `QUOTE` for prints that haven't been ordered yet
+    pub invoice_number: String,
+    pub invoice_date: NaiveDate,
+    /// Why the goods are exported, always `SALE`
+    pub reason: String,
+    pub currency: String,
+    /// ISO 3166-1 alpha-2 country the prints were made in, the lab's
+    pub origin_country: String,
+    pub items: Vec<CustomsItem>,
+    /// Declared value of every item
+    pub total_value: Money,
+}
+
+impl CommercialInvoice {
+    /// Create an invoice, failing if an item isn't in `currency`
+    pub fn new(
+        invoice_number: &str,
+        invoice_date: NaiveDate,
+        currency: &str,
+        origin_country: &str,
+        items: Vec<CustomsItem>,
+    ) -> Result<Self, MoneyError> {
+        let values = items
+            .iter()
+            .map(CustomsItem::value)
+            .collect::<Result<Vec<_>, _>>()?;
+        let total_value = Money::sum(&values, currency)?;
+
+        Ok(CommercialInvoice {
+            invoice_number: invoice_number.to_string(),
+            invoice_date,
+            reason: EXPORT_REASON.to_string(),
+            currency: currency.to_string(),
+            origin_country: origin_country.to_string(),
+            items,
+            total_value,
+        })
+    }
+
+    /// Invoice for quoting prints that haven't been ordered yet
+    pub fn for_quote(
+        date: NaiveDate,
+        currency: &str,
+        origin_country: &str,
+        items: Vec<CustomsItem>,
+    ) -> Result<Self, MoneyError> {
+        CommercialInvoice::new("QUOTE", date, currency, origin_country, items)
+    }
+
+    /// Invoice for a placed order, declaring every line at the price paid and
+    /// under the HS code it was sold with
+    ///
+    /// Weights come from the catalog `sizes`; a size missing from it weighs
+    /// nothing.
+    pub fn for_order(
+        order: &PrintOrder,
+        sizes: &[PrintSize],
+        origin_country: &str,
+    ) -> Result<Self, MoneyError> {
+        let items = order
+            .items
+            .iter()
+            .map(|item| CustomsItem {
+                description: print_description(&item.size, Some(&item.finish)),
+                hs_code: item.hs_code.clone(),
+                quantity: item.print_count(),
+                unit_value: item.unit_price.clone(),
+                unit_weight_lbs: sizes
+                    .iter()
+                    .find(|size| size.size_id == item.size)
+                    .map_or(0.0, |size| size.weight_lbs),
+            })
+            .collect();
+        CommercialInvoice::new(
+            &order.order_id,
+            order.created_at.date_naive(),
+            &order.currency,
+            origin_country,
+            items,
+        )
+    }
+}
+
+/// Whether a shipment between two addresses needs customs paperwork
+pub fn crosses_border(from: &ShipmentAddress, to: &ShipmentAddress) -> bool {
+    !from.country.eq_ignore_ascii_case(&to.country)
+}
+
+/// Check an HS code is 6 to 10 digits
+pub fn validate_hs_code(hs_code: &str) -> Result<(), String> {
+    if (6..=10).contains(&hs_code.len()) && hs_code.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(())
+    } else {
+        Err(format!("HS code must be 6 to 10 digits: {}", hs_code))
+    }
+}
+
+fn print_description(size: &str, finish: Option<&str>) -> String {
+    match finish {
+        Some(finish) => format!("Photographic prints, {} {}", size, finish),
+        None => format!("Photographic prints, {}", size),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invoice_declares_prints_at_price_paid() {
+        let items = vec![
+            CustomsItem {
+                description: "Photographic prints, 4x6 glossy".to_string(),
+                hs_code: DEFAULT_HS_CODE.to_string(),
+                quantity: 20,
+                unit_value: Money::from_minor(150, "USD"),
+                unit_weight_lbs: 0.02,
+            },
+            CustomsItem {
+                description: "Photographic prints, 8x10 matte".to_string(),
+                hs_code: "49119100".to_string(),
+                quantity: 2,
+                unit_value: Money::from_minor(625, "USD"),
+                unit_weight_lbs: 0.06,
+            },
+        ];
+        let date = NaiveDate::from_ymd_opt(2025, 11, 24).unwrap();
+
+        let invoice =
+            CommercialInvoice::new("ord_20251124_0001", date, "USD", "US", items).unwrap();
+        assert_eq!(invoice.total_value, Money::from_minor(4250, "USD"));
+        assert_eq!(invoice.reason, "SALE");
+
+        let euros = CustomsItem {
+            unit_value: Money::from_minor(100, "EUR"),
+            ..invoice.items[0].clone()
+        };
+        assert!(CommercialInvoice::new("quote", date, "USD", "US", vec![euros]).is_err());
+    }
+
+    #[test]
+    fn test_validate_hs_code() {
+        assert!(validate_hs_code("491191").is_ok());
+        assert!(validate_hs_code("4911910000").is_ok());
+        assert!(validate_hs_code("4911.91").is_err());
+        assert!(validate_hs_code("4911").is_err());
+        assert!(validate_hs_code("49119100001").is_err());
+    }
+}
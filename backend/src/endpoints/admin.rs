@@ -23,22 +23,110 @@ Other common admin tasks:
 - GET    /admin/customers        - List customers
 - GET    /admin/stats            - Get sales/statistics overview
 */
-use axum::{Json, extract::State};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::NaiveDate;
 use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
     AppState,
-    endpoints::auth::{CreateAdminRequest, MessageResponse, UserResponse},
+    auth::Claims,
+    endpoints::{
+        auth::{CreateAdminRequest, MessageResponse, UserResponse, create_admin},
+        orders::{OrdersListResponse, error_response},
+        shipping::load_order,
+    },
+    models::{
+        image::{DerivativeKind, StoredImage},
+        order_status::{OrderStatus, OrderStatusChange},
+        payment::Refund,
+        print_order::PrintOrder,
+        shipping_label::StoredLabel,
+    },
+    money::Money,
+    repositories::{RepositoryError, orders::OrderFilter},
 };
 
+/// Orders listed per page when no limit is given
+const DEFAULT_ORDERS_LIMIT: i64 = 100;
+
+/// Most orders one page may list
+const MAX_ORDERS_LIMIT: i64 = 500;
+
+/// Query parameters for listing orders (admin only)
+#[derive(Debug, Deserialize)]
+pub struct AdminOrdersQuery {
+    pub status: Option<OrderStatus>,
+    /// Only orders created on or after this date (YYYY-MM-DD)
+    pub from: Option<NaiveDate>,
+    /// Only orders created on or before this date (YYYY-MM-DD)
+    pub to: Option<NaiveDate>,
+    /// Orders per page, at most 500
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Request payload for an order status change (admin only)
+#[derive(Debug, Deserialize)]
+pub struct UpdateOrderStatusRequest {
+    pub status: OrderStatus,
+    /// Optional free-form reason stored in the order history
+    pub note: Option<String>,
+}
+
+/// Request payload for handing a pickup order over (admin only)
+#[derive(Debug, Deserialize)]
+pub struct ConfirmPickupRequest {
+    /// Code the customer shows at the counter
+    pub code: String,
+}
+
+/// Full order details for admins, including status history, images, refunds and labels
+#[derive(Debug, Serialize)]
+pub struct AdminOrderDetailResponse {
+    pub order: PrintOrder,
+    pub status_history: Vec<OrderStatusChange>,
+    pub images: Vec<AdminOrderImage>,
+    pub refunds: Vec<Refund>,
+    /// Shipping labels bought for the order, voided ones included
+    pub shipping_labels: Vec<StoredLabel>,
+    /// How much of the grand total can still be refunded
+    pub refundable_amount: Money,
+    pub message: String,
+}
+
+/// An image printed in an order, with links to its rendered previews
+#[derive(Debug, Serialize)]
+pub struct AdminOrderImage {
+    #[serde(flatten)]
+    pub image: StoredImage,
+    /// Missing until the thumbnail has been rendered, and for HEIC images
+    /// uploaded before HEIC was rejected
+    pub thumbnail_url: Option<String>,
+    pub previews: Vec<ImagePreview>,
+}
+
+/// Crop preview of an image for one print size
+#[derive(Debug, Serialize)]
+pub struct ImagePreview {
+    pub size: String,
+    pub url: String,
+    pub width_px: i32,
+    pub height_px: i32,
+}
+
 /// POST /api/admin/users (admin only) - Create new admin user
 pub async fn create_admin_endpoint(
     State(state): State<AppState>,
     Json(request): Json<CreateAdminRequest>,
 ) -> Result<Json<UserResponse>, (StatusCode, Json<MessageResponse>)> {
-    let mut user_store = state.user_store.write().await;
-
-    match user_store.create_admin(request) {
+    match create_admin(state.users.as_ref(), request).await {
         Ok(response) => Ok(Json(response)),
         Err(error) => Err((
             StatusCode::BAD_REQUEST,
@@ -46,3 +134,463 @@ pub async fn create_admin_endpoint(
         )),
     }
 }
+
+/// GET /api/admin/orders (admin only) - List all orders, filtered by status and date
+pub async fn list_orders_endpoint(
+    State(state): State<AppState>,
+    Query(query): Query<AdminOrdersQuery>,
+) -> Response {
+    let (limit, offset) = match order_page(&query) {
+        Ok(page) => page,
+        Err(message) => {
+            return error_response(StatusCode::BAD_REQUEST, "INVALID_QUERY", message);
+        }
+    };
+    let filter = OrderFilter {
+        user_id: None,
+        status: query.status,
+        from: query.from,
+        to: query.to,
+        limit: Some(limit),
+        offset: Some(offset),
+    };
+
+    let listed = match state.orders.list(&filter).await {
+        Ok(orders) => state
+            .orders
+            .count(&filter)
+            .await
+            .map(|total| (orders, total)),
+        Err(err) => Err(err),
+    };
+    match listed {
+        Ok((orders, total)) => Json(OrdersListResponse { orders, total }).into_response(),
+        Err(err) => {
+            tracing::error!("Failed to list orders: {}", err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "ORDER_LOOKUP_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// Limit and offset of the page of orders a query asks for
+///
+/// Limits over `MAX_ORDERS_LIMIT` are capped rather than rejected.
+fn order_page(query: &AdminOrdersQuery) -> Result<(i64, i64), String> {
+    let limit = query.limit.unwrap_or(DEFAULT_ORDERS_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    if limit < 0 {
+        return Err("limit can't be negative".to_string());
+    }
+    if offset < 0 {
+        return Err("offset can't be negative".to_string());
+    }
+    Ok((limit.min(MAX_ORDERS_LIMIT), offset))
+}
+
+/// GET /api/admin/orders/:order_id (admin only) - Get full order details
+pub async fn get_order_endpoint(
+    State(state): State<AppState>,
+    Path(order_id): Path<String>,
+) -> Response {
+    match load_admin_order_detail(&state, &order_id).await {
+        Ok(Some(detail)) => Json(AdminOrderDetailResponse {
+            message: "Order retrieved successfully".to_string(),
+            ..detail
+        })
+        .into_response(),
+        Ok(None) => error_response(
+            StatusCode::NOT_FOUND,
+            "ORDER_NOT_FOUND",
+            "Order not found".to_string(),
+        ),
+        Err(err) => {
+            tracing::error!("Failed to load order {}: {}", order_id, err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "ORDER_LOOKUP_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// PATCH /api/admin/orders/:order_id/status (admin only) - Move an order to a new status
+pub async fn update_order_status_endpoint(
+    State(state): State<AppState>,
+    Path(order_id): Path<String>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<UpdateOrderStatusRequest>,
+) -> Response {
+    let order = match load_order(&state, &order_id).await {
+        Ok(order) => order,
+        Err(response) => return response,
+    };
+
+    if !order.status.can_transition_to(request.status) {
+        return error_response(
+            StatusCode::CONFLICT,
+            "INVALID_STATUS_TRANSITION",
+            format!(
+                "Cannot move order from {} to {}",
+                order.status, request.status
+            ),
+        );
+    }
+    if let Err(message) = check_payment_status(request.status) {
+        return error_response(StatusCode::CONFLICT, "INVALID_STATUS_TRANSITION", message);
+    }
+    if let Err(message) = check_fulfillment_status(&order, request.status) {
+        return error_response(StatusCode::CONFLICT, "INVALID_STATUS_TRANSITION", message);
+    }
+
+    let changed_by = claims.sub.parse::<Uuid>().ok();
+    if let Err(err) = state
+        .orders
+        .update_status(
+            &order_id,
+            order.status,
+            request.status,
+            changed_by,
+            request.note,
+        )
+        .await
+    {
+        tracing::error!("Failed to update status of order {}: {}", order_id, err);
+        let status = match err {
+            RepositoryError::Conflict(_) => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        return error_response(status, "ORDER_STATUS_UPDATE_FAILED", err.to_string());
+    }
+
+    tracing::info!(
+        "Order {} moved from {} to {} by {}",
+        order_id,
+        order.status,
+        request.status,
+        claims.email
+    );
+
+    admin_order_detail_response(&state, &order_id, "Order status updated successfully").await
+}
+
+/// POST /api/admin/orders/:order_id/pickup (admin only) - Hand a pickup order over
+///
+/// Staff check the code the customer shows against the order's; a match
+/// marks the order delivered.
+pub async fn confirm_pickup_endpoint(
+    State(state): State<AppState>,
+    Path(order_id): Path<String>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<ConfirmPickupRequest>,
+) -> Response {
+    let order = match load_order(&state, &order_id).await {
+        Ok(order) => order,
+        Err(response) => return response,
+    };
+
+    if !order.is_pickup() {
+        return error_response(
+            StatusCode::CONFLICT,
+            "NOT_PICKUP_ORDER",
+            "Order is shipped, not picked up".to_string(),
+        );
+    }
+    if order.status != OrderStatus::ReadyForPickup {
+        return error_response(
+            StatusCode::CONFLICT,
+            "ORDER_NOT_READY",
+            format!("Can't hand over an order that is {}", order.status),
+        );
+    }
+    if order.pickup_code.as_deref() != Some(request.code.trim()) {
+        tracing::warn!("Wrong pickup code given for order {}", order_id);
+        return error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "INVALID_PICKUP_CODE",
+            "Pickup code doesn't match the order".to_string(),
+        );
+    }
+
+    let changed_by = claims.sub.parse::<Uuid>().ok();
+    if let Err(err) = state
+        .orders
+        .update_status(
+            &order_id,
+            OrderStatus::ReadyForPickup,
+            OrderStatus::Delivered,
+            changed_by,
+            Some("Picked up by the customer".to_string()),
+        )
+        .await
+    {
+        tracing::error!("Failed to hand over order {}: {}", order_id, err);
+        let status = match err {
+            RepositoryError::Conflict(_) => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        return error_response(status, "ORDER_STATUS_UPDATE_FAILED", err.to_string());
+    }
+
+    tracing::info!(
+        "Order {} picked up, confirmed by {}",
+        order_id,
+        claims.email
+    );
+    admin_order_detail_response(&state, &order_id, "Order picked up successfully").await
+}
+
+/// Check a status is one admins may set by hand
+///
+/// Orders only become paid once the provider has taken the money, which
+/// capturing the payment or its webhook records, and only become refunded
+/// once the money is returned, through a refund or the provider's webhook.
+fn check_payment_status(next: OrderStatus) -> Result<(), String> {
+    match next {
+        OrderStatus::Paid => {
+            Err("Orders are marked paid when their payment is captured".to_string())
+        }
+        OrderStatus::Refunded => Err("Refund the payment to mark an order refunded".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Check a status change fits how the order is fulfilled
+///
+/// Pickup orders are never shipped and only become delivered through
+/// [`confirm_pickup_endpoint`]; shipped orders are never ready for pickup.
+fn check_fulfillment_status(order: &PrintOrder, next: OrderStatus) -> Result<(), String> {
+    match next {
+        OrderStatus::Shipped if order.is_pickup() => {
+            Err("Pickup orders are collected, not shipped".to_string())
+        }
+        OrderStatus::Delivered if order.is_pickup() => {
+            Err("Confirm the customer's pickup code to hand over a pickup order".to_string())
+        }
+        OrderStatus::ReadyForPickup if !order.is_pickup() => {
+            Err("Only pickup orders can be ready for pickup".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Respond with an order's full details after a change
+async fn admin_order_detail_response(state: &AppState, order_id: &str, message: &str) -> Response {
+    match load_admin_order_detail(state, order_id).await {
+        Ok(Some(detail)) => Json(AdminOrderDetailResponse {
+            message: message.to_string(),
+            ..detail
+        })
+        .into_response(),
+        Ok(None) => error_response(
+            StatusCode::NOT_FOUND,
+            "ORDER_NOT_FOUND",
+            "Order not found".to_string(),
+        ),
+        Err(err) => error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "ORDER_LOOKUP_FAILED",
+            err.to_string(),
+        ),
+    }
+}
+
+/// GET /api/admin/images/:image_id/thumbnail (admin only) - Download an image thumbnail
+pub async fn get_image_thumbnail_endpoint(
+    State(state): State<AppState>,
+    Path(image_id): Path<String>,
+) -> Response {
+    serve_derivative(&state, &image_id, DerivativeKind::Thumbnail, None).await
+}
+
+/// GET /api/admin/images/:image_id/previews/:size_id (admin only) - Download a crop preview
+pub async fn get_image_preview_endpoint(
+    State(state): State<AppState>,
+    Path((image_id, size_id)): Path<(String, String)>,
+) -> Response {
+    serve_derivative(&state, &image_id, DerivativeKind::Preview, Some(&size_id)).await
+}
+
+/// Respond with a stored thumbnail or preview JPEG
+async fn serve_derivative(
+    state: &AppState,
+    image_id: &str,
+    kind: DerivativeKind,
+    size_id: Option<&str>,
+) -> Response {
+    let derivative = match state.images.find_derivative(image_id, kind, size_id).await {
+        Ok(Some(derivative)) => derivative,
+        Ok(None) => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                "PREVIEW_NOT_FOUND",
+                "Preview not found".to_string(),
+            );
+        }
+        Err(err) => {
+            tracing::error!("Failed to look up preview of {}: {}", image_id, err);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "PREVIEW_LOOKUP_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+
+    match state.storage.get(&derivative.storage_key).await {
+        Ok(data) => ([(header::CONTENT_TYPE, "image/jpeg")], data).into_response(),
+        Err(err) => {
+            tracing::error!("Failed to read {}: {}", derivative.storage_key, err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "PREVIEW_LOOKUP_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// Load an order together with its status history, images, refunds and labels
+///
+/// The returned response has an empty message for the caller to fill in.
+async fn load_admin_order_detail(
+    state: &AppState,
+    order_id: &str,
+) -> Result<Option<AdminOrderDetailResponse>, RepositoryError> {
+    let Some(order) = state.orders.find_by_id(order_id).await? else {
+        return Ok(None);
+    };
+    let history = state.orders.status_history(order_id).await?;
+
+    // Images are loaded by the items rather than by the order they're
+    // attached to, since an image reused from an earlier order stays
+    // attached to that one
+    let mut image_ids: Vec<String> = Vec::new();
+    for image_id in order.items.iter().flat_map(|item| &item.image_ids) {
+        if !image_ids.contains(image_id) {
+            image_ids.push(image_id.clone());
+        }
+    }
+    let mut stored = state.images.find_by_ids(&image_ids).await?;
+    stored.sort_by_key(|image| image_ids.iter().position(|id| *id == image.image_id));
+    let derivatives = state.images.list_derivatives(&image_ids).await?;
+    let images = stored
+        .into_iter()
+        .map(|image| {
+            let mut thumbnail_url = None;
+            let mut previews = Vec::new();
+            for derivative in derivatives.iter().filter(|d| d.image_id == image.image_id) {
+                match (derivative.kind, &derivative.size_id) {
+                    (DerivativeKind::Thumbnail, _) => {
+                        thumbnail_url =
+                            Some(format!("/api/admin/images/{}/thumbnail", image.image_id));
+                    }
+                    (DerivativeKind::Preview, Some(size_id)) => previews.push(ImagePreview {
+                        size: size_id.clone(),
+                        url: format!("/api/admin/images/{}/previews/{}", image.image_id, size_id),
+                        width_px: derivative.width_px,
+                        height_px: derivative.height_px,
+                    }),
+                    (DerivativeKind::Preview, None) => {}
+                }
+            }
+            AdminOrderImage {
+                image,
+                thumbnail_url,
+                previews,
+            }
+        })
+        .collect();
+
+    let refunds = state.payments.list_refunds(order_id).await?;
+    let shipping_labels = state.shipping_labels.list_for_order(order_id).await?;
+    let refundable_amount = order
+        .refundable_amount()
+        .unwrap_or_else(|_| Money::zero(&order.currency));
+    Ok(Some(AdminOrderDetailResponse {
+        order,
+        status_history: history,
+        images,
+        refunds,
+        shipping_labels,
+        refundable_amount,
+        message: String::new(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{app_state, print_order, stored_image};
+    use sqlx::PgPool;
+
+    fn page_query(limit: Option<i64>, offset: Option<i64>) -> AdminOrdersQuery {
+        AdminOrdersQuery {
+            status: None,
+            from: None,
+            to: None,
+            limit,
+            offset,
+        }
+    }
+
+    #[test]
+    fn test_check_payment_status() {
+        assert!(check_payment_status(OrderStatus::Paid).is_err());
+        assert!(check_payment_status(OrderStatus::Refunded).is_err());
+        assert!(check_payment_status(OrderStatus::InProduction).is_ok());
+        assert!(check_payment_status(OrderStatus::Cancelled).is_ok());
+    }
+
+    #[test]
+    fn test_order_page() {
+        assert_eq!(order_page(&page_query(None, None)), Ok((100, 0)));
+        assert_eq!(order_page(&page_query(Some(20), Some(40))), Ok((20, 40)));
+        assert_eq!(order_page(&page_query(Some(0), None)), Ok((0, 0)));
+        // Oversized pages are capped
+        assert_eq!(order_page(&page_query(Some(10_000), None)), Ok((500, 0)));
+
+        assert!(order_page(&page_query(Some(-1), None)).is_err());
+        assert!(order_page(&page_query(None, Some(-1))).is_err());
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_load_admin_order_detail_shared_image(pool: PgPool) {
+        let state = app_state(pool);
+        for image_id in ["img_shared", "img_new"] {
+            state.images.create(&stored_image(image_id)).await.unwrap();
+        }
+
+        // The first order claims the image, the second reuses it
+        let mut first = print_order("ord_1", OrderStatus::PendingPayment);
+        first.items[0].image_ids = vec!["img_shared".to_string()];
+        state.orders.create(&first).await.unwrap();
+        let mut second = print_order("ord_2", OrderStatus::PendingPayment);
+        second.items[0].image_ids = vec!["img_new".to_string(), "img_shared".to_string()];
+        state.orders.create(&second).await.unwrap();
+
+        let image_ids = |detail: &AdminOrderDetailResponse| -> Vec<String> {
+            detail
+                .images
+                .iter()
+                .map(|image| image.image.image_id.clone())
+                .collect()
+        };
+        let detail = load_admin_order_detail(&state, "ord_1")
+            .await
+            .unwrap()
+            .expect("Order should exist");
+        assert_eq!(image_ids(&detail), ["img_shared"]);
+        let detail = load_admin_order_detail(&state, "ord_2")
+            .await
+            .unwrap()
+            .expect("Order should exist");
+        assert_eq!(image_ids(&detail), ["img_new", "img_shared"]);
+        assert_eq!(detail.images[1].image.order_id.as_deref(), Some("ord_1"));
+    }
+}
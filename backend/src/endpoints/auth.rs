@@ -2,6 +2,7 @@ use crate::{
     AppState,
     auth::{Claims, TokenResponse, generate_token},
     models::user::{PublicUser, User},
+    repositories::users::UserRepository,
 };
 use axum::{
     Extension,
@@ -10,7 +11,6 @@ use axum::{
     response::Json,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use uuid::Uuid;
 
 /// Request payload for user registration
@@ -105,351 +105,335 @@ pub struct MessageResponse {
     pub message: String,
 }
 
-/// Simple in-memory user store for demonstration
-/// TODO: Move users to PostgreSQL
-#[derive(Debug, Default)]
-pub struct UserStore {
-    users: HashMap<String, User>, // email -> user
-    password_reset_tokens: HashMap<String, (String, chrono::DateTime<chrono::Utc>)>, // token -> (email, expiry)
-}
-
-impl UserStore {
-    pub fn new() -> Self {
-        Self::default()
+/// Create the bootstrap admin from environment variables if it doesn't exist yet
+///
+/// # Environment Variables
+///
+/// - `BOOTSTRAP_ADMIN_NAME`: Display name of the bootstrap admin
+/// - `BOOTSTRAP_ADMIN_EMAIL`: Email address of the bootstrap admin
+/// - `BOOTSTRAP_ADMIN_PASSWORD`: Initial password of the bootstrap admin
+pub async fn ensure_bootstrap_admin(users: &dyn UserRepository) -> Result<(), String> {
+    // Get bootstrap admin details from env
+    let name = std::env::var("BOOTSTRAP_ADMIN_NAME").expect("BOOTSTRAP_ADMIN_NAME must be set");
+    let email = std::env::var("BOOTSTRAP_ADMIN_EMAIL").expect("BOOTSTRAP_ADMIN_EMAIL must be set");
+    let password =
+        std::env::var("BOOTSTRAP_ADMIN_PASSWORD").expect("BOOTSTRAP_ADMIN_PASSWORD must be set");
+
+    // Users now survive restarts, so only create the admin on first boot
+    if users
+        .find_by_email(&email)
+        .await
+        .map_err(|e| e.to_string())?
+        .is_some()
+    {
+        tracing::info!("Bootstrap admin already exists: {}", email);
+        return Ok(());
     }
 
-    /// Create a bootstrap admin user for testing
-    pub fn new_with_admin() -> Self {
-        let mut store = Self::new();
-
-        // Create a bootstrap admin user
-        // let admin_request = CreateAdminRequest {
-        //     email: "admin@example.com".to_string(),
-        //     name: "Bootstrap Admin".to_string(),
-        //     password: "AdminPass123!".to_string(),
-        // };
-
-        // Get bootstrap admin details from env
-        let name = std::env::var("BOOTSTRAP_ADMIN_NAME").expect("BOOTSTRAP_ADMIN_NAME must be set");
-        let email =
-            std::env::var("BOOTSTRAP_ADMIN_EMAIL").expect("BOOTSTRAP_ADMIN_EMAIL must be set");
-        let password = std::env::var("BOOTSTRAP_ADMIN_PASSWORD")
-            .expect("BOOTSTRAP_ADMIN_PASSWORD must be set");
-
-        let admin_request = CreateAdminRequest {
-            email,
-            name,
-            password,
-        };
-
-        tracing::info!("Bootstrap admin user created: {}", admin_request.name);
-
-        // Log other fields, but only in debug builds
-        #[cfg(debug_assertions)]
-        {
-            tracing::info!(
-                "Bootstrap admin email: {}, password: {}",
-                admin_request.email,
-                admin_request.password
-            );
-        }
-
-        // Use the create_admin method directly
-        let _ = store.create_admin(admin_request);
-
-        store
+    let admin_request = CreateAdminRequest {
+        email,
+        name,
+        password,
+    };
+
+    tracing::info!("Bootstrap admin user created: {}", admin_request.name);
+
+    // Log other fields, but only in debug builds
+    #[cfg(debug_assertions)]
+    {
+        tracing::info!(
+            "Bootstrap admin email: {}, password: {}",
+            admin_request.email,
+            admin_request.password
+        );
     }
 
-    /// Register a new user (always creates a customer)
-    pub fn register(&mut self, request: RegisterRequest) -> Result<AuthResponse, String> {
-        // Check if user already exists
-        if self.users.contains_key(&request.email) {
-            return Err("User with this email already exists".to_string());
-        }
-
-        // Create new user (includes validation) - always a customer
-        let user = User::new(request.email.clone(), request.name, &request.password)
-            .map_err(|e| e.to_string())?;
-
-        let public_user = user.to_public();
-
-        // Generate JWT token
-        let token = generate_token(
-            user.id,
-            &user.email,
-            &user.name,
-            user.is_admin,
-            None, // Use default expiration
-        )
-        .map_err(|e| format!("Failed to generate token: {}", e))?;
-
-        // Store user
-        self.users.insert(request.email, user);
+    create_admin(users, admin_request).await.map(|_| ())
+}
 
-        Ok(AuthResponse {
-            user: public_user,
-            token,
-            message: "User registered successfully".to_string(),
-        })
+/// Register a new user (always creates a customer)
+pub async fn register(
+    users: &dyn UserRepository,
+    request: RegisterRequest,
+) -> Result<AuthResponse, String> {
+    // Check if user already exists
+    if users
+        .find_by_email(&request.email)
+        .await
+        .map_err(|e| e.to_string())?
+        .is_some()
+    {
+        return Err("User with this email already exists".to_string());
     }
 
-    /// Create admin user (admin only operation)
-    pub fn create_admin(&mut self, request: CreateAdminRequest) -> Result<UserResponse, String> {
-        // Check if user already exists
-        if self.users.contains_key(&request.email) {
-            return Err("User with this email already exists".to_string());
-        }
-
-        // Create new admin user (includes validation)
-        let mut user = User::new(request.email.clone(), request.name, &request.password)
-            .map_err(|e| e.to_string())?;
+    // Create new user (includes validation) - always a customer
+    let user =
+        User::new(request.email, request.name, &request.password).map_err(|e| e.to_string())?;
 
-        // Set admin role
-        user.set_admin(true);
+    // Generate JWT token
+    let token = generate_token(
+        user.id,
+        &user.email,
+        &user.name,
+        user.is_admin,
+        None, // Use default expiration
+    )
+    .map_err(|e| format!("Failed to generate token: {}", e))?;
 
-        let public_user = user.to_public();
+    // Store user
+    users.create(&user).await.map_err(|e| e.to_string())?;
 
-        // Store user
-        self.users.insert(request.email, user);
+    Ok(AuthResponse {
+        user: user.to_public(),
+        token,
+        message: "User registered successfully".to_string(),
+    })
+}
 
-        Ok(UserResponse {
-            user: public_user,
-            message: "Admin user created successfully".to_string(),
-        })
+/// Create admin user (admin only operation)
+pub async fn create_admin(
+    users: &dyn UserRepository,
+    request: CreateAdminRequest,
+) -> Result<UserResponse, String> {
+    // Check if user already exists
+    if users
+        .find_by_email(&request.email)
+        .await
+        .map_err(|e| e.to_string())?
+        .is_some()
+    {
+        return Err("User with this email already exists".to_string());
     }
 
-    /// Authenticate a user login
-    pub fn login(&self, request: LoginRequest) -> Result<AuthResponse, String> {
-        // Find user by email
-        let user = self
-            .users
-            .get(&request.email)
-            .ok_or("Invalid email or password".to_string())?;
-
-        // Verify password
-        let is_valid = user
-            .verify_password(&request.password)
-            .map_err(|e| format!("Authentication error: {}", e))?;
-
-        if !is_valid {
-            return Err("Invalid email or password".to_string());
-        }
+    // Create new admin user (includes validation)
+    let mut user =
+        User::new(request.email, request.name, &request.password).map_err(|e| e.to_string())?;
 
-        // Generate JWT token
-        let token = generate_token(
-            user.id,
-            &user.email,
-            &user.name,
-            user.is_admin,
-            None, // Use default expiration
-        )
-        .map_err(|e| format!("Failed to generate token: {}", e))?;
+    // Set admin role
+    user.set_admin(true);
 
-        Ok(AuthResponse {
-            user: user.to_public(),
-            token,
-            message: "Login successful".to_string(),
-        })
-    }
+    // Store user
+    users.create(&user).await.map_err(|e| e.to_string())?;
 
-    /// Get user by ID
-    pub fn get_user_by_id(&self, user_id: &Uuid) -> Option<&User> {
-        self.users.values().find(|user| &user.id == user_id)
-    }
+    Ok(UserResponse {
+        user: user.to_public(),
+        message: "Admin user created successfully".to_string(),
+    })
+}
 
-    /// Get user by email
-    pub fn get_user_by_email(&self, email: &str) -> Option<&User> {
-        self.users.get(email)
+/// Authenticate a user login
+pub async fn login(
+    users: &dyn UserRepository,
+    request: LoginRequest,
+) -> Result<AuthResponse, String> {
+    // Find user by email
+    let user = users
+        .find_by_email(&request.email)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("Invalid email or password".to_string())?;
+
+    // Verify password
+    let is_valid = user
+        .verify_password(&request.password)
+        .map_err(|e| format!("Authentication error: {}", e))?;
+
+    if !is_valid {
+        return Err("Invalid email or password".to_string());
     }
 
-    /// Update user profile
-    pub fn update_user(
-        &mut self,
-        user_id: &Uuid,
-        request: UpdateProfileRequest,
-    ) -> Result<UserResponse, String> {
-        // If email is being updated, check for conflicts first
-        if let Some(ref new_email) = request.email {
-            // Find the current user's email
-            let current_email = self
-                .users
-                .iter()
-                .find(|(_, user)| &user.id == user_id)
-                .map(|(email, _)| email.clone())
-                .ok_or("User not found".to_string())?;
-
-            if new_email != &current_email && self.users.contains_key(new_email) {
-                return Err("Email already in use".to_string());
-            }
-        }
-
-        // Find user by ID and get their current email
-        let (old_email, user_exists) = self
-            .users
-            .iter()
-            .find(|(_, user)| &user.id == user_id)
-            .map(|(email, _)| (email.clone(), true))
-            .ok_or("User not found".to_string())?;
-
-        if !user_exists {
-            return Err("User not found".to_string());
-        }
-
-        // Update the user
-        let user = self.users.get_mut(&old_email).unwrap();
-        user.update(request.email.clone(), request.name);
-
-        // If email changed, update the HashMap key
-        if let Some(new_email) = request.email
-            && new_email != old_email
-        {
-            let user = self.users.remove(&old_email).unwrap();
-            self.users.insert(new_email.clone(), user);
-
-            let user = self.users.get(&new_email).unwrap();
-            return Ok(UserResponse {
-                user: user.to_public(),
-                message: "Profile updated successfully".to_string(),
-            });
-        }
+    // Generate JWT token
+    let token = generate_token(
+        user.id,
+        &user.email,
+        &user.name,
+        user.is_admin,
+        None, // Use default expiration
+    )
+    .map_err(|e| format!("Failed to generate token: {}", e))?;
+
+    Ok(AuthResponse {
+        user: user.to_public(),
+        token,
+        message: "Login successful".to_string(),
+    })
+}
 
-        let user = self.users.get(&old_email).unwrap();
-        Ok(UserResponse {
-            user: user.to_public(),
-            message: "Profile updated successfully".to_string(),
-        })
+/// Update user profile
+pub async fn update_user(
+    users: &dyn UserRepository,
+    user_id: &Uuid,
+    request: UpdateProfileRequest,
+) -> Result<UserResponse, String> {
+    let mut user = users
+        .find_by_id(user_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("User not found".to_string())?;
+
+    // If email is being updated, check for conflicts first
+    if let Some(ref new_email) = request.email
+        && new_email != &user.email
+        && users
+            .find_by_email(new_email)
+            .await
+            .map_err(|e| e.to_string())?
+            .is_some()
+    {
+        return Err("Email already in use".to_string());
     }
 
-    /// Update user password
-    pub fn update_password(
-        &mut self,
-        user_id: &Uuid,
-        request: UpdatePasswordRequest,
-    ) -> Result<MessageResponse, String> {
-        // Find user by ID
-        let user = self
-            .users
-            .values_mut()
-            .find(|user| &user.id == user_id)
-            .ok_or("User not found".to_string())?;
-
-        // Verify current password
-        let is_valid = user
-            .verify_password(&request.current_password)
-            .map_err(|e| format!("Authentication error: {}", e))?;
-
-        if !is_valid {
-            return Err("Current password is incorrect".to_string());
-        }
+    // Update the user
+    user.update(request.email, request.name);
+    users.update(&user).await.map_err(|e| e.to_string())?;
 
-        // Update password
-        user.update_password(&request.new_password)
-            .map_err(|e| e.to_string())?;
+    Ok(UserResponse {
+        user: user.to_public(),
+        message: "Profile updated successfully".to_string(),
+    })
+}
 
-        Ok(MessageResponse {
-            message: "Password updated successfully".to_string(),
-        })
+/// Update user password
+pub async fn update_password(
+    users: &dyn UserRepository,
+    user_id: &Uuid,
+    request: UpdatePasswordRequest,
+) -> Result<MessageResponse, String> {
+    // Find user by ID
+    let mut user = users
+        .find_by_id(user_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("User not found".to_string())?;
+
+    // Verify current password
+    let is_valid = user
+        .verify_password(&request.current_password)
+        .map_err(|e| format!("Authentication error: {}", e))?;
+
+    if !is_valid {
+        return Err("Current password is incorrect".to_string());
     }
 
-    /// Delete user (admin or self-access)
-    pub fn delete_user(&mut self, user_id: &Uuid) -> Result<MessageResponse, String> {
-        // Find and remove user
-        let user_email = self
-            .users
-            .iter()
-            .find(|(_, user)| &user.id == user_id)
-            .map(|(email, _)| email.clone())
-            .ok_or("User not found".to_string())?;
-
-        self.users.remove(&user_email);
-
-        Ok(MessageResponse {
-            message: "User deleted successfully".to_string(),
-        })
-    }
+    // Update password
+    user.update_password(&request.new_password)
+        .map_err(|e| e.to_string())?;
+    users.update(&user).await.map_err(|e| e.to_string())?;
 
-    /// Update user role (admin only)
-    pub fn update_user_role(
-        &mut self,
-        user_id: &Uuid,
-        request: UpdateRoleRequest,
-    ) -> Result<UserResponse, String> {
-        // Find user by ID
-        let user = self
-            .users
-            .values_mut()
-            .find(|user| &user.id == user_id)
-            .ok_or("User not found".to_string())?;
-
-        user.set_admin(request.is_admin);
-
-        Ok(UserResponse {
-            user: user.to_public(),
-            message: "Role updated successfully".to_string(),
-        })
-    }
+    Ok(MessageResponse {
+        message: "Password updated successfully".to_string(),
+    })
+}
 
-    /// List all users (admin only)
-    pub fn list_users(&self) -> UsersListResponse {
-        let users: Vec<PublicUser> = self.users.values().map(|user| user.to_public()).collect();
+/// Delete user (admin or self-access)
+pub async fn delete_user(
+    users: &dyn UserRepository,
+    user_id: &Uuid,
+) -> Result<MessageResponse, String> {
+    let deleted = users.delete(user_id).await.map_err(|e| e.to_string())?;
 
-        UsersListResponse {
-            total: users.len(),
-            users,
-        }
+    if !deleted {
+        return Err("User not found".to_string());
     }
 
-    /// Generate password reset token
-    pub fn generate_password_reset_token(&mut self, email: &str) -> Result<String, String> {
-        // Check if user exists
-        if !self.users.contains_key(email) {
-            return Err("User not found".to_string());
-        }
-
-        // Generate reset token
-        let token = Uuid::new_v4().to_string();
-        let expiry = chrono::Utc::now() + chrono::Duration::hours(1); // 1 hour expiry
+    Ok(MessageResponse {
+        message: "User deleted successfully".to_string(),
+    })
+}
 
-        self.password_reset_tokens
-            .insert(token.clone(), (email.to_string(), expiry));
+/// Update user role (admin only)
+pub async fn update_user_role(
+    users: &dyn UserRepository,
+    user_id: &Uuid,
+    request: UpdateRoleRequest,
+) -> Result<UserResponse, String> {
+    // Find user by ID
+    let mut user = users
+        .find_by_id(user_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("User not found".to_string())?;
+
+    user.set_admin(request.is_admin);
+    users.update(&user).await.map_err(|e| e.to_string())?;
+
+    Ok(UserResponse {
+        user: user.to_public(),
+        message: "Role updated successfully".to_string(),
+    })
+}
 
-        Ok(token)
-    }
+/// List all users (admin only)
+pub async fn list_users(users: &dyn UserRepository) -> Result<UsersListResponse, String> {
+    let users: Vec<PublicUser> = users
+        .list()
+        .await
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|user| user.to_public())
+        .collect();
+
+    Ok(UsersListResponse {
+        total: users.len(),
+        users,
+    })
+}
 
-    /// Reset password with token
-    pub fn reset_password(
-        &mut self,
-        request: ResetPasswordRequest,
-    ) -> Result<MessageResponse, String> {
-        // Validate token
-        let (email, expiry) = self
-            .password_reset_tokens
-            .get(&request.token)
-            .ok_or("Invalid or expired reset token".to_string())?
-            .clone();
-
-        // Check if token is expired
-        if chrono::Utc::now() > expiry {
-            self.password_reset_tokens.remove(&request.token);
-            return Err("Reset token has expired".to_string());
-        }
+/// Generate password reset token
+pub async fn generate_password_reset_token(
+    users: &dyn UserRepository,
+    email: &str,
+) -> Result<String, String> {
+    // Check if user exists
+    let user = users
+        .find_by_email(email)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("User not found".to_string())?;
+
+    // Generate reset token
+    let token = Uuid::new_v4().to_string();
+    let expiry = chrono::Utc::now() + chrono::Duration::hours(1); // 1 hour expiry
+
+    users
+        .store_reset_token(&token, &user.id, expiry)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(token)
+}
 
-        // Find user and update password
-        let user = self
-            .users
-            .get_mut(&email)
-            .ok_or("User not found".to_string())?;
+/// Reset password with token
+pub async fn reset_password(
+    users: &dyn UserRepository,
+    request: ResetPasswordRequest,
+) -> Result<MessageResponse, String> {
+    // Validate and consume token
+    let (user_id, expiry) = users
+        .take_reset_token(&request.token)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("Invalid or expired reset token".to_string())?;
+
+    // Check if token is expired
+    if chrono::Utc::now() > expiry {
+        return Err("Reset token has expired".to_string());
+    }
 
-        user.update_password(&request.new_password)
-            .map_err(|e| e.to_string())?;
+    // Find user and update password
+    let mut user = users
+        .find_by_id(&user_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("User not found".to_string())?;
 
-        // Remove used token
-        self.password_reset_tokens.remove(&request.token);
+    user.update_password(&request.new_password)
+        .map_err(|e| e.to_string())?;
+    users.update(&user).await.map_err(|e| e.to_string())?;
 
-        Ok(MessageResponse {
-            message: "Password reset successfully".to_string(),
-        })
-    }
+    Ok(MessageResponse {
+        message: "Password reset successfully".to_string(),
+    })
 }
 
 /// POST /api/auth/register
@@ -457,9 +441,7 @@ pub async fn register_endpoint(
     State(state): State<AppState>,
     Json(request): Json<RegisterRequest>,
 ) -> Result<Json<AuthResponse>, (StatusCode, Json<MessageResponse>)> {
-    let mut user_store = state.user_store.write().await;
-
-    match user_store.register(request) {
+    match register(state.users.as_ref(), request).await {
         Ok(response) => Ok(Json(response)),
         Err(error) => Err((
             StatusCode::BAD_REQUEST,
@@ -473,9 +455,7 @@ pub async fn login_endpoint(
     State(state): State<AppState>,
     Json(request): Json<LoginRequest>,
 ) -> Result<Json<AuthResponse>, (StatusCode, Json<MessageResponse>)> {
-    let user_store = state.user_store.read().await;
-
-    match user_store.login(request) {
+    match login(state.users.as_ref(), request).await {
         Ok(response) => Ok(Json(response)),
         Err(error) => Err((
             StatusCode::UNAUTHORIZED,
@@ -498,7 +478,6 @@ pub async fn me_endpoint(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
 ) -> Result<Json<UserResponse>, (StatusCode, Json<MessageResponse>)> {
-    let user_store = state.user_store.read().await;
     let user_id = claims.sub.parse::<Uuid>().map_err(|_| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -508,14 +487,26 @@ pub async fn me_endpoint(
         )
     })?;
 
-    let user = user_store.get_user_by_id(&user_id).ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(MessageResponse {
-                message: "User not found".to_string(),
-            }),
-        )
-    })?;
+    let user = state
+        .users
+        .find_by_id(&user_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(MessageResponse {
+                    message: e.to_string(),
+                }),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(MessageResponse {
+                    message: "User not found".to_string(),
+                }),
+            )
+        })?;
 
     Ok(Json(UserResponse {
         user: user.to_public(),
@@ -548,15 +539,26 @@ pub async fn get_user_endpoint(
         ));
     }
 
-    let user_store = state.user_store.read().await;
-    let user = user_store.get_user_by_id(&user_id).ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(MessageResponse {
-                message: "User not found".to_string(),
-            }),
-        )
-    })?;
+    let user = state
+        .users
+        .find_by_id(&user_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(MessageResponse {
+                    message: e.to_string(),
+                }),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(MessageResponse {
+                    message: "User not found".to_string(),
+                }),
+            )
+        })?;
 
     Ok(Json(UserResponse {
         user: user.to_public(),
@@ -590,8 +592,7 @@ pub async fn update_user_endpoint(
         ));
     }
 
-    let mut user_store = state.user_store.write().await;
-    match user_store.update_user(&user_id, update_request) {
+    match update_user(state.users.as_ref(), &user_id, update_request).await {
         Ok(response) => Ok(Json(response)),
         Err(error) => Err((
             StatusCode::BAD_REQUEST,
@@ -626,8 +627,7 @@ pub async fn update_password_endpoint(
         ));
     }
 
-    let mut user_store = state.user_store.write().await;
-    match user_store.update_password(&user_id, password_request) {
+    match update_password(state.users.as_ref(), &user_id, password_request).await {
         Ok(response) => Ok(Json(response)),
         Err(error) => Err((
             StatusCode::BAD_REQUEST,
@@ -661,8 +661,7 @@ pub async fn delete_user_endpoint(
         ));
     }
 
-    let mut user_store = state.user_store.write().await;
-    match user_store.delete_user(&user_id) {
+    match delete_user(state.users.as_ref(), &user_id).await {
         Ok(response) => Ok(Json(response)),
         Err(error) => Err((
             StatusCode::NOT_FOUND,
@@ -672,9 +671,16 @@ pub async fn delete_user_endpoint(
 }
 
 /// GET /api/users (admin only)
-pub async fn list_users_endpoint(State(state): State<AppState>) -> Json<UsersListResponse> {
-    let user_store = state.user_store.read().await;
-    Json(user_store.list_users())
+pub async fn list_users_endpoint(
+    State(state): State<AppState>,
+) -> Result<Json<UsersListResponse>, (StatusCode, Json<MessageResponse>)> {
+    match list_users(state.users.as_ref()).await {
+        Ok(response) => Ok(Json(response)),
+        Err(error) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(MessageResponse { message: error }),
+        )),
+    }
 }
 
 /// PATCH /api/users/:id/role (admin only)
@@ -683,8 +689,7 @@ pub async fn update_user_role_endpoint(
     Path(user_id): Path<Uuid>,
     Json(role_request): Json<UpdateRoleRequest>,
 ) -> Result<Json<UserResponse>, (StatusCode, Json<MessageResponse>)> {
-    let mut user_store = state.user_store.write().await;
-    match user_store.update_user_role(&user_id, role_request) {
+    match update_user_role(state.users.as_ref(), &user_id, role_request).await {
         Ok(response) => Ok(Json(response)),
         Err(error) => Err((
             StatusCode::NOT_FOUND,
@@ -698,9 +703,7 @@ pub async fn forgot_password_endpoint(
     State(state): State<AppState>,
     Json(request): Json<ForgotPasswordRequest>,
 ) -> Result<Json<MessageResponse>, (StatusCode, Json<MessageResponse>)> {
-    let mut user_store = state.user_store.write().await;
-
-    match user_store.generate_password_reset_token(&request.email) {
+    match generate_password_reset_token(state.users.as_ref(), &request.email).await {
         Ok(token) => {
             // In a real application, you would send this token via email
             tracing::info!("Password reset token for {}: {}", request.email, token);
@@ -721,9 +724,7 @@ pub async fn reset_password_endpoint(
     State(state): State<AppState>,
     Json(request): Json<ResetPasswordRequest>,
 ) -> Result<Json<MessageResponse>, (StatusCode, Json<MessageResponse>)> {
-    let mut user_store = state.user_store.write().await;
-
-    match user_store.reset_password(request) {
+    match reset_password(state.users.as_ref(), request).await {
         Ok(response) => Ok(Json(response)),
         Err(error) => Err((
             StatusCode::BAD_REQUEST,
@@ -735,10 +736,11 @@ pub async fn reset_password_endpoint(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::repositories::users::UserStore;
 
-    #[test]
-    fn test_user_registration() {
-        let mut store = UserStore::new();
+    #[tokio::test]
+    async fn test_user_registration() {
+        let store = UserStore::new();
 
         let request = RegisterRequest {
             email: "test@example.com".to_string(),
@@ -746,17 +748,17 @@ mod tests {
             password: "SecurePass123!".to_string(),
         };
 
-        let response = store
-            .register(request)
+        let response = register(&store, request)
+            .await
             .expect("Registration should succeed");
         assert_eq!(response.user.email, "test@example.com");
         assert_eq!(response.user.name, "Test User");
         assert!(!response.user.is_admin);
     }
 
-    #[test]
-    fn test_admin_creation() {
-        let mut store = UserStore::new();
+    #[tokio::test]
+    async fn test_admin_creation() {
+        let store = UserStore::new();
 
         let request = CreateAdminRequest {
             email: "admin@example.com".to_string(),
@@ -764,17 +766,17 @@ mod tests {
             password: "SecurePass123!".to_string(),
         };
 
-        let response = store
-            .create_admin(request)
+        let response = create_admin(&store, request)
+            .await
             .expect("Admin creation should succeed");
         assert_eq!(response.user.email, "admin@example.com");
         assert_eq!(response.user.name, "Admin User");
         assert!(response.user.is_admin);
     }
 
-    #[test]
-    fn test_login() {
-        let mut store = UserStore::new();
+    #[tokio::test]
+    async fn test_login() {
+        let store = UserStore::new();
 
         let register_request = RegisterRequest {
             email: "test@example.com".to_string(),
@@ -782,8 +784,8 @@ mod tests {
             password: "SecurePass123!".to_string(),
         };
 
-        store
-            .register(register_request)
+        register(&store, register_request)
+            .await
             .expect("Registration should succeed");
 
         let login_request = LoginRequest {
@@ -791,7 +793,9 @@ mod tests {
             password: "SecurePass123!".to_string(),
         };
 
-        let response = store.login(login_request).expect("Login should succeed");
+        let response = login(&store, login_request)
+            .await
+            .expect("Login should succeed");
         assert_eq!(response.user.email, "test@example.com");
         assert!(!response.token.token.is_empty());
     }
@@ -0,0 +1,415 @@
+//! Fulfillment location endpoints
+//!
+//! Admins manage the print labs orders are routed to. New orders are routed
+//! across the active labs as soon as they change; orders already placed keep
+//! the lab they were routed to. Labs that offer pickup are listed to
+//! customers, who can collect their prints there instead of having them
+//! shipped.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppState,
+    endpoints::orders::error_response,
+    models::fulfillment_location::FulfillmentLocation,
+    repositories::{
+        RepositoryError,
+        locations::{LocationUpdate, NewLocation},
+    },
+};
+
+/// Every fulfillment location, inactive ones included
+#[derive(Debug, Serialize)]
+pub struct LocationListResponse {
+    pub locations: Vec<FulfillmentLocation>,
+}
+
+/// A lab customers can collect orders from
+#[derive(Debug, Serialize)]
+pub struct PickupLocation {
+    pub location_id: String,
+    pub name: String,
+    pub phone: Option<String>,
+    pub line1: String,
+    pub line2: Option<String>,
+    pub city: String,
+    pub state: String,
+    pub postal_code: String,
+    pub country: String,
+    pub pickup_hours: Option<String>,
+    /// Print sizes the lab produces, empty for every size
+    pub size_ids: Vec<String>,
+}
+
+impl From<FulfillmentLocation> for PickupLocation {
+    fn from(location: FulfillmentLocation) -> Self {
+        PickupLocation {
+            location_id: location.location_id,
+            name: location.name,
+            phone: location.phone,
+            line1: location.line1,
+            line2: location.line2,
+            city: location.city,
+            state: location.state,
+            postal_code: location.postal_code,
+            country: location.country,
+            pickup_hours: location.pickup_hours,
+            size_ids: location.size_ids,
+        }
+    }
+}
+
+/// Every active lab offering pickup
+#[derive(Debug, Serialize)]
+pub struct PickupLocationListResponse {
+    pub locations: Vec<PickupLocation>,
+}
+
+/// Response for a single fulfillment location
+#[derive(Debug, Serialize)]
+pub struct LocationResponse {
+    pub location: FulfillmentLocation,
+    pub message: String,
+}
+
+/// Request payload for adding a fulfillment location (admin only)
+#[derive(Debug, Deserialize)]
+pub struct CreateLocationRequest {
+    /// Key recorded on orders, e.g. `denver`
+    pub location_id: String,
+    pub name: String,
+    pub contact_name: String,
+    pub company: Option<String>,
+    pub phone: Option<String>,
+    pub line1: String,
+    pub line2: Option<String>,
+    pub city: String,
+    pub state: String,
+    pub postal_code: String,
+    pub country: String,
+    /// Countries (`US`) or states (`US-CA`) the lab is preferred for
+    #[serde(default)]
+    pub regions: Vec<String>,
+    /// Print sizes the lab produces, empty for every size
+    #[serde(default)]
+    pub size_ids: Vec<String>,
+    #[serde(default)]
+    pub priority: i32,
+    /// Whether customers can collect orders here
+    #[serde(default)]
+    pub pickup: bool,
+    pub pickup_hours: Option<String>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+/// Request payload for editing a fulfillment location (admin only)
+#[derive(Debug, Deserialize)]
+pub struct UpdateLocationRequest {
+    pub name: Option<String>,
+    pub contact_name: Option<String>,
+    pub company: Option<String>,
+    pub phone: Option<String>,
+    pub line1: Option<String>,
+    pub line2: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    pub regions: Option<Vec<String>>,
+    pub size_ids: Option<Vec<String>>,
+    pub priority: Option<i32>,
+    pub pickup: Option<bool>,
+    pub pickup_hours: Option<String>,
+    pub active: Option<bool>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+/// GET /api/admin/locations (admin only) - List fulfillment locations by priority
+pub async fn list_locations_endpoint(State(state): State<AppState>) -> Response {
+    match state.locations.list(true).await {
+        Ok(locations) => Json(LocationListResponse { locations }).into_response(),
+        Err(err) => {
+            tracing::error!("Failed to load fulfillment locations: {}", err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "LOCATION_LOOKUP_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// GET /api/pickup-locations - List the labs customers can collect orders from
+pub async fn list_pickup_locations_endpoint(State(state): State<AppState>) -> Response {
+    match state.locations.list(false).await {
+        Ok(locations) => Json(PickupLocationListResponse {
+            locations: locations
+                .into_iter()
+                .filter(|location| location.pickup)
+                .map(PickupLocation::from)
+                .collect(),
+        })
+        .into_response(),
+        Err(err) => {
+            tracing::error!("Failed to load pickup locations: {}", err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "LOCATION_LOOKUP_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// POST /api/admin/locations (admin only) - Add a fulfillment location
+pub async fn create_location_endpoint(
+    State(state): State<AppState>,
+    Json(request): Json<CreateLocationRequest>,
+) -> Response {
+    let location_id = request.location_id.trim().to_string();
+    if location_id.is_empty() || location_id.contains(char::is_whitespace) {
+        return validation_error(
+            "Location ID must be non-empty and contain no whitespace".to_string(),
+        );
+    }
+    let required = [
+        ("name", &request.name),
+        ("contact_name", &request.contact_name),
+        ("line1", &request.line1),
+        ("city", &request.city),
+        ("postal_code", &request.postal_code),
+    ];
+    if let Some((field, _)) = required.iter().find(|(_, value)| value.trim().is_empty()) {
+        return validation_error(format!("{} is required", field));
+    }
+    if let Err(message) = validate_location_fields(Some(&request.country), Some(&request.regions)) {
+        return validation_error(message);
+    }
+
+    let new_location = NewLocation {
+        location_id,
+        name: request.name,
+        contact_name: request.contact_name,
+        company: request.company,
+        phone: request.phone,
+        line1: request.line1,
+        line2: request.line2,
+        city: request.city,
+        state: request.state,
+        postal_code: request.postal_code,
+        country: request.country,
+        regions: request.regions,
+        size_ids: request.size_ids,
+        priority: request.priority,
+        pickup: request.pickup,
+        pickup_hours: request.pickup_hours,
+        active: request.active,
+    };
+
+    match state.locations.create(&new_location).await {
+        Ok(location) => {
+            tracing::info!("Added fulfillment location {}", location.location_id);
+            (
+                StatusCode::CREATED,
+                Json(LocationResponse {
+                    location,
+                    message: "Fulfillment location created successfully".to_string(),
+                }),
+            )
+                .into_response()
+        }
+        Err(RepositoryError::Conflict(_)) => error_response(
+            StatusCode::CONFLICT,
+            "LOCATION_EXISTS",
+            format!(
+                "Fulfillment location {} already exists",
+                new_location.location_id
+            ),
+        ),
+        Err(err) => {
+            tracing::error!("Failed to create fulfillment location: {}", err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "LOCATION_UPDATE_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// PATCH /api/admin/locations/:location_id (admin only) - Edit a fulfillment location
+pub async fn update_location_endpoint(
+    State(state): State<AppState>,
+    Path(location_id): Path<String>,
+    Json(request): Json<UpdateLocationRequest>,
+) -> Response {
+    if let Err(message) =
+        validate_location_fields(request.country.as_deref(), request.regions.as_deref())
+    {
+        return validation_error(message);
+    }
+
+    let update = LocationUpdate {
+        name: request.name,
+        contact_name: request.contact_name,
+        company: request.company,
+        phone: request.phone,
+        line1: request.line1,
+        line2: request.line2,
+        city: request.city,
+        state: request.state,
+        postal_code: request.postal_code,
+        country: request.country,
+        regions: request.regions,
+        size_ids: request.size_ids,
+        priority: request.priority,
+        pickup: request.pickup,
+        pickup_hours: request.pickup_hours,
+        active: request.active,
+    };
+
+    match state.locations.update(&location_id, &update).await {
+        Ok(Some(location)) => {
+            tracing::info!("Updated fulfillment location {}", location_id);
+            Json(LocationResponse {
+                location,
+                message: "Fulfillment location updated successfully".to_string(),
+            })
+            .into_response()
+        }
+        Ok(None) => location_not_found(),
+        Err(err) => {
+            tracing::error!(
+                "Failed to update fulfillment location {}: {}",
+                location_id,
+                err
+            );
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "LOCATION_UPDATE_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// DELETE /api/admin/locations/:location_id (admin only) - Remove a fulfillment location
+///
+/// Locations orders were routed to can't be removed, only deactivated.
+pub async fn delete_location_endpoint(
+    State(state): State<AppState>,
+    Path(location_id): Path<String>,
+) -> Response {
+    match state.locations.delete(&location_id).await {
+        Ok(true) => {
+            tracing::info!("Removed fulfillment location {}", location_id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => location_not_found(),
+        Err(RepositoryError::Conflict(message)) => error_response(
+            StatusCode::CONFLICT,
+            "LOCATION_IN_USE",
+            format!("{}; deactivate it instead", message),
+        ),
+        Err(err) => {
+            tracing::error!(
+                "Failed to remove fulfillment location {}: {}",
+                location_id,
+                err
+            );
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "LOCATION_UPDATE_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// Check the country code and routing regions of a location, skipping
+/// absent fields
+fn validate_location_fields(
+    country: Option<&str>,
+    regions: Option<&[String]>,
+) -> Result<(), String> {
+    if let Some(country) = country
+        && !is_country_code(country)
+    {
+        return Err(format!(
+            "Country must be an ISO 3166-1 alpha-2 code such as US: {}",
+            country
+        ));
+    }
+    validate_regions(regions.unwrap_or_default())
+}
+
+/// Check regions are countries such as `US` or states such as `US-CA`
+pub(crate) fn validate_regions(regions: &[String]) -> Result<(), String> {
+    for region in regions {
+        let valid = match region.split_once('-') {
+            Some((country, state)) => {
+                is_country_code(country)
+                    && !state.is_empty()
+                    && state
+                        .bytes()
+                        .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+            }
+            None => is_country_code(region),
+        };
+        if !valid {
+            return Err(format!(
+                "Regions must be countries such as US or states such as US-CA: {}",
+                region
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn is_country_code(value: &str) -> bool {
+    value.len() == 2 && value.bytes().all(|b| b.is_ascii_uppercase())
+}
+
+fn validation_error(message: String) -> Response {
+    error_response(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "VALIDATION_ERROR",
+        message,
+    )
+}
+
+fn location_not_found() -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        "LOCATION_NOT_FOUND",
+        "Fulfillment location not found".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_location_fields() {
+        let regions = vec!["US".to_string(), "US-CA".to_string(), "CA-ON".to_string()];
+        assert!(validate_location_fields(Some("US"), Some(&regions)).is_ok());
+        assert!(validate_location_fields(None, None).is_ok());
+
+        assert!(validate_location_fields(Some("USA"), None).is_err());
+        assert!(validate_location_fields(Some("us"), None).is_err());
+        for region in ["us-ca", "US-", "California"] {
+            assert!(validate_location_fields(None, Some(&[region.to_string()])).is_err());
+        }
+    }
+}
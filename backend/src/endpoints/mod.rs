@@ -8,18 +8,17 @@ Public, customer-facing:
 | `GET`  | `/api/orders/:order_id`          | Retrieve order status and details (customer view).                                                                               |
 | `GET`  | `/api/prints/sizes`              | Get available print sizes, prices, and descriptions.                                                                             |
 | `POST` | `/api/shipping/quote`            | Get a live shipping cost based on address, package weight, and size.                                                             |
-| `POST` | `/api/payments/intent`           | Create a payment intent (PayPal) for an order.                                                                            |
+| `POST` | `/api/shipping/validate-address` | Validate an address and suggest corrected candidates, with residential/commercial classification.                                |
+| `POST` | `/api/payments/intent`           | Create a payment intent (PayPal order or Stripe PaymentIntent) for an order.                                                     |
 | `POST` | `/api/payments/webhook`          | Handle payment provider webhooks (order paid, failed, refunded).                                                                 |
-| `GET`  | `/api/orders/:order_id/tracking` | Get shipping tracking info (pulled from shipping API).                                                                           |
+| `GET`  | `/api/orders/:order_id/tracking` | Get normalized tracking events for the order's shipment from its carrier.                                                        |
+| `GET`  | `/api/pickup-locations`          | List the labs customers can collect orders from, with their opening hours.                                                       |
 */
-// TODO: Implement orders api
 pub mod orders;
-// TODO: Implement prints api
+pub mod payments;
 pub mod prints;
-// TODO: Implement shipping api
 pub mod shipping;
-// TODO: Implement payments api
-pub mod payments;
+pub mod uploads;
 // TODO: Implement auth api
 pub mod auth;
 
@@ -31,12 +30,25 @@ Admin only, private:
 | `GET`    | `/api/admin/orders`                          | List all orders with filters for status (pending, in progress, shipped, etc.). |
 | `GET`    | `/api/admin/orders/:order_id`                | Get full details, uploaded files, shipping info for a single order.            |
 | `PATCH`  | `/api/admin/orders/:order_id/status`         | Update an order status (pending → in progress → shipped).                      |
-| `POST`   | `/api/admin/orders/:order_id/shipping-label` | Generate a shipping label via EasyPost/Shippo API.                             |
+| `POST`   | `/api/admin/orders/:order_id/pickup`         | Hand a pickup order over once the customer's pickup code checks out.           |
+| `POST`   | `/api/admin/orders/:order_id/shipping-label` | Buy a shipping label from the order's carrier.                                 |
+| `GET`    | `/api/admin/orders/:order_id/shipping-label` | Download the order's current shipping label.                                   |
+| `DELETE` | `/api/admin/orders/:order_id/shipping-label` | Void the order's current shipping label.                                       |
 | `PATCH`  | `/api/admin/orders/:order_id/tracking`       | Update tracking info if manual.                                                |
 | `POST`   | `/api/admin/prints/sizes`                    | Add a new print size & price.                                                  |
 | `PATCH`  | `/api/admin/prints/sizes/:size_id`           | Edit an existing print size/price.                                             |
 | `DELETE` | `/api/admin/prints/sizes/:size_id`           | Remove a print size from the catalog.                                          |
+| `GET`    | `/api/admin/locations`                       | List the print labs orders are fulfilled from.                                 |
+| `POST`   | `/api/admin/locations`                       | Add a print lab with its routing regions and sizes.                            |
+| `PATCH`  | `/api/admin/locations/:location_id`          | Edit or deactivate a print lab.                                                |
+| `DELETE` | `/api/admin/locations/:location_id`          | Remove a print lab no order was routed to.                                     |
+| `GET`    | `/api/admin/shipping-rules`                  | List the rules applied to carrier quotes, in the order they apply.             |
+| `POST`   | `/api/admin/shipping-rules`                  | Add a free-shipping threshold, flat rate, markup or hidden service.            |
+| `PUT`    | `/api/admin/shipping-rules/:rule_id`         | Replace a shipping rule.                                                       |
+| `DELETE` | `/api/admin/shipping-rules/:rule_id`         | Remove a shipping rule.                                                        |
 */
 // TODO: Implement admin api
 pub mod admin;
-pub mod db;
\ No newline at end of file
+pub mod db;
+pub mod locations;
+pub mod shipping_rules;
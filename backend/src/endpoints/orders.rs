@@ -29,13 +29,15 @@ Example POST JSON body
     }
   ],
   "special_instructions": "Please adjust colors for warmer tones.",
-  "shipping_option": "USPS_Priority",
+  "shipping_option": "UPS_Ground",
   "payment": {
-    "method": "paypal",
-    "order_id": "5O190127TN364715T"
+    "method": "paypal"
   }
 }
 
+Pickup orders set "fulfillment_method": "pickup" and a "pickup_location_id"
+instead of a shipping address and option.
+
 Example Response JSON
 {
   "order_id": "ord_20250812_0001",
@@ -45,11 +47,11 @@ Example Response JSON
     "approval_url": "https://www.sandbox.paypal.com/checkoutnow?token=5O190127TN364715T"
   },
   "total": {
-    "items_subtotal": 28.00,
-    "shipping": 7.50,
-    "tax": 2.48,
+    "items_subtotal": { "amount": "28.00", "currency": "USD" },
+    "shipping": { "amount": "11.87", "currency": "USD" },
+    "tax": { "amount": "2.79", "currency": "USD" },
     "currency": "USD",
-    "grand_total": 37.98
+    "grand_total": { "amount": "42.66", "currency": "USD" }
   },
   "estimated_delivery": {
     "min_date": "2025-08-18",
@@ -59,15 +61,44 @@ Example Response JSON
 }
 */
 
-use crate::AppState;
+use crate::{
+    AppState,
+    auth::Claims,
+    calendar::BusinessCalendar,
+    carriers::{CarrierError, ShippingQuote},
+    config::PrintQualityConfig,
+    customs::CustomsItem,
+    endpoints::{locations::PickupLocation, shipping::shipment_address},
+    fulfillment::Route,
+    imaging::{ImageDimensions, effective_dpi},
+    models::{
+        fulfillment_location::FulfillmentLocation,
+        image::StoredImage,
+        order_status::OrderStatus,
+        print_catalog::{PrintFinish, PrintSize},
+        print_order::{FulfillmentMethod, PrintOrder, PrintOrderItem},
+    },
+    money::{Money, MoneyError},
+    payments::{self, CreateIntentRequest, PaymentError},
+    previews,
+    repositories::{
+        RepositoryError,
+        catalog::CatalogRepository,
+        orders::{OrderFilter, OrderRepository},
+        payments::NewPayment,
+    },
+    shipping, shipping_rules,
+};
 use axum::{
-    Json,
-    extract::State,
+    Extension, Json,
+    extract::{Path, State},
     http::StatusCode,
     response::{IntoResponse, Response},
 };
-use chrono::Utc;
+use chrono::{NaiveDate, Utc};
+use rand::{Rng, rngs::OsRng};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 // Request structures matching the example JSON
 #[derive(Debug, Deserialize)]
@@ -75,7 +106,13 @@ pub struct OrderRequest {
     pub customer: CustomerRequest,
     pub prints: Vec<PrintRequest>,
     pub special_instructions: Option<String>,
+    /// Carrier service to ship with; not used for pickup
+    #[serde(default)]
     pub shipping_option: String,
+    #[serde(default)]
+    pub fulfillment_method: FulfillmentMethod,
+    /// Lab to collect a pickup order from
+    pub pickup_location_id: Option<String>,
     pub payment: PaymentRequest,
 }
 
@@ -84,10 +121,11 @@ pub struct CustomerRequest {
     pub name: String,
     pub email: String,
     pub phone: String,
-    pub shipping_address: AddressRequest,
+    /// Required unless the order is picked up
+    pub shipping_address: Option<AddressRequest>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AddressRequest {
     pub line1: String,
     pub line2: Option<String>,
@@ -108,40 +146,94 @@ pub struct PrintRequest {
 #[derive(Debug, Deserialize)]
 pub struct PaymentRequest {
     pub method: String,
-    pub order_id: Option<String>,
 }
 
 // Response structures matching the example JSON
 #[derive(Debug, Serialize)]
 pub struct OrderResponse {
     pub order_id: String,
-    pub status: String,
+    pub status: OrderStatus,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub paypal: Option<PayPalResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stripe: Option<StripeResponse>,
     pub total: TotalResponse,
+    /// For pickup orders, when the prints are ready to collect
     pub estimated_delivery: DeliveryEstimate,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pickup: Option<PickupResponse>,
+    /// Prints whose images are below the configured resolution
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<PrintQualityWarning>,
     pub message: String,
 }
 
+/// An image that will print below the minimum resolution
+#[derive(Debug, Serialize)]
+pub struct PrintQualityWarning {
+    pub image_id: String,
+    pub size: String,
+    pub effective_dpi: u32,
+    pub minimum_dpi: u32,
+    pub message: String,
+}
+
+/// Where and how a pickup order is collected
+#[derive(Debug, Serialize)]
+pub struct PickupResponse {
+    pub location: PickupLocation,
+    /// Code the customer shows staff to collect the order
+    pub code: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct PayPalResponse {
     pub order_id: String,
     pub approval_url: String,
 }
 
+/// Card payment for the client to confirm with Stripe.js
+#[derive(Debug, Serialize)]
+pub struct StripeResponse {
+    pub payment_intent_id: String,
+    pub client_secret: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct TotalResponse {
-    pub items_subtotal: f64,
-    pub shipping: f64,
-    pub tax: f64,
+    pub items_subtotal: Money,
+    pub shipping: Money,
+    pub tax: Money,
     pub currency: String,
-    pub grand_total: f64,
+    pub grand_total: Money,
+    /// Duties and taxes the carrier estimates the customer will owe on
+    /// delivery abroad; not part of the grand total
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duties_and_taxes: Option<Money>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct DeliveryEstimate {
-    pub min_date: String, // ISO date format
-    pub max_date: String, // ISO date format
+    pub min_date: NaiveDate, // Serialized as ISO date
+    pub max_date: NaiveDate, // Serialized as ISO date
+}
+
+/// Response for a single stored order
+#[derive(Debug, Serialize)]
+pub struct OrderDetailResponse {
+    pub order: PrintOrder,
+    /// Code to collect a pickup order, shown to the customer who placed it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pickup_code: Option<String>,
+    pub message: String,
+}
+
+/// Response for order listings
+#[derive(Debug, Serialize)]
+pub struct OrdersListResponse {
+    pub orders: Vec<PrintOrder>,
+    /// Number of orders matching the query, across all pages
+    pub total: i64,
 }
 
 // Error response structure
@@ -155,6 +247,7 @@ pub struct ErrorResponse {
 /// Orders endpoint - handles order creation with proper error handling
 pub async fn orders_endpoint(
     State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
     Json(payload): Json<OrderRequest>,
 ) -> Response {
     tracing::info!(
@@ -163,91 +256,402 @@ pub async fn orders_endpoint(
     );
     tracing::debug!("Order request payload: {:?}", payload);
 
-    match process_order(payload, &app_state).await {
+    let user_id = match claims.sub.parse::<Uuid>() {
+        Ok(user_id) => user_id,
+        Err(_) => {
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "INVALID_USER",
+                "Invalid user ID".to_string(),
+            );
+        }
+    };
+
+    match process_order(payload, user_id, &app_state).await {
         Ok(response) => {
             tracing::info!("Order created successfully with ID: {}", response.order_id);
             (StatusCode::CREATED, Json(response)).into_response()
         }
         Err(err) => {
             tracing::error!("Order processing failed: {}", err);
-            let error_response = ErrorResponse {
-                error: "ORDER_PROCESSING_FAILED".to_string(),
-                message: err.to_string(),
-                details: None,
+            // Storage failures are our fault, provider failures are theirs and
+            // everything else is a bad request
+            let status = if err.is::<RepositoryError>() {
+                StatusCode::INTERNAL_SERVER_ERROR
+            } else if err.is::<PaymentError>() || err.is::<CarrierError>() {
+                StatusCode::BAD_GATEWAY
+            } else {
+                StatusCode::BAD_REQUEST
             };
-            (StatusCode::BAD_REQUEST, Json(error_response)).into_response()
+            error_response(status, "ORDER_PROCESSING_FAILED", err.to_string())
+        }
+    }
+}
+
+/// GET /api/orders - List the current user's orders
+pub async fn list_my_orders_endpoint(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Response {
+    let Ok(user_id) = claims.sub.parse::<Uuid>() else {
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "INVALID_USER",
+            "Invalid user ID".to_string(),
+        );
+    };
+
+    let filter = OrderFilter {
+        user_id: Some(user_id),
+        ..Default::default()
+    };
+
+    let listed = match app_state.orders.list(&filter).await {
+        Ok(orders) => app_state
+            .orders
+            .count(&filter)
+            .await
+            .map(|total| (orders, total)),
+        Err(err) => Err(err),
+    };
+    match listed {
+        Ok((orders, total)) => Json(OrdersListResponse { orders, total }).into_response(),
+        Err(err) => {
+            tracing::error!("Failed to list orders: {}", err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "ORDER_LOOKUP_FAILED",
+                err.to_string(),
+            )
         }
     }
 }
 
+/// GET /api/orders/:order_id - Retrieve order status and details (customer view)
+pub async fn get_order_endpoint(
+    State(app_state): State<AppState>,
+    Path(order_id): Path<String>,
+    Extension(claims): Extension<Claims>,
+) -> Response {
+    let order = match app_state.orders.find_by_id(&order_id).await {
+        Ok(Some(order)) => order,
+        Ok(None) => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                "ORDER_NOT_FOUND",
+                "Order not found".to_string(),
+            );
+        }
+        Err(err) => {
+            tracing::error!("Failed to load order {}: {}", order_id, err);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "ORDER_LOOKUP_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+
+    // Customers may only see their own orders
+    let is_owner = order.user_id.map(|id| id.to_string()) == Some(claims.sub.clone());
+    if !claims.admin && !is_owner {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            "ACCESS_DENIED",
+            "Access denied".to_string(),
+        );
+    }
+
+    let pickup_code = if is_owner {
+        order.pickup_code.clone()
+    } else {
+        None
+    };
+    Json(OrderDetailResponse {
+        order,
+        pickup_code,
+        message: "Order retrieved successfully".to_string(),
+    })
+    .into_response()
+}
+
+/// Build a JSON error response in the orders API format
+pub(crate) fn error_response(status: StatusCode, error: &str, message: String) -> Response {
+    let error_response = ErrorResponse {
+        error: error.to_string(),
+        message,
+        details: None,
+    };
+    (status, Json(error_response)).into_response()
+}
+
 /// Process the order and return a response or error
 async fn process_order(
     request: OrderRequest,
+    user_id: Uuid,
     app_state: &AppState,
 ) -> Result<OrderResponse, Box<dyn std::error::Error + Send + Sync>> {
     tracing::debug!("Starting order processing");
 
-    // Validate the request
+    // Validate the request, including ownership of the referenced images
     tracing::debug!("Validating order request");
-    validate_order_request(&request)?;
+    let image_ids: Vec<String> = request
+        .prints
+        .iter()
+        .flat_map(|print| print.image_ids.iter().cloned())
+        .collect();
+    let images = app_state.images.find_by_ids(&image_ids).await?;
+    validate_order_request(&request, user_id, &images)?;
+
+    // Look up catalog prices and check the images are sharp enough to print
+    let entries = resolve_catalog(app_state.catalog.as_ref(), &request.prints).await?;
+    let warnings =
+        check_print_quality(&request.prints, &entries, &images, &app_state.print_quality);
+    if !warnings.is_empty() {
+        if app_state.print_quality.strict {
+            let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+            return Err(messages.join("; ").into());
+        }
+        tracing::warn!("Order has {} low-resolution print(s)", warnings.len());
+    }
+
+    // Quote the order's package and charge the service the customer picked;
+    // pickup orders are made at the chosen lab and ship for free
+    let ship_date = shipping::ship_date(
+        &app_state.calendar,
+        Utc::now().date_naive(),
+        entries.iter().map(|(size, _)| size),
+    );
+    let (location, quote) = match request.fulfillment_method {
+        FulfillmentMethod::Ship => {
+            tracing::debug!("Quoting shipping");
+            let (location, quote) =
+                quote_shipping(&request, &entries, ship_date, app_state).await?;
+            tracing::info!(
+                "{} from {} quoted at {}",
+                quote.description,
+                location.location_id,
+                quote.price
+            );
+            (location, Some(quote))
+        }
+        FulfillmentMethod::Pickup => {
+            let location = pickup_location(&request, &entries, app_state).await?;
+            tracing::info!("Order will be picked up at {}", location.location_id);
+            (location, None)
+        }
+    };
 
     // Generate order ID
-    let order_id = generate_order_id();
+    let order_id = generate_order_id(app_state.orders.as_ref()).await?;
     tracing::info!("Generated order ID: {}", order_id);
 
-    // Now we have access to the UPS client and access token through app_state
-    // Example usage:
-    // let ups_rates = app_state.ups_client.get_rates(&rate_request).await?;
-    // let auth_header = format!("Bearer {}", app_state.access_token);
-    #[allow(unused_variables)]
-    let _ups_client = &app_state.ups_client;
-    #[allow(unused_variables)]
-    let _access_token = &app_state.access_token;
-
-    // Calculate totals
+    // Price line items and calculate totals
     tracing::debug!("Calculating order totals");
-    let total = calculate_totals(&request.prints, &request.shipping_option)?;
-    tracing::info!("Order total calculated: ${:.2}", total.grand_total);
+    let items = price_line_items(&order_id, &request.prints, &entries)?;
+    let shipping_price = match &quote {
+        Some(quote) => quote.price.clone(),
+        None => Money::zero(ORDER_CURRENCY),
+    };
+    let total = TotalResponse {
+        duties_and_taxes: quote.as_ref().and_then(|q| q.duties_and_taxes.clone()),
+        ..calculate_totals(&items, &shipping_price)?
+    };
+    tracing::info!("Order total calculated: {}", total.grand_total);
 
-    // Create delivery estimate
+    // Create delivery estimate; pickup orders can be collected once printed
     tracing::debug!("Calculating delivery estimate");
-    let delivery_estimate = calculate_delivery_estimate(&request.shipping_option)?;
+    let delivery_estimate = match &quote {
+        Some(quote) => calculate_delivery_estimate(&app_state.calendar, ship_date, quote),
+        None => DeliveryEstimate {
+            min_date: ship_date,
+            max_date: ship_date,
+        },
+    };
 
-    // Handle payment processing
+    // Pick the payment provider before anything is saved
     tracing::debug!("Processing payment method: {}", request.payment.method);
-    let (status, paypal_response) = match request.payment.method.as_str() {
-        "paypal" => {
-            tracing::info!("Processing PayPal payment");
-            let paypal = process_paypal_payment(&request.payment, total.grand_total)?;
-            ("pending_payment".to_string(), Some(paypal))
-        }
-        "credit_card" => {
-            tracing::info!("Processing credit card payment");
-            // For credit card, we'd process immediately
-            ("processing".to_string(), None)
-        }
+    let provider_name = match request.payment.method.as_str() {
+        "paypal" => "PayPal",
+        "credit_card" => "Card",
         _ => {
             tracing::error!("Unsupported payment method: {}", request.payment.method);
             return Err("Unsupported payment method".into());
         }
     };
+    let provider = app_state
+        .payment_providers
+        .get(&request.payment.method)
+        .ok_or_else(|| format!("{} payments are not available", provider_name))?;
+
+    // Pickup orders are addressed to the lab they're collected from
+    let (address, shipping_option, pickup_code) = match request.fulfillment_method {
+        FulfillmentMethod::Ship => (
+            request
+                .customer
+                .shipping_address
+                .ok_or("Shipping address is required")?,
+            request.shipping_option,
+            None,
+        ),
+        FulfillmentMethod::Pickup => (
+            AddressRequest {
+                line1: location.line1.clone(),
+                line2: location.line2.clone(),
+                city: location.city.clone(),
+                state: location.state.clone(),
+                postal_code: location.postal_code.clone(),
+                country: location.country.clone(),
+            },
+            PICKUP_SHIPPING_OPTION.to_string(),
+            Some(generate_pickup_code()),
+        ),
+    };
+    let pickup = pickup_code.clone().map(|code| PickupResponse {
+        location: PickupLocation::from(location.clone()),
+        code,
+    });
+
+    // Persist the order and its line items in one transaction. The order
+    // waits for payment until the provider confirms it, and is saved before
+    // the payment is created so the provider's webhooks always find it.
+    let status = OrderStatus::PendingPayment;
+    let now = Utc::now();
+    let order = PrintOrder {
+        order_id: order_id.clone(),
+        user_id: Some(user_id),
+        status,
+        customer_name: request.customer.name,
+        customer_email: request.customer.email,
+        customer_phone: request.customer.phone,
+        shipping_line1: address.line1,
+        shipping_line2: address.line2,
+        shipping_city: address.city,
+        shipping_state: address.state,
+        shipping_postal_code: address.postal_code,
+        shipping_country: address.country,
+        special_instructions: request.special_instructions,
+        shipping_option,
+        fulfillment_method: request.fulfillment_method,
+        fulfillment_location_id: Some(location.location_id),
+        tracking_number: None,
+        pickup_code,
+        payment_method: request.payment.method,
+        payment_reference: None,
+        items_subtotal: total.items_subtotal.clone(),
+        shipping: total.shipping.clone(),
+        tax: total.tax.clone(),
+        grand_total: total.grand_total.clone(),
+        amount_refunded: Money::zero(&total.currency),
+        currency: total.currency.clone(),
+        estimated_delivery_min: delivery_estimate.min_date,
+        estimated_delivery_max: delivery_estimate.max_date,
+        created_at: now,
+        updated_at: now,
+        items,
+    };
+    tracing::debug!("Saving order {}", order_id);
+    app_state.orders.create(&order).await?;
+
+    tracing::info!("Creating {} payment", provider_name);
+    let created = provider
+        .create_intent(&CreateIntentRequest {
+            order_id: order_id.clone(),
+            amount: total.grand_total.clone(),
+            description: Some(format!("Print order {}", order_id)),
+            request_id: payments::order_request_id(&order_id),
+        })
+        .await;
+    let intent = match created {
+        Ok(intent) => intent,
+        Err(err) => {
+            // Nothing can be paid, so the order won't be either
+            if let Err(cancel_err) = app_state
+                .orders
+                .update_status(
+                    &order_id,
+                    status,
+                    OrderStatus::Cancelled,
+                    None,
+                    Some(format!("Payment could not be created: {}", err)),
+                )
+                .await
+            {
+                tracing::error!("Failed to cancel order {}: {}", order_id, cancel_err);
+            }
+            return Err(err.into());
+        }
+    };
+    app_state
+        .orders
+        .set_payment_reference(&order_id, &intent.intent_id)
+        .await?;
+    app_state
+        .payments
+        .create(&NewPayment {
+            order_id: order_id.clone(),
+            provider: order.payment_method.clone(),
+            intent_id: intent.intent_id.clone(),
+            status: intent.status,
+            amount: intent.amount.clone(),
+        })
+        .await?;
+
+    let (paypal_response, stripe_response, message) = match order.payment_method.as_str() {
+        "paypal" => (
+            Some(PayPalResponse {
+                order_id: intent.intent_id.clone(),
+                approval_url: intent
+                    .approval_url
+                    .clone()
+                    .ok_or("PayPal did not return an approval link")?,
+            }),
+            None,
+            "Order created successfully. Please complete payment using the provided PayPal link.",
+        ),
+        _ => (
+            None,
+            Some(StripeResponse {
+                payment_intent_id: intent.intent_id.clone(),
+                client_secret: intent
+                    .client_secret
+                    .clone()
+                    .ok_or("Stripe did not return a client secret")?,
+            }),
+            "Order created successfully. Please confirm the card payment.",
+        ),
+    };
+
+    // Render a crop preview of every image for each size it's printed at
+    for image in images {
+        let sizes = sizes_for_image(&order.items, &entries, &image.image_id);
+        if !sizes.is_empty() {
+            previews::spawn_render(app_state.clone(), image, false, sizes);
+        }
+    }
 
     tracing::info!("Order processing completed successfully");
     Ok(OrderResponse {
         order_id: order_id.clone(),
         status,
         paypal: paypal_response,
+        stripe: stripe_response,
         total,
         estimated_delivery: delivery_estimate,
-        message:
-            "Order created successfully. Please complete payment using the provided PayPal link."
-                .to_string(),
+        pickup,
+        warnings,
+        message: message.to_string(),
     })
 }
 
 /// Validate the order request
+///
+/// `images` are the stored uploads for the image IDs in the request; every
+/// referenced image must be among them and belong to `user_id`.
 fn validate_order_request(
     request: &OrderRequest,
+    user_id: Uuid,
+    images: &[StoredImage],
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     if request.customer.name.trim().is_empty() {
         return Err("Customer name is required".into());
@@ -257,6 +661,16 @@ fn validate_order_request(
         return Err("Customer email is required".into());
     }
 
+    match request.fulfillment_method {
+        FulfillmentMethod::Ship if request.customer.shipping_address.is_none() => {
+            return Err("Shipping address is required".into());
+        }
+        FulfillmentMethod::Pickup if request.pickup_location_id.is_none() => {
+            return Err("Pickup location is required".into());
+        }
+        _ => {}
+    }
+
     if request.prints.is_empty() {
         return Err("At least one print item is required".into());
     }
@@ -265,114 +679,627 @@ fn validate_order_request(
         if print.quantity == 0 {
             return Err("Print quantity must be greater than 0".into());
         }
+        if print.quantity > MAX_PRINT_QUANTITY {
+            return Err(format!("Print quantity can be at most {}", MAX_PRINT_QUANTITY).into());
+        }
         if print.image_ids.is_empty() {
             return Err("At least one image ID is required for each print".into());
         }
+        for image_id in &print.image_ids {
+            // Other users' images are reported the same way as missing ones
+            let owned = images
+                .iter()
+                .any(|image| &image.image_id == image_id && image.user_id == Some(user_id));
+            if !owned {
+                return Err(format!("Unknown image ID: {}", image_id).into());
+            }
+        }
     }
 
     Ok(())
 }
 
 /// Generate a unique order ID
-fn generate_order_id() -> String {
-    let now = Utc::now();
-    format!("ord_{}_0001", now.format("%Y%m%d"))
+///
+/// IDs follow the `ord_YYYYMMDD_NNNN` format, where `NNNN` comes from a
+/// per-day database counter so concurrent requests never share a number.
+async fn generate_order_id(orders: &dyn OrderRepository) -> Result<String, RepositoryError> {
+    let today = Utc::now().date_naive();
+    let number = orders.next_order_number(today).await?;
+    Ok(format_order_id(today, number))
 }
 
-/// Calculate order totals
-fn calculate_totals(
+/// Format an order ID from its day and sequence number
+fn format_order_id(day: NaiveDate, number: i32) -> String {
+    format!("ord_{}_{:04}", day.format("%Y%m%d"), number)
+}
+
+/// Generate the code a customer shows to collect a pickup order
+fn generate_pickup_code() -> String {
+    format!("{:06}", OsRng.gen_range(0..1_000_000))
+}
+
+/// Most prints one line of an order or quote may ask for, per image
+pub(crate) const MAX_PRINT_QUANTITY: u32 = 1_000;
+
+/// Shipping option recorded on pickup orders
+pub(crate) const PICKUP_SHIPPING_OPTION: &str = "pickup";
+
+/// Currency all orders are priced in
+pub(crate) const ORDER_CURRENCY: &str = "USD";
+
+/// Sales tax rate in basis points (7%)
+const SALES_TAX_BASIS_POINTS: i64 = 700;
+
+/// Look up the catalog size and finish of each print request
+async fn resolve_catalog(
+    catalog: &dyn CatalogRepository,
     prints: &[PrintRequest],
-    shipping_option: &str,
-) -> Result<TotalResponse, Box<dyn std::error::Error + Send + Sync>> {
-    let mut items_subtotal = 0.0;
+) -> Result<Vec<(PrintSize, PrintFinish)>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut entries = Vec::with_capacity(prints.len());
 
-    // Calculate print costs based on size and quantity
     for print in prints {
-        let unit_price = match print.size.as_str() {
-            "4x6" => 1.50,
-            "5x7" => 2.00,
-            "8x10" => 4.00,
-            "11x14" => 8.00,
+        let size = match catalog.find_size(&print.size).await? {
+            Some(size) if size.active => size,
             _ => return Err(format!("Unsupported print size: {}", print.size).into()),
         };
-
-        // Add finish premium
-        let finish_premium = match print.finish.as_str() {
-            "glossy" => 0.0,
-            "matte" => 0.25,
-            "metallic" => 0.50,
+        let finish = match catalog.find_finish(&print.finish).await? {
+            Some(finish) if finish.active => finish,
             _ => return Err(format!("Unsupported finish: {}", print.finish).into()),
         };
+        entries.push((size, finish));
+    }
+
+    Ok(entries)
+}
 
-        let total_images = print.image_ids.len() as f64;
-        items_subtotal += (unit_price + finish_premium) * print.quantity as f64 * total_images;
+/// Distinct catalog sizes an image is printed at
+///
+/// `entries` holds the catalog size and finish of each line item, in order.
+fn sizes_for_image(
+    items: &[PrintOrderItem],
+    entries: &[(PrintSize, PrintFinish)],
+    image_id: &str,
+) -> Vec<PrintSize> {
+    let mut sizes: Vec<PrintSize> = Vec::new();
+    for (item, (size, _)) in items.iter().zip(entries) {
+        let uses_image = item.image_ids.iter().any(|id| id == image_id);
+        if uses_image && !sizes.iter().any(|s| s.size_id == size.size_id) {
+            sizes.push(size.clone());
+        }
     }
+    sizes
+}
 
-    // Calculate shipping
-    let shipping = match shipping_option {
-        "USPS_Ground" => 5.00,
-        "USPS_Priority" => 7.50,
-        "USPS_Express" => 12.00,
-        "UPS_Ground" => 6.50,
-        "UPS_2Day" => 10.00,
-        "UPS_Overnight" => 20.00,
-        _ => return Err(format!("Unsupported shipping option: {}", shipping_option).into()),
-    };
+/// Price each print request as an order line item
+///
+/// `entries` holds the catalog size and finish of each print, in order.
+fn price_line_items(
+    order_id: &str,
+    prints: &[PrintRequest],
+    entries: &[(PrintSize, PrintFinish)],
+) -> Result<Vec<PrintOrderItem>, Box<dyn std::error::Error + Send + Sync>> {
+    prints
+        .iter()
+        .zip(entries)
+        .map(|(print, (size, finish))| price_line_item(order_id, print, size, finish))
+        .collect()
+}
 
-    // Calculate tax (example: 7% sales tax)
-    let tax = (items_subtotal + shipping) * 0.07;
-    let grand_total = items_subtotal + shipping + tax;
+/// Check that every image is sharp enough for the size it's printed at
+///
+/// Images without recorded dimensions are skipped.
+fn check_print_quality(
+    prints: &[PrintRequest],
+    entries: &[(PrintSize, PrintFinish)],
+    images: &[StoredImage],
+    config: &PrintQualityConfig,
+) -> Vec<PrintQualityWarning> {
+    let mut warnings: Vec<PrintQualityWarning> = Vec::new();
 
-    Ok(TotalResponse {
-        items_subtotal: (items_subtotal * 100.0).round() / 100.0,
-        shipping: (shipping * 100.0).round() / 100.0,
-        tax: (tax * 100.0).round() / 100.0,
-        currency: "USD".to_string(),
-        grand_total: (grand_total * 100.0).round() / 100.0,
-    })
+    for (print, (size, _)) in prints.iter().zip(entries) {
+        for image_id in &print.image_ids {
+            let Some(image) = images.iter().find(|image| &image.image_id == image_id) else {
+                continue;
+            };
+            let (Some(width), Some(height)) = (image.width_px, image.height_px) else {
+                continue;
+            };
+            let already_warned = warnings
+                .iter()
+                .any(|w| &w.image_id == image_id && w.size == size.size_id);
+            if already_warned {
+                continue;
+            }
+
+            let dimensions = ImageDimensions {
+                width: width as u32,
+                height: height as u32,
+            };
+            let dpi = effective_dpi(dimensions, size.width_in, size.height_in);
+            if dpi < config.min_dpi {
+                warnings.push(PrintQualityWarning {
+                    image_id: image_id.clone(),
+                    size: size.size_id.clone(),
+                    effective_dpi: dpi.floor() as u32,
+                    minimum_dpi: config.min_dpi.ceil() as u32,
+                    message: format!(
+                        "Image {} is only {} DPI at {}; at least {} DPI is recommended",
+                        image_id,
+                        dpi.floor(),
+                        size.size_id,
+                        config.min_dpi.ceil()
+                    ),
+                });
+            }
+        }
+    }
+
+    warnings
 }
 
-/// Calculate delivery estimate based on shipping option
-fn calculate_delivery_estimate(
-    shipping_option: &str,
-) -> Result<DeliveryEstimate, Box<dyn std::error::Error + Send + Sync>> {
-    let now = Utc::now();
+/// Route the order to a lab, quote shipping from there and pick the service
+/// the customer chose, priced by the shipping rules
+async fn quote_shipping(
+    request: &OrderRequest,
+    entries: &[(PrintSize, PrintFinish)],
+    ship_date: NaiveDate,
+    app_state: &AppState,
+) -> Result<(FulfillmentLocation, ShippingQuote), Box<dyn std::error::Error + Send + Sync>> {
+    let prints: Vec<(&PrintSize, i64)> = request
+        .prints
+        .iter()
+        .zip(entries)
+        .map(|(print, (size, _))| (size, print.quantity as i64 * print.image_ids.len() as i64))
+        .collect();
+    let Some((carrier, service)) = app_state.carriers.find_service(&request.shipping_option) else {
+        return Err(format!("Unsupported shipping option: {}", request.shipping_option).into());
+    };
+
+    let customer = &request.customer;
+    let address = customer
+        .shipping_address
+        .as_ref()
+        .ok_or("Shipping address is required")?;
+    let ship_to = shipment_address(address, &customer.name, Some(&customer.phone));
+    let packages = app_state.packaging.pack(&prints)?;
+    // Prints going abroad are declared at the price the customer pays
+    let customs_items = request
+        .prints
+        .iter()
+        .zip(entries)
+        .map(|(print, (size, finish))| {
+            Ok(CustomsItem::prints(
+                size,
+                Some(&finish.finish_id),
+                print.quantity as i64 * print.image_ids.len() as i64,
+                size.unit_price(finish)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, MoneyError>>()?;
+    let size_ids: Vec<&str> = entries
+        .iter()
+        .map(|(size, _)| size.size_id.as_str())
+        .collect();
+
+    let locations = app_state.locations.list(false).await?;
+    let Route { location, quotes } = app_state
+        .routing
+        .route(locations, &size_ids, &ship_to, |location| {
+            let shipment = shipping::quote_shipment(
+                &location,
+                &ship_to,
+                &packages,
+                ship_date,
+                ORDER_CURRENCY,
+                &customs_items,
+            );
+            async move {
+                let quotes = carrier.rate(&shipment?).await?;
+                Ok(quotes
+                    .into_iter()
+                    .filter(|quote| quote.service_code == service.code)
+                    .collect())
+            }
+        })
+        .await?;
+
+    // Charge the price the shipping rules set; hidden services aren't offered
+    let subtotal = request
+        .prints
+        .iter()
+        .zip(entries)
+        .map(|(print, (size, finish))| {
+            size.unit_price(finish)?
+                .checked_mul(print.quantity as i64 * print.image_ids.len() as i64)
+        })
+        .collect::<Result<Vec<_>, MoneyError>>()?;
+    let subtotal = Money::sum(&subtotal, ORDER_CURRENCY)?;
+    let rules = app_state.shipping_rules.list(false).await?;
+    let quotes = shipping_rules::apply_rules(&rules, &subtotal, &ship_to, quotes)?;
 
-    let (min_days, max_days) = match shipping_option {
-        "USPS_Ground" => (5, 8),
-        "USPS_Priority" => (2, 3),
-        "USPS_Express" => (1, 2),
-        "UPS_Ground" => (3, 5),
-        "UPS_2Day" => (2, 2),
-        "UPS_Overnight" => (1, 1),
-        _ => return Err(format!("Unsupported shipping option: {}", shipping_option).into()),
+    let quote = quotes.into_iter().next().ok_or_else(|| {
+        format!(
+            "Shipping option {} isn't available to this address",
+            request.shipping_option
+        )
+    })?;
+    Ok((location, quote))
+}
+
+/// Look up the lab the customer collects the order from, checking it offers
+/// pickup and produces every print
+async fn pickup_location(
+    request: &OrderRequest,
+    entries: &[(PrintSize, PrintFinish)],
+    app_state: &AppState,
+) -> Result<FulfillmentLocation, Box<dyn std::error::Error + Send + Sync>> {
+    let location_id = request
+        .pickup_location_id
+        .as_deref()
+        .ok_or("Pickup location is required")?;
+    let location = match app_state.locations.find(location_id).await? {
+        Some(location) if location.active && location.pickup => location,
+        _ => return Err(format!("Unknown pickup location: {}", location_id).into()),
     };
+    if let Some((size, _)) = entries
+        .iter()
+        .find(|(size, _)| !location.produces(&size.size_id))
+    {
+        return Err(format!("{} doesn't print {}", location.name, size.size_id).into());
+    }
+    Ok(location)
+}
+
+/// Price a single print request at a catalog size and finish
+fn price_line_item(
+    order_id: &str,
+    print: &PrintRequest,
+    size: &PrintSize,
+    finish: &PrintFinish,
+) -> Result<PrintOrderItem, Box<dyn std::error::Error + Send + Sync>> {
+    let unit_price = size.unit_price(finish)?;
+    if unit_price.currency() != ORDER_CURRENCY {
+        return Err(format!(
+            "Print size {} is priced in {}, expected {}",
+            size.size_id,
+            unit_price.currency(),
+            ORDER_CURRENCY
+        )
+        .into());
+    }
 
-    let min_date = now + chrono::Duration::days(min_days);
-    let max_date = now + chrono::Duration::days(max_days);
+    let quantity = i32::try_from(print.quantity)
+        .map_err(|_| format!("Print quantity {} is too large", print.quantity))?;
+    let total_images = print.image_ids.len() as i64;
+    let line_total = unit_price.checked_mul(i64::from(quantity) * total_images)?;
 
-    Ok(DeliveryEstimate {
-        min_date: min_date.format("%Y-%m-%d").to_string(),
-        max_date: max_date.format("%Y-%m-%d").to_string(),
+    Ok(PrintOrderItem {
+        item_id: 0,
+        order_id: order_id.to_string(),
+        size: print.size.clone(),
+        finish: print.finish.clone(),
+        quantity,
+        image_ids: print.image_ids.clone(),
+        unit_price,
+        line_total,
+        hs_code: size.hs_code.clone(),
     })
 }
 
-/// Process PayPal payment
-fn process_paypal_payment(
-    payment: &PaymentRequest,
-    _total: f64,
-) -> Result<PayPalResponse, Box<dyn std::error::Error + Send + Sync>> {
-    // In a real implementation, this would integrate with PayPal API
-    let order_id = payment
-        .order_id
-        .as_ref()
-        .ok_or("PayPal order ID is required")?;
+/// Calculate order totals with the quoted shipping price
+fn calculate_totals(
+    items: &[PrintOrderItem],
+    shipping: &Money,
+) -> Result<TotalResponse, Box<dyn std::error::Error + Send + Sync>> {
+    let items_subtotal = Money::sum(items.iter().map(|item| &item.line_total), ORDER_CURRENCY)?;
+    if shipping.currency() != ORDER_CURRENCY {
+        return Err(format!(
+            "Shipping is quoted in {}, expected {}",
+            shipping.currency(),
+            ORDER_CURRENCY
+        )
+        .into());
+    }
+    let shipping = shipping.clone();
 
-    Ok(PayPalResponse {
-        order_id: order_id.clone(),
-        approval_url: format!(
-            "https://www.sandbox.paypal.com/checkoutnow?token={}",
-            order_id
-        ),
+    // Calculate tax (example: 7% sales tax), rounded half-up to the cent
+    let taxable = items_subtotal.checked_add(&shipping)?;
+    let tax = taxable.apply_basis_points(SALES_TAX_BASIS_POINTS)?;
+    let grand_total = taxable.checked_add(&tax)?;
+
+    Ok(TotalResponse {
+        items_subtotal,
+        shipping,
+        tax,
+        currency: ORDER_CURRENCY.to_string(),
+        grand_total,
+        duties_and_taxes: None,
     })
 }
+
+/// Calculate delivery estimate for a shipment leaving on `ship_date`
+///
+/// The carrier's arrival date is used when it has one; otherwise the quoted
+/// transit time is counted in business days.
+pub(crate) fn calculate_delivery_estimate(
+    calendar: &BusinessCalendar,
+    ship_date: NaiveDate,
+    quote: &ShippingQuote,
+) -> DeliveryEstimate {
+    if let Some(arrival_date) = quote.arrival_date {
+        return DeliveryEstimate {
+            min_date: arrival_date,
+            max_date: arrival_date,
+        };
+    }
+
+    DeliveryEstimate {
+        min_date: calendar.add_business_days(ship_date, quote.transit_days_min),
+        max_date: calendar.add_business_days(ship_date, quote.transit_days_max),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn test_format_order_id() {
+        let day = NaiveDate::from_ymd_opt(2025, 8, 12).unwrap();
+        assert_eq!(format_order_id(day, 1), "ord_20250812_0001");
+        assert_eq!(format_order_id(day, 42), "ord_20250812_0042");
+        // Busy days widen the number instead of wrapping around
+        assert_eq!(format_order_id(day, 12345), "ord_20250812_12345");
+    }
+
+    fn catalog_size(size_id: &str, price_cents: i64) -> PrintSize {
+        PrintSize {
+            size_id: size_id.to_string(),
+            description: format!("{} print", size_id),
+            price: Money::from_minor(price_cents, "USD"),
+            ..test_support::print_size()
+        }
+    }
+
+    fn catalog_finish(finish_id: &str, premium_cents: i64) -> PrintFinish {
+        PrintFinish {
+            finish_id: finish_id.to_string(),
+            description: format!("{} paper", finish_id),
+            premium: Money::from_minor(premium_cents, "USD"),
+            active: true,
+            sort_order: 0,
+        }
+    }
+
+    #[test]
+    fn test_calculate_totals_is_exact() {
+        let prints = [
+            PrintRequest {
+                size: "4x6".to_string(),
+                quantity: 10,
+                finish: "glossy".to_string(),
+                image_ids: vec!["img_001".to_string(), "img_002".to_string()],
+            },
+            PrintRequest {
+                size: "8x10".to_string(),
+                quantity: 2,
+                finish: "matte".to_string(),
+                image_ids: vec!["img_003".to_string()],
+            },
+        ];
+        let items = vec![
+            price_line_item(
+                "ord_1",
+                &prints[0],
+                &catalog_size("4x6", 150),
+                &catalog_finish("glossy", 0),
+            )
+            .unwrap(),
+            price_line_item(
+                "ord_1",
+                &prints[1],
+                &catalog_size("8x10", 400),
+                &catalog_finish("matte", 25),
+            )
+            .unwrap(),
+        ];
+        assert_eq!(items[0].line_total, Money::from_minor(3000, "USD"));
+        assert_eq!(items[1].unit_price, Money::from_minor(425, "USD"));
+
+        let total = calculate_totals(&items, &Money::from_minor(750, "USD")).unwrap();
+        assert_eq!(total.items_subtotal, Money::from_minor(3850, "USD"));
+        // 7% of $46.00 is exactly $3.22
+        assert_eq!(total.tax, Money::from_minor(322, "USD"));
+        assert_eq!(total.grand_total, Money::from_minor(4922, "USD"));
+    }
+
+    #[test]
+    fn test_price_line_item_rejects_oversized_quantity() {
+        let print = PrintRequest {
+            size: "4x6".to_string(),
+            quantity: u32::MAX,
+            finish: "glossy".to_string(),
+            image_ids: vec!["img_001".to_string()],
+        };
+        let err = price_line_item(
+            "ord_1",
+            &print,
+            &catalog_size("4x6", 150),
+            &catalog_finish("glossy", 0),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Print quantity 4294967295 is too large");
+    }
+
+    fn test_request(image_ids: &[&str]) -> OrderRequest {
+        serde_json::from_value(serde_json::json!({
+            "customer": {
+                "name": "Jane Doe",
+                "email": "jane.doe@example.com",
+                "phone": "+1-555-234-5678",
+                "shipping_address": {
+                    "line1": "123 Main Street",
+                    "city": "Denver",
+                    "state": "CO",
+                    "postal_code": "80202",
+                    "country": "US"
+                }
+            },
+            "prints": [
+                { "size": "4x6", "quantity": 1, "finish": "glossy", "image_ids": image_ids }
+            ],
+            "shipping_option": "UPS_Ground",
+            "payment": { "method": "credit_card" }
+        }))
+        .unwrap()
+    }
+
+    fn stored_image(image_id: &str, user_id: Uuid) -> StoredImage {
+        StoredImage {
+            image_id: image_id.to_string(),
+            user_id: Some(user_id),
+            order_id: None,
+            storage_key: format!("uploads/{}/{}/original.jpg", user_id, image_id),
+            original_filename: None,
+            content_type: "image/jpeg".to_string(),
+            size_bytes: 1024,
+            width_px: Some(4032),
+            height_px: Some(3024),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_validate_order_request_image_ownership() {
+        let me = Uuid::new_v4();
+        let someone_else = Uuid::new_v4();
+        let images = vec![
+            stored_image("img_mine", me),
+            stored_image("img_theirs", someone_else),
+        ];
+
+        assert!(validate_order_request(&test_request(&["img_mine"]), me, &images).is_ok());
+
+        let err = validate_order_request(&test_request(&["img_missing"]), me, &images)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "Unknown image ID: img_missing");
+
+        let err = validate_order_request(&test_request(&["img_mine", "img_theirs"]), me, &images)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "Unknown image ID: img_theirs");
+    }
+
+    #[test]
+    fn test_validate_order_request_fulfillment_method() {
+        let me = Uuid::new_v4();
+        let images = vec![stored_image("img_mine", me)];
+
+        let mut request = test_request(&["img_mine"]);
+        request.customer.shipping_address = None;
+        let err = validate_order_request(&request, me, &images)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "Shipping address is required");
+
+        // Pickup orders need a lab instead of an address
+        request.fulfillment_method = FulfillmentMethod::Pickup;
+        let err = validate_order_request(&request, me, &images)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "Pickup location is required");
+        request.pickup_location_id = Some("denver".to_string());
+        assert!(validate_order_request(&request, me, &images).is_ok());
+    }
+
+    #[test]
+    fn test_validate_order_request_quantity() {
+        let me = Uuid::new_v4();
+        let images = vec![stored_image("img_mine", me)];
+
+        let mut request = test_request(&["img_mine"]);
+        request.prints[0].quantity = MAX_PRINT_QUANTITY;
+        assert!(validate_order_request(&request, me, &images).is_ok());
+
+        request.prints[0].quantity = u32::MAX;
+        let err = validate_order_request(&request, me, &images)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "Print quantity can be at most 1000");
+    }
+
+    #[test]
+    fn test_generate_pickup_code() {
+        let code = generate_pickup_code();
+        assert_eq!(code.len(), 6);
+        assert!(code.bytes().all(|b| b.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_check_print_quality() {
+        let me = Uuid::new_v4();
+        let mut phone_photo = stored_image("img_photo", me);
+        (phone_photo.width_px, phone_photo.height_px) = (Some(4032), Some(3024));
+        let mut thumbnail = stored_image("img_thumb", me);
+        (thumbnail.width_px, thumbnail.height_px) = (Some(640), Some(480));
+        let mut unknown = stored_image("img_unknown", me);
+        (unknown.width_px, unknown.height_px) = (None, None);
+        let images = vec![phone_photo, thumbnail, unknown];
+
+        let mut large = catalog_size("11x14", 800);
+        (large.width_in, large.height_in) = (11.0, 14.0);
+        let mut request = test_request(&["img_photo", "img_thumb", "img_unknown"]);
+        request.prints[0].size = "11x14".to_string();
+        let entries = vec![(large, catalog_finish("glossy", 0))];
+
+        let config = PrintQualityConfig::default();
+        let warnings = check_print_quality(&request.prints, &entries, &images, &config);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].image_id, "img_thumb");
+        assert_eq!(warnings[0].size, "11x14");
+        assert_eq!(warnings[0].effective_dpi, 43);
+        assert_eq!(warnings[0].minimum_dpi, 150);
+
+        // The same thumbnail is fine on a 4x6 at a lower threshold
+        let entries = vec![(catalog_size("4x6", 150), catalog_finish("glossy", 0))];
+        let config = PrintQualityConfig {
+            min_dpi: 100.0,
+            strict: true,
+        };
+        assert!(check_print_quality(&request.prints, &entries, &images, &config).is_empty());
+    }
+
+    #[test]
+    fn test_calculate_delivery_estimate() {
+        let calendar = BusinessCalendar::default();
+        let friday = NaiveDate::from_ymd_opt(2025, 11, 21).unwrap();
+        let service = crate::carriers::CarrierService {
+            shipping_option: "UPS_Ground",
+            code: "03",
+            description: "UPS Ground",
+            transit_days: (1, 5),
+        };
+        let mut quote = ShippingQuote::new("ups", &service, Money::from_minor(1187, "USD"), None);
+
+        // Transit days skip the weekend
+        let estimate = calculate_delivery_estimate(&calendar, friday, &quote);
+        assert_eq!(
+            estimate.min_date,
+            NaiveDate::from_ymd_opt(2025, 11, 24).unwrap()
+        );
+        assert_eq!(
+            estimate.max_date,
+            NaiveDate::from_ymd_opt(2025, 11, 28).unwrap()
+        );
+
+        // The carrier's own arrival date wins
+        let arrival = NaiveDate::from_ymd_opt(2025, 11, 26).unwrap();
+        quote.arrival_date = Some(arrival);
+        let estimate = calculate_delivery_estimate(&calendar, friday, &quote);
+        assert_eq!(estimate.min_date, arrival);
+        assert_eq!(estimate.max_date, arrival);
+    }
+}
@@ -1 +1,1314 @@
+use axum::{
+    Extension, Json,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
+use crate::{
+    AppState,
+    auth::Claims,
+    endpoints::orders::error_response,
+    models::{
+        order_status::OrderStatus,
+        payment::{Payment, Refund},
+        print_order::PrintOrder,
+    },
+    money::{Money, MoneyError},
+    payments::{
+        self, CreateIntentRequest, PaymentError, PaymentStatus, WebhookAction, WebhookEvent,
+    },
+    repositories::{
+        RepositoryError,
+        payments::{NewPayment, NewRefund, NewRefundItem},
+    },
+};
+
+/// Request payload naming the order to pay for
+#[derive(Debug, Deserialize)]
+pub struct PaymentOrderRequest {
+    pub order_id: String,
+}
+
+/// An order's payment after an intent or capture request
+#[derive(Debug, Serialize)]
+pub struct PaymentResponse {
+    pub order_id: String,
+    pub order_status: OrderStatus,
+    pub payment: Payment,
+    /// Page where the customer approves the payment (PayPal)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_url: Option<String>,
+    /// Secret for confirming the payment with Stripe.js (card payments)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    pub message: String,
+}
+
+/// POST /api/payments/intent - Create a new payment for an order awaiting payment
+///
+/// Used when the customer needs another try, e.g. after cancelling on PayPal.
+pub async fn create_intent_endpoint(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<PaymentOrderRequest>,
+) -> Response {
+    let order = match load_own_order(&state, &claims, &request.order_id).await {
+        Ok(order) => order,
+        Err(response) => return response,
+    };
+    if order.status != OrderStatus::PendingPayment {
+        return error_response(
+            StatusCode::CONFLICT,
+            "ORDER_NOT_PAYABLE",
+            format!("Order is {}", order.status),
+        );
+    }
+    let Some(provider) = state.payment_providers.get(&order.payment_method) else {
+        return error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "PAYMENT_METHOD_UNAVAILABLE",
+            format!("{} payments are not available", order.payment_method),
+        );
+    };
+
+    // Each try follows the payment before it, so a retried request for the
+    // same try gets the same ID
+    let request_id = match state.payments.latest_for_order(&order.order_id).await {
+        Ok(Some(previous)) => format!("retry-{}", previous.intent_id),
+        Ok(None) => payments::order_request_id(&order.order_id),
+        Err(err) => {
+            tracing::error!("Failed to load payment for {}: {}", order.order_id, err);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "PAYMENT_LOOKUP_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+    let intent = match provider
+        .create_intent(&CreateIntentRequest {
+            order_id: order.order_id.clone(),
+            amount: order.grand_total.clone(),
+            description: Some(format!("Print order {}", order.order_id)),
+            request_id,
+        })
+        .await
+    {
+        Ok(intent) => intent,
+        Err(err) => return payment_error_response(&order.order_id, err),
+    };
+
+    let recorded = async {
+        let payment = state
+            .payments
+            .create(&NewPayment {
+                order_id: order.order_id.clone(),
+                provider: order.payment_method.clone(),
+                intent_id: intent.intent_id.clone(),
+                status: intent.status,
+                amount: intent.amount.clone(),
+            })
+            .await?;
+        state
+            .orders
+            .set_payment_reference(&order.order_id, &intent.intent_id)
+            .await?;
+        Ok::<_, RepositoryError>(payment)
+    }
+    .await;
+    let payment = match recorded {
+        Ok(payment) => payment,
+        Err(err) => {
+            tracing::error!("Failed to record payment for {}: {}", order.order_id, err);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "PAYMENT_RECORD_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+
+    tracing::info!(
+        "Created {} payment {} for order {}",
+        payment.provider,
+        payment.intent_id,
+        order.order_id
+    );
+    (
+        StatusCode::CREATED,
+        Json(PaymentResponse {
+            order_id: order.order_id,
+            order_status: order.status,
+            payment,
+            approval_url: intent.approval_url,
+            client_secret: intent.client_secret,
+            message: "Payment created. Please complete it with the payment provider.".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// POST /api/payments/capture - Take the money for an approved payment
+///
+/// The order becomes `paid` once the provider confirms the capture.
+/// Capturing an already captured payment returns it unchanged.
+pub async fn capture_payment_endpoint(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<PaymentOrderRequest>,
+) -> Response {
+    let order = match load_own_order(&state, &claims, &request.order_id).await {
+        Ok(order) => order,
+        Err(response) => return response,
+    };
+    let payment = match state.payments.latest_for_order(&order.order_id).await {
+        Ok(Some(payment)) => payment,
+        Ok(None) => {
+            return error_response(
+                StatusCode::CONFLICT,
+                "NO_PAYMENT",
+                "Order has no payment to capture".to_string(),
+            );
+        }
+        Err(err) => {
+            tracing::error!("Failed to load payment of {}: {}", order.order_id, err);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "PAYMENT_LOOKUP_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+
+    if payment.status == PaymentStatus::Completed {
+        return Json(PaymentResponse {
+            order_id: order.order_id,
+            order_status: order.status,
+            payment,
+            approval_url: None,
+            client_secret: None,
+            message: "Payment already captured".to_string(),
+        })
+        .into_response();
+    }
+    if order.status != OrderStatus::PendingPayment {
+        return error_response(
+            StatusCode::CONFLICT,
+            "ORDER_NOT_PAYABLE",
+            format!("Order is {}", order.status),
+        );
+    }
+    let Some(provider) = state.payment_providers.get(&payment.provider) else {
+        return error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "PAYMENT_METHOD_UNAVAILABLE",
+            format!("{} payments are not available", payment.provider),
+        );
+    };
+
+    let capture = match provider.capture(&payment.intent_id).await {
+        Ok(capture) => capture,
+        Err(err) => return payment_error_response(&order.order_id, err),
+    };
+    let payment = match state
+        .payments
+        .update_status(
+            payment.payment_id,
+            capture.status,
+            Some(&capture.capture_id),
+        )
+        .await
+    {
+        Ok(payment) => payment,
+        Err(err) => {
+            // The money was taken, so this needs a human to reconcile it
+            tracing::error!(
+                "Captured {} for order {} but failed to record it: {}",
+                capture.capture_id,
+                order.order_id,
+                err
+            );
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "PAYMENT_RECORD_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+
+    let (status, order_status, message) = match capture.status {
+        PaymentStatus::Completed => {
+            let note = format!(
+                "Payment captured by {} ({})",
+                payment.provider, capture.capture_id
+            );
+            if let Err(err) = mark_order_paid(&state, &order.order_id, note).await {
+                tracing::error!("Failed to mark order {} paid: {}", order.order_id, err);
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "ORDER_STATUS_UPDATE_FAILED",
+                    err.to_string(),
+                );
+            }
+            tracing::info!(
+                "Order {} paid with {} capture {}",
+                order.order_id,
+                payment.provider,
+                capture.capture_id
+            );
+            (StatusCode::OK, OrderStatus::Paid, "Payment captured")
+        }
+        PaymentStatus::Failed => (
+            StatusCode::PAYMENT_REQUIRED,
+            order.status,
+            "Payment was declined",
+        ),
+        _ => (
+            StatusCode::ACCEPTED,
+            order.status,
+            "Payment is being processed",
+        ),
+    };
+
+    (
+        status,
+        Json(PaymentResponse {
+            order_id: order.order_id,
+            order_status,
+            payment,
+            approval_url: None,
+            client_secret: None,
+            message: message.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// Request payload for a refund (admin only)
+///
+/// With neither `amount` nor `items`, everything still refundable is refunded.
+#[derive(Debug, Deserialize)]
+pub struct RefundRequest {
+    pub order_id: String,
+    /// Amount to refund in the order's currency, e.g. `"5.00"`
+    pub amount: Option<String>,
+    /// Line items to refund, each with its share of the tax
+    #[serde(default)]
+    pub items: Vec<RefundItemRequest>,
+    /// Why the money is being returned, kept with the refund
+    pub reason: String,
+}
+
+/// Prints of a line item to refund
+#[derive(Debug, Deserialize)]
+pub struct RefundItemRequest {
+    pub item_id: i64,
+    /// Number of prints; all prints not yet refunded when omitted
+    pub quantity: Option<i32>,
+}
+
+/// A recorded refund and what's left to refund
+#[derive(Debug, Serialize)]
+pub struct RefundResponse {
+    pub order_id: String,
+    pub order_status: OrderStatus,
+    pub refund: Refund,
+    pub amount_refunded: Money,
+    pub refundable_amount: Money,
+    pub message: String,
+}
+
+/// POST /api/admin/payments/refund (admin only) - Refund some or all of an order
+///
+/// The refund goes through the provider that took the payment. Once nothing
+/// is left to refund, the payment and order become `refunded`.
+pub async fn refund_payment_endpoint(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<RefundRequest>,
+) -> Response {
+    let reason = request.reason.trim().to_string();
+    if reason.is_empty() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "INVALID_REFUND",
+            "A reason is required".to_string(),
+        );
+    }
+
+    let loaded = async {
+        let order = state.orders.find_by_id(&request.order_id).await?;
+        let payment = state.payments.latest_for_order(&request.order_id).await?;
+        let refunds = state.payments.list_refunds(&request.order_id).await?;
+        Ok::<_, RepositoryError>((order, payment, refunds))
+    }
+    .await;
+    let (order, payment, refunds) = match loaded {
+        Ok((Some(order), payment, refunds)) => (order, payment, refunds),
+        Ok((None, _, _)) => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                "ORDER_NOT_FOUND",
+                "Order not found".to_string(),
+            );
+        }
+        Err(err) => {
+            tracing::error!("Failed to load order {}: {}", request.order_id, err);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "ORDER_LOOKUP_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+
+    let Some((payment, capture_id)) = payment
+        .filter(|payment| payment.status == PaymentStatus::Completed)
+        .and_then(|payment| payment.capture_id.clone().map(|id| (payment, id)))
+    else {
+        return error_response(
+            StatusCode::CONFLICT,
+            "NOT_REFUNDABLE",
+            "Order has no captured payment to refund".to_string(),
+        );
+    };
+
+    let (amount, items) = match refund_amount(&order, &refunds, &request) {
+        Ok(refund) => refund,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, "INVALID_REFUND", message),
+    };
+    let Some(provider) = state.payment_providers.get(&payment.provider) else {
+        return error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "PAYMENT_METHOD_UNAVAILABLE",
+            format!("{} payments are not available", payment.provider),
+        );
+    };
+
+    // Stable across retries until the refund is recorded, so the provider
+    // refunds once even if the first response was lost
+    let request_id = format!(
+        "refund-{}-{}-{}",
+        capture_id,
+        refunds.len(),
+        amount.amount_minor()
+    );
+    let result = match provider
+        .refund(&capture_id, Some(&amount), &request_id)
+        .await
+    {
+        Ok(result) => result,
+        Err(err) => return payment_error_response(&order.order_id, err),
+    };
+    let refunded_by = claims.sub.parse::<Uuid>().ok();
+    let recorded = state
+        .payments
+        .create_refund(&NewRefund {
+            order_id: order.order_id.clone(),
+            payment_id: payment.payment_id,
+            provider_refund_id: result.refund_id.clone(),
+            status: result.status,
+            amount: result.amount.clone(),
+            reason: reason.clone(),
+            refunded_by,
+            items,
+        })
+        .await;
+    let refund = match recorded {
+        Ok(refund) => refund,
+        Err(err) => {
+            // The money went back, so this needs a human to reconcile it
+            tracing::error!(
+                "Refunded {} for order {} ({}) but failed to record it: {}",
+                result.amount,
+                order.order_id,
+                result.refund_id,
+                err
+            );
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "REFUND_RECORD_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+    if refund.status == PaymentStatus::Failed {
+        return error_response(
+            StatusCode::BAD_GATEWAY,
+            "REFUND_FAILED",
+            format!(
+                "{} declined refund {}",
+                payment.provider, refund.provider_refund_id
+            ),
+        );
+    }
+    tracing::info!(
+        "Refunded {} of order {} by {}: {}",
+        refund.amount,
+        order.order_id,
+        claims.email,
+        reason
+    );
+
+    let order =
+        match settle_refunded_order(&state, &order.order_id, &payment, refunded_by, reason).await {
+            Ok(order) => order,
+            Err(err) => {
+                tracing::error!(
+                    "Failed to update order {} after refund: {}",
+                    order.order_id,
+                    err
+                );
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "ORDER_STATUS_UPDATE_FAILED",
+                    err.to_string(),
+                );
+            }
+        };
+
+    let refundable_amount = order
+        .refundable_amount()
+        .unwrap_or_else(|_| Money::zero(&order.currency));
+    (
+        StatusCode::CREATED,
+        Json(RefundResponse {
+            order_id: order.order_id,
+            order_status: order.status,
+            refund,
+            amount_refunded: order.amount_refunded,
+            refundable_amount,
+            message: "Refund issued".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// Mark the payment and order refunded once nothing is left to refund
+///
+/// Returns the order as it is afterwards.
+async fn settle_refunded_order(
+    state: &AppState,
+    order_id: &str,
+    payment: &Payment,
+    refunded_by: Option<Uuid>,
+    reason: String,
+) -> Result<PrintOrder, RepositoryError> {
+    let not_found = || RepositoryError::NotFound(format!("Order {} not found", order_id));
+    let order = state
+        .orders
+        .find_by_id(order_id)
+        .await?
+        .ok_or_else(not_found)?;
+    let fully_refunded = order
+        .refundable_amount()
+        .is_ok_and(|rest| rest.is_zero() || rest.is_negative());
+    if !fully_refunded {
+        return Ok(order);
+    }
+
+    state
+        .payments
+        .update_status(payment.payment_id, PaymentStatus::Refunded, None)
+        .await?;
+    // Cancelled orders keep their status; the refund is in their history
+    if !order.status.can_transition_to(OrderStatus::Refunded) {
+        return Ok(order);
+    }
+    match state
+        .orders
+        .update_status(
+            order_id,
+            order.status,
+            OrderStatus::Refunded,
+            refunded_by,
+            Some(reason),
+        )
+        .await
+    {
+        Ok(()) | Err(RepositoryError::Conflict(_)) => {}
+        Err(err) => return Err(err),
+    }
+    state
+        .orders
+        .find_by_id(order_id)
+        .await?
+        .ok_or_else(not_found)
+}
+
+/// Work out how much a refund request returns, and which prints it covers
+fn refund_amount(
+    order: &PrintOrder,
+    refunds: &[Refund],
+    request: &RefundRequest,
+) -> Result<(Money, Vec<NewRefundItem>), String> {
+    let refundable = order.refundable_amount().map_err(|e| e.to_string())?;
+    if refundable.is_zero() || refundable.is_negative() {
+        return Err("Order has already been fully refunded".to_string());
+    }
+    let exceeds_refundable = |amount: &Money| {
+        refundable
+            .checked_sub(amount)
+            .map(|rest| rest.is_negative())
+            .map_err(|e| e.to_string())
+    };
+
+    let mut items: Vec<NewRefundItem> = Vec::new();
+    let amount = match (&request.amount, request.items.is_empty()) {
+        (Some(_), false) => {
+            return Err("Give either an amount or line items, not both".to_string());
+        }
+        (Some(amount), true) => {
+            let amount = Money::parse(amount, &order.currency).map_err(|e| e.to_string())?;
+            if amount.is_zero() || amount.is_negative() {
+                return Err("Refund amount must be positive".to_string());
+            }
+            if exceeds_refundable(&amount)? {
+                return Err(format!("Only {} can still be refunded", refundable));
+            }
+            amount
+        }
+        (None, true) => refundable.clone(),
+        (None, false) => {
+            let taxable = order
+                .items_subtotal
+                .checked_add(&order.shipping)
+                .map_err(|e| e.to_string())?;
+            let mut total = Money::zero(&order.currency);
+            for requested in &request.items {
+                let item = order
+                    .items
+                    .iter()
+                    .find(|item| item.item_id == requested.item_id)
+                    .ok_or_else(|| format!("Order has no item {}", requested.item_id))?;
+                if items.iter().any(|i| i.item_id == item.item_id) {
+                    return Err(format!("Item {} is listed twice", item.item_id));
+                }
+                let already_refunded: i64 = refunds
+                    .iter()
+                    .filter(|refund| refund.status != PaymentStatus::Failed)
+                    .flat_map(|refund| &refund.items)
+                    .filter(|refunded| refunded.item_id == item.item_id)
+                    .map(|refunded| refunded.quantity as i64)
+                    .sum();
+                let remaining = item.print_count() - already_refunded;
+                let quantity = requested.quantity.map_or(remaining, |q| q as i64);
+                if quantity < 1 || quantity > remaining {
+                    return Err(format!(
+                        "Item {} has {} prints left to refund",
+                        item.item_id, remaining
+                    ));
+                }
+
+                let prints = item
+                    .unit_price
+                    .checked_mul(quantity)
+                    .map_err(|e| e.to_string())?;
+                let amount =
+                    with_tax_share(&prints, &taxable, &order.tax).map_err(|e| e.to_string())?;
+                total = total.checked_add(&amount).map_err(|e| e.to_string())?;
+                items.push(NewRefundItem {
+                    item_id: item.item_id,
+                    quantity: quantity as i32,
+                    amount,
+                });
+            }
+            // Rounded tax shares can add up to a cent more than is left
+            if exceeds_refundable(&total)? {
+                refundable.clone()
+            } else {
+                total
+            }
+        }
+    };
+    Ok((amount, items))
+}
+
+/// Add the tax charged on `amount` to it
+///
+/// Tax was charged on items and shipping together, so a part of the order
+/// carries a proportional share, rounded half-up to the minor unit.
+fn with_tax_share(amount: &Money, taxable: &Money, tax: &Money) -> Result<Money, MoneyError> {
+    if taxable.is_zero() {
+        return Ok(amount.clone());
+    }
+    let taxable_minor = taxable.amount_minor() as i128;
+    let share = (tax.amount_minor() as i128 * amount.amount_minor() as i128 * 2 + taxable_minor)
+        / (2 * taxable_minor);
+    amount.checked_add(&Money::from_minor(share as i64, tax.currency()))
+}
+
+/// Acknowledgement sent back to the payment provider
+#[derive(Debug, Serialize)]
+pub struct WebhookResponse {
+    pub event_id: String,
+    pub message: String,
+}
+
+/// POST /api/payments/webhook - Receive payment provider notifications
+///
+/// The request is matched to a provider by its signature headers and
+/// verified before anything is stored. Redelivered events are acknowledged
+/// without being applied again.
+pub async fn payment_webhook_endpoint(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let mut verified = None;
+    for provider in state.payment_providers.iter() {
+        match provider.verify_webhook(&headers, &body).await {
+            Ok(Some(event)) => {
+                verified = Some((provider.method().to_string(), event));
+                break;
+            }
+            Ok(None) => {}
+            Err(err) => {
+                tracing::warn!("Rejected {} webhook: {}", provider.method(), err);
+                let (status, code) = match err {
+                    PaymentError::Signature(_) | PaymentError::Parse(_) => {
+                        (StatusCode::BAD_REQUEST, "INVALID_WEBHOOK")
+                    }
+                    PaymentError::Config(_) => {
+                        (StatusCode::INTERNAL_SERVER_ERROR, "PAYMENT_CONFIG_ERROR")
+                    }
+                    // Verification itself failed; the provider will retry
+                    _ => (StatusCode::BAD_GATEWAY, "PAYMENT_PROVIDER_ERROR"),
+                };
+                return error_response(status, code, err.to_string());
+            }
+        }
+    }
+    let Some((provider, event)) = verified else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "INVALID_WEBHOOK",
+            "Request is not from a configured payment provider".to_string(),
+        );
+    };
+
+    let payload = String::from_utf8_lossy(&body);
+    let stored = match state
+        .payments
+        .record_event(&provider, &event.event_id, &event.event_type, &payload)
+        .await
+    {
+        Ok(stored) => stored,
+        Err(err) => {
+            tracing::error!(
+                "Failed to store {} event {}: {}",
+                provider,
+                event.event_id,
+                err
+            );
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "WEBHOOK_RECORD_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+    if stored.processed_at.is_some() {
+        tracing::info!("Skipping duplicate {} event {}", provider, event.event_id);
+        return Json(WebhookResponse {
+            event_id: event.event_id,
+            message: "Event already processed".to_string(),
+        })
+        .into_response();
+    }
+
+    // Failures leave the event unprocessed, so the provider's retry applies it
+    let applied = async {
+        apply_webhook_event(&state, &provider, &event).await?;
+        state.payments.mark_event_processed(stored.id).await
+    }
+    .await;
+    if let Err(err) = applied {
+        tracing::error!(
+            "Failed to apply {} event {}: {}",
+            provider,
+            event.event_id,
+            err
+        );
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "WEBHOOK_PROCESSING_FAILED",
+            err.to_string(),
+        );
+    }
+
+    Json(WebhookResponse {
+        event_id: event.event_id,
+        message: "Event processed".to_string(),
+    })
+    .into_response()
+}
+
+/// Update the payment and its order for a verified webhook event
+///
+/// Every change is guarded by the current status, so applying an event
+/// twice, or after the capture endpoint got there first, changes nothing.
+async fn apply_webhook_event(
+    state: &AppState,
+    provider: &str,
+    event: &WebhookEvent,
+) -> Result<(), RepositoryError> {
+    if event.action == WebhookAction::Ignored {
+        return Ok(());
+    }
+    let mut payment = None;
+    if let Some(intent_id) = &event.intent_id {
+        payment = state.payments.find_by_intent(provider, intent_id).await?;
+    }
+    if payment.is_none()
+        && let Some(capture_id) = &event.capture_id
+    {
+        payment = state.payments.find_by_capture(provider, capture_id).await?;
+    }
+    let Some(payment) = payment else {
+        tracing::warn!(
+            "{} event {} ({}) is for an unknown payment",
+            provider,
+            event.event_id,
+            event.event_type
+        );
+        return Ok(());
+    };
+
+    match &event.action {
+        WebhookAction::Paid => {
+            // A capture the provider completed after we gave up on it still counts
+            if matches!(
+                payment.status,
+                PaymentStatus::Pending
+                    | PaymentStatus::Approved
+                    | PaymentStatus::Failed
+                    | PaymentStatus::Cancelled
+            ) {
+                state
+                    .payments
+                    .update_status(
+                        payment.payment_id,
+                        PaymentStatus::Completed,
+                        event.capture_id.as_deref(),
+                    )
+                    .await?;
+            }
+            let note = format!("Payment confirmed by {} ({})", provider, event.event_id);
+            mark_order_paid(state, &payment.order_id, note).await?;
+            tracing::info!("Order {} paid ({} webhook)", payment.order_id, provider);
+        }
+        WebhookAction::Failed => {
+            // The order stays pending_payment so the customer can try again
+            if matches!(
+                payment.status,
+                PaymentStatus::Pending | PaymentStatus::Approved
+            ) {
+                state
+                    .payments
+                    .update_status(payment.payment_id, PaymentStatus::Failed, None)
+                    .await?;
+            }
+            tracing::info!(
+                "Payment {} for {} failed",
+                payment.intent_id,
+                payment.order_id
+            );
+        }
+        WebhookAction::Refunded { total_refunded } => {
+            // Covers refunds made in the provider's dashboard
+            state
+                .orders
+                .raise_amount_refunded(&payment.order_id, total_refunded)
+                .await?;
+            let fully_refunded = payment
+                .amount
+                .checked_sub(total_refunded)
+                .map(|rest| rest.is_zero() || rest.is_negative())
+                .unwrap_or(false);
+            if !fully_refunded {
+                tracing::info!(
+                    "Order {} partially refunded: {} of {}",
+                    payment.order_id,
+                    total_refunded,
+                    payment.amount
+                );
+                return Ok(());
+            }
+            if payment.status != PaymentStatus::Refunded {
+                state
+                    .payments
+                    .update_status(payment.payment_id, PaymentStatus::Refunded, None)
+                    .await?;
+            }
+            if let Some(order) = state.orders.find_by_id(&payment.order_id).await?
+                && order.status.can_transition_to(OrderStatus::Refunded)
+            {
+                let note = format!("Refunded through {} ({})", provider, event.event_id);
+                match state
+                    .orders
+                    .update_status(
+                        &order.order_id,
+                        order.status,
+                        OrderStatus::Refunded,
+                        None,
+                        Some(note),
+                    )
+                    .await
+                {
+                    Ok(()) | Err(RepositoryError::Conflict(_)) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            tracing::info!("Order {} refunded ({} webhook)", payment.order_id, provider);
+        }
+        WebhookAction::Disputed => {
+            if payment.status != PaymentStatus::Disputed {
+                state
+                    .payments
+                    .update_status(payment.payment_id, PaymentStatus::Disputed, None)
+                    .await?;
+                // The order keeps its status; the note flags it for staff
+                let note = format!("Payment disputed through {} ({})", provider, event.event_id);
+                state
+                    .orders
+                    .add_status_note(&payment.order_id, &note)
+                    .await?;
+            }
+            tracing::warn!(
+                "Payment {} for order {} was disputed",
+                payment.intent_id,
+                payment.order_id
+            );
+        }
+        WebhookAction::Ignored => {}
+    }
+    Ok(())
+}
+
+/// Move an order from `pending_payment` to `paid`
+///
+/// An order someone else already moved on is left alone.
+async fn mark_order_paid(
+    state: &AppState,
+    order_id: &str,
+    note: String,
+) -> Result<(), RepositoryError> {
+    match state
+        .orders
+        .update_status(
+            order_id,
+            OrderStatus::PendingPayment,
+            OrderStatus::Paid,
+            None,
+            Some(note),
+        )
+        .await
+    {
+        Ok(()) | Err(RepositoryError::Conflict(_)) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Load an order belonging to the caller
+async fn load_own_order(
+    state: &AppState,
+    claims: &Claims,
+    order_id: &str,
+) -> Result<PrintOrder, Response> {
+    let user_id = claims.sub.parse::<Uuid>().ok();
+    match state.orders.find_by_id(order_id).await {
+        Ok(Some(order)) if user_id.is_some() && order.user_id == user_id => Ok(order),
+        Ok(Some(_)) => Err(error_response(
+            StatusCode::FORBIDDEN,
+            "ACCESS_DENIED",
+            "Access denied".to_string(),
+        )),
+        Ok(None) => Err(error_response(
+            StatusCode::NOT_FOUND,
+            "ORDER_NOT_FOUND",
+            "Order not found".to_string(),
+        )),
+        Err(err) => {
+            tracing::error!("Failed to load order {}: {}", order_id, err);
+            Err(error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "ORDER_LOOKUP_FAILED",
+                err.to_string(),
+            ))
+        }
+    }
+}
+
+/// Turn a provider failure into an API error response
+pub(crate) fn payment_error_response(order_id: &str, err: PaymentError) -> Response {
+    tracing::error!("Payment provider error for order {}: {}", order_id, err);
+    let (status, code) = match err {
+        PaymentError::NotFound(_) => (StatusCode::NOT_FOUND, "PAYMENT_NOT_FOUND"),
+        PaymentError::InvalidState(_) => (StatusCode::CONFLICT, "PAYMENT_NOT_READY"),
+        PaymentError::Config(_) => (StatusCode::INTERNAL_SERVER_ERROR, "PAYMENT_CONFIG_ERROR"),
+        _ => (StatusCode::BAD_GATEWAY, "PAYMENT_PROVIDER_ERROR"),
+    };
+    error_response(status, code, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::user::User,
+        payments::{PaymentProvider, PaymentProviders, mock::MockPaymentProvider},
+        test_support,
+    };
+    use sqlx::PgPool;
+    use std::sync::Arc;
+
+    /// State with a mock PayPal provider and an order `ord_1` paid through it
+    ///
+    /// The payment is captured when `payment_status` is `Completed`.
+    async fn paid_order(
+        pool: PgPool,
+        order_status: OrderStatus,
+        payment_status: PaymentStatus,
+    ) -> (AppState, Arc<MockPaymentProvider>, Payment) {
+        let provider = Arc::new(MockPaymentProvider::new("paypal"));
+        let state = AppState {
+            payment_providers: PaymentProviders::new(vec![provider.clone()]),
+            ..test_support::app_state(pool)
+        };
+        let order = test_support::print_order("ord_1", order_status);
+        state.orders.create(&order).await.unwrap();
+
+        let intent = provider
+            .create_intent(&CreateIntentRequest {
+                order_id: order.order_id.clone(),
+                amount: order.grand_total.clone(),
+                description: None,
+                request_id: payments::order_request_id(&order.order_id),
+            })
+            .await
+            .unwrap();
+        let mut payment = state
+            .payments
+            .create(&NewPayment {
+                order_id: order.order_id.clone(),
+                provider: "paypal".to_string(),
+                intent_id: intent.intent_id.clone(),
+                status: payment_status,
+                amount: order.grand_total.clone(),
+            })
+            .await
+            .unwrap();
+        if payment_status == PaymentStatus::Completed {
+            provider.approve(&intent.intent_id).await.unwrap();
+            let capture = provider.capture(&intent.intent_id).await.unwrap();
+            payment = state
+                .payments
+                .update_status(
+                    payment.payment_id,
+                    PaymentStatus::Completed,
+                    Some(&capture.capture_id),
+                )
+                .await
+                .unwrap();
+        }
+        (state, provider, payment)
+    }
+
+    fn webhook_event(event_id: &str, payment: &Payment, action: WebhookAction) -> WebhookEvent {
+        WebhookEvent {
+            event_id: event_id.to_string(),
+            event_type: "PAYMENT.CAPTURE.COMPLETED".to_string(),
+            intent_id: Some(payment.intent_id.clone()),
+            capture_id: None,
+            action,
+        }
+    }
+
+    async fn response_json(response: Response) -> (StatusCode, serde_json::Value) {
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    async fn deliver(
+        state: &AppState,
+        provider: &MockPaymentProvider,
+        event: WebhookEvent,
+    ) -> (StatusCode, serde_json::Value) {
+        let headers = provider.webhook(event).await;
+        let response =
+            payment_webhook_endpoint(State(state.clone()), headers, Bytes::from_static(b"{}"))
+                .await;
+        response_json(response).await
+    }
+
+    /// Ask for a refund as `admin`
+    async fn refund(
+        state: &AppState,
+        admin: &User,
+        request: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let claims = Claims {
+            sub: admin.id.to_string(),
+            email: admin.email.clone(),
+            name: admin.name.clone(),
+            admin: true,
+            exp: 0,
+            iat: 0,
+        };
+        let request = serde_json::from_value(request).unwrap();
+        let response =
+            refund_payment_endpoint(State(state.clone()), Extension(claims), Json(request)).await;
+        response_json(response).await
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_webhook_dedup_and_replay(pool: PgPool) {
+        let (state, provider, payment) =
+            paid_order(pool, OrderStatus::PendingPayment, PaymentStatus::Approved).await;
+
+        let paid = webhook_event("WH-1", &payment, WebhookAction::Paid);
+        let (status, body) = deliver(&state, &provider, paid.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["message"], "Event processed");
+        let order = state.orders.find_by_id("ord_1").await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::Paid);
+        assert_eq!(state.orders.status_history("ord_1").await.unwrap().len(), 2);
+
+        // Redelivery of the same event is acknowledged but not applied
+        let (status, body) = deliver(&state, &provider, paid).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["message"], "Event already processed");
+
+        // A second event saying the same thing changes nothing
+        let (status, body) = deliver(
+            &state,
+            &provider,
+            webhook_event("WH-2", &payment, WebhookAction::Paid),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["message"], "Event processed");
+        let history = state.orders.status_history("ord_1").await.unwrap();
+        assert_eq!(history.len(), 2);
+
+        // Requests no provider recognizes are rejected
+        let response =
+            payment_webhook_endpoint(State(state.clone()), HeaderMap::new(), Bytes::new()).await;
+        let (status, body) = response_json(response).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "INVALID_WEBHOOK");
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_webhook_failed_then_paid(pool: PgPool) {
+        let (state, provider, payment) =
+            paid_order(pool, OrderStatus::PendingPayment, PaymentStatus::Approved).await;
+
+        deliver(
+            &state,
+            &provider,
+            webhook_event("WH-1", &payment, WebhookAction::Failed),
+        )
+        .await;
+        let stored = state.payments.latest_for_order("ord_1").await.unwrap();
+        assert_eq!(stored.unwrap().status, PaymentStatus::Failed);
+        let order = state.orders.find_by_id("ord_1").await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::PendingPayment);
+
+        // The provider completed the capture after all
+        let paid = WebhookEvent {
+            capture_id: Some("CAP-1".to_string()),
+            ..webhook_event("WH-2", &payment, WebhookAction::Paid)
+        };
+        deliver(&state, &provider, paid).await;
+        let stored = state
+            .payments
+            .latest_for_order("ord_1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.status, PaymentStatus::Completed);
+        assert_eq!(stored.capture_id.as_deref(), Some("CAP-1"));
+        let order = state.orders.find_by_id("ord_1").await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::Paid);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_webhook_refunded(pool: PgPool) {
+        let (state, provider, payment) =
+            paid_order(pool, OrderStatus::Paid, PaymentStatus::Completed).await;
+
+        let refunded = |event_id: &str, minor: i64| {
+            webhook_event(
+                event_id,
+                &payment,
+                WebhookAction::Refunded {
+                    total_refunded: Money::from_minor(minor, "USD"),
+                },
+            )
+        };
+        deliver(&state, &provider, refunded("WH-1", 400)).await;
+        let order = state.orders.find_by_id("ord_1").await.unwrap().unwrap();
+        assert_eq!(order.amount_refunded, Money::from_minor(400, "USD"));
+        assert_eq!(order.status, OrderStatus::Paid);
+
+        // Totals only go up, even when events arrive out of order
+        deliver(&state, &provider, refunded("WH-3", 1017)).await;
+        deliver(&state, &provider, refunded("WH-2", 700)).await;
+        let order = state.orders.find_by_id("ord_1").await.unwrap().unwrap();
+        assert_eq!(order.amount_refunded, Money::from_minor(1017, "USD"));
+        assert_eq!(order.status, OrderStatus::Refunded);
+        let stored = state.payments.latest_for_order("ord_1").await.unwrap();
+        assert_eq!(stored.unwrap().status, PaymentStatus::Refunded);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_webhook_disputed(pool: PgPool) {
+        let (state, provider, payment) =
+            paid_order(pool, OrderStatus::Paid, PaymentStatus::Completed).await;
+
+        deliver(
+            &state,
+            &provider,
+            webhook_event("WH-1", &payment, WebhookAction::Disputed),
+        )
+        .await;
+        deliver(
+            &state,
+            &provider,
+            webhook_event("WH-2", &payment, WebhookAction::Disputed),
+        )
+        .await;
+
+        let stored = state.payments.latest_for_order("ord_1").await.unwrap();
+        assert_eq!(stored.unwrap().status, PaymentStatus::Disputed);
+        let order = state.orders.find_by_id("ord_1").await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::Paid);
+        // Noted once for staff, not once per event
+        let history = state.orders.status_history("ord_1").await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].to_status, OrderStatus::Paid);
+        assert_eq!(
+            history[1].note.as_deref(),
+            Some("Payment disputed through paypal (WH-1)")
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_refund_validation(pool: PgPool) {
+        let (state, _provider, _payment) =
+            paid_order(pool, OrderStatus::Paid, PaymentStatus::Completed).await;
+        let order = state.orders.find_by_id("ord_1").await.unwrap().unwrap();
+        let item_id = order.items[0].item_id;
+        let admin = User::new(
+            "admin@example.com".to_string(),
+            "Admin".to_string(),
+            "SecurePass123!",
+        )
+        .unwrap();
+        state.users.create(&admin).await.unwrap();
+
+        let rejected = [
+            serde_json::json!({"order_id": "ord_1", "reason": " "}),
+            serde_json::json!({"order_id": "ord_1", "reason": "Damaged", "amount": "1.00",
+                "items": [{"item_id": item_id}]}),
+            serde_json::json!({"order_id": "ord_1", "reason": "Damaged", "amount": "0.00"}),
+            serde_json::json!({"order_id": "ord_1", "reason": "Damaged", "amount": "10.18"}),
+            serde_json::json!({"order_id": "ord_1", "reason": "Damaged",
+                "items": [{"item_id": item_id + 1}]}),
+            serde_json::json!({"order_id": "ord_1", "reason": "Damaged",
+                "items": [{"item_id": item_id, "quantity": 3}]}),
+            serde_json::json!({"order_id": "ord_1", "reason": "Damaged",
+                "items": [{"item_id": item_id}, {"item_id": item_id}]}),
+        ];
+        for request in rejected {
+            let (status, body) = refund(&state, &admin, request.clone()).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", request);
+            assert_eq!(body["error"], "INVALID_REFUND");
+        }
+
+        // One print with its share of the tax: 1.50 + 0.11
+        let (status, body) = refund(
+            &state,
+            &admin,
+            serde_json::json!({"order_id": "ord_1", "reason": "Damaged",
+                "items": [{"item_id": item_id, "quantity": 1}]}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["order_status"], "paid");
+        let refunds = state.payments.list_refunds("ord_1").await.unwrap();
+        assert_eq!(refunds[0].amount, Money::from_minor(161, "USD"));
+        assert_eq!(refunds[0].items[0].quantity, 1);
+        assert_eq!(refunds[0].refunded_by, Some(admin.id));
+
+        // Only one print is left to refund
+        let (status, _) = refund(
+            &state,
+            &admin,
+            serde_json::json!({"order_id": "ord_1", "reason": "Damaged",
+                "items": [{"item_id": item_id, "quantity": 2}]}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // The rest settles the order
+        let (status, body) = refund(
+            &state,
+            &admin,
+            serde_json::json!({"order_id": "ord_1", "reason": "Lost in the mail"}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["order_status"], "refunded");
+        let order = state.orders.find_by_id("ord_1").await.unwrap().unwrap();
+        assert_eq!(order.amount_refunded, Money::from_minor(1017, "USD"));
+        let (status, body) = refund(
+            &state,
+            &admin,
+            serde_json::json!({"order_id": "ord_1", "reason": "Damaged"}),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"], "NOT_REFUNDABLE");
+    }
+
+    #[test]
+    fn test_with_tax_share() {
+        let taxable = Money::from_minor(4600, "USD");
+        let tax = Money::from_minor(322, "USD");
+
+        // 3.98 of 46.00 carries 0.2786 of the 3.22 tax
+        assert_eq!(
+            with_tax_share(&Money::from_minor(398, "USD"), &taxable, &tax).unwrap(),
+            Money::from_minor(426, "USD")
+        );
+        assert_eq!(
+            with_tax_share(&taxable, &taxable, &tax).unwrap(),
+            Money::from_minor(4922, "USD")
+        );
+        // Nothing was taxed
+        assert_eq!(
+            with_tax_share(
+                &Money::from_minor(398, "USD"),
+                &Money::zero("USD"),
+                &Money::zero("USD")
+            )
+            .unwrap(),
+            Money::from_minor(398, "USD")
+        );
+    }
+}
@@ -1 +1,317 @@
+//! Print catalog endpoints
+//!
+//! Customers read the available sizes and finishes; admins manage sizes.
+//! Order pricing reads from the same catalog, so changes apply to new
+//! orders immediately.
 
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppState, customs,
+    endpoints::orders::error_response,
+    models::print_catalog::{PrintFinish, PrintSize},
+    money::Money,
+    repositories::{
+        RepositoryError,
+        catalog::{NewPrintSize, PrintSizeUpdate},
+    },
+};
+
+/// Available print sizes and finishes
+#[derive(Debug, Serialize)]
+pub struct PrintCatalogResponse {
+    pub sizes: Vec<PrintSize>,
+    pub finishes: Vec<PrintFinish>,
+}
+
+/// Response for a single catalog size
+#[derive(Debug, Serialize)]
+pub struct PrintSizeResponse {
+    pub size: PrintSize,
+    pub message: String,
+}
+
+/// Request payload for adding a print size (admin only)
+#[derive(Debug, Deserialize)]
+pub struct CreatePrintSizeRequest {
+    /// Catalog key used in order requests, e.g. `12x18`
+    pub size_id: String,
+    pub description: String,
+    pub price: Money,
+    pub width_in: f32,
+    pub height_in: f32,
+    pub weight_lbs: f32,
+    /// Business days to produce the print before it ships
+    #[serde(default = "default_production_days")]
+    pub production_days: i32,
+    /// HS code prints are declared under at customs
+    #[serde(default = "default_hs_code")]
+    pub hs_code: String,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    #[serde(default)]
+    pub sort_order: i32,
+}
+
+/// Request payload for editing a print size (admin only)
+#[derive(Debug, Deserialize)]
+pub struct UpdatePrintSizeRequest {
+    pub description: Option<String>,
+    pub price: Option<Money>,
+    pub width_in: Option<f32>,
+    pub height_in: Option<f32>,
+    pub weight_lbs: Option<f32>,
+    pub production_days: Option<i32>,
+    pub hs_code: Option<String>,
+    pub active: Option<bool>,
+    pub sort_order: Option<i32>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+fn default_production_days() -> i32 {
+    1
+}
+
+fn default_hs_code() -> String {
+    customs::DEFAULT_HS_CODE.to_string()
+}
+
+/// GET /api/prints/sizes - List active print sizes and finishes
+pub async fn list_sizes_endpoint(State(state): State<AppState>) -> Response {
+    let sizes = state.catalog.list_sizes(false).await;
+    let finishes = state.catalog.list_finishes(false).await;
+
+    match (sizes, finishes) {
+        (Ok(sizes), Ok(finishes)) => Json(PrintCatalogResponse { sizes, finishes }).into_response(),
+        (Err(err), _) | (_, Err(err)) => {
+            tracing::error!("Failed to load print catalog: {}", err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "CATALOG_LOOKUP_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// POST /api/admin/prints/sizes (admin only) - Add a print size
+pub async fn create_size_endpoint(
+    State(state): State<AppState>,
+    Json(request): Json<CreatePrintSizeRequest>,
+) -> Response {
+    let size_id = request.size_id.trim().to_string();
+    if size_id.is_empty() || size_id.contains(char::is_whitespace) {
+        return validation_error("Size ID must be non-empty and contain no whitespace".to_string());
+    }
+    if let Err(message) = validate_size_fields(
+        Some(&request.price),
+        Some(request.width_in),
+        Some(request.height_in),
+        Some(request.weight_lbs),
+        Some(request.production_days),
+        Some(&request.hs_code),
+    ) {
+        return validation_error(message);
+    }
+
+    let new_size = NewPrintSize {
+        size_id,
+        description: request.description,
+        price: request.price,
+        width_in: request.width_in,
+        height_in: request.height_in,
+        weight_lbs: request.weight_lbs,
+        production_days: request.production_days,
+        hs_code: request.hs_code,
+        active: request.active,
+        sort_order: request.sort_order,
+    };
+
+    match state.catalog.create_size(&new_size).await {
+        Ok(size) => {
+            tracing::info!("Added print size {}", size.size_id);
+            (
+                StatusCode::CREATED,
+                Json(PrintSizeResponse {
+                    size,
+                    message: "Print size created successfully".to_string(),
+                }),
+            )
+                .into_response()
+        }
+        Err(RepositoryError::Conflict(_)) => error_response(
+            StatusCode::CONFLICT,
+            "SIZE_EXISTS",
+            format!("Print size {} already exists", new_size.size_id),
+        ),
+        Err(err) => {
+            tracing::error!("Failed to create print size: {}", err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "CATALOG_UPDATE_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// PATCH /api/admin/prints/sizes/:size_id (admin only) - Edit a print size
+pub async fn update_size_endpoint(
+    State(state): State<AppState>,
+    Path(size_id): Path<String>,
+    Json(request): Json<UpdatePrintSizeRequest>,
+) -> Response {
+    if let Err(message) = validate_size_fields(
+        request.price.as_ref(),
+        request.width_in,
+        request.height_in,
+        request.weight_lbs,
+        request.production_days,
+        request.hs_code.as_deref(),
+    ) {
+        return validation_error(message);
+    }
+
+    let update = PrintSizeUpdate {
+        description: request.description,
+        price: request.price,
+        width_in: request.width_in,
+        height_in: request.height_in,
+        weight_lbs: request.weight_lbs,
+        production_days: request.production_days,
+        hs_code: request.hs_code,
+        active: request.active,
+        sort_order: request.sort_order,
+    };
+
+    match state.catalog.update_size(&size_id, &update).await {
+        Ok(Some(size)) => {
+            tracing::info!("Updated print size {}", size_id);
+            Json(PrintSizeResponse {
+                size,
+                message: "Print size updated successfully".to_string(),
+            })
+            .into_response()
+        }
+        Ok(None) => size_not_found(),
+        Err(err) => {
+            tracing::error!("Failed to update print size {}: {}", size_id, err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "CATALOG_UPDATE_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// DELETE /api/admin/prints/sizes/:size_id (admin only) - Remove a print size
+pub async fn delete_size_endpoint(
+    State(state): State<AppState>,
+    Path(size_id): Path<String>,
+) -> Response {
+    match state.catalog.delete_size(&size_id).await {
+        Ok(true) => {
+            tracing::info!("Removed print size {}", size_id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => size_not_found(),
+        Err(err) => {
+            tracing::error!("Failed to remove print size {}: {}", size_id, err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "CATALOG_UPDATE_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// Check the price, physical measurements and customs code of a size,
+/// skipping absent fields
+fn validate_size_fields(
+    price: Option<&Money>,
+    width_in: Option<f32>,
+    height_in: Option<f32>,
+    weight_lbs: Option<f32>,
+    production_days: Option<i32>,
+    hs_code: Option<&str>,
+) -> Result<(), String> {
+    if price.is_some_and(Money::is_negative) {
+        return Err("Price cannot be negative".to_string());
+    }
+    for (name, value) in [
+        ("width_in", width_in),
+        ("height_in", height_in),
+        ("weight_lbs", weight_lbs),
+    ] {
+        if let Some(value) = value
+            && !(value.is_finite() && value > 0.0)
+        {
+            return Err(format!("{} must be greater than 0", name));
+        }
+    }
+    if production_days.is_some_and(|days| days < 0) {
+        return Err("production_days cannot be negative".to_string());
+    }
+    if let Some(hs_code) = hs_code {
+        customs::validate_hs_code(hs_code)?;
+    }
+    Ok(())
+}
+
+fn validation_error(message: String) -> Response {
+    error_response(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "VALIDATION_ERROR",
+        message,
+    )
+}
+
+fn size_not_found() -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        "SIZE_NOT_FOUND",
+        "Print size not found".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_size_fields() {
+        let price = Money::from_minor(150, "USD");
+        assert!(
+            validate_size_fields(
+                Some(&price),
+                Some(4.0),
+                Some(6.0),
+                Some(0.02),
+                Some(1),
+                Some("491191")
+            )
+            .is_ok()
+        );
+        assert!(validate_size_fields(None, None, None, None, None, None).is_ok());
+        // Sizes can be made to order without lead time
+        assert!(validate_size_fields(None, None, None, None, Some(0), None).is_ok());
+
+        let negative = Money::from_minor(-1, "USD");
+        assert!(validate_size_fields(Some(&negative), None, None, None, None, None).is_err());
+        assert!(validate_size_fields(None, Some(0.0), None, None, None, None).is_err());
+        assert!(validate_size_fields(None, None, None, Some(f32::NAN), None, None).is_err());
+        assert!(validate_size_fields(None, None, None, None, Some(-1), None).is_err());
+        assert!(validate_size_fields(None, None, None, None, None, Some("4911.91")).is_err());
+    }
+}
@@ -1 +1,956 @@
-// not too sure what will go here, this will mostly be an internal function
+//! Shipping endpoints
+//!
+//! Customers get live quotes from every configured carrier and can check an
+//! address before checkout. Admins buy, download and void the label of an
+//! order, and get its commercial invoice when it crosses a border. Tracking
+//! is read back from the carrier the label was bought from.
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    AppState,
+    auth::Claims,
+    carriers::{
+        AddressClassification, Carrier, CarrierError, LabelFormat, LabelRequest, Shipment,
+        ShipmentAddress, ShippingQuote, TrackingInfo, TrackingStatus,
+    },
+    customs::{self, CommercialInvoice, CustomsItem},
+    endpoints::orders::{
+        AddressRequest, DeliveryEstimate, MAX_PRINT_QUANTITY, ORDER_CURRENCY,
+        calculate_delivery_estimate, error_response,
+    },
+    fulfillment::{Route, RoutingError},
+    models::{
+        fulfillment_location::FulfillmentLocation, order_status::OrderStatus,
+        print_catalog::PrintSize, print_order::PrintOrder, shipping_label::StoredLabel,
+    },
+    money::Money,
+    packing::Package,
+    repositories::{RepositoryError, shipping_labels::NewShippingLabel},
+    shipping, shipping_rules,
+    types::AddressValidationResult,
+};
+
+/// Request payload for a shipping quote
+#[derive(Debug, Deserialize)]
+pub struct ShippingQuoteRequest {
+    pub address: AddressRequest,
+    pub prints: Vec<QuotePrintRequest>,
+}
+
+/// Prints of one size to ship
+#[derive(Debug, Deserialize)]
+pub struct QuotePrintRequest {
+    pub size: String,
+    /// Number of prints at this size
+    pub quantity: u32,
+}
+
+/// Every available service across carriers, cheapest first
+#[derive(Debug, Serialize)]
+pub struct ShippingQuoteResponse {
+    pub quotes: Vec<QuoteOption>,
+    /// How the prints would be packed
+    pub packages: Vec<Package>,
+    /// Business day the prints would be produced and handed to the carrier
+    pub ship_date: NaiveDate,
+    /// Print lab the prints would be produced at and shipped from
+    pub fulfillment_location_id: String,
+}
+
+/// A quoted service and when it would arrive
+#[derive(Debug, Serialize)]
+pub struct QuoteOption {
+    #[serde(flatten)]
+    pub quote: ShippingQuote,
+    pub estimated_delivery: DeliveryEstimate,
+}
+
+/// How an address checked out, with the addresses the carrier suggests instead
+#[derive(Debug, Serialize)]
+pub struct ValidateAddressResponse {
+    pub result: AddressValidationResult,
+    /// Classification of the address as given
+    pub classification: AddressClassification,
+    /// Corrected or alternative addresses, best match first
+    pub candidates: Vec<SuggestedAddress>,
+    pub message: String,
+}
+
+/// An address the carrier suggests, in the shape orders take
+#[derive(Debug, Serialize)]
+pub struct SuggestedAddress {
+    #[serde(flatten)]
+    pub address: AddressRequest,
+    pub classification: AddressClassification,
+}
+
+/// Request payload for buying a shipping label (admin only)
+#[derive(Debug, Deserialize)]
+pub struct CreateLabelRequest {
+    pub format: LabelFormat,
+}
+
+/// A shipping label and where to download it
+#[derive(Debug, Serialize)]
+pub struct ShippingLabelResponse {
+    pub label: StoredLabel,
+    /// Download link, while the label hasn't been voided
+    pub label_url: Option<String>,
+    pub message: String,
+}
+
+/// POST /api/shipping/quote - Quote every carrier's services for a print list
+pub async fn quote_endpoint(
+    State(app_state): State<AppState>,
+    Json(request): Json<ShippingQuoteRequest>,
+) -> Response {
+    if request.prints.is_empty() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "INVALID_QUOTE_REQUEST",
+            "At least one print item is required".to_string(),
+        );
+    }
+
+    let mut sizes: Vec<(PrintSize, i64)> = Vec::with_capacity(request.prints.len());
+    for print in &request.prints {
+        if print.quantity == 0 {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "INVALID_QUOTE_REQUEST",
+                "Print quantity must be greater than 0".to_string(),
+            );
+        }
+        if print.quantity > MAX_PRINT_QUANTITY {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "INVALID_QUOTE_REQUEST",
+                format!("Print quantity can be at most {}", MAX_PRINT_QUANTITY),
+            );
+        }
+        match app_state.catalog.find_size(&print.size).await {
+            Ok(Some(size)) if size.active => sizes.push((size, print.quantity as i64)),
+            Ok(_) => {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    "INVALID_QUOTE_REQUEST",
+                    format!("Unsupported print size: {}", print.size),
+                );
+            }
+            Err(err) => {
+                tracing::error!("Failed to load print size {}: {}", print.size, err);
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "CATALOG_LOOKUP_FAILED",
+                    err.to_string(),
+                );
+            }
+        }
+    }
+
+    let prints: Vec<(&PrintSize, i64)> = sizes.iter().map(|(size, n)| (size, *n)).collect();
+    let packages = match app_state.packaging.pack(&prints) {
+        Ok(packages) => packages,
+        Err(err) => {
+            return error_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "PACKING_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+    let ship_date = shipping::ship_date(
+        &app_state.calendar,
+        Utc::now().date_naive(),
+        sizes.iter().map(|(size, _)| size),
+    );
+    let ship_to = shipment_address(&request.address, "Customer", None);
+    let locations = match app_state.locations.list(false).await {
+        Ok(locations) => locations,
+        Err(err) => {
+            tracing::error!("Failed to load fulfillment locations: {}", err);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "LOCATION_LOOKUP_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+    // Before a finish is picked, prints abroad are declared at their base price
+    let customs_items: Vec<CustomsItem> = sizes
+        .iter()
+        .map(|(size, quantity)| CustomsItem::prints(size, None, *quantity, size.price.clone()))
+        .collect();
+    let size_ids: Vec<&str> = sizes
+        .iter()
+        .map(|(size, _)| size.size_id.as_str())
+        .collect();
+
+    let route = app_state
+        .routing
+        .route(locations, &size_ids, &ship_to, |location| {
+            let shipment = shipping::quote_shipment(
+                &location,
+                &ship_to,
+                &packages,
+                ship_date,
+                ORDER_CURRENCY,
+                &customs_items,
+            );
+            let carriers = &app_state.carriers;
+            async move { shipping::quote_all(carriers, &shipment?).await }
+        })
+        .await;
+
+    let Route { location, quotes } = match route {
+        Ok(route) => route,
+        Err(RoutingError::NoLocation(message)) => {
+            return error_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "NO_FULFILLMENT_LOCATION",
+                message,
+            );
+        }
+        Err(RoutingError::Carrier(err)) => {
+            tracing::error!("Failed to quote shipping: {}", err);
+            return error_response(
+                StatusCode::BAD_GATEWAY,
+                "SHIPPING_QUOTE_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+
+    // Offer the services at the prices the shipping rules set; before a
+    // finish is picked, thresholds compare against the prints' base price
+    let rules = match app_state.shipping_rules.list(false).await {
+        Ok(rules) => rules,
+        Err(err) => {
+            tracing::error!("Failed to load shipping rules: {}", err);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "SHIPPING_RULE_LOOKUP_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+    let priced = sizes
+        .iter()
+        .map(|(size, quantity)| size.price.checked_mul(*quantity))
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|values| Money::sum(&values, ORDER_CURRENCY))
+        .and_then(|subtotal| shipping_rules::apply_rules(&rules, &subtotal, &ship_to, quotes));
+    let quotes = match priced {
+        Ok(quotes) => quotes,
+        Err(err) => {
+            tracing::error!("Failed to apply shipping rules: {}", err);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "SHIPPING_RULES_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+
+    Json(ShippingQuoteResponse {
+        quotes: quotes
+            .into_iter()
+            .map(|quote| QuoteOption {
+                estimated_delivery: calculate_delivery_estimate(
+                    &app_state.calendar,
+                    ship_date,
+                    &quote,
+                ),
+                quote,
+            })
+            .collect(),
+        packages,
+        ship_date,
+        fulfillment_location_id: location.location_id,
+    })
+    .into_response()
+}
+
+/// POST /api/shipping/validate-address - Check an address with UPS before checkout
+///
+/// Returns UPS's verdict and its normalized candidates, so checkout can ask
+/// the customer whether they meant one of them.
+pub async fn validate_address_endpoint(
+    State(app_state): State<AppState>,
+    Json(request): Json<AddressRequest>,
+) -> Response {
+    if request.line1.trim().is_empty()
+        || request.city.trim().is_empty()
+        || request.postal_code.trim().is_empty()
+        || request.country.trim().is_empty()
+    {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "INVALID_ADDRESS",
+            "Street, city, postal code and country are required".to_string(),
+        );
+    }
+    let Some(carrier) = app_state.carriers.get("ups") else {
+        return error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "CARRIER_UNAVAILABLE",
+            "ups is not configured".to_string(),
+        );
+    };
+
+    let address = shipment_address(&request, "Customer", None);
+    match carrier.validate_address(&address).await {
+        Ok(validation) => Json(ValidateAddressResponse {
+            result: validation.result,
+            classification: validation.classification,
+            candidates: validation
+                .candidates
+                .into_iter()
+                .map(|candidate| SuggestedAddress {
+                    address: AddressRequest {
+                        line1: candidate.address.line1,
+                        line2: candidate.address.line2,
+                        city: candidate.address.city,
+                        state: candidate.address.state,
+                        postal_code: candidate.address.postal_code,
+                        country: candidate.address.country,
+                    },
+                    classification: candidate.classification,
+                })
+                .collect(),
+            message: "Address validated successfully".to_string(),
+        })
+        .into_response(),
+        Err(err) => {
+            tracing::error!("Failed to validate address: {}", err);
+            error_response(
+                StatusCode::BAD_GATEWAY,
+                "ADDRESS_VALIDATION_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// Convert a customer address into the form carriers take
+pub(crate) fn shipment_address(
+    address: &AddressRequest,
+    name: &str,
+    phone: Option<&str>,
+) -> ShipmentAddress {
+    ShipmentAddress {
+        name: name.to_string(),
+        company: None,
+        phone: phone.map(str::to_string),
+        line1: address.line1.clone(),
+        line2: address.line2.clone(),
+        city: address.city.clone(),
+        state: address.state.clone(),
+        postal_code: address.postal_code.clone(),
+        country: address.country.clone(),
+    }
+}
+
+/// Ship-to address of an order
+fn order_ship_to(order: &PrintOrder) -> ShipmentAddress {
+    ShipmentAddress {
+        name: order.customer_name.clone(),
+        company: None,
+        phone: Some(order.customer_phone.clone()),
+        line1: order.shipping_line1.clone(),
+        line2: order.shipping_line2.clone(),
+        city: order.shipping_city.clone(),
+        state: order.shipping_state.clone(),
+        postal_code: order.shipping_postal_code.clone(),
+        country: order.shipping_country.clone(),
+    }
+}
+
+/// Load an order, or respond with why it can't be
+pub(crate) async fn load_order(
+    app_state: &AppState,
+    order_id: &str,
+) -> Result<PrintOrder, Response> {
+    match app_state.orders.find_by_id(order_id).await {
+        Ok(Some(order)) => Ok(order),
+        Ok(None) => Err(error_response(
+            StatusCode::NOT_FOUND,
+            "ORDER_NOT_FOUND",
+            "Order not found".to_string(),
+        )),
+        Err(err) => {
+            tracing::error!("Failed to load order {}: {}", order_id, err);
+            Err(error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "ORDER_LOOKUP_FAILED",
+                err.to_string(),
+            ))
+        }
+    }
+}
+
+/// Load the catalog size of each order item, with the item's print count
+async fn load_order_sizes(
+    app_state: &AppState,
+    order: &PrintOrder,
+) -> Result<Vec<(PrintSize, i64)>, Response> {
+    let mut sizes = Vec::with_capacity(order.items.len());
+    for item in &order.items {
+        match app_state.catalog.find_size(&item.size).await {
+            Ok(Some(size)) => sizes.push((size, item.print_count())),
+            Ok(None) => {
+                return Err(error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "CATALOG_LOOKUP_FAILED",
+                    format!("Print size {} is no longer in the catalog", item.size),
+                ));
+            }
+            Err(err) => {
+                tracing::error!("Failed to load print size {}: {}", item.size, err);
+                return Err(error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "CATALOG_LOOKUP_FAILED",
+                    err.to_string(),
+                ));
+            }
+        }
+    }
+    Ok(sizes)
+}
+
+/// Load the lab the order ships from
+///
+/// Orders placed before labs were recorded ship from the first active one.
+async fn load_order_location(
+    app_state: &AppState,
+    order: &PrintOrder,
+) -> Result<FulfillmentLocation, Response> {
+    let location = match &order.fulfillment_location_id {
+        Some(location_id) => app_state.locations.find(location_id).await,
+        None => app_state
+            .locations
+            .list(false)
+            .await
+            .map(|locations| locations.into_iter().next()),
+    };
+    match location {
+        Ok(Some(location)) => Ok(location),
+        Ok(None) => Err(error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "NO_FULFILLMENT_LOCATION",
+            format!("Order {} has no fulfillment location", order.order_id),
+        )),
+        Err(err) => {
+            tracing::error!(
+                "Failed to load fulfillment location of order {}: {}",
+                order.order_id,
+                err
+            );
+            Err(error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "LOCATION_LOOKUP_FAILED",
+                err.to_string(),
+            ))
+        }
+    }
+}
+
+/// Load the order's label that hasn't been voided, or respond with why it can't be
+async fn load_active_label(app_state: &AppState, order_id: &str) -> Result<StoredLabel, Response> {
+    match app_state.shipping_labels.find_active(order_id).await {
+        Ok(Some(label)) => Ok(label),
+        Ok(None) => Err(error_response(
+            StatusCode::NOT_FOUND,
+            "LABEL_NOT_FOUND",
+            "Order has no shipping label".to_string(),
+        )),
+        Err(err) => {
+            tracing::error!("Failed to load label of order {}: {}", order_id, err);
+            Err(error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "LABEL_LOOKUP_FAILED",
+                err.to_string(),
+            ))
+        }
+    }
+}
+
+/// Customs invoice of an order shipping abroad (admin only)
+#[derive(Debug, Serialize)]
+pub struct CommercialInvoiceResponse {
+    #[serde(flatten)]
+    pub invoice: CommercialInvoice,
+    /// Who the prints ship from
+    pub exporter: ShipmentAddress,
+    /// Who the prints ship to
+    pub consignee: ShipmentAddress,
+    pub message: String,
+}
+
+/// Where an order's shipment is
+#[derive(Debug, Serialize)]
+pub struct OrderTrackingResponse {
+    pub order_id: String,
+    pub carrier: String,
+    #[serde(flatten)]
+    pub tracking: TrackingInfo,
+    pub message: String,
+}
+
+/// Respond to a carrier failure; requests the carrier can't serve are our
+/// problem to fix, anything else is the carrier's
+fn carrier_error_response(order_id: &str, err: CarrierError) -> Response {
+    tracing::error!("Carrier request for order {} failed: {}", order_id, err);
+    match err {
+        CarrierError::Unsupported(_) | CarrierError::Config(_) => error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "LABEL_NOT_SUPPORTED",
+            err.to_string(),
+        ),
+        _ => error_response(StatusCode::BAD_GATEWAY, "CARRIER_ERROR", err.to_string()),
+    }
+}
+
+/// Void a bought label that couldn't be kept, so it isn't charged
+async fn void_unrecorded(
+    carrier: &Arc<dyn Carrier>,
+    order_id: &str,
+    tracking_number: &str,
+    reason: String,
+) {
+    tracing::error!(
+        "Failed to keep label {} of order {}: {}",
+        tracking_number,
+        order_id,
+        reason
+    );
+    if let Err(err) = carrier.void_label(tracking_number).await {
+        tracing::error!(
+            "Failed to void unrecorded label {}; void it by hand: {}",
+            tracking_number,
+            err
+        );
+    }
+}
+
+/// POST /api/admin/orders/:order_id/shipping-label (admin only) - Buy a shipping label
+///
+/// The label is bought from the carrier of the order's shipping option for
+/// the packed order, stored in object storage, and its tracking number saved
+/// on the order.
+pub async fn create_label_endpoint(
+    State(app_state): State<AppState>,
+    Path(order_id): Path<String>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<CreateLabelRequest>,
+) -> Response {
+    let order = match load_order(&app_state, &order_id).await {
+        Ok(order) => order,
+        Err(response) => return response,
+    };
+    if order.is_pickup() {
+        return error_response(
+            StatusCode::CONFLICT,
+            "ORDER_NOT_SHIPPABLE",
+            "Pickup orders are collected from their lab, not shipped".to_string(),
+        );
+    }
+    if !matches!(
+        order.status,
+        OrderStatus::Paid | OrderStatus::InProduction | OrderStatus::Printed
+    ) {
+        return error_response(
+            StatusCode::CONFLICT,
+            "ORDER_NOT_SHIPPABLE",
+            format!("Can't buy a label for an order that is {}", order.status),
+        );
+    }
+    match app_state.shipping_labels.find_active(&order_id).await {
+        Ok(None) => {}
+        Ok(Some(label)) => {
+            return error_response(
+                StatusCode::CONFLICT,
+                "LABEL_EXISTS",
+                format!(
+                    "Order already has label {}; void it first",
+                    label.tracking_number
+                ),
+            );
+        }
+        Err(err) => {
+            tracing::error!("Failed to load label of order {}: {}", order_id, err);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "LABEL_LOOKUP_FAILED",
+                err.to_string(),
+            );
+        }
+    }
+    let Some((carrier, service)) = app_state.carriers.find_service(&order.shipping_option) else {
+        return error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "SHIPPING_OPTION_UNAVAILABLE",
+            format!("No configured carrier offers {}", order.shipping_option),
+        );
+    };
+
+    // Pack the order the same way it was quoted
+    let sizes = match load_order_sizes(&app_state, &order).await {
+        Ok(sizes) => sizes,
+        Err(response) => return response,
+    };
+    let prints: Vec<(&PrintSize, i64)> = sizes.iter().map(|(size, n)| (size, *n)).collect();
+    let packages = match app_state.packaging.pack(&prints) {
+        Ok(packages) => packages,
+        Err(err) => {
+            return error_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "PACKING_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+    let location = match load_order_location(&app_state, &order).await {
+        Ok(location) => location,
+        Err(response) => return response,
+    };
+    let ship_from = location.address();
+    let ship_to = order_ship_to(&order);
+    let customs = if customs::crosses_border(&ship_from, &ship_to) {
+        let sizes: Vec<PrintSize> = sizes.into_iter().map(|(size, _)| size).collect();
+        match CommercialInvoice::for_order(&order, &sizes, &location.country) {
+            Ok(invoice) => Some(invoice),
+            Err(err) => {
+                tracing::error!("Failed to declare order {} for customs: {}", order_id, err);
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "CUSTOMS_DECLARATION_FAILED",
+                    err.to_string(),
+                );
+            }
+        }
+    } else {
+        None
+    };
+    let label_request = LabelRequest {
+        shipment: Shipment {
+            ship_from,
+            ship_to,
+            packages: shipping::package_dimensions(&packages),
+            // The prints are made by the time a label is bought
+            ship_date: app_state
+                .calendar
+                .next_business_day(Utc::now().date_naive()),
+            customs,
+        },
+        service_code: service.code.to_string(),
+        format: request.format,
+        reference: order.order_id.clone(),
+    };
+
+    let label = match carrier.create_label(&label_request).await {
+        Ok(label) => label,
+        Err(err) => return carrier_error_response(&order_id, err),
+    };
+    tracing::info!(
+        "Bought {} label {} for order {}",
+        carrier.name(),
+        label.tracking_number,
+        order_id
+    );
+
+    let storage_key = format!(
+        "labels/{}/{}.{}",
+        order.order_id,
+        label.tracking_number,
+        label.format.extension()
+    );
+    if let Err(err) = app_state
+        .storage
+        .put(
+            &storage_key,
+            label.data.clone(),
+            label.format.content_type(),
+        )
+        .await
+    {
+        void_unrecorded(carrier, &order_id, &label.tracking_number, err.to_string()).await;
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "LABEL_STORAGE_FAILED",
+            err.to_string(),
+        );
+    }
+
+    let recorded = app_state
+        .shipping_labels
+        .create(&NewShippingLabel {
+            order_id: order.order_id.clone(),
+            carrier: carrier.name().to_string(),
+            service_code: service.code.to_string(),
+            tracking_number: label.tracking_number.clone(),
+            format: label.format,
+            storage_key: storage_key.clone(),
+            price: label.price.clone(),
+            created_by: claims.sub.parse::<Uuid>().ok(),
+        })
+        .await;
+    match recorded {
+        Ok(stored) => (
+            StatusCode::CREATED,
+            Json(ShippingLabelResponse {
+                label_url: Some(format!(
+                    "/api/admin/orders/{}/shipping-label",
+                    stored.order_id
+                )),
+                label: stored,
+                message: "Shipping label created successfully".to_string(),
+            }),
+        )
+            .into_response(),
+        Err(err) => {
+            if let Err(err) = app_state.storage.delete(&storage_key).await {
+                tracing::warn!("Failed to clean up {}: {}", storage_key, err);
+            }
+            let status = match err {
+                // Another admin bought a label at the same time
+                RepositoryError::Conflict(_) => StatusCode::CONFLICT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            void_unrecorded(carrier, &order_id, &label.tracking_number, err.to_string()).await;
+            error_response(status, "LABEL_RECORD_FAILED", err.to_string())
+        }
+    }
+}
+
+/// GET /api/admin/orders/:order_id/shipping-label (admin only) - Download the current label
+pub async fn get_label_endpoint(
+    State(app_state): State<AppState>,
+    Path(order_id): Path<String>,
+) -> Response {
+    let label = match load_active_label(&app_state, &order_id).await {
+        Ok(label) => label,
+        Err(response) => return response,
+    };
+
+    match app_state.storage.get(&label.storage_key).await {
+        Ok(data) => (
+            [
+                (
+                    header::CONTENT_TYPE,
+                    label.format.content_type().to_string(),
+                ),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!(
+                        "attachment; filename=\"{}.{}\"",
+                        label.tracking_number,
+                        label.format.extension()
+                    ),
+                ),
+            ],
+            data,
+        )
+            .into_response(),
+        Err(err) => {
+            tracing::error!("Failed to read {}: {}", label.storage_key, err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "LABEL_LOOKUP_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// GET /api/admin/orders/:order_id/commercial-invoice (admin only) - Customs invoice to pack
+/// with an order shipping abroad
+///
+/// The invoice is the one filed with the carrier when the label is bought:
+/// every line at the price paid, under the HS code it was sold with.
+pub async fn get_commercial_invoice_endpoint(
+    State(app_state): State<AppState>,
+    Path(order_id): Path<String>,
+) -> Response {
+    let order = match load_order(&app_state, &order_id).await {
+        Ok(order) => order,
+        Err(response) => return response,
+    };
+    let location = match load_order_location(&app_state, &order).await {
+        Ok(location) => location,
+        Err(response) => return response,
+    };
+    let ship_from = location.address();
+    let ship_to = order_ship_to(&order);
+    if !customs::crosses_border(&ship_from, &ship_to) {
+        return error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "CUSTOMS_NOT_REQUIRED",
+            format!("Order ships within {}", ship_to.country),
+        );
+    }
+    let sizes: Vec<PrintSize> = match load_order_sizes(&app_state, &order).await {
+        Ok(sizes) => sizes.into_iter().map(|(size, _)| size).collect(),
+        Err(response) => return response,
+    };
+
+    match CommercialInvoice::for_order(&order, &sizes, &location.country) {
+        Ok(invoice) => Json(CommercialInvoiceResponse {
+            invoice,
+            exporter: ship_from,
+            consignee: ship_to,
+            message: "Commercial invoice retrieved successfully".to_string(),
+        })
+        .into_response(),
+        Err(err) => {
+            tracing::error!("Failed to declare order {} for customs: {}", order_id, err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "CUSTOMS_DECLARATION_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// DELETE /api/admin/orders/:order_id/shipping-label (admin only) - Void the current label
+///
+/// The label is voided with its carrier, so it isn't charged, and its
+/// tracking number is cleared from the order. The voided label stays in the
+/// order's label history.
+pub async fn void_label_endpoint(
+    State(app_state): State<AppState>,
+    Path(order_id): Path<String>,
+    Extension(claims): Extension<Claims>,
+) -> Response {
+    let label = match load_active_label(&app_state, &order_id).await {
+        Ok(label) => label,
+        Err(response) => return response,
+    };
+    let Some(carrier) = app_state.carriers.get(&label.carrier) else {
+        return error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "CARRIER_UNAVAILABLE",
+            format!("{} is not configured", label.carrier),
+        );
+    };
+
+    if let Err(err) = carrier.void_label(&label.tracking_number).await {
+        return carrier_error_response(&order_id, err);
+    }
+    match app_state.shipping_labels.mark_voided(label.label_id).await {
+        Ok(label) => {
+            tracing::info!(
+                "Voided {} label {} of order {} by {}",
+                label.carrier,
+                label.tracking_number,
+                order_id,
+                claims.email
+            );
+            Json(ShippingLabelResponse {
+                label,
+                label_url: None,
+                message: "Shipping label voided successfully".to_string(),
+            })
+            .into_response()
+        }
+        Err(err) => {
+            // The carrier voided it, so this needs a human to reconcile it
+            tracing::error!(
+                "Voided label {} of order {} but failed to record it: {}",
+                label.tracking_number,
+                order_id,
+                err
+            );
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "LABEL_RECORD_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// GET /api/orders/:order_id/tracking - Where the order's shipment is
+///
+/// Tracks the order's current label with its carrier. Until the carrier has
+/// scanned the package it reports `pre_transit` with no events.
+pub async fn get_tracking_endpoint(
+    State(app_state): State<AppState>,
+    Path(order_id): Path<String>,
+    Extension(claims): Extension<Claims>,
+) -> Response {
+    let order = match load_order(&app_state, &order_id).await {
+        Ok(order) => order,
+        Err(response) => return response,
+    };
+
+    // Customers may only track their own orders
+    let is_owner = order.user_id.map(|id| id.to_string()) == Some(claims.sub.clone());
+    if !claims.admin && !is_owner {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            "ACCESS_DENIED",
+            "Access denied".to_string(),
+        );
+    }
+
+    let label = match app_state.shipping_labels.find_active(&order_id).await {
+        Ok(Some(label)) => label,
+        Ok(None) => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                "TRACKING_NOT_AVAILABLE",
+                "Order has not been shipped yet".to_string(),
+            );
+        }
+        Err(err) => {
+            tracing::error!("Failed to load label of order {}: {}", order_id, err);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "LABEL_LOOKUP_FAILED",
+                err.to_string(),
+            );
+        }
+    };
+    let Some(carrier) = app_state.carriers.get(&label.carrier) else {
+        return error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "CARRIER_UNAVAILABLE",
+            format!("{} is not configured", label.carrier),
+        );
+    };
+
+    let tracking = match carrier.track(&label.tracking_number).await {
+        Ok(tracking) => tracking,
+        // Carriers only know a label once the package has been scanned
+        Err(CarrierError::NotFound(_)) => TrackingInfo {
+            tracking_number: label.tracking_number.clone(),
+            status: TrackingStatus::PreTransit,
+            estimated_delivery: None,
+            events: Vec::new(),
+        },
+        Err(err) => {
+            tracing::error!("Failed to track order {}: {}", order_id, err);
+            return error_response(StatusCode::BAD_GATEWAY, "CARRIER_ERROR", err.to_string());
+        }
+    };
+
+    Json(OrderTrackingResponse {
+        order_id,
+        carrier: label.carrier,
+        tracking,
+        message: "Tracking retrieved successfully".to_string(),
+    })
+    .into_response()
+}
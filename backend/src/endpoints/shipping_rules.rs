@@ -0,0 +1,318 @@
+//! Shipping rule endpoints
+//!
+//! Admins manage the rules applied to carrier quotes, such as free shipping
+//! over a subtotal or flat rates for some states. Quotes and new orders use
+//! the rules as soon as they change; orders already placed keep the shipping
+//! they were charged.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppState,
+    carriers::Carriers,
+    endpoints::{
+        locations::validate_regions,
+        orders::{ORDER_CURRENCY, error_response},
+    },
+    models::shipping_rule::{ShippingRule, ShippingRuleKind},
+    money::Money,
+    repositories::shipping_rules::NewShippingRule,
+};
+
+/// Every shipping rule in the order they apply, inactive ones included
+#[derive(Debug, Serialize)]
+pub struct ShippingRuleListResponse {
+    pub rules: Vec<ShippingRule>,
+}
+
+/// Response for a single shipping rule
+#[derive(Debug, Serialize)]
+pub struct ShippingRuleResponse {
+    pub rule: ShippingRule,
+    pub message: String,
+}
+
+/// Request payload for adding or replacing a shipping rule (admin only)
+#[derive(Debug, Deserialize)]
+pub struct ShippingRuleRequest {
+    pub name: String,
+    /// Lower applies first
+    #[serde(default)]
+    pub position: i32,
+    pub kind: ShippingRuleKind,
+    /// Shipping options the rule applies to, empty for every option
+    #[serde(default)]
+    pub shipping_options: Vec<String>,
+    /// Countries (`US`) or states (`US-CA`) the rule applies to, empty for
+    /// everywhere
+    #[serde(default)]
+    pub regions: Vec<String>,
+    pub min_subtotal: Option<Money>,
+    pub amount: Option<Money>,
+    pub basis_points: Option<i32>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+/// GET /api/admin/shipping-rules (admin only) - List shipping rules in the order they apply
+pub async fn list_rules_endpoint(State(state): State<AppState>) -> Response {
+    match state.shipping_rules.list(true).await {
+        Ok(rules) => Json(ShippingRuleListResponse { rules }).into_response(),
+        Err(err) => {
+            tracing::error!("Failed to load shipping rules: {}", err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "SHIPPING_RULE_LOOKUP_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// POST /api/admin/shipping-rules (admin only) - Add a shipping rule
+pub async fn create_rule_endpoint(
+    State(state): State<AppState>,
+    Json(request): Json<ShippingRuleRequest>,
+) -> Response {
+    let rule = match new_rule(&state.carriers, request) {
+        Ok(rule) => rule,
+        Err(message) => return validation_error(message),
+    };
+
+    match state.shipping_rules.create(&rule).await {
+        Ok(rule) => {
+            tracing::info!("Added shipping rule {} ({})", rule.rule_id, rule.name);
+            (
+                StatusCode::CREATED,
+                Json(ShippingRuleResponse {
+                    rule,
+                    message: "Shipping rule created successfully".to_string(),
+                }),
+            )
+                .into_response()
+        }
+        Err(err) => {
+            tracing::error!("Failed to create shipping rule: {}", err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "SHIPPING_RULE_UPDATE_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// PUT /api/admin/shipping-rules/:rule_id (admin only) - Replace a shipping rule
+pub async fn replace_rule_endpoint(
+    State(state): State<AppState>,
+    Path(rule_id): Path<i64>,
+    Json(request): Json<ShippingRuleRequest>,
+) -> Response {
+    let rule = match new_rule(&state.carriers, request) {
+        Ok(rule) => rule,
+        Err(message) => return validation_error(message),
+    };
+
+    match state.shipping_rules.replace(rule_id, &rule).await {
+        Ok(Some(rule)) => {
+            tracing::info!("Updated shipping rule {}", rule_id);
+            Json(ShippingRuleResponse {
+                rule,
+                message: "Shipping rule updated successfully".to_string(),
+            })
+            .into_response()
+        }
+        Ok(None) => rule_not_found(),
+        Err(err) => {
+            tracing::error!("Failed to update shipping rule {}: {}", rule_id, err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "SHIPPING_RULE_UPDATE_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// DELETE /api/admin/shipping-rules/:rule_id (admin only) - Remove a shipping rule
+pub async fn delete_rule_endpoint(
+    State(state): State<AppState>,
+    Path(rule_id): Path<i64>,
+) -> Response {
+    match state.shipping_rules.delete(rule_id).await {
+        Ok(true) => {
+            tracing::info!("Removed shipping rule {}", rule_id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => rule_not_found(),
+        Err(err) => {
+            tracing::error!("Failed to remove shipping rule {}: {}", rule_id, err);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "SHIPPING_RULE_UPDATE_FAILED",
+                err.to_string(),
+            )
+        }
+    }
+}
+
+/// Check a rule request and turn it into a rule to store
+fn new_rule(carriers: &Carriers, request: ShippingRuleRequest) -> Result<NewShippingRule, String> {
+    validate_rule(&request)?;
+    if let Some(option) = request
+        .shipping_options
+        .iter()
+        .find(|option| carriers.find_service(option).is_none())
+    {
+        return Err(format!("Unknown shipping option: {}", option));
+    }
+
+    Ok(NewShippingRule {
+        name: request.name.trim().to_string(),
+        position: request.position,
+        kind: request.kind,
+        shipping_options: request.shipping_options,
+        regions: request.regions,
+        min_subtotal: request.min_subtotal,
+        amount: request.amount,
+        basis_points: request.basis_points,
+        currency: ORDER_CURRENCY.to_string(),
+        active: request.active,
+    })
+}
+
+/// Check a rule's name, regions and amounts fit its kind
+fn validate_rule(request: &ShippingRuleRequest) -> Result<(), String> {
+    if request.name.trim().is_empty() {
+        return Err("name is required".to_string());
+    }
+    validate_regions(&request.regions)?;
+
+    for money in [&request.min_subtotal, &request.amount]
+        .into_iter()
+        .flatten()
+    {
+        if money.currency() != ORDER_CURRENCY {
+            return Err(format!(
+                "Amounts must be in {}, got {}",
+                ORDER_CURRENCY,
+                money.currency()
+            ));
+        }
+    }
+    if request
+        .min_subtotal
+        .as_ref()
+        .is_some_and(Money::is_negative)
+    {
+        return Err("min_subtotal can't be negative".to_string());
+    }
+
+    match request.kind {
+        ShippingRuleKind::FreeShipping | ShippingRuleKind::Hide => {
+            if request.amount.is_some() || request.basis_points.is_some() {
+                return Err(
+                    "Free shipping and hide rules take no amount or basis_points".to_string(),
+                );
+            }
+        }
+        ShippingRuleKind::FlatRate => {
+            match &request.amount {
+                Some(amount) if !amount.is_negative() => {}
+                _ => return Err("Flat rates need an amount of zero or more".to_string()),
+            }
+            if request.basis_points.is_some() {
+                return Err("Flat rates take no basis_points".to_string());
+            }
+        }
+        ShippingRuleKind::Adjust => {
+            if request.amount.is_none() && request.basis_points.is_none() {
+                return Err("Adjustments need an amount, basis_points or both".to_string());
+            }
+            if request.basis_points.is_some_and(|bp| bp < -10_000) {
+                return Err("basis_points can't discount more than 100%".to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validation_error(message: String) -> Response {
+    error_response(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "VALIDATION_ERROR",
+        message,
+    )
+}
+
+fn rule_not_found() -> Response {
+    error_response(
+        StatusCode::NOT_FOUND,
+        "SHIPPING_RULE_NOT_FOUND",
+        "Shipping rule not found".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(value: serde_json::Value) -> ShippingRuleRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_validate_rule() {
+        let free = request(serde_json::json!({
+            "name": "Free shipping over $50",
+            "kind": "free_shipping",
+            "min_subtotal": { "amount": "50.00", "currency": "USD" }
+        }));
+        assert!(validate_rule(&free).is_ok());
+        assert!(free.active);
+
+        let flat = request(serde_json::json!({
+            "name": "Hawaii and Alaska",
+            "kind": "flat_rate",
+            "regions": ["US-HI", "US-AK"],
+            "amount": { "amount": "25.00", "currency": "USD" }
+        }));
+        assert!(validate_rule(&flat).is_ok());
+
+        let invalid = [
+            serde_json::json!({ "name": " ", "kind": "hide" }),
+            serde_json::json!({ "name": "Hawaii", "kind": "hide", "regions": ["Hawaii"] }),
+            serde_json::json!({ "name": "Flat", "kind": "flat_rate" }),
+            serde_json::json!({
+                "name": "Flat",
+                "kind": "flat_rate",
+                "amount": { "amount": "-1.00", "currency": "USD" }
+            }),
+            serde_json::json!({
+                "name": "Euros",
+                "kind": "flat_rate",
+                "amount": { "amount": "5.00", "currency": "EUR" }
+            }),
+            serde_json::json!({
+                "name": "Free",
+                "kind": "free_shipping",
+                "amount": { "amount": "5.00", "currency": "USD" }
+            }),
+            serde_json::json!({ "name": "Nothing", "kind": "adjust" }),
+            serde_json::json!({ "name": "Too much", "kind": "adjust", "basis_points": -10001 }),
+        ];
+        for value in invalid {
+            assert!(validate_rule(&request(value.clone())).is_err(), "{}", value);
+        }
+    }
+}
@@ -0,0 +1,359 @@
+//! Image uploads for print orders
+//!
+//! Images are uploaded before the order that uses them is placed, so the
+//! special order ID `draft` stages uploads for the caller's next order.
+//! Uploading to an existing order adds images to it while it's still
+//! awaiting payment. Either way the returned image IDs are what
+//! `PrintRequest.image_ids` refers to.
+
+use axum::{
+    Extension, Json,
+    body::Bytes,
+    extract::{Multipart, Path, State, multipart::Field},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    AppState,
+    auth::Claims,
+    endpoints::orders::error_response,
+    imaging::{ImageDimensions, read_dimensions},
+    models::{image::StoredImage, order_status::OrderStatus},
+    previews,
+    storage::{ObjectStorage, ObjectUpload, StorageError},
+};
+
+/// Order ID used to stage uploads for an order that hasn't been placed yet
+pub const DRAFT_ORDER_ID: &str = "draft";
+
+/// Largest accepted image file
+pub const MAX_IMAGE_BYTES: usize = 50 * 1024 * 1024;
+
+/// Leading bytes of an image read to find its format and pixel dimensions;
+/// the dimensions sit well within this unless the metadata is unusually large
+const HEADER_BYTES: usize = 1024 * 1024;
+
+/// Largest accepted upload request, across all files
+pub const MAX_UPLOAD_REQUEST_BYTES: usize = 200 * 1024 * 1024;
+
+/// Response for a successful upload
+#[derive(Debug, Serialize)]
+pub struct UploadResponse {
+    pub images: Vec<StoredImage>,
+    pub message: String,
+}
+
+/// POST /api/orders/:order_id/upload - Upload image files for an order
+///
+/// Accepts `multipart/form-data`; every part with a filename is stored as
+/// an image.
+pub async fn upload_images_endpoint(
+    State(app_state): State<AppState>,
+    Path(order_id): Path<String>,
+    Extension(claims): Extension<Claims>,
+    mut multipart: Multipart,
+) -> Response {
+    let Ok(user_id) = claims.sub.parse::<Uuid>() else {
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "INVALID_USER",
+            "Invalid user ID".to_string(),
+        );
+    };
+
+    let attach_to = if order_id == DRAFT_ORDER_ID {
+        None
+    } else {
+        match app_state.orders.find_by_id(&order_id).await {
+            Ok(Some(order)) if order.user_id != Some(user_id) => {
+                return error_response(
+                    StatusCode::FORBIDDEN,
+                    "ACCESS_DENIED",
+                    "Access denied".to_string(),
+                );
+            }
+            Ok(Some(order)) if order.status != OrderStatus::PendingPayment => {
+                return error_response(
+                    StatusCode::CONFLICT,
+                    "ORDER_LOCKED",
+                    format!("Images can't be added to an order that is {}", order.status),
+                );
+            }
+            Ok(Some(order)) => Some(order.order_id),
+            Ok(None) => {
+                return error_response(
+                    StatusCode::NOT_FOUND,
+                    "ORDER_NOT_FOUND",
+                    "Order not found".to_string(),
+                );
+            }
+            Err(err) => {
+                tracing::error!("Failed to load order {}: {}", order_id, err);
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "ORDER_LOOKUP_FAILED",
+                    err.to_string(),
+                );
+            }
+        }
+    };
+
+    let mut images = Vec::new();
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(err) => {
+                return error_response(err.status(), "INVALID_UPLOAD", err.body_text());
+            }
+        };
+        // Plain form fields carry no file
+        let Some(filename) = field.file_name().map(str::to_string) else {
+            continue;
+        };
+
+        let image_id = format!("img_{}", Uuid::new_v4().simple());
+        let key_prefix = format!("uploads/{}/{}", user_id, image_id);
+        let file =
+            match store_field(app_state.storage.as_ref(), field, &filename, &key_prefix).await {
+                Ok(file) => file,
+                Err(response) => return response,
+            };
+
+        let image = StoredImage {
+            storage_key: file.storage_key,
+            image_id,
+            user_id: Some(user_id),
+            order_id: attach_to.clone(),
+            original_filename: Some(filename),
+            content_type: file.content_type.to_string(),
+            size_bytes: file.size_bytes as i64,
+            width_px: i32::try_from(file.dimensions.width).ok(),
+            height_px: i32::try_from(file.dimensions.height).ok(),
+            created_at: Utc::now(),
+        };
+
+        if let Err(err) = app_state.images.create(&image).await {
+            tracing::error!("Failed to record image {}: {}", image.image_id, err);
+            // Don't leave an orphaned object behind
+            if let Err(err) = app_state.storage.delete(&image.storage_key).await {
+                tracing::warn!("Failed to clean up {}: {}", image.storage_key, err);
+            }
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "UPLOAD_FAILED",
+                err.to_string(),
+            );
+        }
+
+        tracing::info!(
+            "Stored image {} ({} bytes) for user {}",
+            image.image_id,
+            image.size_bytes,
+            user_id
+        );
+        previews::spawn_render(app_state.clone(), image.clone(), true, Vec::new());
+        images.push(image);
+    }
+
+    if images.is_empty() {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "NO_FILES",
+            "No image files were uploaded".to_string(),
+        );
+    }
+
+    (
+        StatusCode::CREATED,
+        Json(UploadResponse {
+            images,
+            message: "Images uploaded successfully".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// A file part written to storage
+struct StoredFile {
+    storage_key: String,
+    content_type: &'static str,
+    size_bytes: usize,
+    dimensions: ImageDimensions,
+}
+
+/// Stream a file part to storage under `key_prefix`, checking it's an image
+///
+/// Only the first `HEADER_BYTES` are held in memory, to read the format and
+/// dimensions from; the rest goes straight to storage. The upload is
+/// abandoned as soon as the file grows past `MAX_IMAGE_BYTES`.
+async fn store_field(
+    storage: &dyn ObjectStorage,
+    mut field: Field<'_>,
+    filename: &str,
+    key_prefix: &str,
+) -> Result<StoredFile, Response> {
+    let mut header = Vec::new();
+    while header.len() < HEADER_BYTES {
+        match next_chunk(&mut field).await? {
+            Some(chunk) => header.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+
+    let Some((content_type, extension)) = sniff_image_type(&header) else {
+        return Err(error_response(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "UNSUPPORTED_IMAGE_TYPE",
+            format!("{} is not a JPEG or PNG image", filename),
+        ));
+    };
+    // HEIC can't be decoded for thumbnails and crop previews, so it's turned
+    // away rather than stored without them
+    if content_type == "image/heic" {
+        return Err(error_response(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "UNSUPPORTED_IMAGE_TYPE",
+            format!(
+                "{} is a HEIC image; export it as a JPEG and upload that instead",
+                filename
+            ),
+        ));
+    }
+    let dimensions = read_dimensions(&header, content_type).map_err(|err| {
+        error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "INVALID_IMAGE",
+            format!("{}: {}", filename, err),
+        )
+    })?;
+
+    let storage_key = format!("{}/original.{}", key_prefix, extension);
+    let upload_failed = |err: StorageError| {
+        tracing::error!("Failed to store {}: {}", storage_key, err);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "UPLOAD_FAILED",
+            err.to_string(),
+        )
+    };
+    let mut upload = storage
+        .start_upload(&storage_key, content_type)
+        .await
+        .map_err(upload_failed)?;
+
+    let size_bytes = match copy_field(&mut field, header, upload.as_mut()).await {
+        Ok(size_bytes) => size_bytes,
+        Err(response) => {
+            if let Err(err) = upload.abort().await {
+                tracing::warn!("Failed to abort upload of {}: {}", storage_key, err);
+            }
+            return Err(response);
+        }
+    };
+    upload.finish().await.map_err(upload_failed)?;
+
+    Ok(StoredFile {
+        storage_key,
+        content_type,
+        size_bytes,
+        dimensions,
+    })
+}
+
+/// Write the header already read and the rest of a file part to an upload,
+/// returning the file's size
+async fn copy_field(
+    field: &mut Field<'_>,
+    header: Vec<u8>,
+    upload: &mut dyn ObjectUpload,
+) -> Result<usize, Response> {
+    let write_failed = |err: StorageError| {
+        tracing::error!("Failed to store upload: {}", err);
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "UPLOAD_FAILED",
+            err.to_string(),
+        )
+    };
+
+    let mut size_bytes = header.len();
+    upload.write(&header).await.map_err(write_failed)?;
+    drop(header);
+    while let Some(chunk) = next_chunk(field).await? {
+        size_bytes += chunk.len();
+        if size_bytes > MAX_IMAGE_BYTES {
+            return Err(too_large());
+        }
+        upload.write(&chunk).await.map_err(write_failed)?;
+    }
+    Ok(size_bytes)
+}
+
+/// Next chunk of a file part, `None` once it's all been read
+async fn next_chunk(field: &mut Field<'_>) -> Result<Option<Bytes>, Response> {
+    field
+        .chunk()
+        .await
+        .map_err(|err| error_response(err.status(), "INVALID_UPLOAD", err.body_text()))
+}
+
+fn too_large() -> Response {
+    error_response(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "IMAGE_TOO_LARGE",
+        format!("Images may be at most {} MB", MAX_IMAGE_BYTES / 1024 / 1024),
+    )
+}
+
+/// Detect the image format from its leading bytes
+///
+/// Returns the content type and file extension. The client-supplied content
+/// type isn't trusted.
+pub fn sniff_image_type(data: &[u8]) -> Option<(&'static str, &'static str)> {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(("image/jpeg", "jpg"));
+    }
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(("image/png", "png"));
+    }
+    // HEIF containers start with an `ftyp` box naming the brand
+    if data.len() >= 12 && &data[4..8] == b"ftyp" {
+        match &data[8..12] {
+            b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" | b"mif1" | b"msf1" => {
+                return Some(("image/heic", "heic"));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_image_type() {
+        assert_eq!(
+            sniff_image_type(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00]),
+            Some(("image/jpeg", "jpg"))
+        );
+        assert_eq!(
+            sniff_image_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some(("image/png", "png"))
+        );
+        assert_eq!(
+            sniff_image_type(b"\0\0\0\x18ftypheic\0\0\0\0"),
+            Some(("image/heic", "heic"))
+        );
+        // MP4 shares the container format but isn't an image
+        assert_eq!(sniff_image_type(b"\0\0\0\x18ftypisom\0\0\0\0"), None);
+        assert_eq!(sniff_image_type(b"GIF89a"), None);
+        assert_eq!(sniff_image_type(b""), None);
+    }
+}
@@ -0,0 +1,395 @@
+//! Fulfillment routing
+//!
+//! Orders are produced at one of the print labs in `fulfillment_locations`
+//! and shipped from there. The lab is picked by routing rules applied in
+//! order, each narrowing down the labs the rule before it left:
+//!
+//! - `availability`: labs that produce every print size of the order
+//! - `region`: labs preferred for the destination's state, or else for its
+//!   country; when no lab is, they are all kept
+//! - `cheapest`: the lab with the cheapest shipping quote, which settles the
+//!   route so rules after it have no effect
+//!
+//! Labs still tied after the last rule go by priority.
+
+use std::{env, fmt, future::Future, str::FromStr};
+
+use crate::{
+    carriers::{CarrierError, ShipmentAddress, ShippingQuote},
+    models::fulfillment_location::FulfillmentLocation,
+    repositories::locations::{LocationRepository, NewLocation},
+};
+
+/// A way of picking the lab an order is routed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoutingRule {
+    Availability,
+    Region,
+    Cheapest,
+}
+
+impl FromStr for RoutingRule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "availability" => Ok(RoutingRule::Availability),
+            "region" => Ok(RoutingRule::Region),
+            "cheapest" => Ok(RoutingRule::Cheapest),
+            _ => Err(format!("Unknown routing rule: {}", value)),
+        }
+    }
+}
+
+/// Routing rules, in the order they're applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutingConfig {
+    pub rules: Vec<RoutingRule>,
+}
+
+impl Default for RoutingConfig {
+    /// Labs that can produce the order, nearest first, then cheapest to ship from
+    fn default() -> Self {
+        RoutingConfig {
+            rules: vec![
+                RoutingRule::Availability,
+                RoutingRule::Region,
+                RoutingRule::Cheapest,
+            ],
+        }
+    }
+}
+
+/// Why an order couldn't be routed
+#[derive(Debug)]
+pub enum RoutingError {
+    /// No active location can fulfill the order
+    NoLocation(String),
+    /// Every location's carriers failed to quote
+    Carrier(CarrierError),
+}
+
+impl fmt::Display for RoutingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoutingError::NoLocation(msg) => write!(f, "{}", msg),
+            RoutingError::Carrier(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for RoutingError {}
+
+/// The location an order is routed to, with its shipping quotes from there
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub location: FulfillmentLocation,
+    pub quotes: Vec<ShippingQuote>,
+}
+
+impl RoutingConfig {
+    /// Create a RoutingConfig from environment variables
+    ///
+    /// # Environment Variables
+    ///
+    /// - `FULFILLMENT_ROUTING`: Comma-separated routing rules applied in order,
+    ///   from `availability`, `region` and `cheapest` (optional, defaults to
+    ///   `availability,region,cheapest`)
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("FULFILLMENT_ROUTING") {
+            Ok(value) => Ok(RoutingConfig {
+                rules: value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|rule| !rule.is_empty())
+                    .map(str::parse)
+                    .collect::<Result<Vec<_>, _>>()?,
+            }),
+            Err(_) => Ok(RoutingConfig::default()),
+        }
+    }
+
+    /// Pick the location prints of `size_ids` ship to `destination` from
+    ///
+    /// `quote` quotes shipping from a location. It's called for every
+    /// location left when the `cheapest` rule is reached, and otherwise only
+    /// for the location picked. Inactive locations are skipped.
+    pub async fn route<F, Fut>(
+        &self,
+        locations: Vec<FulfillmentLocation>,
+        size_ids: &[&str],
+        destination: &ShipmentAddress,
+        quote: F,
+    ) -> Result<Route, RoutingError>
+    where
+        F: Fn(FulfillmentLocation) -> Fut,
+        Fut: Future<Output = Result<Vec<ShippingQuote>, CarrierError>>,
+    {
+        let mut candidates: Vec<FulfillmentLocation> =
+            locations.into_iter().filter(|l| l.active).collect();
+        candidates.sort_by_key(|location| location.priority);
+        if candidates.is_empty() {
+            return Err(RoutingError::NoLocation(
+                "No fulfillment location is active".to_string(),
+            ));
+        }
+
+        for rule in &self.rules {
+            match rule {
+                RoutingRule::Availability => {
+                    candidates.retain(|location| size_ids.iter().all(|s| location.produces(s)));
+                    if candidates.is_empty() {
+                        return Err(RoutingError::NoLocation(format!(
+                            "No fulfillment location produces every print size: {}",
+                            size_ids.join(", ")
+                        )));
+                    }
+                }
+                RoutingRule::Region => {
+                    let best = candidates
+                        .iter()
+                        .map(|location| location.region_match(destination))
+                        .max()
+                        .unwrap_or(0);
+                    if best > 0 {
+                        candidates.retain(|location| location.region_match(destination) == best);
+                    }
+                }
+                RoutingRule::Cheapest => return cheapest(candidates, &quote).await,
+            }
+        }
+
+        let location = candidates.remove(0);
+        let quotes = quote(location.clone())
+            .await
+            .map_err(RoutingError::Carrier)?;
+        Ok(Route { location, quotes })
+    }
+}
+
+/// Quote every candidate and keep the one with the cheapest service
+///
+/// A candidate whose quote fails is logged and left out. When none of them
+/// quote any service, the first is kept.
+async fn cheapest<F, Fut>(
+    candidates: Vec<FulfillmentLocation>,
+    quote: &F,
+) -> Result<Route, RoutingError>
+where
+    F: Fn(FulfillmentLocation) -> Fut,
+    Fut: Future<Output = Result<Vec<ShippingQuote>, CarrierError>>,
+{
+    let mut best: Option<(i64, Route)> = None;
+    let mut unquoted = None;
+    let mut first_error = None;
+    for location in candidates {
+        match quote(location.clone()).await {
+            Ok(quotes) => {
+                let Some(price) = quotes.iter().map(|q| q.price.amount_minor()).min() else {
+                    unquoted.get_or_insert(Route { location, quotes });
+                    continue;
+                };
+                if best.as_ref().is_none_or(|(cheapest, _)| price < *cheapest) {
+                    best = Some((price, Route { location, quotes }));
+                }
+            }
+            Err(err) => {
+                tracing::warn!(
+                    "Failed to quote shipping from {}: {}",
+                    location.location_id,
+                    err
+                );
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+
+    match (best, unquoted, first_error) {
+        (Some((_, route)), _, _) | (None, Some(route), _) => Ok(route),
+        (None, None, Some(err)) => Err(RoutingError::Carrier(err)),
+        (None, None, None) => Err(RoutingError::NoLocation(
+            "No fulfillment location is active".to_string(),
+        )),
+    }
+}
+
+/// Add the ship-from address as the first location when there are none
+///
+/// Keeps a single-lab setup working without configuring locations.
+pub async fn ensure_default_location(
+    locations: &dyn LocationRepository,
+    ship_from: ShipmentAddress,
+) -> Result<(), String> {
+    let existing = locations.list(true).await.map_err(|e| e.to_string())?;
+    if !existing.is_empty() {
+        tracing::info!("{} fulfillment location(s) configured", existing.len());
+        return Ok(());
+    }
+
+    let location = NewLocation {
+        location_id: "main".to_string(),
+        name: ship_from
+            .company
+            .clone()
+            .unwrap_or_else(|| ship_from.name.clone()),
+        contact_name: ship_from.name,
+        company: ship_from.company,
+        phone: ship_from.phone,
+        line1: ship_from.line1,
+        line2: ship_from.line2,
+        city: ship_from.city,
+        state: ship_from.state,
+        postal_code: ship_from.postal_code,
+        country: ship_from.country.to_ascii_uppercase(),
+        regions: Vec::new(),
+        size_ids: Vec::new(),
+        priority: 0,
+        pickup: false,
+        pickup_hours: None,
+        active: true,
+    };
+    locations
+        .create(&location)
+        .await
+        .map_err(|e| e.to_string())?;
+    tracing::info!("Added the ship-from address as fulfillment location main");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{carriers::CarrierService, money::Money, test_support::shipment_address};
+    use chrono::Utc;
+
+    const GROUND: CarrierService = CarrierService {
+        shipping_option: "Test_Ground",
+        code: "GROUND",
+        description: "Test Ground",
+        transit_days: (2, 4),
+    };
+
+    fn location(location_id: &str, state: &str, priority: i32) -> FulfillmentLocation {
+        FulfillmentLocation {
+            location_id: location_id.to_string(),
+            name: format!("{} lab", location_id),
+            contact_name: "Shipping Desk".to_string(),
+            company: None,
+            phone: None,
+            line1: "1 Lab Way".to_string(),
+            line2: None,
+            city: "Somewhere".to_string(),
+            state: state.to_string(),
+            postal_code: "00000".to_string(),
+            country: "US".to_string(),
+            regions: vec![format!("US-{}", state)],
+            size_ids: Vec::new(),
+            priority,
+            pickup: false,
+            pickup_hours: None,
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn destination(state: &str) -> ShipmentAddress {
+        ShipmentAddress {
+            state: state.to_string(),
+            ..shipment_address()
+        }
+    }
+
+    /// Quotes Ground at a price per state the lab is in
+    async fn quote_from(location: FulfillmentLocation) -> Result<Vec<ShippingQuote>, CarrierError> {
+        let price = match location.state.as_str() {
+            "CO" => 1187,
+            "CA" => 995,
+            _ => return Err(CarrierError::Network("connection refused".to_string())),
+        };
+        Ok(vec![ShippingQuote::new(
+            "test",
+            &GROUND,
+            Money::from_minor(price, "USD"),
+            None,
+        )])
+    }
+
+    fn labs() -> Vec<FulfillmentLocation> {
+        let mut oakland = location("oakland", "CA", 20);
+        oakland.size_ids = vec!["4x6".to_string(), "5x7".to_string()];
+        vec![location("denver", "CO", 10), oakland]
+    }
+
+    #[tokio::test]
+    async fn test_route_by_rules() {
+        let config = RoutingConfig::default();
+
+        // Both labs produce 4x6, and Oakland ships cheaper
+        let route = config
+            .route(labs(), &["4x6"], &destination("TX"), quote_from)
+            .await
+            .unwrap();
+        assert_eq!(route.location.location_id, "oakland");
+        assert_eq!(route.quotes[0].price, Money::from_minor(995, "USD"));
+
+        // Only Denver produces 8x10
+        let route = config
+            .route(labs(), &["4x6", "8x10"], &destination("TX"), quote_from)
+            .await
+            .unwrap();
+        assert_eq!(route.location.location_id, "denver");
+
+        // Colorado addresses go to the Denver lab whatever the price
+        let route = config
+            .route(labs(), &["4x6"], &destination("CO"), quote_from)
+            .await
+            .unwrap();
+        assert_eq!(route.location.location_id, "denver");
+
+        // Without rules, priority decides
+        let config = RoutingConfig { rules: Vec::new() };
+        let route = config
+            .route(labs(), &["4x6"], &destination("CA"), quote_from)
+            .await
+            .unwrap();
+        assert_eq!(route.location.location_id, "denver");
+    }
+
+    #[tokio::test]
+    async fn test_route_without_location() {
+        let config = RoutingConfig::default();
+        let mut labs = labs();
+        labs[0].size_ids = vec!["4x6".to_string(), "8x10".to_string()];
+        let err = config
+            .route(labs.clone(), &["16x20"], &destination("TX"), quote_from)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RoutingError::NoLocation(_)));
+
+        // A lab whose carriers fail is passed over for one that quotes
+        labs[1].state = "NV".to_string();
+        let route = config
+            .route(labs.clone(), &["4x6"], &destination("TX"), quote_from)
+            .await
+            .unwrap();
+        assert_eq!(route.location.location_id, "denver");
+
+        labs.truncate(1);
+        labs[0].active = false;
+        let err = config
+            .route(labs, &["4x6"], &destination("TX"), quote_from)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RoutingError::NoLocation(_)));
+    }
+
+    #[test]
+    fn test_routing_rules_parse() {
+        assert_eq!(
+            "region".parse::<RoutingRule>().unwrap(),
+            RoutingRule::Region
+        );
+        assert!("nearest".parse::<RoutingRule>().is_err());
+    }
+}
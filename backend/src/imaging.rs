@@ -0,0 +1,349 @@
+//! Image inspection for print orders
+//!
+//! Reads pixel dimensions from uploaded JPEG, PNG and HEIC files, works out
+//! how sharp they will print at a given size, and renders JPEG thumbnails
+//! and crop previews. HEIC pixel data can't be decoded here, so new HEIC
+//! uploads are rejected; ones stored before that still have their size read
+//! but get no previews.
+
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, imageops::FilterType};
+use serde::Serialize;
+use std::{fmt, io::Cursor};
+
+/// Image inspection errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImagingError {
+    /// The content type isn't one we can read
+    UnsupportedFormat(String),
+    /// The file is truncated or corrupt
+    Decode(String),
+}
+
+impl fmt::Display for ImagingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImagingError::UnsupportedFormat(content_type) => {
+                write!(f, "Unsupported image format: {}", content_type)
+            }
+            ImagingError::Decode(msg) => write!(f, "Could not read image: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ImagingError {}
+
+/// Pixel dimensions of an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ImageDimensions {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Read an image's pixel dimensions without decoding the pixel data
+///
+/// `content_type` is the sniffed type stored with the upload. JPEGs
+/// converted from HEIC are ordinary JPEGs, so they take the JPEG path.
+pub fn read_dimensions(data: &[u8], content_type: &str) -> Result<ImageDimensions, ImagingError> {
+    let format = match content_type {
+        "image/jpeg" => ImageFormat::Jpeg,
+        "image/png" => ImageFormat::Png,
+        "image/heic" => {
+            return heic_dimensions(data)
+                .ok_or_else(|| ImagingError::Decode("no image size in HEIC file".to_string()));
+        }
+        other => return Err(ImagingError::UnsupportedFormat(other.to_string())),
+    };
+
+    let (width, height) = ImageReader::with_format(Cursor::new(data), format)
+        .into_dimensions()
+        .map_err(|e| ImagingError::Decode(e.to_string()))?;
+    Ok(ImageDimensions { width, height })
+}
+
+/// Find the primary image size in a HEIF container
+///
+/// Sizes live in `ispe` properties under `meta/iprp/ipco`. A file can hold
+/// thumbnails and tiles too, so the largest size wins.
+fn heic_dimensions(data: &[u8]) -> Option<ImageDimensions> {
+    let mut largest = None;
+    walk_heif_boxes(data, &mut largest);
+    largest
+}
+
+fn walk_heif_boxes(data: &[u8], largest: &mut Option<ImageDimensions>) {
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        let (header, size) = match size {
+            // 64-bit size follows the type
+            1 if pos + 16 <= data.len() => (
+                16,
+                u64::from_be_bytes(data[pos + 8..pos + 16].try_into().unwrap()) as usize,
+            ),
+            // Box extends to the end of its parent
+            0 => (8, data.len() - pos),
+            size => (8, size),
+        };
+        if size < header || size > data.len() - pos {
+            return;
+        }
+        let body = &data[pos + header..pos + size];
+
+        match kind {
+            // `meta` is a full box: version and flags precede its children
+            b"meta" if body.len() >= 4 => walk_heif_boxes(&body[4..], largest),
+            b"iprp" | b"ipco" => walk_heif_boxes(body, largest),
+            b"ispe" if body.len() >= 12 => {
+                let dimensions = ImageDimensions {
+                    width: u32::from_be_bytes(body[4..8].try_into().unwrap()),
+                    height: u32::from_be_bytes(body[8..12].try_into().unwrap()),
+                };
+                let area = |d: &ImageDimensions| d.width as u64 * d.height as u64;
+                if largest.is_none_or(|current| area(&dimensions) > area(&current)) {
+                    *largest = Some(dimensions);
+                }
+            }
+            _ => {}
+        }
+        pos += size;
+    }
+}
+
+/// Longest side of a thumbnail in pixels
+pub const THUMBNAIL_MAX_SIDE: u32 = 256;
+
+/// Longest side of a crop preview in pixels
+pub const PREVIEW_MAX_SIDE: u32 = 800;
+
+/// A rendered JPEG
+#[derive(Debug, Clone)]
+pub struct RenderedImage {
+    pub data: Vec<u8>,
+    pub dimensions: ImageDimensions,
+}
+
+/// Decode a JPEG or PNG, rotated upright according to its EXIF orientation
+pub fn decode_upright(data: &[u8], content_type: &str) -> Result<DynamicImage, ImagingError> {
+    let format = match content_type {
+        "image/jpeg" => ImageFormat::Jpeg,
+        "image/png" => ImageFormat::Png,
+        other => return Err(ImagingError::UnsupportedFormat(other.to_string())),
+    };
+
+    let decode_error = |e: image::ImageError| ImagingError::Decode(e.to_string());
+    let mut decoder = ImageReader::with_format(Cursor::new(data), format)
+        .into_decoder()
+        .map_err(decode_error)?;
+    let orientation = decoder.orientation().map_err(decode_error)?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(decode_error)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+/// Scale an image down to fit within `THUMBNAIL_MAX_SIDE`, keeping its aspect ratio
+pub fn render_thumbnail(image: &DynamicImage) -> Result<RenderedImage, ImagingError> {
+    encode_jpeg(image.thumbnail(THUMBNAIL_MAX_SIDE, THUMBNAIL_MAX_SIDE))
+}
+
+/// Show how an image will be cropped to fill a `width_in` x `height_in` print
+///
+/// The print is turned to match the image's orientation, the image is
+/// center-cropped to the print's aspect ratio, then scaled down to fit
+/// within `PREVIEW_MAX_SIDE`.
+pub fn render_crop_preview(
+    image: &DynamicImage,
+    width_in: f32,
+    height_in: f32,
+) -> Result<RenderedImage, ImagingError> {
+    let (w, h) = (image.width(), image.height());
+    let (short_in, long_in) = (width_in.min(height_in), width_in.max(height_in));
+    let (print_w, print_h) = if w >= h {
+        (long_in, short_in)
+    } else {
+        (short_in, long_in)
+    };
+
+    // Keep the full extent of whichever side limits the crop
+    let (crop_w, crop_h) = if w as f32 / h as f32 > print_w / print_h {
+        (
+            ((h as f32 * print_w / print_h).round() as u32).clamp(1, w),
+            h,
+        )
+    } else {
+        (
+            w,
+            ((w as f32 * print_h / print_w).round() as u32).clamp(1, h),
+        )
+    };
+    let cropped = image.crop_imm((w - crop_w) / 2, (h - crop_h) / 2, crop_w, crop_h);
+
+    let preview = if crop_w.max(crop_h) > PREVIEW_MAX_SIDE {
+        cropped.resize(PREVIEW_MAX_SIDE, PREVIEW_MAX_SIDE, FilterType::Triangle)
+    } else {
+        cropped
+    };
+    encode_jpeg(preview)
+}
+
+fn encode_jpeg(image: DynamicImage) -> Result<RenderedImage, ImagingError> {
+    let rgb = image.to_rgb8();
+    let dimensions = ImageDimensions {
+        width: rgb.width(),
+        height: rgb.height(),
+    };
+    let mut data = Vec::new();
+    rgb.write_to(&mut Cursor::new(&mut data), ImageFormat::Jpeg)
+        .map_err(|e| ImagingError::Decode(e.to_string()))?;
+    Ok(RenderedImage { data, dimensions })
+}
+
+/// Effective resolution of an image printed at `width_in` x `height_in`
+///
+/// Prints are cropped to fill the paper, so the short side of the crop
+/// limits the resolution. The image may be rotated to match the print's
+/// orientation, whichever gives the sharper result.
+pub fn effective_dpi(dimensions: ImageDimensions, width_in: f32, height_in: f32) -> f32 {
+    let (w, h) = (dimensions.width as f32, dimensions.height as f32);
+    let as_is = (w / width_in).min(h / height_in);
+    let rotated = (w / height_in).min(h / width_in);
+    as_is.max(rotated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wrap a payload in an ISO BMFF box
+    fn heif_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    fn ispe(width: u32, height: u32) -> Vec<u8> {
+        let mut payload = vec![0; 4];
+        payload.extend_from_slice(&width.to_be_bytes());
+        payload.extend_from_slice(&height.to_be_bytes());
+        heif_box(b"ispe", &payload)
+    }
+
+    #[test]
+    fn test_png_dimensions() {
+        let mut png = Vec::new();
+        image::RgbImage::new(40, 30)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        assert_eq!(
+            read_dimensions(&png, "image/png"),
+            Ok(ImageDimensions {
+                width: 40,
+                height: 30
+            })
+        );
+    }
+
+    #[test]
+    fn test_jpeg_dimensions() {
+        let mut jpeg = Vec::new();
+        image::RgbImage::new(64, 48)
+            .write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+            .unwrap();
+        assert_eq!(
+            read_dimensions(&jpeg, "image/jpeg"),
+            Ok(ImageDimensions {
+                width: 64,
+                height: 48
+            })
+        );
+        assert!(read_dimensions(&jpeg[..20], "image/jpeg").is_err());
+    }
+
+    #[test]
+    fn test_heic_dimensions() {
+        // A thumbnail and the primary image, in either order
+        let ipco = heif_box(b"ipco", &[ispe(320, 240), ispe(4032, 3024)].concat());
+        let iprp = heif_box(b"iprp", &ipco);
+        let meta = heif_box(b"meta", &[vec![0; 4], iprp].concat());
+        let heic = [heif_box(b"ftyp", b"heic\0\0\0\0mif1heic"), meta].concat();
+
+        assert_eq!(
+            read_dimensions(&heic, "image/heic"),
+            Ok(ImageDimensions {
+                width: 4032,
+                height: 3024
+            })
+        );
+        assert!(read_dimensions(&heic[..30], "image/heic").is_err());
+    }
+
+    #[test]
+    fn test_effective_dpi() {
+        // 12 MP phone photo: 4032 x 3024
+        let photo = ImageDimensions {
+            width: 4032,
+            height: 3024,
+        };
+        // Landscape photo on a portrait 8x10 is rotated: min(4032/10, 3024/8)
+        assert_eq!(effective_dpi(photo, 8.0, 10.0), 378.0);
+        // 11x14: min(4032/14, 3024/11) = 274.9
+        assert!((effective_dpi(photo, 11.0, 14.0) - 274.909).abs() < 0.01);
+
+        let thumbnail = ImageDimensions {
+            width: 640,
+            height: 480,
+        };
+        assert!((effective_dpi(thumbnail, 11.0, 14.0) - 43.636).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        assert_eq!(
+            read_dimensions(b"GIF89a", "image/gif"),
+            Err(ImagingError::UnsupportedFormat("image/gif".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_render_thumbnail() {
+        let image = DynamicImage::new_rgb8(1600, 1200);
+        let thumbnail = render_thumbnail(&image).unwrap();
+        assert_eq!(
+            thumbnail.dimensions,
+            ImageDimensions {
+                width: 256,
+                height: 192
+            }
+        );
+        assert_eq!(
+            read_dimensions(&thumbnail.data, "image/jpeg"),
+            Ok(thumbnail.dimensions)
+        );
+    }
+
+    #[test]
+    fn test_render_crop_preview() {
+        // Landscape 4:3 photo on a 4x6 print is cropped to 3:2
+        let landscape = DynamicImage::new_rgb8(1600, 1200);
+        let preview = render_crop_preview(&landscape, 4.0, 6.0).unwrap();
+        assert_eq!(
+            preview.dimensions,
+            ImageDimensions {
+                width: 800,
+                height: 534
+            }
+        );
+
+        // Portrait photo on an 8x10 stays portrait and is cropped to 4:5
+        let portrait = DynamicImage::new_rgb8(300, 600);
+        let preview = render_crop_preview(&portrait, 8.0, 10.0).unwrap();
+        assert_eq!(
+            preview.dimensions,
+            ImageDimensions {
+                width: 300,
+                height: 375
+            }
+        );
+    }
+}
@@ -4,30 +4,81 @@
 //! including address validation and shipping rate calculations.
 
 pub mod auth;
+pub mod calendar;
+pub mod carriers;
 pub mod client;
 pub mod config;
+pub mod customs;
 pub mod endpoints;
 pub mod error;
+pub mod fulfillment;
+pub mod imaging;
 pub mod middleware;
 pub mod models;
+pub mod money;
+pub mod packing;
+pub mod payments;
+pub mod previews;
+pub mod repositories;
+pub mod shipping;
+pub mod shipping_rules;
+pub mod storage;
+#[cfg(test)]
+mod test_support;
+pub mod tracking;
 pub mod types;
 pub mod utils;
 
 // Re-export commonly used types
+use calendar::BusinessCalendar;
+use carriers::Carriers;
 pub use client::UpsClient;
-pub use config::UpsConfig;
+pub use config::{PrintQualityConfig, UpsConfig};
 pub use error::{Result, UpsError};
-pub use types::{AddressValidationResult, RateRequestOptions, ShippingRateRequest};
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use fulfillment::RoutingConfig;
+pub use money::{Money, MoneyError};
+use packing::PackagingConfig;
+use payments::PaymentProviders;
+use repositories::{
+    catalog::CatalogRepository, idempotency::IdempotencyRepository, images::ImageRepository,
+    locations::LocationRepository, orders::OrderRepository, payments::PaymentRepository,
+    shipping_labels::ShippingLabelRepository, shipping_rules::ShippingRuleRepository,
+    users::UserRepository,
+};
 use sqlx::postgres::PgPool;
+use std::sync::Arc;
+use storage::ObjectStorage;
+pub use types::{AddressValidationResult, RateRequestOptions, ShippingRateRequest};
 
-/// Application state that holds the UPS client and access token
+/// Application state that holds the carriers, repositories and storage
 #[derive(Debug, Clone)]
 pub struct AppState {
-    pub ups_client: UpsClient,
-    pub access_token: String,
-    pub user_store: Arc<RwLock<endpoints::auth::UserStore>>,
+    /// Shipping carriers, UPS first; each keeps its own OAuth token fresh
+    pub carriers: Carriers,
+    /// Print labs orders are produced at and shipped from
+    pub locations: Arc<dyn LocationRepository>,
+    /// How orders are routed to a lab
+    pub routing: RoutingConfig,
+    /// Free shipping, flat rates and markups applied to carrier quotes
+    pub shipping_rules: Arc<dyn ShippingRuleRepository>,
+    /// Containers orders are packed into
+    pub packaging: PackagingConfig,
+    /// Business days production and transit times are counted in
+    pub calendar: BusinessCalendar,
+    pub users: Arc<dyn UserRepository>,
+    pub orders: Arc<dyn OrderRepository>,
+    pub catalog: Arc<dyn CatalogRepository>,
+    pub images: Arc<dyn ImageRepository>,
+    /// Object storage for uploaded files
+    pub storage: Arc<dyn ObjectStorage>,
+    pub print_quality: PrintQualityConfig,
+    /// Payment providers by payment method
+    pub payment_providers: PaymentProviders,
+    pub payments: Arc<dyn PaymentRepository>,
+    /// Shipping labels bought for orders
+    pub shipping_labels: Arc<dyn ShippingLabelRepository>,
+    /// Idempotency keys of order and payment requests
+    pub idempotency: Arc<dyn IdempotencyRepository>,
     pub db_pool: PgPool,
 }
 
@@ -4,12 +4,27 @@
 use axum::Router;
 use clap::Parser;
 use dotenvy::dotenv;
+use sqlx::postgres::PgPoolOptions;
 use std::sync::Arc;
-use sushi::{AppState, Result as UpsResult, UpsClient, UpsConfig, endpoints, middleware};
-use tokio::sync::RwLock;
+use sushi::repositories::{
+    catalog::PgCatalogRepository,
+    idempotency::PgIdempotencyRepository,
+    images::PgImageRepository,
+    locations::{LocationRepository, PgLocationRepository},
+    orders::PgOrderRepository,
+    payments::PgPaymentRepository,
+    shipping_labels::PgShippingLabelRepository,
+    shipping_rules::PgShippingRuleRepository,
+    users::{PgUserRepository, UserRepository},
+};
+use sushi::{
+    AppState, PrintQualityConfig, Result as UpsResult, UpsClient, UpsConfig,
+    calendar::BusinessCalendar, carriers::CarrierConfig, endpoints, fulfillment, middleware,
+    packing::PackagingConfig, payments::PaymentConfig, storage::StorageConfig,
+    tracking::TrackingConfig, utils,
+};
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use sqlx::{postgres::PgPoolOptions};
 
 /// SUSHI - UPS Address Validation Tool
 #[derive(Parser, Debug)]
@@ -39,10 +54,15 @@ async fn main() -> UpsResult<()> {
 
     let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     let db_pool = PgPoolOptions::new()
-    .max_connections(5)
-    .connect(&database_url)
-    .await
-    .expect("Failed to connect to Postgres");
+        .max_connections(5)
+        .connect(&database_url)
+        .await
+        .expect("Failed to connect to Postgres");
+
+    sqlx::migrate!("./migrations")
+        .run(&db_pool)
+        .await
+        .expect("Failed to run database migrations");
 
     // Initialize tracing subscriber for structured logging
     tracing_subscriber::registry()
@@ -73,24 +93,83 @@ async fn main() -> UpsResult<()> {
         config.display();
     }
 
+    let storage = StorageConfig::from_env()
+        .map_err(sushi::error::UpsError::Config)?
+        .build()
+        .await;
+
+    let ship_from = utils::load_ship_from_data(&args.ship_from)?.into();
+    let routing = fulfillment::RoutingConfig::from_env().map_err(sushi::error::UpsError::Config)?;
+
+    let print_quality = PrintQualityConfig::from_env().map_err(sushi::error::UpsError::Config)?;
+    let packaging = PackagingConfig::from_env().map_err(sushi::error::UpsError::Config)?;
+    let calendar = BusinessCalendar::from_env().map_err(sushi::error::UpsError::Config)?;
+    let tracking = TrackingConfig::from_env().map_err(sushi::error::UpsError::Config)?;
+
+    let payment_providers = PaymentConfig::from_env()
+        .map_err(sushi::error::UpsError::Config)?
+        .build();
+    tracing::info!("Payment methods enabled: {:?}", payment_providers.methods());
+
     // Throw a fit if JWT_SECRET is not set
     let _ = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
 
     tracing::info!("Starting TPS Orders API server");
 
-    // Create UPS client and get access token
+    // Create UPS client and fetch the first access token, so bad credentials fail fast
     let client = UpsClient::new(config).with_debug(args.debug);
     tracing::info!("Authenticating with UPS API...");
-    let access_token = client.get_access_token().await?;
+    client.get_access_token().await?;
     tracing::info!("✅ Successfully authenticated with UPS API");
 
-    // Create application state with bootstrap admin
-    let user_store = Arc::new(RwLock::new(endpoints::auth::UserStore::new_with_admin()));
+    let carriers = CarrierConfig::from_env()
+        .map_err(sushi::error::UpsError::Config)?
+        .build(client);
+    tracing::info!("Shipping carriers enabled: {:?}", carriers.names());
+
+    // Create application state and make sure the bootstrap admin exists
+    let users: Arc<dyn UserRepository> = Arc::new(PgUserRepository::new(db_pool.clone()));
+    endpoints::auth::ensure_bootstrap_admin(users.as_ref())
+        .await
+        .expect("Failed to create bootstrap admin");
+    let locations: Arc<dyn LocationRepository> =
+        Arc::new(PgLocationRepository::new(db_pool.clone()));
+    fulfillment::ensure_default_location(locations.as_ref(), ship_from)
+        .await
+        .expect("Failed to add the default fulfillment location");
+
     let app_state = AppState {
-    ups_client: client,
-    access_token,
-    user_store,
-    db_pool, 
+        carriers,
+        locations,
+        routing,
+        shipping_rules: Arc::new(PgShippingRuleRepository::new(db_pool.clone())),
+        packaging,
+        calendar,
+        users,
+        orders: Arc::new(PgOrderRepository::new(db_pool.clone())),
+        catalog: Arc::new(PgCatalogRepository::new(db_pool.clone())),
+        images: Arc::new(PgImageRepository::new(db_pool.clone())),
+        storage,
+        print_quality,
+        payment_providers,
+        payments: Arc::new(PgPaymentRepository::new(db_pool.clone())),
+        shipping_labels: Arc::new(PgShippingLabelRepository::new(db_pool.clone())),
+        idempotency: Arc::new(PgIdempotencyRepository::new(db_pool.clone())),
+        db_pool,
+    };
+
+    // Move shipped orders to delivered once their carrier confirms it
+    match tracking.poll_interval {
+        Some(interval) => sushi::tracking::spawn_delivery_poller(app_state.clone(), interval),
+        None => tracing::info!("Delivery tracking disabled"),
+    }
+
+    // Order and payment calls can be retried safely with an Idempotency-Key
+    let idempotent = || {
+        axum::middleware::from_fn_with_state(
+            app_state.idempotency.clone(),
+            middleware::idempotency_middleware,
+        )
     };
 
     // Startup axum server with tracing middleware
@@ -123,6 +202,20 @@ async fn main() -> UpsResult<()> {
             "/api/auth/reset-password",
             axum::routing::post(endpoints::auth::reset_password_endpoint),
         )
+        // Payment provider webhooks, authenticated by their signatures
+        .route(
+            "/api/payments/webhook",
+            axum::routing::post(endpoints::payments::payment_webhook_endpoint),
+        )
+        // Public catalog routes
+        .route(
+            "/api/prints/sizes",
+            axum::routing::get(endpoints::prints::list_sizes_endpoint),
+        )
+        .route(
+            "/api/pickup-locations",
+            axum::routing::get(endpoints::locations::list_pickup_locations_endpoint),
+        )
         // Protected routes (require authentication)
         .nest(
             "/api",
@@ -140,7 +233,43 @@ async fn main() -> UpsResult<()> {
                 )
                 .route(
                     "/orders",
-                    axum::routing::post(endpoints::orders::orders_endpoint),
+                    axum::routing::get(endpoints::orders::list_my_orders_endpoint)
+                        .post(endpoints::orders::orders_endpoint)
+                        .layer(idempotent()),
+                )
+                .route(
+                    "/orders/{order_id}",
+                    axum::routing::get(endpoints::orders::get_order_endpoint),
+                )
+                .route(
+                    "/orders/{order_id}/tracking",
+                    axum::routing::get(endpoints::shipping::get_tracking_endpoint),
+                )
+                .route(
+                    "/shipping/quote",
+                    axum::routing::post(endpoints::shipping::quote_endpoint),
+                )
+                .route(
+                    "/shipping/validate-address",
+                    axum::routing::post(endpoints::shipping::validate_address_endpoint),
+                )
+                .route(
+                    "/payments/intent",
+                    axum::routing::post(endpoints::payments::create_intent_endpoint)
+                        .layer(idempotent()),
+                )
+                .route(
+                    "/payments/capture",
+                    axum::routing::post(endpoints::payments::capture_payment_endpoint)
+                        .layer(idempotent()),
+                )
+                .route(
+                    "/orders/{order_id}/upload",
+                    axum::routing::post(endpoints::uploads::upload_images_endpoint).layer(
+                        axum::extract::DefaultBodyLimit::max(
+                            endpoints::uploads::MAX_UPLOAD_REQUEST_BYTES,
+                        ),
+                    ),
                 )
                 .layer(axum::middleware::from_fn(middleware::auth_middleware)),
         )
@@ -160,6 +289,74 @@ async fn main() -> UpsResult<()> {
                     "/admin/create-admin",
                     axum::routing::post(endpoints::admin::create_admin_endpoint),
                 )
+                .route(
+                    "/admin/orders",
+                    axum::routing::get(endpoints::admin::list_orders_endpoint),
+                )
+                .route(
+                    "/admin/orders/{order_id}",
+                    axum::routing::get(endpoints::admin::get_order_endpoint),
+                )
+                .route(
+                    "/admin/orders/{order_id}/status",
+                    axum::routing::patch(endpoints::admin::update_order_status_endpoint),
+                )
+                .route(
+                    "/admin/orders/{order_id}/pickup",
+                    axum::routing::post(endpoints::admin::confirm_pickup_endpoint),
+                )
+                .route(
+                    "/admin/orders/{order_id}/shipping-label",
+                    axum::routing::post(endpoints::shipping::create_label_endpoint)
+                        .get(endpoints::shipping::get_label_endpoint)
+                        .delete(endpoints::shipping::void_label_endpoint),
+                )
+                .route(
+                    "/admin/orders/{order_id}/commercial-invoice",
+                    axum::routing::get(endpoints::shipping::get_commercial_invoice_endpoint),
+                )
+                .route(
+                    "/admin/payments/refund",
+                    axum::routing::post(endpoints::payments::refund_payment_endpoint)
+                        .layer(idempotent()),
+                )
+                .route(
+                    "/admin/images/{image_id}/thumbnail",
+                    axum::routing::get(endpoints::admin::get_image_thumbnail_endpoint),
+                )
+                .route(
+                    "/admin/images/{image_id}/previews/{size_id}",
+                    axum::routing::get(endpoints::admin::get_image_preview_endpoint),
+                )
+                .route(
+                    "/admin/prints/sizes",
+                    axum::routing::post(endpoints::prints::create_size_endpoint),
+                )
+                .route(
+                    "/admin/prints/sizes/{size_id}",
+                    axum::routing::patch(endpoints::prints::update_size_endpoint)
+                        .delete(endpoints::prints::delete_size_endpoint),
+                )
+                .route(
+                    "/admin/locations",
+                    axum::routing::get(endpoints::locations::list_locations_endpoint)
+                        .post(endpoints::locations::create_location_endpoint),
+                )
+                .route(
+                    "/admin/locations/{location_id}",
+                    axum::routing::patch(endpoints::locations::update_location_endpoint)
+                        .delete(endpoints::locations::delete_location_endpoint),
+                )
+                .route(
+                    "/admin/shipping-rules",
+                    axum::routing::get(endpoints::shipping_rules::list_rules_endpoint)
+                        .post(endpoints::shipping_rules::create_rule_endpoint),
+                )
+                .route(
+                    "/admin/shipping-rules/{rule_id}",
+                    axum::routing::put(endpoints::shipping_rules::replace_rule_endpoint)
+                        .delete(endpoints::shipping_rules::delete_rule_endpoint),
+                )
                 .layer(axum::middleware::from_fn(middleware::admin_middleware)),
         )
         .route("/db_health", axum::routing::get(endpoints::db::db_health))
@@ -1,12 +1,26 @@
-//! Authentication middleware for protecting routes
+//! Authentication middleware for protecting routes, and idempotency keys
+//! for requests that must not run twice
 
-use crate::auth::{Claims, extract_token_from_header, validate_token};
+use crate::{
+    auth::{Claims, extract_token_from_header, validate_token},
+    endpoints::orders::error_response,
+    repositories::idempotency::IdempotencyRepository,
+};
 use axum::{
-    extract::Request,
-    http::{HeaderMap, StatusCode},
+    body::Body,
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+/// Largest request body an idempotent request may have
+const MAX_IDEMPOTENT_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Longest accepted `Idempotency-Key`
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
 
 /// Extract and validate JWT token from request
 pub async fn auth_middleware(
@@ -63,3 +77,370 @@ pub async fn admin_middleware(
 pub fn get_current_user(request: &Request) -> Option<&Claims> {
     request.extensions().get::<Claims>()
 }
+
+/// Make POST requests with an `Idempotency-Key` header safe to retry
+///
+/// The first request with a key runs normally and its response is stored.
+/// Retries with the same key get that response back, marked with
+/// `Idempotent-Replayed: true`; reusing the key for a different request is
+/// a 422. Server errors aren't stored, so those requests can be retried, and
+/// neither are requests that never finish, e.g. because the client went away.
+/// Must run after `auth_middleware`, since keys are scoped per user.
+pub async fn idempotency_middleware(
+    State(idempotency): State<Arc<dyn IdempotencyRepository>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(key) = request.headers().get("idempotency-key") else {
+        return next.run(request).await;
+    };
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN => key.to_string(),
+        _ => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "INVALID_IDEMPOTENCY_KEY",
+                format!(
+                    "Idempotency-Key must be 1 to {} visible characters",
+                    MAX_IDEMPOTENCY_KEY_LEN
+                ),
+            );
+        }
+    };
+    let Some(user_id) = request.extensions().get::<Claims>().map(|c| c.sub.clone()) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+
+    let (parts, body) = request.into_parts();
+    let body = match axum::body::to_bytes(body, MAX_IDEMPOTENT_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(parts.method.as_str());
+    hasher.update(b" ");
+    hasher.update(parts.uri.path());
+    hasher.update(b"\n");
+    hasher.update(&body);
+    let request_hash = hex::encode(hasher.finalize());
+
+    match idempotency.claim(&user_id, &key, &request_hash).await {
+        Ok(None) => {}
+        Ok(Some(record)) if record.request_hash != request_hash => {
+            return error_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "IDEMPOTENCY_KEY_REUSED",
+                "Idempotency-Key was already used for a different request".to_string(),
+            );
+        }
+        Ok(Some(record)) => {
+            let (Some(status), Some(stored)) = (record.response_status, record.response_body)
+            else {
+                return error_response(
+                    StatusCode::CONFLICT,
+                    "IDEMPOTENCY_KEY_IN_USE",
+                    "A request with this Idempotency-Key is still in progress".to_string(),
+                );
+            };
+            tracing::info!("Replaying response for idempotency key {}", key);
+            let status = StatusCode::from_u16(status as u16).unwrap_or(StatusCode::OK);
+            return (
+                status,
+                [
+                    (
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static("application/json"),
+                    ),
+                    (
+                        header::HeaderName::from_static("idempotent-replayed"),
+                        HeaderValue::from_static("true"),
+                    ),
+                ],
+                stored,
+            )
+                .into_response();
+        }
+        Err(err) => {
+            tracing::error!("Failed to claim idempotency key {}: {}", key, err);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "IDEMPOTENCY_CHECK_FAILED",
+                err.to_string(),
+            );
+        }
+    }
+
+    // Released if this future is dropped before the response is read
+    let claim = ClaimGuard {
+        idempotency: Arc::clone(&idempotency),
+        user_id: user_id.clone(),
+        key: key.clone(),
+        armed: true,
+    };
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    let (parts, body) = response.into_parts();
+    let body = axum::body::to_bytes(body, usize::MAX).await;
+    claim.disarm();
+    let body = match body {
+        Ok(body) => body,
+        Err(err) => {
+            tracing::error!(
+                "Failed to read response for idempotency key {}: {}",
+                key,
+                err
+            );
+            let _ = idempotency.release(&user_id, &key).await;
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let stored = if parts.status.is_server_error() {
+        idempotency.release(&user_id, &key).await
+    } else {
+        let response_hash = hex::encode(Sha256::digest(&body));
+        idempotency
+            .complete(&user_id, &key, parts.status.as_u16(), &body, &response_hash)
+            .await
+    };
+    if let Err(err) = stored {
+        // The request itself went through, so still answer it
+        tracing::error!("Failed to store idempotency key {}: {}", key, err);
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Releases a claimed idempotency key when dropped, so a request cancelled
+/// mid-handler (the client disconnected, or the server is shutting down)
+/// doesn't leave its key claimed
+struct ClaimGuard {
+    idempotency: Arc<dyn IdempotencyRepository>,
+    user_id: String,
+    key: String,
+    armed: bool,
+}
+
+impl ClaimGuard {
+    /// The request finished; the caller stores or releases the key itself
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for ClaimGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let idempotency = Arc::clone(&self.idempotency);
+        let user_id = std::mem::take(&mut self.user_id);
+        let key = std::mem::take(&mut self.key);
+        tracing::warn!("Request with idempotency key {} was cancelled", key);
+        runtime.spawn(async move {
+            if let Err(err) = idempotency.release(&user_id, &key).await {
+                tracing::error!("Failed to release idempotency key {}: {}", key, err);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{repositories::idempotency::IdempotencyStore, test_support::spawn_stub_server};
+    use axum::{Json, Router, routing::post};
+    use serde_json::{Value, json};
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+    use tokio::sync::Notify;
+
+    /// Stand-in for an order endpoint that counts how often it really runs
+    #[derive(Debug, Default)]
+    struct Handler {
+        calls: AtomicUsize,
+        /// Set to hold the first request until notified
+        gate: Option<Notify>,
+    }
+
+    async fn create_order(
+        State(handler): State<Arc<Handler>>,
+        Json(body): Json<Value>,
+    ) -> (StatusCode, Json<Value>) {
+        let call = handler.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        if let (1, Some(gate)) = (call, &handler.gate) {
+            gate.notified().await;
+        }
+        if body["fail"] == true {
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(json!({ "error": "UPSTREAM_FAILED" })),
+            );
+        }
+        (StatusCode::CREATED, Json(json!({ "order": call })))
+    }
+
+    async fn authenticated(mut request: Request, next: Next) -> Response {
+        request.extensions_mut().insert(Claims {
+            sub: "user-1".to_string(),
+            email: "test@example.com".to_string(),
+            name: "Test User".to_string(),
+            admin: false,
+            exp: usize::MAX,
+            iat: 0,
+        });
+        next.run(request).await
+    }
+
+    /// Drop requests that take too long, as a proxy or a client hanging up
+    /// would
+    async fn timeout(request: Request, next: Next) -> Response {
+        tokio::time::timeout(Duration::from_secs(1), next.run(request))
+            .await
+            .unwrap_or_else(|_| StatusCode::GATEWAY_TIMEOUT.into_response())
+    }
+
+    async fn spawn_app(handler: Arc<Handler>) -> String {
+        let idempotency: Arc<dyn IdempotencyRepository> = Arc::new(IdempotencyStore::new());
+        let router = Router::new()
+            .route("/orders", post(create_order))
+            .with_state(handler)
+            .layer(axum::middleware::from_fn_with_state(
+                idempotency,
+                idempotency_middleware,
+            ))
+            .layer(axum::middleware::from_fn(timeout))
+            .layer(axum::middleware::from_fn(authenticated));
+        format!("{}/orders", spawn_stub_server(router).await)
+    }
+
+    async fn send(url: &str, key: &str, body: Value) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(url)
+            .header("Idempotency-Key", key)
+            .json(&body)
+            .send()
+            .await
+            .expect("Request should reach the server")
+    }
+
+    fn replayed(response: &reqwest::Response) -> bool {
+        response.headers().get("idempotent-replayed").is_some()
+    }
+
+    async fn wait_for_calls(handler: &Handler, calls: usize) {
+        for _ in 0..200 {
+            if handler.calls.load(Ordering::SeqCst) >= calls {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("Handler was never called");
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_replay_and_reuse() {
+        let handler = Arc::new(Handler::default());
+        let url = spawn_app(handler.clone()).await;
+        let order = json!({ "size": "4x6" });
+
+        let first = send(&url, "key-1", order.clone()).await;
+        assert_eq!(first.status(), StatusCode::CREATED);
+        assert!(!replayed(&first));
+        let first: Value = first.json().await.unwrap();
+
+        let retry = send(&url, "key-1", order.clone()).await;
+        assert_eq!(retry.status(), StatusCode::CREATED);
+        assert!(replayed(&retry));
+        assert_eq!(retry.json::<Value>().await.unwrap(), first);
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 1);
+
+        let reused = send(&url, "key-1", json!({ "size": "8x10" })).await;
+        assert_eq!(reused.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let reused: Value = reused.json().await.unwrap();
+        assert_eq!(reused["error"], "IDEMPOTENCY_KEY_REUSED");
+
+        // Without a key every request runs
+        let response = reqwest::Client::new()
+            .post(&url)
+            .json(&order)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_are_not_stored() {
+        let handler = Arc::new(Handler::default());
+        let url = spawn_app(handler.clone()).await;
+        let order = json!({ "size": "4x6", "fail": true });
+
+        for _ in 0..2 {
+            let response = send(&url, "key-1", order.clone()).await;
+            assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+            assert!(!replayed(&response));
+        }
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_while_in_progress() {
+        let handler = Arc::new(Handler {
+            gate: Some(Notify::new()),
+            ..Handler::default()
+        });
+        let url = spawn_app(handler.clone()).await;
+        let order = json!({ "size": "4x6" });
+
+        let first = tokio::spawn({
+            let (url, order) = (url.clone(), order.clone());
+            async move { send(&url, "key-1", order).await }
+        });
+        wait_for_calls(&handler, 1).await;
+
+        let retry = send(&url, "key-1", order.clone()).await;
+        assert_eq!(retry.status(), StatusCode::CONFLICT);
+        let retry: Value = retry.json().await.unwrap();
+        assert_eq!(retry["error"], "IDEMPOTENCY_KEY_IN_USE");
+
+        handler.gate.as_ref().unwrap().notify_one();
+        assert_eq!(first.await.unwrap().status(), StatusCode::CREATED);
+        let retry = send(&url, "key-1", order).await;
+        assert!(replayed(&retry));
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_releases_key() {
+        let handler = Arc::new(Handler {
+            gate: Some(Notify::new()),
+            ..Handler::default()
+        });
+        let url = spawn_app(handler.clone()).await;
+        let order = json!({ "size": "4x6" });
+
+        // The first request never finishes and is dropped mid-handler
+        let first = send(&url, "key-1", order.clone()).await;
+        assert_eq!(first.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        for _ in 0..200 {
+            let retry = send(&url, "key-1", order.clone()).await;
+            if retry.status() == StatusCode::CREATED {
+                assert!(!replayed(&retry));
+                assert_eq!(handler.calls.load(Ordering::SeqCst), 2);
+                return;
+            }
+            assert_eq!(retry.status(), StatusCode::CONFLICT);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("Key was never released");
+    }
+}
@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+use crate::carriers::ShipmentAddress;
+
+/// A print lab orders are produced at and shipped from, as stored in the
+/// `fulfillment_locations` table
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow)]
+pub struct FulfillmentLocation {
+    /// Key recorded on orders, e.g. `denver`
+    pub location_id: String,
+    pub name: String,
+    /// Contact printed on labels as the shipper
+    pub contact_name: String,
+    pub company: Option<String>,
+    pub phone: Option<String>,
+    pub line1: String,
+    pub line2: Option<String>,
+    pub city: String,
+    pub state: String,
+    pub postal_code: String,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+    /// Destinations the lab is preferred for: countries such as `US`, or
+    /// states and provinces such as `US-CA`
+    pub regions: Vec<String>,
+    /// Print sizes the lab can produce, empty for every size
+    pub size_ids: Vec<String>,
+    /// Lower goes first when the routing rules leave a tie
+    pub priority: i32,
+    /// Whether customers can collect orders here
+    pub pickup: bool,
+    /// Opening hours shown to customers collecting orders
+    pub pickup_hours: Option<String>,
+    /// Inactive locations aren't routed new orders
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl FulfillmentLocation {
+    /// Address packages ship from
+    pub fn address(&self) -> ShipmentAddress {
+        ShipmentAddress {
+            name: self.contact_name.clone(),
+            company: self.company.clone(),
+            phone: self.phone.clone(),
+            line1: self.line1.clone(),
+            line2: self.line2.clone(),
+            city: self.city.clone(),
+            state: self.state.clone(),
+            postal_code: self.postal_code.clone(),
+            country: self.country.clone(),
+        }
+    }
+
+    /// Whether the lab can produce a print size
+    pub fn produces(&self, size_id: &str) -> bool {
+        self.size_ids.is_empty() || self.size_ids.iter().any(|size| size == size_id)
+    }
+
+    /// How closely the lab's regions match a destination: 2 for its state,
+    /// 1 for its country and 0 for neither
+    pub fn region_match(&self, destination: &ShipmentAddress) -> u8 {
+        self.regions
+            .iter()
+            .map(|region| destination.region_match(region))
+            .max()
+            .unwrap_or(0)
+    }
+}
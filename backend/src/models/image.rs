@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Metadata for an uploaded print image, as stored in the `images` table
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StoredImage {
+    pub image_id: String,
+    /// Account that uploaded the image
+    pub user_id: Option<uuid::Uuid>,
+    /// Order the image is attached to, `None` while staged
+    pub order_id: Option<String>,
+    /// Object storage key of the original file
+    #[serde(skip)]
+    pub storage_key: String,
+    pub original_filename: Option<String>,
+    pub content_type: String,
+    pub size_bytes: i64,
+    /// Pixel dimensions, if known
+    pub width_px: Option<i32>,
+    pub height_px: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Kind of image rendered from an upload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum DerivativeKind {
+    /// Small preview of the whole image
+    Thumbnail,
+    /// Image cropped to the aspect ratio of a print size
+    Preview,
+}
+
+/// A thumbnail or preview stored next to its original upload
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ImageDerivative {
+    pub image_id: String,
+    pub kind: DerivativeKind,
+    /// Print size a preview is cropped for, `None` for thumbnails
+    pub size_id: Option<String>,
+    #[serde(skip)]
+    pub storage_key: String,
+    pub width_px: i32,
+    pub height_px: i32,
+    pub created_at: DateTime<Utc>,
+}
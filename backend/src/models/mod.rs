@@ -1,12 +1,23 @@
 pub mod address;
 pub mod customer;
+pub mod fulfillment_location;
+pub mod image;
 pub mod order;
 pub mod order_item;
+pub mod order_status;
+pub mod payment;
+pub mod print_catalog;
+pub mod print_order;
 pub mod ship_from;
+pub mod shipping_label;
+pub mod shipping_rule;
 pub mod ups_api_response;
 pub mod ups_error;
 pub mod ups_rate_request;
 pub mod ups_rate_response;
 pub mod ups_request;
 pub mod ups_response;
+pub mod ups_ship_request;
+pub mod ups_ship_response;
+pub mod ups_track_response;
 pub mod user;
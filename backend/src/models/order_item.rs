@@ -1,3 +1,4 @@
+use crate::money::Money;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -5,5 +6,5 @@ pub struct OrderItem {
     pub product_id: String,
     pub name: String,
     pub quantity: u32,
-    pub unit_price: f64,
+    pub unit_price: Money,
 }
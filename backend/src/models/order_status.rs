@@ -0,0 +1,166 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Lifecycle status of a print order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum OrderStatus {
+    /// Order placed, waiting for the customer to pay
+    PendingPayment,
+    /// Payment received
+    Paid,
+    /// Prints are being produced
+    InProduction,
+    /// Prints are done and waiting to be packed
+    Printed,
+    /// Prints are waiting at the lab for the customer to collect
+    ReadyForPickup,
+    /// Handed over to the carrier
+    Shipped,
+    /// Carrier confirmed delivery, or the customer collected the prints
+    Delivered,
+    /// Cancelled before fulfillment
+    Cancelled,
+    /// Payment returned to the customer
+    Refunded,
+}
+
+impl OrderStatus {
+    /// Statuses this status may move to
+    ///
+    /// | From               | To                                          |
+    /// | ------------------ | ------------------------------------------- |
+    /// | `pending_payment`  | `paid`, `cancelled`                         |
+    /// | `paid`             | `in_production`, `cancelled`, `refunded`    |
+    /// | `in_production`    | `printed`, `cancelled`, `refunded`          |
+    /// | `printed`          | `shipped`, `ready_for_pickup`, `refunded`   |
+    /// | `ready_for_pickup` | `delivered`, `refunded`                     |
+    /// | `shipped`          | `delivered`, `refunded`                     |
+    /// | `delivered`        | `refunded`                                  |
+    /// | `cancelled`        | -                                           |
+    /// | `refunded`         | -                                           |
+    ///
+    /// Whether an order ships or is picked up is checked by the caller.
+    pub fn allowed_transitions(&self) -> &'static [OrderStatus] {
+        use OrderStatus::*;
+        match self {
+            PendingPayment => &[Paid, Cancelled],
+            Paid => &[InProduction, Cancelled, Refunded],
+            InProduction => &[Printed, Cancelled, Refunded],
+            Printed => &[Shipped, ReadyForPickup, Refunded],
+            ReadyForPickup => &[Delivered, Refunded],
+            Shipped => &[Delivered, Refunded],
+            Delivered => &[Refunded],
+            Cancelled => &[],
+            Refunded => &[],
+        }
+    }
+
+    /// Check whether moving to `next` is a legal transition
+    pub fn can_transition_to(&self, next: OrderStatus) -> bool {
+        self.allowed_transitions().contains(&next)
+    }
+
+    /// Whether no further transitions are possible
+    pub fn is_terminal(&self) -> bool {
+        self.allowed_transitions().is_empty()
+    }
+
+    /// Get the status as its API/database string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::PendingPayment => "pending_payment",
+            OrderStatus::Paid => "paid",
+            OrderStatus::InProduction => "in_production",
+            OrderStatus::Printed => "printed",
+            OrderStatus::ReadyForPickup => "ready_for_pickup",
+            OrderStatus::Shipped => "shipped",
+            OrderStatus::Delivered => "delivered",
+            OrderStatus::Cancelled => "cancelled",
+            OrderStatus::Refunded => "refunded",
+        }
+    }
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One entry in an order's status history
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrderStatusChange {
+    /// Previous status, `None` for the entry recorded at order creation
+    pub from_status: Option<OrderStatus>,
+    pub to_status: OrderStatus,
+    /// User who made the change, `None` for system changes
+    pub changed_by: Option<uuid::Uuid>,
+    pub note: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_happy_path_transitions() {
+        use OrderStatus::*;
+        let path = [
+            PendingPayment,
+            Paid,
+            InProduction,
+            Printed,
+            Shipped,
+            Delivered,
+        ];
+        for pair in path.windows(2) {
+            assert!(
+                pair[0].can_transition_to(pair[1]),
+                "{} -> {} should be allowed",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+
+    #[test]
+    fn test_pickup_transitions() {
+        use OrderStatus::*;
+        assert!(Printed.can_transition_to(ReadyForPickup));
+        assert!(ReadyForPickup.can_transition_to(Delivered));
+        assert!(ReadyForPickup.can_transition_to(Refunded));
+        assert!(!ReadyForPickup.can_transition_to(Shipped));
+        assert!(!InProduction.can_transition_to(ReadyForPickup));
+        assert_eq!(
+            serde_json::to_string(&ReadyForPickup).unwrap(),
+            "\"ready_for_pickup\""
+        );
+    }
+
+    #[test]
+    fn test_illegal_transitions() {
+        use OrderStatus::*;
+        assert!(!PendingPayment.can_transition_to(Shipped));
+        assert!(!PendingPayment.can_transition_to(Refunded));
+        assert!(!Shipped.can_transition_to(Cancelled));
+        assert!(!Delivered.can_transition_to(Shipped));
+        assert!(!Paid.can_transition_to(Paid));
+        assert!(Cancelled.is_terminal());
+        assert!(Refunded.is_terminal());
+    }
+
+    #[test]
+    fn test_status_serialization() {
+        let json = serde_json::to_string(&OrderStatus::InProduction).unwrap();
+        assert_eq!(json, "\"in_production\"");
+        assert_eq!(OrderStatus::InProduction.as_str(), "in_production");
+
+        let status: OrderStatus = serde_json::from_str("\"pending_payment\"").unwrap();
+        assert_eq!(status, OrderStatus::PendingPayment);
+        assert!(serde_json::from_str::<OrderStatus>("\"processing\"").is_err());
+    }
+}
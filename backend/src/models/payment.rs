@@ -0,0 +1,120 @@
+use crate::{
+    money::{Money, money_column},
+    payments::PaymentStatus,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, Row, postgres::PgRow};
+use uuid::Uuid;
+
+/// A payment attempt for an order
+#[derive(Debug, Clone, Serialize)]
+pub struct Payment {
+    pub payment_id: i64,
+    pub order_id: String,
+    /// Payment method of the provider that handled it, e.g. `paypal`
+    pub provider: String,
+    /// The provider's payment ID
+    pub intent_id: String,
+    /// The provider's capture ID, set once the money is taken
+    pub capture_id: Option<String>,
+    pub status: PaymentStatus,
+    pub amount: Money,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl<'r> FromRow<'r, PgRow> for Payment {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        let currency: String = row.try_get("currency")?;
+        Ok(Payment {
+            payment_id: row.try_get("payment_id")?,
+            order_id: row.try_get("order_id")?,
+            provider: row.try_get("provider")?,
+            intent_id: row.try_get("intent_id")?,
+            capture_id: row.try_get("capture_id")?,
+            status: row.try_get("status")?,
+            amount: money_column(row, "amount", &currency)?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+/// A webhook event received from a payment provider
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PaymentEvent {
+    pub id: i64,
+    /// Payment method of the provider that sent it
+    pub provider: String,
+    /// The provider's ID for the event
+    pub event_id: String,
+    pub event_type: String,
+    /// The request body exactly as received
+    pub payload: String,
+    pub received_at: DateTime<Utc>,
+    /// When the event was applied, `None` if it still needs processing
+    pub processed_at: Option<DateTime<Utc>>,
+}
+
+/// Money returned to the customer for an order
+#[derive(Debug, Clone, Serialize)]
+pub struct Refund {
+    pub refund_id: i64,
+    pub order_id: String,
+    /// The payment the money came from
+    pub payment_id: i64,
+    /// The provider's ID for the refund
+    pub provider_refund_id: String,
+    /// `completed`, `pending` while the provider processes it, or `failed`
+    pub status: PaymentStatus,
+    pub amount: Money,
+    pub reason: String,
+    /// Admin who issued the refund, if the account still exists
+    pub refunded_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    /// Line items covered, empty for refunds of an amount
+    pub items: Vec<RefundItem>,
+}
+
+/// Prints of one line item covered by a refund
+#[derive(Debug, Clone, Serialize)]
+pub struct RefundItem {
+    #[serde(skip)]
+    pub refund_id: i64,
+    pub item_id: i64,
+    /// Number of prints refunded
+    pub quantity: i32,
+    pub amount: Money,
+}
+
+impl<'r> FromRow<'r, PgRow> for Refund {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        let currency: String = row.try_get("currency")?;
+        Ok(Refund {
+            refund_id: row.try_get("refund_id")?,
+            order_id: row.try_get("order_id")?,
+            payment_id: row.try_get("payment_id")?,
+            provider_refund_id: row.try_get("provider_refund_id")?,
+            status: row.try_get("status")?,
+            amount: money_column(row, "amount", &currency)?,
+            reason: row.try_get("reason")?,
+            refunded_by: row.try_get("refunded_by")?,
+            created_at: row.try_get("created_at")?,
+            items: Vec::new(),
+        })
+    }
+}
+
+/// Item rows carry their refund's currency in a `currency` column
+impl<'r> FromRow<'r, PgRow> for RefundItem {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        let currency: String = row.try_get("currency")?;
+        Ok(RefundItem {
+            refund_id: row.try_get("refund_id")?,
+            item_id: row.try_get("item_id")?,
+            quantity: row.try_get("quantity")?,
+            amount: money_column(row, "amount", &currency)?,
+        })
+    }
+}
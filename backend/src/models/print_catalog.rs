@@ -0,0 +1,81 @@
+use crate::money::{Money, money_column};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, Row, postgres::PgRow};
+
+/// A print size offered in the catalog
+#[derive(Debug, Clone, Serialize)]
+pub struct PrintSize {
+    /// Catalog key, e.g. `4x6`, used in order requests
+    pub size_id: String,
+    pub description: String,
+    /// Base price per print before any finish premium
+    pub price: Money,
+    /// Print width in inches
+    pub width_in: f32,
+    /// Print height in inches
+    pub height_in: f32,
+    /// Weight of a single print in pounds
+    pub weight_lbs: f32,
+    /// Business days needed to produce the print before it can ship
+    pub production_days: i32,
+    /// HS code prints of this size are declared under at customs
+    pub hs_code: String,
+    /// Inactive sizes are hidden from customers and can't be ordered
+    pub active: bool,
+    pub sort_order: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A paper finish offered in the catalog
+#[derive(Debug, Clone, Serialize)]
+pub struct PrintFinish {
+    /// Catalog key, e.g. `matte`, used in order requests
+    pub finish_id: String,
+    pub description: String,
+    /// Amount added to each print's base price
+    pub premium: Money,
+    pub active: bool,
+    pub sort_order: i32,
+}
+
+impl PrintSize {
+    /// Unit price of this size with a finish applied
+    pub fn unit_price(&self, finish: &PrintFinish) -> Result<Money, crate::money::MoneyError> {
+        self.price.checked_add(&finish.premium)
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for PrintSize {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        let currency: String = row.try_get("currency")?;
+        Ok(PrintSize {
+            size_id: row.try_get("size_id")?,
+            description: row.try_get("description")?,
+            price: money_column(row, "price", &currency)?,
+            width_in: row.try_get("width_in")?,
+            height_in: row.try_get("height_in")?,
+            weight_lbs: row.try_get("weight_lbs")?,
+            production_days: row.try_get("production_days")?,
+            hs_code: row.try_get("hs_code")?,
+            active: row.try_get("active")?,
+            sort_order: row.try_get("sort_order")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for PrintFinish {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        let currency: String = row.try_get("currency")?;
+        Ok(PrintFinish {
+            finish_id: row.try_get("finish_id")?,
+            description: row.try_get("description")?,
+            premium: money_column(row, "premium", &currency)?,
+            active: row.try_get("active")?,
+            sort_order: row.try_get("sort_order")?,
+        })
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{
+    models::order_status::OrderStatus,
+    money::{Money, MoneyError, money_column},
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Row, postgres::PgRow};
+
+/// How an order reaches the customer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum FulfillmentMethod {
+    /// Shipped by carrier to the shipping address
+    #[default]
+    Ship,
+    /// Collected by the customer from the order's fulfillment location
+    Pickup,
+}
+
+/// A print order as stored in the `orders` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintOrder {
+    pub order_id: String,
+    /// Account that placed the order, if it still exists
+    pub user_id: Option<uuid::Uuid>,
+    pub status: OrderStatus,
+    pub customer_name: String,
+    pub customer_email: String,
+    pub customer_phone: String,
+    pub shipping_line1: String,
+    pub shipping_line2: Option<String>,
+    pub shipping_city: String,
+    pub shipping_state: String,
+    pub shipping_postal_code: String,
+    pub shipping_country: String,
+    pub special_instructions: Option<String>,
+    /// Carrier service the order ships with, `pickup` for pickup orders
+    pub shipping_option: String,
+    pub fulfillment_method: FulfillmentMethod,
+    /// Print lab that produces and ships the order; orders placed before
+    /// labs were recorded have none
+    pub fulfillment_location_id: Option<String>,
+    /// Tracking number of the order's current shipping label
+    pub tracking_number: Option<String>,
+    /// Code the customer shows to collect a pickup order; only ever shown
+    /// to the customer
+    #[serde(skip)]
+    pub pickup_code: Option<String>,
+    pub payment_method: String,
+    /// Provider-side payment reference (e.g. the PayPal order ID)
+    pub payment_reference: Option<String>,
+    pub items_subtotal: Money,
+    pub shipping: Money,
+    pub tax: Money,
+    pub grand_total: Money,
+    /// Money returned to the customer so far
+    pub amount_refunded: Money,
+    pub currency: String,
+    pub estimated_delivery_min: NaiveDate,
+    pub estimated_delivery_max: NaiveDate,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Print line items, loaded separately from `order_items`
+    pub items: Vec<PrintOrderItem>,
+}
+
+impl PrintOrder {
+    /// How much of the grand total can still be refunded
+    pub fn refundable_amount(&self) -> Result<Money, MoneyError> {
+        self.grand_total.checked_sub(&self.amount_refunded)
+    }
+
+    /// Whether the customer collects the order instead of it being shipped
+    pub fn is_pickup(&self) -> bool {
+        self.fulfillment_method == FulfillmentMethod::Pickup
+    }
+}
+
+/// A single print line item as stored in the `order_items` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintOrderItem {
+    /// Database ID, assigned when the order is stored
+    pub item_id: i64,
+    #[serde(skip)]
+    pub order_id: String,
+    pub size: String,
+    pub finish: String,
+    pub quantity: i32,
+    pub image_ids: Vec<String>,
+    pub unit_price: Money,
+    pub line_total: Money,
+    /// HS code the prints are declared under at customs
+    pub hs_code: String,
+}
+
+impl<'r> FromRow<'r, PgRow> for PrintOrder {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        let currency: String = row.try_get("currency")?;
+        Ok(PrintOrder {
+            order_id: row.try_get("order_id")?,
+            user_id: row.try_get("user_id")?,
+            status: row.try_get("status")?,
+            customer_name: row.try_get("customer_name")?,
+            customer_email: row.try_get("customer_email")?,
+            customer_phone: row.try_get("customer_phone")?,
+            shipping_line1: row.try_get("shipping_line1")?,
+            shipping_line2: row.try_get("shipping_line2")?,
+            shipping_city: row.try_get("shipping_city")?,
+            shipping_state: row.try_get("shipping_state")?,
+            shipping_postal_code: row.try_get("shipping_postal_code")?,
+            shipping_country: row.try_get("shipping_country")?,
+            special_instructions: row.try_get("special_instructions")?,
+            shipping_option: row.try_get("shipping_option")?,
+            fulfillment_method: row.try_get("fulfillment_method")?,
+            fulfillment_location_id: row.try_get("fulfillment_location_id")?,
+            tracking_number: row.try_get("tracking_number")?,
+            pickup_code: row.try_get("pickup_code")?,
+            payment_method: row.try_get("payment_method")?,
+            payment_reference: row.try_get("payment_reference")?,
+            items_subtotal: money_column(row, "items_subtotal", &currency)?,
+            shipping: money_column(row, "shipping", &currency)?,
+            tax: money_column(row, "tax", &currency)?,
+            grand_total: money_column(row, "grand_total", &currency)?,
+            amount_refunded: money_column(row, "amount_refunded", &currency)?,
+            estimated_delivery_min: row.try_get("estimated_delivery_min")?,
+            estimated_delivery_max: row.try_get("estimated_delivery_max")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+            currency,
+            items: Vec::new(),
+        })
+    }
+}
+
+impl PrintOrderItem {
+    /// Number of prints in the line: every image at the ordered quantity
+    pub fn print_count(&self) -> i64 {
+        self.quantity as i64 * self.image_ids.len() as i64
+    }
+}
+
+/// Item rows carry their order's currency in a `currency` column
+impl<'r> FromRow<'r, PgRow> for PrintOrderItem {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        let currency: String = row.try_get("currency")?;
+        Ok(PrintOrderItem {
+            item_id: row.try_get("item_id")?,
+            order_id: row.try_get("order_id")?,
+            size: row.try_get("size")?,
+            finish: row.try_get("finish")?,
+            quantity: row.try_get("quantity")?,
+            image_ids: row.try_get("image_ids")?,
+            unit_price: money_column(row, "unit_price", &currency)?,
+            line_total: money_column(row, "line_total", &currency)?,
+            hs_code: row.try_get("hs_code")?,
+        })
+    }
+}
@@ -4,4 +4,7 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ShipFrom {
     pub from: AddressKeyFormat,
+    /// Shipper phone number, required on FedEx labels
+    #[serde(default)]
+    pub phone: Option<String>,
 }
@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, Row, postgres::PgRow};
+use uuid::Uuid;
+
+use crate::{
+    carriers::LabelFormat,
+    money::{Money, money_column},
+};
+
+/// A label bought for an order, as stored in the `shipping_labels` table
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredLabel {
+    pub label_id: i64,
+    pub order_id: String,
+    /// Carrier the label was bought from, e.g. `ups`
+    pub carrier: String,
+    /// The carrier's code for the service
+    pub service_code: String,
+    pub tracking_number: String,
+    pub format: LabelFormat,
+    /// Object storage key of the label file
+    #[serde(skip)]
+    pub storage_key: String,
+    /// What the carrier charged, when it said
+    pub price: Option<Money>,
+    /// Admin who bought the label, if the account still exists
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    /// When the label was voided, `None` while it can still be used
+    pub voided_at: Option<DateTime<Utc>>,
+}
+
+impl<'r> FromRow<'r, PgRow> for StoredLabel {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        let currency: Option<String> = row.try_get("currency")?;
+        Ok(StoredLabel {
+            label_id: row.try_get("label_id")?,
+            order_id: row.try_get("order_id")?,
+            carrier: row.try_get("carrier")?,
+            service_code: row.try_get("service_code")?,
+            tracking_number: row.try_get("tracking_number")?,
+            format: row.try_get("format")?,
+            storage_key: row.try_get("storage_key")?,
+            price: match currency {
+                Some(currency) => Some(money_column(row, "price", &currency)?),
+                None => None,
+            },
+            created_by: row.try_get("created_by")?,
+            created_at: row.try_get("created_at")?,
+            voided_at: row.try_get("voided_at")?,
+        })
+    }
+}
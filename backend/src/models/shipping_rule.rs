@@ -0,0 +1,67 @@
+use crate::money::{Money, optional_money_column};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Row, postgres::PgRow};
+
+/// What a shipping rule does to the quotes it matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum ShippingRuleKind {
+    /// Ship for nothing
+    FreeShipping,
+    /// Ship for the rule's `amount`
+    FlatRate,
+    /// Add `basis_points` of the price, then `amount`; negative values
+    /// discount
+    Adjust,
+    /// Don't offer the service
+    Hide,
+}
+
+/// A rule applied to carrier quotes, as stored in the `shipping_rules` table
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShippingRule {
+    pub rule_id: i64,
+    pub name: String,
+    /// Lower applies first; later rules see the price earlier ones set
+    pub position: i32,
+    pub kind: ShippingRuleKind,
+    /// Shipping options the rule applies to, such as `UPS_Ground`, empty for
+    /// every option
+    pub shipping_options: Vec<String>,
+    /// Destinations the rule applies to: countries such as `US`, or states
+    /// and provinces such as `US-CA`, empty for everywhere
+    pub regions: Vec<String>,
+    /// Only applies when the prints cost at least this much
+    pub min_subtotal: Option<Money>,
+    /// Price of a flat rate, or a fixed amount added by an adjustment
+    pub amount: Option<Money>,
+    /// Percentage of the price added by an adjustment, in basis points
+    /// (1000 = 10%)
+    pub basis_points: Option<i32>,
+    /// Inactive rules aren't applied
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl<'r> FromRow<'r, PgRow> for ShippingRule {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        let currency: String = row.try_get("currency")?;
+        Ok(ShippingRule {
+            rule_id: row.try_get("rule_id")?,
+            name: row.try_get("name")?,
+            position: row.try_get("position")?,
+            kind: row.try_get("kind")?,
+            shipping_options: row.try_get("shipping_options")?,
+            regions: row.try_get("regions")?,
+            min_subtotal: optional_money_column(row, "min_subtotal", &currency)?,
+            amount: optional_money_column(row, "amount", &currency)?,
+            basis_points: row.try_get("basis_points")?,
+            active: row.try_get("active")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
@@ -40,8 +40,53 @@ pub struct Shipment {
     pub service: Service,
     #[serde(rename = "NumOfPieces")]
     pub num_of_pieces: String,
+    /// Declared value of the goods, for shipments that cross a border
+    #[serde(rename = "InvoiceLineTotal", skip_serializing_if = "Option::is_none")]
+    pub invoice_line_total: Option<InvoiceLineTotal>,
+    /// Present, and empty, to have taxes returned in `TaxCharges`
+    #[serde(
+        rename = "TaxInformationIndicator",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub tax_information_indicator: Option<String>,
+    /// Required by the `ShopTimeInTransit` option
+    #[serde(
+        rename = "ShipmentTotalWeight",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub shipment_total_weight: Option<PackageWeight>,
+    /// Required by the `ShopTimeInTransit` option
+    #[serde(
+        rename = "DeliveryTimeInformation",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub delivery_time_information: Option<DeliveryTimeInformation>,
     #[serde(rename = "Package")]
-    pub package: Package,
+    pub package: Vec<Package>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InvoiceLineTotal {
+    #[serde(rename = "CurrencyCode")]
+    pub currency_code: String,
+    #[serde(rename = "MonetaryValue")]
+    pub monetary_value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeliveryTimeInformation {
+    /// `03` for non-document packages
+    #[serde(rename = "PackageBillType")]
+    pub package_bill_type: String,
+    #[serde(rename = "Pickup")]
+    pub pickup: Pickup,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Pickup {
+    /// Date the shipment is handed to UPS, `YYYYMMDD`
+    #[serde(rename = "Date")]
+    pub date: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -1,3 +1,5 @@
+use crate::money::{Money, MoneyError};
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -82,6 +84,9 @@ pub struct RatedShipment {
     pub negotiated_rate_charges: Option<NegotiatedRateCharges>,
     #[serde(rename = "GuaranteedDelivery", skip_serializing_if = "Option::is_none")]
     pub guaranteed_delivery: Option<GuaranteedDelivery>,
+    /// Only returned for the `ShopTimeInTransit` and `RateTimeInTransit` options
+    #[serde(rename = "TimeInTransit", skip_serializing_if = "Option::is_none")]
+    pub time_in_transit: Option<TimeInTransit>,
     #[serde(rename = "RatedPackage")]
     pub rated_package: Vec<RatedPackage>,
 }
@@ -110,6 +115,13 @@ pub struct Charges {
     pub monetary_value: String,
 }
 
+impl Charges {
+    /// Parse the charge into an exact `Money` amount
+    pub fn amount(&self) -> Result<Money, MoneyError> {
+        Money::parse(&self.monetary_value, &self.currency_code)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TaxCharge {
     #[serde(rename = "Type")]
@@ -137,6 +149,63 @@ pub struct GuaranteedDelivery {
     pub delivery_by_time: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimeInTransit {
+    #[serde(rename = "PickupDate", skip_serializing_if = "Option::is_none")]
+    pub pickup_date: Option<String>,
+    #[serde(rename = "ServiceSummary")]
+    pub service_summary: ServiceSummary,
+}
+
+impl TimeInTransit {
+    /// Business days between pickup and arrival
+    pub fn business_days(&self) -> Option<u32> {
+        self.service_summary
+            .estimated_arrival
+            .business_days_in_transit
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// Estimated arrival date
+    pub fn arrival_date(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(
+            &self.service_summary.estimated_arrival.arrival.date,
+            "%Y%m%d",
+        )
+        .ok()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServiceSummary {
+    #[serde(rename = "EstimatedArrival")]
+    pub estimated_arrival: EstimatedArrival,
+    #[serde(rename = "SaturdayDelivery", skip_serializing_if = "Option::is_none")]
+    pub saturday_delivery: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EstimatedArrival {
+    #[serde(rename = "Arrival")]
+    pub arrival: DateTimeInfo,
+    #[serde(rename = "BusinessDaysInTransit")]
+    pub business_days_in_transit: String,
+    #[serde(rename = "DayOfWeek", skip_serializing_if = "Option::is_none")]
+    pub day_of_week: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DateTimeInfo {
+    /// `YYYYMMDD`
+    #[serde(rename = "Date")]
+    pub date: String,
+    /// `HHMMSS`
+    #[serde(rename = "Time", skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RatedPackage {
     #[serde(rename = "TransportationCharges")]
@@ -163,3 +232,45 @@ pub struct UnitOfMeasurement {
     #[serde(rename = "Description")]
     pub description: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_charges_amount() {
+        let charges: Charges =
+            serde_json::from_str(r#"{"CurrencyCode":"USD","MonetaryValue":"14.37"}"#).unwrap();
+        assert_eq!(charges.amount().unwrap(), Money::from_minor(1437, "USD"));
+
+        let charges = Charges {
+            currency_code: "USD".to_string(),
+            monetary_value: "not a number".to_string(),
+        };
+        assert!(charges.amount().is_err());
+    }
+
+    #[test]
+    fn test_time_in_transit() {
+        let time_in_transit: TimeInTransit = serde_json::from_str(
+            r#"{
+                "PickupDate": "20251126",
+                "ServiceSummary": {
+                    "EstimatedArrival": {
+                        "Arrival": {"Date": "20251202", "Time": "233000"},
+                        "BusinessDaysInTransit": "3",
+                        "DayOfWeek": "TUE"
+                    },
+                    "SaturdayDelivery": "0"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(time_in_transit.business_days(), Some(3));
+        assert_eq!(
+            time_in_transit.arrival_date(),
+            NaiveDate::from_ymd_opt(2025, 12, 2)
+        );
+    }
+}
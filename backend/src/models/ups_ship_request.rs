@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::ups_rate_request::{
+    Dimensions, InvoiceLineTotal, PackageWeight, PackagingType, PaymentDetails, RateAddress,
+    Service, TransactionReference,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UPSShipRequest {
+    #[serde(rename = "ShipmentRequest")]
+    pub shipment_request: ShipmentRequest,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShipmentRequest {
+    #[serde(rename = "Request")]
+    pub request: ShipRequestInfo,
+    #[serde(rename = "Shipment")]
+    pub shipment: ShipShipment,
+    #[serde(rename = "LabelSpecification")]
+    pub label_specification: LabelSpecification,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShipRequestInfo {
+    /// `nonvalidate` skips street-level validation of the ship-to address
+    #[serde(rename = "RequestOption")]
+    pub request_option: String,
+    #[serde(rename = "TransactionReference")]
+    pub transaction_reference: TransactionReference,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShipShipment {
+    #[serde(rename = "Description")]
+    pub description: String,
+    #[serde(rename = "Shipper")]
+    pub shipper: ShipParty,
+    #[serde(rename = "ShipTo")]
+    pub ship_to: ShipParty,
+    #[serde(rename = "ShipFrom")]
+    pub ship_from: ShipParty,
+    #[serde(rename = "PaymentInformation")]
+    pub payment_information: PaymentDetails,
+    #[serde(rename = "Service")]
+    pub service: Service,
+    /// Declared value of the goods, for shipments that cross a border
+    #[serde(rename = "InvoiceLineTotal", skip_serializing_if = "Option::is_none")]
+    pub invoice_line_total: Option<InvoiceLineTotal>,
+    #[serde(
+        rename = "ShipmentServiceOptions",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub shipment_service_options: Option<ShipmentServiceOptions>,
+    #[serde(rename = "Package")]
+    pub package: Vec<ShipPackage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShipmentServiceOptions {
+    #[serde(rename = "InternationalForms", skip_serializing_if = "Option::is_none")]
+    pub international_forms: Option<InternationalForms>,
+}
+
+/// Customs forms UPS files electronically with the shipment
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InternationalForms {
+    /// `01` for a commercial invoice
+    #[serde(rename = "FormType")]
+    pub form_type: Vec<String>,
+    #[serde(rename = "InvoiceNumber")]
+    pub invoice_number: String,
+    /// `YYYYMMDD`
+    #[serde(rename = "InvoiceDate")]
+    pub invoice_date: String,
+    /// `SALE`, `GIFT`, `SAMPLE`, `RETURN`, `REPAIR` or `INTERCOMPANYDATA`
+    #[serde(rename = "ReasonForExport")]
+    pub reason_for_export: String,
+    #[serde(rename = "CurrencyCode")]
+    pub currency_code: String,
+    #[serde(rename = "Contacts")]
+    pub contacts: FormContacts,
+    #[serde(rename = "Product")]
+    pub product: Vec<Product>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FormContacts {
+    /// Who bought the goods, required for an invoice
+    #[serde(rename = "SoldTo")]
+    pub sold_to: ShipParty,
+}
+
+/// One line of a commercial invoice
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Product {
+    /// Up to three lines of 35 characters
+    #[serde(rename = "Description")]
+    pub description: Vec<String>,
+    #[serde(rename = "Unit")]
+    pub unit: ProductUnit,
+    /// Harmonized System code
+    #[serde(rename = "CommodityCode")]
+    pub commodity_code: String,
+    #[serde(rename = "OriginCountryCode")]
+    pub origin_country_code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProductUnit {
+    #[serde(rename = "Number")]
+    pub number: String,
+    /// Value of a single unit
+    #[serde(rename = "Value")]
+    pub value: String,
+    #[serde(rename = "UnitOfMeasurement")]
+    pub unit_of_measurement: ProductUnitOfMeasurement,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProductUnitOfMeasurement {
+    /// `PCS` for pieces
+    #[serde(rename = "Code")]
+    pub code: String,
+}
+
+/// Shipper, ship-to or ship-from party of a shipment
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShipParty {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "AttentionName", skip_serializing_if = "Option::is_none")]
+    pub attention_name: Option<String>,
+    /// Only set on the shipper
+    #[serde(rename = "ShipperNumber", skip_serializing_if = "Option::is_none")]
+    pub shipper_number: Option<String>,
+    #[serde(rename = "Phone", skip_serializing_if = "Option::is_none")]
+    pub phone: Option<Phone>,
+    #[serde(rename = "Address")]
+    pub address: RateAddress,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Phone {
+    #[serde(rename = "Number")]
+    pub number: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShipPackage {
+    #[serde(rename = "Packaging")]
+    pub packaging: PackagingType,
+    #[serde(rename = "Dimensions")]
+    pub dimensions: Dimensions,
+    #[serde(rename = "PackageWeight")]
+    pub package_weight: PackageWeight,
+    #[serde(
+        rename = "ReferenceNumber",
+        skip_serializing_if = "Vec::is_empty",
+        default
+    )]
+    pub reference_number: Vec<ReferenceNumber>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReferenceNumber {
+    #[serde(rename = "Value")]
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LabelSpecification {
+    #[serde(rename = "LabelImageFormat")]
+    pub label_image_format: LabelImageFormat,
+    /// Required for thermal formats such as ZPL
+    #[serde(rename = "LabelStockSize", skip_serializing_if = "Option::is_none")]
+    pub label_stock_size: Option<LabelStockSize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LabelImageFormat {
+    /// `GIF`, `PNG`, `ZPL`, `EPL` or `SPL`
+    #[serde(rename = "Code")]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LabelStockSize {
+    /// Height in inches, `6` or `8`
+    #[serde(rename = "Height")]
+    pub height: String,
+    /// Width in inches, always `4`
+    #[serde(rename = "Width")]
+    pub width: String,
+}
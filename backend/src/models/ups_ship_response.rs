@@ -0,0 +1,103 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::models::ups_rate_response::{Charges, ResponseStatus};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UPSShipResponse {
+    #[serde(rename = "ShipmentResponse")]
+    pub shipment_response: ShipmentResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShipmentResponse {
+    #[serde(rename = "Response")]
+    pub response: ShipResponseInfo,
+    #[serde(rename = "ShipmentResults")]
+    pub shipment_results: ShipmentResults,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShipResponseInfo {
+    #[serde(rename = "ResponseStatus")]
+    pub response_status: ResponseStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShipmentResults {
+    #[serde(rename = "ShipmentCharges", skip_serializing_if = "Option::is_none")]
+    pub shipment_charges: Option<ShipmentCharges>,
+    #[serde(rename = "ShipmentIdentificationNumber")]
+    pub shipment_identification_number: String,
+    /// UPS sends a single object for one package and an array for several
+    #[serde(rename = "PackageResults", deserialize_with = "one_or_many")]
+    pub package_results: Vec<PackageResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShipmentCharges {
+    #[serde(rename = "TotalCharges")]
+    pub total_charges: Charges,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PackageResult {
+    #[serde(rename = "TrackingNumber")]
+    pub tracking_number: String,
+    #[serde(rename = "ShippingLabel", skip_serializing_if = "Option::is_none")]
+    pub shipping_label: Option<ShippingLabelImage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShippingLabelImage {
+    #[serde(rename = "ImageFormat")]
+    pub image_format: ImageFormat,
+    /// Base64-encoded label
+    #[serde(rename = "GraphicImage")]
+    pub graphic_image: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImageFormat {
+    #[serde(rename = "Code")]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UPSVoidResponse {
+    #[serde(rename = "VoidShipmentResponse")]
+    pub void_shipment_response: VoidShipmentResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VoidShipmentResponse {
+    #[serde(rename = "Response")]
+    pub response: ShipResponseInfo,
+    #[serde(rename = "SummaryResult")]
+    pub summary_result: VoidSummaryResult,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VoidSummaryResult {
+    /// Code `1` when the shipment was voided
+    #[serde(rename = "Status")]
+    pub status: ResponseStatus,
+}
+
+/// Accept either a single value or an array of them
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UPSTrackResponse {
+    #[serde(rename = "trackResponse")]
+    pub track_response: TrackResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrackResponse {
+    pub shipment: Vec<TrackShipment>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrackShipment {
+    #[serde(rename = "inquiryNumber")]
+    pub inquiry_number: String,
+    #[serde(default)]
+    pub package: Vec<TrackPackage>,
+    /// Set instead of packages when UPS has no information, e.g. `TW0001`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<TrackWarning>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrackWarning {
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrackPackage {
+    #[serde(rename = "trackingNumber")]
+    pub tracking_number: String,
+    #[serde(rename = "currentStatus", skip_serializing_if = "Option::is_none")]
+    pub current_status: Option<CurrentStatus>,
+    #[serde(rename = "deliveryDate", default)]
+    pub delivery_date: Vec<DeliveryDate>,
+    /// Scans, newest first
+    #[serde(default)]
+    pub activity: Vec<TrackActivity>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CurrentStatus {
+    pub code: String,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeliveryDate {
+    /// `SDD` scheduled, `RDD` rescheduled or `DEL` delivered
+    #[serde(rename = "type")]
+    pub date_type: String,
+    /// `YYYYMMDD`
+    pub date: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrackActivity {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<ActivityLocation>,
+    pub status: ActivityStatus,
+    /// Local date of the scan, `YYYYMMDD`
+    pub date: String,
+    /// Local time of the scan, `HHMMSS`
+    pub time: String,
+    /// UTC date of the scan, `YYYYMMDD`
+    #[serde(rename = "gmtDate", skip_serializing_if = "Option::is_none")]
+    pub gmt_date: Option<String>,
+    /// UTC time of the scan, `HH:MM:SS`
+    #[serde(rename = "gmtTime", skip_serializing_if = "Option::is_none")]
+    pub gmt_time: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ActivityLocation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<ActivityAddress>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ActivityAddress {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    #[serde(rename = "stateProvince", skip_serializing_if = "Option::is_none")]
+    pub state_province: Option<String>,
+    #[serde(rename = "countryCode", skip_serializing_if = "Option::is_none")]
+    pub country_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ActivityStatus {
+    /// `M` label created, `P` picked up, `I` in transit, `O` out for delivery,
+    /// `D` delivered, `X` exception, `RS` returned to shipper
+    #[serde(rename = "type")]
+    pub status_type: String,
+    pub description: String,
+    pub code: String,
+}
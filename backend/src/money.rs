@@ -0,0 +1,303 @@
+//! Exact money handling in integer minor units
+//!
+//! Amounts are stored as an `i64` count of the currency's minor unit (cents
+//! for USD), so sums like `0.10 + 0.20` are exact. Decimal strings from
+//! providers and `NUMERIC` database columns are converted without going
+//! through floating point.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::{Row, postgres::PgRow};
+use std::fmt;
+
+/// Money-related errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoneyError {
+    /// The amount couldn't be parsed or has too many decimal places
+    InvalidAmount(String),
+    /// Arithmetic between two different currencies
+    CurrencyMismatch(String, String),
+    /// The result doesn't fit in an `i64` of minor units
+    Overflow,
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyError::InvalidAmount(msg) => write!(f, "Invalid amount: {}", msg),
+            MoneyError::CurrencyMismatch(a, b) => write!(f, "Currency mismatch: {} vs {}", a, b),
+            MoneyError::Overflow => write!(f, "Amount overflow"),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+/// Number of decimal places used by a currency's minor unit (ISO 4217)
+pub fn minor_unit_digits(currency: &str) -> u32 {
+    match currency {
+        "JPY" | "KRW" | "VND" | "CLP" | "ISK" => 0,
+        "BHD" | "KWD" | "OMR" | "JOD" | "TND" => 3,
+        _ => 2,
+    }
+}
+
+/// An amount of money in a specific currency
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Money {
+    amount_minor: i64,
+    currency: String,
+}
+
+impl Money {
+    /// Create an amount from minor units (e.g. `from_minor(750, "USD")` is $7.50)
+    pub fn from_minor(amount_minor: i64, currency: &str) -> Self {
+        Money {
+            amount_minor,
+            currency: currency.to_ascii_uppercase(),
+        }
+    }
+
+    /// Zero in the given currency
+    pub fn zero(currency: &str) -> Self {
+        Money::from_minor(0, currency)
+    }
+
+    /// Parse a decimal string such as `"12.34"`
+    ///
+    /// Fails if the string has more decimal places than the currency allows,
+    /// rather than silently rounding.
+    pub fn parse(amount: &str, currency: &str) -> Result<Self, MoneyError> {
+        let decimal: Decimal = amount
+            .trim()
+            .parse()
+            .map_err(|_| MoneyError::InvalidAmount(amount.to_string()))?;
+        Money::from_decimal(decimal, currency)
+    }
+
+    /// Convert from an exact decimal (e.g. a `NUMERIC` column)
+    pub fn from_decimal(amount: Decimal, currency: &str) -> Result<Self, MoneyError> {
+        let currency = currency.to_ascii_uppercase();
+        let digits = minor_unit_digits(&currency);
+
+        let normalized = amount.normalize();
+        if normalized.scale() > digits {
+            return Err(MoneyError::InvalidAmount(format!(
+                "{} has more than {} decimal places for {}",
+                amount, digits, currency
+            )));
+        }
+
+        let minor = normalized
+            .checked_mul(Decimal::from(10i64.pow(digits)))
+            .ok_or(MoneyError::Overflow)?;
+        let amount_minor = i64::try_from(minor).map_err(|_| MoneyError::Overflow)?;
+
+        Ok(Money {
+            amount_minor,
+            currency,
+        })
+    }
+
+    /// Convert to an exact decimal with the currency's number of decimal places
+    pub fn to_decimal(&self) -> Decimal {
+        Decimal::new(self.amount_minor, minor_unit_digits(&self.currency))
+    }
+
+    /// Amount in minor units
+    pub fn amount_minor(&self) -> i64 {
+        self.amount_minor
+    }
+
+    /// ISO 4217 currency code
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amount_minor == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.amount_minor < 0
+    }
+
+    /// Add two amounts of the same currency
+    pub fn checked_add(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.ensure_same_currency(other)?;
+        let amount_minor = self
+            .amount_minor
+            .checked_add(other.amount_minor)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Money::from_minor(amount_minor, &self.currency))
+    }
+
+    /// Subtract an amount of the same currency
+    pub fn checked_sub(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.ensure_same_currency(other)?;
+        let amount_minor = self
+            .amount_minor
+            .checked_sub(other.amount_minor)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Money::from_minor(amount_minor, &self.currency))
+    }
+
+    /// Multiply by a whole quantity
+    pub fn checked_mul(&self, quantity: i64) -> Result<Money, MoneyError> {
+        let amount_minor = self
+            .amount_minor
+            .checked_mul(quantity)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Money::from_minor(amount_minor, &self.currency))
+    }
+
+    /// Apply a rate in basis points (700 = 7%), rounding half away from zero
+    pub fn apply_basis_points(&self, basis_points: i64) -> Result<Money, MoneyError> {
+        let scaled = (self.amount_minor as i128) * (basis_points as i128);
+        let rounded = if scaled >= 0 {
+            (scaled + 5_000) / 10_000
+        } else {
+            (scaled - 5_000) / 10_000
+        };
+        let amount_minor = i64::try_from(rounded).map_err(|_| MoneyError::Overflow)?;
+        Ok(Money::from_minor(amount_minor, &self.currency))
+    }
+
+    /// Sum amounts, all of which must be in `currency`
+    pub fn sum<'a>(
+        amounts: impl IntoIterator<Item = &'a Money>,
+        currency: &str,
+    ) -> Result<Money, MoneyError> {
+        amounts
+            .into_iter()
+            .try_fold(Money::zero(currency), |total, amount| {
+                total.checked_add(amount)
+            })
+    }
+
+    fn ensure_same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency != other.currency {
+            return Err(MoneyError::CurrencyMismatch(
+                self.currency.clone(),
+                other.currency.clone(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.to_decimal(), self.currency)
+    }
+}
+
+/// JSON shape of a `Money` value: `{"amount": "37.98", "currency": "USD"}`
+///
+/// The amount is a string so clients never round-trip it through a float.
+#[derive(Serialize, Deserialize)]
+struct MoneyRepr {
+    amount: String,
+    currency: String,
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MoneyRepr {
+            amount: self.to_decimal().to_string(),
+            currency: self.currency.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = MoneyRepr::deserialize(deserializer)?;
+        Money::parse(&repr.amount, &repr.currency).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Read a `NUMERIC` money column from a row in the given currency
+pub(crate) fn money_column(
+    row: &PgRow,
+    column: &str,
+    currency: &str,
+) -> Result<Money, sqlx::Error> {
+    let amount: Decimal = row.try_get(column)?;
+    decode_money(amount, column, currency)
+}
+
+/// Read a nullable `NUMERIC` money column from a row in the given currency
+pub(crate) fn optional_money_column(
+    row: &PgRow,
+    column: &str,
+    currency: &str,
+) -> Result<Option<Money>, sqlx::Error> {
+    let amount: Option<Decimal> = row.try_get(column)?;
+    amount
+        .map(|amount| decode_money(amount, column, currency))
+        .transpose()
+}
+
+fn decode_money(amount: Decimal, column: &str, currency: &str) -> Result<Money, sqlx::Error> {
+    Money::from_decimal(amount, currency).map_err(|e| sqlx::Error::ColumnDecode {
+        index: column.to_string(),
+        source: Box::new(e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sums_are_exact() {
+        let a = Money::parse("0.1", "USD").unwrap();
+        let b = Money::parse("0.2", "USD").unwrap();
+        let sum = a.checked_add(&b).unwrap();
+        assert_eq!(sum, Money::parse("0.30", "USD").unwrap());
+        assert_eq!(sum.amount_minor(), 30);
+        assert_eq!(sum.to_decimal().to_string(), "0.30");
+    }
+
+    #[test]
+    fn test_parse_rejects_sub_minor_precision() {
+        assert!(Money::parse("12.345", "USD").is_err());
+        assert!(Money::parse("12.340", "USD").is_ok());
+        assert!(Money::parse("12.5", "JPY").is_err());
+        assert!(Money::parse("abc", "USD").is_err());
+        assert_eq!(Money::parse("1200", "JPY").unwrap().amount_minor(), 1200);
+    }
+
+    #[test]
+    fn test_currency_mismatch() {
+        let usd = Money::from_minor(100, "USD");
+        let eur = Money::from_minor(100, "EUR");
+        assert_eq!(
+            usd.checked_add(&eur),
+            Err(MoneyError::CurrencyMismatch(
+                "USD".to_string(),
+                "EUR".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_basis_points_round_half_up() {
+        // 7% of $35.50 is $2.485, which rounds to $2.49
+        let amount = Money::from_minor(3550, "USD");
+        assert_eq!(amount.apply_basis_points(700).unwrap().amount_minor(), 249);
+        // 7% of $0.07 is $0.0049, which rounds to $0.00
+        let amount = Money::from_minor(7, "USD");
+        assert_eq!(amount.apply_basis_points(700).unwrap().amount_minor(), 0);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let money = Money::from_minor(3798, "usd");
+        let json = serde_json::to_string(&money).unwrap();
+        assert_eq!(json, r#"{"amount":"37.98","currency":"USD"}"#);
+        assert_eq!(serde_json::from_str::<Money>(&json).unwrap(), money);
+    }
+}
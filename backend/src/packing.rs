@@ -0,0 +1,542 @@
+//! Packing prints into shipping containers
+//!
+//! Orders ship in the containers listed in `PackagingConfig`: rigid flat
+//! mailers and boxes for prints that ship flat, and mailer tubes for prints
+//! too large to. Packing is first-fit decreasing: the largest prints are
+//! placed first, every package is filled before another is opened, and once
+//! everything is placed each package is moved into the smallest container
+//! that still holds its contents.
+
+use serde::{Deserialize, Serialize};
+use std::{env, fmt, fs};
+
+use crate::{models::print_catalog::PrintSize, types::PackageDimensions};
+
+/// Slack allowed when comparing print and container sizes, in inches
+const FIT_TOLERANCE_IN: f32 = 0.01;
+
+/// Most packages one order may ship in
+pub const MAX_PACKAGES: usize = 50;
+
+/// How a container holds prints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContainerKind {
+    /// Prints are rolled
+    Tube,
+    /// Rigid flat mailer
+    Mailer,
+    Box,
+}
+
+/// A container orders can ship in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Container {
+    pub name: String,
+    pub kind: ContainerKind,
+    /// Outside dimensions in inches, which carriers rate by
+    pub length_in: f32,
+    pub width_in: f32,
+    pub height_in: f32,
+    /// Longest print side that fits, in inches
+    pub max_print_long_in: f32,
+    /// Shortest print side that fits, in inches; for tubes, the usable length
+    /// of the tube, since prints are rolled up along their long side
+    pub max_print_short_in: f32,
+    /// Most prints the container holds
+    pub max_prints: u32,
+    /// Weight of the empty container and its stiffeners, in pounds
+    pub weight_lbs: f32,
+    /// Heaviest the packed container may get, in pounds
+    pub max_weight_lbs: f32,
+}
+
+impl Container {
+    /// Whether a print of this size fits
+    fn fits(&self, long_in: f32, short_in: f32) -> bool {
+        long_in <= self.max_print_long_in + FIT_TOLERANCE_IN
+            && short_in <= self.max_print_short_in + FIT_TOLERANCE_IN
+    }
+
+    /// How many more prints of `weight_lbs` fit, given what's already packed
+    fn room(&self, prints: u32, packed_weight_lbs: f32, weight_lbs: f32) -> u32 {
+        let by_count = self.max_prints.saturating_sub(prints);
+        if weight_lbs <= 0.0 {
+            return by_count;
+        }
+        let spare_lbs = self.max_weight_lbs - self.weight_lbs - packed_weight_lbs;
+        let by_weight = (spare_lbs / weight_lbs + FIT_TOLERANCE_IN).floor().max(0.0) as u32;
+        by_count.min(by_weight)
+    }
+
+    fn volume(&self) -> f32 {
+        self.length_in * self.width_in * self.height_in
+    }
+}
+
+/// Containers orders can ship in
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackagingConfig {
+    pub containers: Vec<Container>,
+}
+
+impl Default for PackagingConfig {
+    fn default() -> Self {
+        let container = |name: &str,
+                         kind,
+                         (length_in, width_in, height_in),
+                         (max_print_long_in, max_print_short_in),
+                         max_prints,
+                         weight_lbs,
+                         max_weight_lbs| Container {
+            name: name.to_string(),
+            kind,
+            length_in,
+            width_in,
+            height_in,
+            max_print_long_in,
+            max_print_short_in,
+            max_prints,
+            weight_lbs,
+            max_weight_lbs,
+        };
+        PackagingConfig {
+            containers: vec![
+                container(
+                    "Small rigid mailer",
+                    ContainerKind::Mailer,
+                    (9.0, 7.0, 1.0),
+                    (7.0, 5.0),
+                    40,
+                    0.25,
+                    5.0,
+                ),
+                container(
+                    "Medium rigid mailer",
+                    ContainerKind::Mailer,
+                    (12.5, 10.0, 1.0),
+                    (11.0, 8.5),
+                    40,
+                    0.4,
+                    10.0,
+                ),
+                container(
+                    "Large rigid mailer",
+                    ContainerKind::Mailer,
+                    (16.0, 13.0, 1.0),
+                    (14.0, 11.0),
+                    25,
+                    0.7,
+                    10.0,
+                ),
+                container(
+                    "Small box",
+                    ContainerKind::Box,
+                    (9.0, 7.0, 4.0),
+                    (7.0, 5.0),
+                    300,
+                    0.6,
+                    20.0,
+                ),
+                container(
+                    "Large box",
+                    ContainerKind::Box,
+                    (16.0, 13.0, 4.0),
+                    (14.0, 11.0),
+                    150,
+                    1.2,
+                    30.0,
+                ),
+                container(
+                    "Mailer tube",
+                    ContainerKind::Tube,
+                    (26.0, 3.0, 3.0),
+                    (40.0, 24.0),
+                    10,
+                    0.4,
+                    5.0,
+                ),
+            ],
+        }
+    }
+}
+
+impl PackagingConfig {
+    /// Create a PackagingConfig from environment variables
+    ///
+    /// # Environment Variables
+    ///
+    /// - `PACKAGING_FILE`: JSON file with the list of containers (optional,
+    ///   defaults to built-in mailers, boxes and a tube)
+    pub fn from_env() -> Result<Self, String> {
+        let Ok(path) = env::var("PACKAGING_FILE") else {
+            return Ok(PackagingConfig::default());
+        };
+        let contents =
+            fs::read_to_string(&path).map_err(|e| format!("Can't read {}: {}", path, e))?;
+        let containers: Vec<Container> =
+            serde_json::from_str(&contents).map_err(|e| format!("Invalid {}: {}", path, e))?;
+
+        if containers.is_empty() {
+            return Err(format!("{} lists no containers", path));
+        }
+        for container in &containers {
+            let sizes = [
+                container.length_in,
+                container.width_in,
+                container.height_in,
+                container.max_print_long_in,
+                container.max_print_short_in,
+                container.max_weight_lbs,
+            ];
+            if sizes.iter().any(|size| !size.is_finite() || *size <= 0.0)
+                || container.weight_lbs < 0.0
+                || container.weight_lbs >= container.max_weight_lbs
+                || container.max_prints == 0
+            {
+                return Err(format!("Invalid container {} in {}", container.name, path));
+            }
+        }
+
+        Ok(PackagingConfig { containers })
+    }
+
+    /// Pack prints, given as catalog size and number of prints
+    pub fn pack(&self, prints: &[(&PrintSize, i64)]) -> Result<Vec<Package>, PackingError> {
+        pack(&self.containers, prints)
+    }
+}
+
+/// Errors returned when packing
+#[derive(Debug, Clone, PartialEq)]
+pub enum PackingError {
+    /// No container fits a print of this size
+    NoContainer(String),
+    /// The prints need more than this many packages
+    TooManyPackages(usize),
+}
+
+impl fmt::Display for PackingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackingError::NoContainer(size) => {
+                write!(f, "No shipping container fits {} prints", size)
+            }
+            PackingError::TooManyPackages(max) => {
+                write!(f, "Orders can ship in at most {} packages", max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PackingError {}
+
+/// A packed container
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Package {
+    /// Name of the container
+    pub container: String,
+    pub kind: ContainerKind,
+    pub prints: Vec<PackedPrints>,
+    /// Outside dimensions and total weight
+    #[serde(skip)]
+    pub dimensions: PackageDimensions,
+}
+
+/// Prints of one size in a package
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PackedPrints {
+    /// Catalog print size
+    pub size: String,
+    pub quantity: i64,
+}
+
+/// A package while it's being filled
+struct OpenPackage {
+    container: usize,
+    prints: Vec<(usize, i64)>,
+    count: u32,
+    weight_lbs: f32,
+    long_in: f32,
+    short_in: f32,
+}
+
+impl OpenPackage {
+    /// Add up to `remaining` prints, returning how many were added
+    fn fill(
+        &mut self,
+        container: &Container,
+        print: usize,
+        size: &PrintSides,
+        remaining: i64,
+    ) -> i64 {
+        if !container.fits(size.long_in, size.short_in) {
+            return 0;
+        }
+        let added =
+            (container.room(self.count, self.weight_lbs, size.weight_lbs) as i64).min(remaining);
+        if added > 0 {
+            match self.prints.iter_mut().find(|(index, _)| *index == print) {
+                Some((_, count)) => *count += added,
+                None => self.prints.push((print, added)),
+            }
+            self.count += added as u32;
+            self.weight_lbs += size.weight_lbs * added as f32;
+            self.long_in = self.long_in.max(size.long_in);
+            self.short_in = self.short_in.max(size.short_in);
+        }
+        added
+    }
+}
+
+/// A print size with its sides lined up, since prints can be rotated
+struct PrintSides {
+    long_in: f32,
+    short_in: f32,
+    weight_lbs: f32,
+}
+
+/// Pack prints into as few containers as possible, each as small as possible
+///
+/// Fails rather than open more than `MAX_PACKAGES` packages.
+pub fn pack(
+    containers: &[Container],
+    prints: &[(&PrintSize, i64)],
+) -> Result<Vec<Package>, PackingError> {
+    let sizes: Vec<PrintSides> = prints
+        .iter()
+        .map(|(size, _)| PrintSides {
+            long_in: size.width_in.max(size.height_in),
+            short_in: size.width_in.min(size.height_in),
+            weight_lbs: size.weight_lbs,
+        })
+        .collect();
+
+    // Largest prints first, so smaller ones fill the gaps
+    let mut order: Vec<usize> = (0..prints.len()).collect();
+    order.sort_by(|&a, &b| {
+        (sizes[b].long_in, sizes[b].short_in)
+            .partial_cmp(&(sizes[a].long_in, sizes[a].short_in))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut packages: Vec<OpenPackage> = Vec::new();
+    for index in order {
+        let (print_size, quantity) = prints[index];
+        let size = &sizes[index];
+        let mut remaining = quantity;
+
+        for package in &mut packages {
+            if remaining <= 0 {
+                break;
+            }
+            remaining -= package.fill(&containers[package.container], index, size, remaining);
+        }
+
+        while remaining > 0 {
+            // The smallest container taking every remaining print, or else
+            // the one taking the most
+            let room = |container: &Container| container.room(0, 0.0, size.weight_lbs) as i64;
+            let best = containers
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| c.fits(size.long_in, size.short_in) && room(c) > 0)
+                .min_by(|(_, a), (_, b)| {
+                    let (a_room, b_room) = (room(a).min(remaining), room(b).min(remaining));
+                    b_room.cmp(&a_room).then(
+                        a.volume()
+                            .partial_cmp(&b.volume())
+                            .unwrap_or(std::cmp::Ordering::Equal),
+                    )
+                })
+                .map(|(i, _)| i)
+                .ok_or_else(|| PackingError::NoContainer(print_size.size_id.clone()))?;
+            if packages.len() >= MAX_PACKAGES {
+                return Err(PackingError::TooManyPackages(MAX_PACKAGES));
+            }
+
+            let mut package = OpenPackage {
+                container: best,
+                prints: Vec::new(),
+                count: 0,
+                weight_lbs: 0.0,
+                long_in: 0.0,
+                short_in: 0.0,
+            };
+            remaining -= package.fill(&containers[best], index, size, remaining);
+            packages.push(package);
+        }
+    }
+
+    Ok(packages
+        .into_iter()
+        .map(|package| {
+            // Move into the smallest container that still holds everything
+            let container = containers
+                .iter()
+                .filter(|c| {
+                    c.fits(package.long_in, package.short_in)
+                        && package.count <= c.max_prints
+                        && c.weight_lbs + package.weight_lbs <= c.max_weight_lbs + FIT_TOLERANCE_IN
+                })
+                .min_by(|a, b| {
+                    a.volume()
+                        .partial_cmp(&b.volume())
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .unwrap_or(&containers[package.container]);
+
+            Package {
+                container: container.name.clone(),
+                kind: container.kind,
+                prints: package
+                    .prints
+                    .iter()
+                    .map(|&(index, quantity)| PackedPrints {
+                        size: prints[index].0.size_id.clone(),
+                        quantity,
+                    })
+                    .collect(),
+                dimensions: PackageDimensions {
+                    length: round_up(container.length_in),
+                    width: round_up(container.width_in),
+                    height: round_up(container.height_in),
+                    weight: round_up(container.weight_lbs + package.weight_lbs),
+                },
+            }
+        })
+        .collect())
+}
+
+/// Round up to a tenth, the precision sent to carriers
+///
+/// Values are first rounded to hundredths so float noise such as
+/// `0.70000005` doesn't bump them up a tenth.
+fn round_up(value: f32) -> f32 {
+    ((value * 100.0).round() / 10.0).ceil() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn print_size(size_id: &str, width_in: f32, height_in: f32, weight_lbs: f32) -> PrintSize {
+        PrintSize {
+            size_id: size_id.to_string(),
+            description: format!("{} print", size_id),
+            width_in,
+            height_in,
+            weight_lbs,
+            ..test_support::print_size()
+        }
+    }
+
+    fn containers(packages: &[Package]) -> Vec<&str> {
+        packages.iter().map(|p| p.container.as_str()).collect()
+    }
+
+    #[test]
+    fn test_small_order_uses_smallest_mailer() {
+        let config = PackagingConfig::default();
+        let small = print_size("4x6", 4.0, 6.0, 0.02);
+
+        let packages = config.pack(&[(&small, 10)]).unwrap();
+        assert_eq!(containers(&packages), ["Small rigid mailer"]);
+        assert_eq!(
+            packages[0].prints,
+            [PackedPrints {
+                size: "4x6".to_string(),
+                quantity: 10
+            }]
+        );
+        // 0.2 of prints plus the mailer
+        assert_eq!(packages[0].dimensions.weight, 0.5);
+        assert_eq!(packages[0].dimensions.length, 9.0);
+    }
+
+    #[test]
+    fn test_mixed_sizes_share_a_package() {
+        let config = PackagingConfig::default();
+        let small = print_size("4x6", 4.0, 6.0, 0.02);
+        let large = print_size("11x14", 14.0, 11.0, 0.12);
+
+        // Listed small first, but the large prints set the container
+        let packages = config.pack(&[(&small, 20), (&large, 2)]).unwrap();
+        assert_eq!(containers(&packages), ["Large rigid mailer"]);
+        let prints: Vec<(&str, i64)> = packages[0]
+            .prints
+            .iter()
+            .map(|p| (p.size.as_str(), p.quantity))
+            .collect();
+        assert_eq!(prints, [("11x14", 2), ("4x6", 20)]);
+    }
+
+    #[test]
+    fn test_large_order_splits_into_boxes() {
+        let config = PackagingConfig::default();
+        let medium = print_size("8x10", 8.0, 10.0, 0.06);
+
+        // 400 prints need three large boxes by count; the last is downsized
+        let packages = config.pack(&[(&medium, 400)]).unwrap();
+        assert_eq!(
+            containers(&packages),
+            ["Large box", "Large box", "Large box"]
+        );
+        let counts: Vec<i64> = packages.iter().map(|p| p.prints[0].quantity).collect();
+        assert_eq!(counts, [150, 150, 100]);
+
+        let packages = config.pack(&[(&medium, 190)]).unwrap();
+        assert_eq!(containers(&packages), ["Large box", "Medium rigid mailer"]);
+
+        // Packing stops instead of opening a box per 150 prints indefinitely
+        let packages = config
+            .pack(&[(&medium, 150 * MAX_PACKAGES as i64)])
+            .unwrap();
+        assert_eq!(packages.len(), MAX_PACKAGES);
+        assert_eq!(
+            config.pack(&[(&medium, i64::from(u32::MAX))]),
+            Err(PackingError::TooManyPackages(MAX_PACKAGES))
+        );
+    }
+
+    #[test]
+    fn test_oversized_prints_are_rolled() {
+        let config = PackagingConfig::default();
+        let poster = print_size("20x30", 20.0, 30.0, 0.3);
+
+        let packages = config.pack(&[(&poster, 12)]).unwrap();
+        assert_eq!(containers(&packages), ["Mailer tube", "Mailer tube"]);
+        assert!(packages.iter().all(|p| p.kind == ContainerKind::Tube));
+
+        let banner = print_size("30x60", 30.0, 60.0, 0.8);
+        assert_eq!(
+            config.pack(&[(&banner, 1)]),
+            Err(PackingError::NoContainer("30x60".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_container_weight_limit() {
+        let config = PackagingConfig {
+            containers: vec![Container {
+                name: "Mailer".to_string(),
+                kind: ContainerKind::Mailer,
+                length_in: 9.0,
+                width_in: 7.0,
+                height_in: 1.0,
+                max_print_long_in: 7.0,
+                max_print_short_in: 5.0,
+                max_prints: 100,
+                weight_lbs: 0.5,
+                max_weight_lbs: 1.5,
+            }],
+        };
+        let heavy = print_size("5x7", 5.0, 7.0, 0.25);
+
+        let packages = config.pack(&[(&heavy, 5)]).unwrap();
+        let counts: Vec<i64> = packages.iter().map(|p| p.prints[0].quantity).collect();
+        assert_eq!(counts, [4, 1]);
+        assert_eq!(packages[0].dimensions.weight, 1.5);
+    }
+}
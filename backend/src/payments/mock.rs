@@ -0,0 +1,240 @@
+//! In-memory payment provider for tests and offline development
+
+use async_trait::async_trait;
+use axum::http::{HeaderMap, HeaderValue};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use super::{
+    CreateIntentRequest, PaymentCapture, PaymentError, PaymentIntent, PaymentProvider,
+    PaymentRefund, PaymentStatus, WebhookEvent,
+};
+use crate::money::Money;
+
+#[derive(Debug, Clone)]
+struct MockPayment {
+    status: PaymentStatus,
+    amount: Money,
+    capture_id: Option<String>,
+    refunded: Money,
+}
+
+/// Payment provider that keeps payments in memory
+///
+/// Payments start out pending; call `approve` to act as the customer, and
+/// `webhook` to send a notification about a payment.
+#[derive(Debug)]
+pub struct MockPaymentProvider {
+    method: String,
+    payments: RwLock<HashMap<String, MockPayment>>, // intent ID -> payment
+    webhooks: RwLock<HashMap<String, WebhookEvent>>, // event ID -> event
+}
+
+/// Header naming the event a mock webhook request carries
+const EVENT_HEADER: &str = "Mock-Event-Id";
+
+impl MockPaymentProvider {
+    /// Create a mock that handles the given payment method
+    pub fn new(method: &str) -> Self {
+        MockPaymentProvider {
+            method: method.to_string(),
+            payments: RwLock::new(HashMap::new()),
+            webhooks: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Approve a pending payment, as the customer would
+    pub async fn approve(&self, intent_id: &str) -> Result<(), PaymentError> {
+        let mut payments = self.payments.write().await;
+        let payment = payments
+            .get_mut(intent_id)
+            .ok_or_else(|| PaymentError::NotFound(intent_id.to_string()))?;
+        if payment.status != PaymentStatus::Pending {
+            return Err(PaymentError::InvalidState(format!(
+                "Payment {} is {}",
+                intent_id, payment.status
+            )));
+        }
+        payment.status = PaymentStatus::Approved;
+        Ok(())
+    }
+
+    /// Queue a webhook event, returning the headers to deliver it with
+    ///
+    /// Any body can go with the headers; `verify_webhook` only reads the
+    /// event ID from them, so redelivering is sending the headers again.
+    pub async fn webhook(&self, event: WebhookEvent) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            EVENT_HEADER,
+            HeaderValue::from_str(&event.event_id).expect("Event ID is not a valid header"),
+        );
+        self.webhooks
+            .write()
+            .await
+            .insert(event.event_id.clone(), event);
+        headers
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for MockPaymentProvider {
+    fn method(&self) -> &str {
+        &self.method
+    }
+
+    async fn create_intent(
+        &self,
+        request: &CreateIntentRequest,
+    ) -> Result<PaymentIntent, PaymentError> {
+        let intent_id = format!("MOCK-{}", Uuid::new_v4().simple());
+        self.payments.write().await.insert(
+            intent_id.clone(),
+            MockPayment {
+                status: PaymentStatus::Pending,
+                amount: request.amount.clone(),
+                capture_id: None,
+                refunded: Money::zero(request.amount.currency()),
+            },
+        );
+        Ok(PaymentIntent {
+            approval_url: Some(format!("https://payments.invalid/approve/{}", intent_id)),
+            client_secret: Some(format!("{}_secret", intent_id)),
+            intent_id,
+            status: PaymentStatus::Pending,
+            amount: request.amount.clone(),
+        })
+    }
+
+    async fn capture(&self, intent_id: &str) -> Result<PaymentCapture, PaymentError> {
+        let mut payments = self.payments.write().await;
+        let payment = payments
+            .get_mut(intent_id)
+            .ok_or_else(|| PaymentError::NotFound(intent_id.to_string()))?;
+        if payment.status != PaymentStatus::Approved {
+            return Err(PaymentError::InvalidState(format!(
+                "Payment {} is {}",
+                intent_id, payment.status
+            )));
+        }
+        let capture_id = format!("MOCKCAP-{}", Uuid::new_v4().simple());
+        payment.status = PaymentStatus::Completed;
+        payment.capture_id = Some(capture_id.clone());
+        Ok(PaymentCapture {
+            capture_id,
+            status: PaymentStatus::Completed,
+            amount: payment.amount.clone(),
+        })
+    }
+
+    async fn refund(
+        &self,
+        capture_id: &str,
+        amount: Option<&Money>,
+        _request_id: &str,
+    ) -> Result<PaymentRefund, PaymentError> {
+        let mut payments = self.payments.write().await;
+        let payment = payments
+            .values_mut()
+            .find(|p| p.capture_id.as_deref() == Some(capture_id))
+            .ok_or_else(|| PaymentError::NotFound(capture_id.to_string()))?;
+
+        let remaining = payment.amount.checked_sub(&payment.refunded)?;
+        let amount = amount.cloned().unwrap_or_else(|| remaining.clone());
+        if amount.is_zero() || amount.is_negative() || remaining.checked_sub(&amount)?.is_negative()
+        {
+            return Err(PaymentError::InvalidState(format!(
+                "Can't refund {} of {}",
+                amount, remaining
+            )));
+        }
+
+        payment.refunded = payment.refunded.checked_add(&amount)?;
+        if payment.refunded == payment.amount {
+            payment.status = PaymentStatus::Refunded;
+        }
+        Ok(PaymentRefund {
+            refund_id: format!("MOCKREF-{}", Uuid::new_v4().simple()),
+            status: PaymentStatus::Completed,
+            amount,
+        })
+    }
+
+    async fn status(&self, intent_id: &str) -> Result<PaymentStatus, PaymentError> {
+        let payments = self.payments.read().await;
+        payments
+            .get(intent_id)
+            .map(|p| p.status)
+            .ok_or_else(|| PaymentError::NotFound(intent_id.to_string()))
+    }
+
+    async fn verify_webhook(
+        &self,
+        headers: &HeaderMap,
+        _body: &[u8],
+    ) -> Result<Option<WebhookEvent>, PaymentError> {
+        let Some(event_id) = headers.get(EVENT_HEADER) else {
+            return Ok(None);
+        };
+        let event_id = event_id
+            .to_str()
+            .map_err(|_| PaymentError::Signature(format!("Malformed {}", EVENT_HEADER)))?;
+        // Events queued with another mock provider aren't ours
+        Ok(self.webhooks.read().await.get(event_id).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_payment_flow() {
+        let provider = MockPaymentProvider::new("paypal");
+        let intent = provider
+            .create_intent(&CreateIntentRequest {
+                order_id: "ord_20250812_0001".to_string(),
+                amount: Money::from_minor(3798, "USD"),
+                description: None,
+                request_id: "order-ord_20250812_0001".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(intent.status, PaymentStatus::Pending);
+
+        // Can't take the money before the customer approves
+        assert!(matches!(
+            provider.capture(&intent.intent_id).await,
+            Err(PaymentError::InvalidState(_))
+        ));
+        provider.approve(&intent.intent_id).await.unwrap();
+        let capture = provider.capture(&intent.intent_id).await.unwrap();
+        assert_eq!(capture.amount, Money::from_minor(3798, "USD"));
+
+        let partial = Money::from_minor(1000, "USD");
+        provider
+            .refund(&capture.capture_id, Some(&partial), "refund-1")
+            .await
+            .unwrap();
+        assert_eq!(
+            provider.status(&intent.intent_id).await.unwrap(),
+            PaymentStatus::Completed
+        );
+        let rest = provider
+            .refund(&capture.capture_id, None, "refund-2")
+            .await
+            .unwrap();
+        assert_eq!(rest.amount, Money::from_minor(2798, "USD"));
+        assert_eq!(
+            provider.status(&intent.intent_id).await.unwrap(),
+            PaymentStatus::Refunded
+        );
+        assert!(
+            provider
+                .refund(&capture.capture_id, None, "refund-3")
+                .await
+                .is_err()
+        );
+    }
+}
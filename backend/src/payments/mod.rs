@@ -0,0 +1,337 @@
+//! Payment providers
+//!
+//! Every provider implements `PaymentProvider`, so order handling doesn't
+//! care which service takes the money. Providers are registered in
+//! `PaymentProviders` under the payment method customers choose (`paypal`,
+//! or `credit_card` for Stripe), and `mock::MockPaymentProvider` can stand in
+//! for any of them when running offline.
+
+pub mod mock;
+pub mod paypal;
+pub mod stripe;
+
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::{env, fmt, sync::Arc};
+
+use crate::money::{Money, MoneyError};
+
+/// Errors returned by payment providers
+#[derive(Debug)]
+pub enum PaymentError {
+    /// Missing or invalid provider configuration
+    Config(String),
+    /// The provider rejected the request
+    Api { status: u16, message: String },
+    /// No payment exists with the given ID
+    NotFound(String),
+    /// The payment isn't in a state that allows the operation
+    InvalidState(String),
+    /// Unexpected response body
+    Parse(String),
+    /// Connection failure
+    Network(String),
+    /// A webhook didn't pass signature verification
+    Signature(String),
+}
+
+impl fmt::Display for PaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentError::Config(msg) => write!(f, "Payment configuration error: {}", msg),
+            PaymentError::Api { status, message } => {
+                write!(f, "Payment provider error ({}): {}", status, message)
+            }
+            PaymentError::NotFound(id) => write!(f, "Payment not found: {}", id),
+            PaymentError::InvalidState(msg) => write!(f, "{}", msg),
+            PaymentError::Parse(msg) => write!(f, "Unexpected payment provider response: {}", msg),
+            PaymentError::Network(msg) => write!(f, "Payment provider unreachable: {}", msg),
+            PaymentError::Signature(msg) => write!(f, "Invalid webhook signature: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for PaymentError {}
+
+impl From<reqwest::Error> for PaymentError {
+    fn from(err: reqwest::Error) -> Self {
+        PaymentError::Network(err.to_string())
+    }
+}
+
+impl From<MoneyError> for PaymentError {
+    fn from(err: MoneyError) -> Self {
+        PaymentError::Parse(err.to_string())
+    }
+}
+
+/// Where a payment is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum PaymentStatus {
+    /// Created, waiting for the customer to approve it
+    Pending,
+    /// Approved by the customer, ready to capture
+    Approved,
+    /// Money has been taken
+    Completed,
+    /// Cancelled or expired before completion
+    Cancelled,
+    /// Declined or otherwise failed
+    Failed,
+    /// Money has been returned to the customer
+    Refunded,
+    /// The customer disputed the payment with their bank or the provider
+    Disputed,
+}
+
+impl PaymentStatus {
+    /// Get the status as its API/database string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentStatus::Pending => "pending",
+            PaymentStatus::Approved => "approved",
+            PaymentStatus::Completed => "completed",
+            PaymentStatus::Cancelled => "cancelled",
+            PaymentStatus::Failed => "failed",
+            PaymentStatus::Refunded => "refunded",
+            PaymentStatus::Disputed => "disputed",
+        }
+    }
+}
+
+impl fmt::Display for PaymentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What to charge the customer for
+#[derive(Debug, Clone)]
+pub struct CreateIntentRequest {
+    /// Our order ID, passed to the provider as a reference
+    pub order_id: String,
+    pub amount: Money,
+    pub description: Option<String>,
+    /// Identifies the attempt to the provider, so retrying it returns the
+    /// payment the first try created rather than a second one
+    pub request_id: String,
+}
+
+/// Request ID of an order's first payment
+///
+/// Retrying the order's checkout sends the same ID, so the provider doesn't
+/// create a second payment for it.
+pub fn order_request_id(order_id: &str) -> String {
+    format!("order-{}", order_id)
+}
+
+/// A payment created with a provider, waiting for the customer
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentIntent {
+    /// The provider's ID for the payment
+    pub intent_id: String,
+    pub status: PaymentStatus,
+    pub amount: Money,
+    /// Page where the customer approves the payment
+    pub approval_url: Option<String>,
+    /// Secret the client uses to confirm the payment itself (Stripe)
+    pub client_secret: Option<String>,
+}
+
+/// Result of capturing an approved payment
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentCapture {
+    /// The provider's ID for the capture, used for refunds
+    pub capture_id: String,
+    pub status: PaymentStatus,
+    pub amount: Money,
+}
+
+/// Result of a refund request
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentRefund {
+    pub refund_id: String,
+    /// `Completed` once the money is on its way back, otherwise `Pending`
+    pub status: PaymentStatus,
+    pub amount: Money,
+}
+
+/// What a webhook event means for the payment it's about
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebhookAction {
+    /// The money was taken
+    Paid,
+    /// The payment was declined or failed
+    Failed,
+    /// Money was returned; the total refunded so far on the capture
+    Refunded { total_refunded: Money },
+    /// The customer opened a dispute or chargeback
+    Disputed,
+    /// An event type we don't act on
+    Ignored,
+}
+
+/// A webhook event that passed signature verification
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookEvent {
+    /// The provider's ID for the event, reused on redelivery
+    pub event_id: String,
+    /// The provider's event type, e.g. `payment_intent.succeeded`
+    pub event_type: String,
+    /// The payment's intent ID, when the event carries it
+    pub intent_id: Option<String>,
+    /// The capture ID, for events about a capture rather than the payment
+    pub capture_id: Option<String>,
+    pub action: WebhookAction,
+}
+
+/// A service that takes payments
+#[async_trait]
+pub trait PaymentProvider: Send + Sync + fmt::Debug {
+    /// Payment method this provider handles, such as `paypal`
+    fn method(&self) -> &str;
+
+    /// Create a payment for the customer to approve
+    async fn create_intent(
+        &self,
+        request: &CreateIntentRequest,
+    ) -> Result<PaymentIntent, PaymentError>;
+
+    /// Take the money for an approved payment
+    async fn capture(&self, intent_id: &str) -> Result<PaymentCapture, PaymentError>;
+
+    /// Refund a capture, in full when `amount` is `None`
+    ///
+    /// Retrying with the same `request_id` returns the first refund instead
+    /// of refunding again.
+    async fn refund(
+        &self,
+        capture_id: &str,
+        amount: Option<&Money>,
+        request_id: &str,
+    ) -> Result<PaymentRefund, PaymentError>;
+
+    /// Current status of a payment
+    async fn status(&self, intent_id: &str) -> Result<PaymentStatus, PaymentError>;
+
+    /// Verify a webhook request and parse the event it carries
+    ///
+    /// Returns `None` if the request wasn't sent by this provider.
+    async fn verify_webhook(
+        &self,
+        _headers: &HeaderMap,
+        _body: &[u8],
+    ) -> Result<Option<WebhookEvent>, PaymentError> {
+        Ok(None)
+    }
+}
+
+/// The configured providers, looked up by payment method
+#[derive(Debug, Clone, Default)]
+pub struct PaymentProviders {
+    providers: Vec<Arc<dyn PaymentProvider>>,
+}
+
+impl PaymentProviders {
+    pub fn new(providers: Vec<Arc<dyn PaymentProvider>>) -> Self {
+        PaymentProviders { providers }
+    }
+
+    /// Provider for a payment method, if one is configured
+    pub fn get(&self, method: &str) -> Option<&Arc<dyn PaymentProvider>> {
+        self.providers.iter().find(|p| p.method() == method)
+    }
+
+    /// All configured providers
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn PaymentProvider>> {
+        self.providers.iter()
+    }
+
+    /// Payment methods that can be used
+    pub fn methods(&self) -> Vec<&str> {
+        self.providers.iter().map(|p| p.method()).collect()
+    }
+}
+
+/// Payment provider settings
+#[derive(Debug, Clone)]
+pub struct PaymentConfig {
+    /// PayPal credentials; PayPal is disabled without them
+    pub paypal: Option<paypal::PayPalConfig>,
+    /// Stripe credentials; card payments are disabled without them
+    pub stripe: Option<stripe::StripeConfig>,
+    /// Use in-memory mock providers instead of the real services
+    pub mock: bool,
+}
+
+impl PaymentConfig {
+    /// Load payment configuration from environment variables
+    ///
+    /// # Environment Variables
+    ///
+    /// - `PAYMENTS_MOCK`: `true` to use offline mock providers (optional)
+    /// - `PAYPAL_API_URL`: PayPal API base URL (optional, defaults to the sandbox)
+    /// - `PAYPAL_CLIENT_ID` / `PAYPAL_CLIENT_SECRET`: REST app credentials
+    /// - `PAYPAL_RETURN_URL` / `PAYPAL_CANCEL_URL`: Where PayPal sends the customer
+    ///   after approving or cancelling
+    /// - `PAYPAL_WEBHOOK_ID`: ID of the PayPal webhook, needed to verify deliveries
+    /// - `STRIPE_API_URL`: Stripe API base URL (optional)
+    /// - `STRIPE_SECRET_KEY`: Stripe secret API key
+    /// - `STRIPE_WEBHOOK_SECRET`: Signing secret of the Stripe webhook endpoint
+    pub fn from_env() -> Result<Self, String> {
+        let mock = match env::var("PAYMENTS_MOCK") {
+            Ok(value) => value
+                .parse::<bool>()
+                .map_err(|_| format!("Invalid PAYMENTS_MOCK: {}", value))?,
+            Err(_) => false,
+        };
+        let paypal = match env::var("PAYPAL_CLIENT_ID") {
+            Ok(client_id) => Some(paypal::PayPalConfig {
+                api_url: env::var("PAYPAL_API_URL")
+                    .unwrap_or_else(|_| paypal::SANDBOX_API_URL.to_string()),
+                client_id,
+                client_secret: env::var("PAYPAL_CLIENT_SECRET")
+                    .map_err(|_| "PAYPAL_CLIENT_SECRET not set")?,
+                return_url: env::var("PAYPAL_RETURN_URL")
+                    .map_err(|_| "PAYPAL_RETURN_URL not set")?,
+                cancel_url: env::var("PAYPAL_CANCEL_URL")
+                    .map_err(|_| "PAYPAL_CANCEL_URL not set")?,
+                webhook_id: env::var("PAYPAL_WEBHOOK_ID").ok(),
+            }),
+            Err(_) => None,
+        };
+        let stripe = env::var("STRIPE_SECRET_KEY")
+            .ok()
+            .map(|secret_key| stripe::StripeConfig {
+                api_url: env::var("STRIPE_API_URL")
+                    .unwrap_or_else(|_| stripe::DEFAULT_API_URL.to_string()),
+                secret_key,
+                webhook_secret: env::var("STRIPE_WEBHOOK_SECRET").ok(),
+            });
+        Ok(PaymentConfig {
+            paypal,
+            stripe,
+            mock,
+        })
+    }
+
+    /// Build the configured providers
+    pub fn build(self) -> PaymentProviders {
+        let mut providers: Vec<Arc<dyn PaymentProvider>> = Vec::new();
+        if self.mock {
+            providers.push(Arc::new(mock::MockPaymentProvider::new("paypal")));
+            providers.push(Arc::new(mock::MockPaymentProvider::new("credit_card")));
+            return PaymentProviders::new(providers);
+        }
+        if let Some(config) = self.paypal {
+            providers.push(Arc::new(paypal::PayPalProvider::new(config)));
+        }
+        if let Some(config) = self.stripe {
+            providers.push(Arc::new(stripe::StripeProvider::new(config)));
+        }
+        PaymentProviders::new(providers)
+    }
+}
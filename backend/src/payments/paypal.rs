@@ -0,0 +1,926 @@
+//! PayPal Orders v2 integration
+//!
+//! The PayPal order is created server-side for the order's grand total, the
+//! customer approves it on PayPal, and we capture it afterwards. Refunds go
+//! through the Payments v2 capture refund API.
+//!
+//! Webhook deliveries are checked with PayPal's verify-webhook-signature API
+//! rather than by validating the certificate chain ourselves.
+
+use async_trait::async_trait;
+use base64::{Engine as _, engine::general_purpose};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use super::{
+    CreateIntentRequest, PaymentCapture, PaymentError, PaymentIntent, PaymentProvider,
+    PaymentRefund, PaymentStatus, WebhookAction, WebhookEvent,
+};
+use crate::money::Money;
+
+/// PayPal sandbox API base URL
+pub const SANDBOX_API_URL: &str = "https://api-m.sandbox.paypal.com";
+
+/// Refresh access tokens this long before PayPal says they expire
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// PayPal REST app settings
+#[derive(Debug, Clone)]
+pub struct PayPalConfig {
+    /// PayPal API base URL (sandbox or `https://api-m.paypal.com`)
+    pub api_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Where PayPal sends the customer after approving the payment
+    pub return_url: String,
+    /// Where PayPal sends the customer after cancelling
+    pub cancel_url: String,
+    /// ID of our webhook in the PayPal app; webhooks are rejected without it
+    pub webhook_id: Option<String>,
+}
+
+/// Payment provider backed by the PayPal Orders v2 API
+#[derive(Debug)]
+pub struct PayPalProvider {
+    config: PayPalConfig,
+    client: reqwest::Client,
+    token: Mutex<Option<(String, Instant)>>, // access token, expiry
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PayPalAmount {
+    currency_code: String,
+    value: String,
+}
+
+impl PayPalAmount {
+    fn from_money(money: &Money) -> Self {
+        PayPalAmount {
+            currency_code: money.currency().to_string(),
+            value: money.to_decimal().to_string(),
+        }
+    }
+
+    fn to_money(&self) -> Result<Money, PaymentError> {
+        Ok(Money::parse(&self.value, &self.currency_code)?)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Link {
+    href: String,
+    rel: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrderResponse {
+    id: String,
+    status: String,
+    #[serde(default)]
+    links: Vec<Link>,
+    #[serde(default)]
+    purchase_units: Vec<PurchaseUnit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PurchaseUnit {
+    payments: Option<UnitPayments>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnitPayments {
+    #[serde(default)]
+    captures: Vec<Capture>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Capture {
+    id: String,
+    status: String,
+    amount: PayPalAmount,
+}
+
+#[derive(Debug, Deserialize)]
+struct RefundResponse {
+    id: String,
+    status: String,
+    amount: Option<PayPalAmount>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifySignatureResponse {
+    verification_status: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PayPalEvent {
+    id: String,
+    event_type: String,
+    resource: serde_json::Value,
+}
+
+/// A capture or refund, as sent in `PAYMENT.CAPTURE.*` events
+#[derive(Debug, Deserialize)]
+struct EventTransaction {
+    id: String,
+    amount: Option<PayPalAmount>,
+    supplementary_data: Option<SupplementaryData>,
+    seller_payable_breakdown: Option<SellerPayableBreakdown>,
+    #[serde(default)]
+    links: Vec<Link>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SupplementaryData {
+    related_ids: Option<RelatedIds>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RelatedIds {
+    order_id: Option<String>,
+    capture_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SellerPayableBreakdown {
+    total_refunded_amount: Option<PayPalAmount>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventDispute {
+    #[serde(default)]
+    disputed_transactions: Vec<DisputedTransaction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DisputedTransaction {
+    seller_transaction_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    name: Option<String>,
+    message: Option<String>,
+    #[serde(default)]
+    details: Vec<ErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorDetail {
+    issue: String,
+}
+
+impl PayPalProvider {
+    pub fn new(config: PayPalConfig) -> Self {
+        PayPalProvider {
+            config,
+            client: reqwest::Client::new(),
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Get a cached access token, fetching a new one when it's about to expire
+    async fn access_token(&self) -> Result<String, PaymentError> {
+        let mut token = self.token.lock().await;
+        if let Some((access_token, expires_at)) = token.as_ref()
+            && Instant::now() < *expires_at
+        {
+            return Ok(access_token.clone());
+        }
+
+        let auth = general_purpose::STANDARD.encode(format!(
+            "{}:{}",
+            self.config.client_id, self.config.client_secret
+        ));
+        let response = self
+            .client
+            .post(format!("{}/v1/oauth2/token", self.config.api_url))
+            .header("Authorization", format!("Basic {}", auth))
+            .form(&[("grant_type", "client_credentials")])
+            .send()
+            .await?;
+        let fetched: TokenResponse = Self::parse_response(response).await?;
+
+        let lifetime = Duration::from_secs(fetched.expires_in).saturating_sub(TOKEN_EXPIRY_MARGIN);
+        *token = Some((fetched.access_token.clone(), Instant::now() + lifetime));
+        Ok(fetched.access_token)
+    }
+
+    /// Send an authenticated JSON POST
+    async fn post<B: Serialize, T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        request_id: &str,
+        body: &B,
+    ) -> Result<T, PaymentError> {
+        let response = self
+            .client
+            .post(format!("{}{}", self.config.api_url, path))
+            .bearer_auth(self.access_token().await?)
+            .header("PayPal-Request-Id", request_id)
+            .header("Prefer", "return=representation")
+            .json(body)
+            .send()
+            .await?;
+        Self::parse_response(response).await
+    }
+
+    /// Decode a successful response, or turn a PayPal error body into a `PaymentError`
+    async fn parse_response<T: for<'de> Deserialize<'de>>(
+        response: reqwest::Response,
+    ) -> Result<T, PaymentError> {
+        let status = response.status();
+        let text = response.text().await?;
+        if status.is_success() {
+            return serde_json::from_str(&text).map_err(|e| PaymentError::Parse(e.to_string()));
+        }
+
+        let error: Option<ErrorResponse> = serde_json::from_str(&text).ok();
+        let issue = error
+            .as_ref()
+            .and_then(|e| e.details.first())
+            .map(|d| d.issue.clone());
+        let message = error
+            .as_ref()
+            .and_then(|e| e.message.clone().or_else(|| e.name.clone()))
+            .unwrap_or(text);
+        let message = match issue {
+            Some(issue) => format!("{} ({})", message, issue),
+            None => message,
+        };
+        Err(match status.as_u16() {
+            404 => PaymentError::NotFound(message),
+            422 => PaymentError::InvalidState(message),
+            status => PaymentError::Api { status, message },
+        })
+    }
+}
+
+/// Map a PayPal order status to ours
+fn order_status(status: &str) -> PaymentStatus {
+    match status {
+        "APPROVED" => PaymentStatus::Approved,
+        "COMPLETED" => PaymentStatus::Completed,
+        "VOIDED" => PaymentStatus::Cancelled,
+        // CREATED, SAVED, PAYER_ACTION_REQUIRED
+        _ => PaymentStatus::Pending,
+    }
+}
+
+/// Turn a PayPal event into the action it calls for
+fn parse_event(event: PayPalEvent) -> Result<WebhookEvent, PaymentError> {
+    let parse_err = |e: serde_json::Error| PaymentError::Parse(e.to_string());
+    let (intent_id, capture_id, action) = match event.event_type.as_str() {
+        "PAYMENT.CAPTURE.COMPLETED" | "PAYMENT.CAPTURE.DENIED" | "PAYMENT.CAPTURE.DECLINED" => {
+            let capture: EventTransaction =
+                serde_json::from_value(event.resource).map_err(parse_err)?;
+            let action = if event.event_type == "PAYMENT.CAPTURE.COMPLETED" {
+                WebhookAction::Paid
+            } else {
+                WebhookAction::Failed
+            };
+            let order_id = capture
+                .supplementary_data
+                .and_then(|data| data.related_ids)
+                .and_then(|ids| ids.order_id);
+            (order_id, Some(capture.id), action)
+        }
+        "PAYMENT.CAPTURE.REFUNDED" | "PAYMENT.CAPTURE.REVERSED" => {
+            // The resource is the refund; its "up" link points at the capture
+            let refund: EventTransaction =
+                serde_json::from_value(event.resource).map_err(parse_err)?;
+            let related = refund.supplementary_data.and_then(|data| data.related_ids);
+            let capture_id = related
+                .as_ref()
+                .and_then(|ids| ids.capture_id.clone())
+                .or_else(|| {
+                    refund
+                        .links
+                        .iter()
+                        .find(|link| link.rel == "up")
+                        .and_then(|link| link.href.rsplit('/').next())
+                        .map(str::to_string)
+                });
+            let total_refunded = refund
+                .seller_payable_breakdown
+                .and_then(|breakdown| breakdown.total_refunded_amount)
+                .or(refund.amount)
+                .ok_or_else(|| PaymentError::Parse(format!("No amount in refund {}", refund.id)))?
+                .to_money()?;
+            (
+                related.and_then(|ids| ids.order_id),
+                capture_id,
+                WebhookAction::Refunded { total_refunded },
+            )
+        }
+        "CUSTOMER.DISPUTE.CREATED" => {
+            let dispute: EventDispute =
+                serde_json::from_value(event.resource).map_err(parse_err)?;
+            let capture_id = dispute
+                .disputed_transactions
+                .into_iter()
+                .find_map(|transaction| transaction.seller_transaction_id);
+            (None, capture_id, WebhookAction::Disputed)
+        }
+        _ => (None, None, WebhookAction::Ignored),
+    };
+    Ok(WebhookEvent {
+        event_id: event.id,
+        event_type: event.event_type,
+        intent_id,
+        capture_id,
+        action,
+    })
+}
+
+/// Map a PayPal capture or refund status to ours
+fn transaction_status(status: &str) -> PaymentStatus {
+    match status {
+        "COMPLETED" | "PARTIALLY_REFUNDED" => PaymentStatus::Completed,
+        "REFUNDED" => PaymentStatus::Refunded,
+        "DECLINED" | "FAILED" => PaymentStatus::Failed,
+        "CANCELLED" => PaymentStatus::Cancelled,
+        _ => PaymentStatus::Pending,
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for PayPalProvider {
+    fn method(&self) -> &str {
+        "paypal"
+    }
+
+    async fn create_intent(
+        &self,
+        request: &CreateIntentRequest,
+    ) -> Result<PaymentIntent, PaymentError> {
+        let body = serde_json::json!({
+            "intent": "CAPTURE",
+            "purchase_units": [{
+                "reference_id": request.order_id,
+                "custom_id": request.order_id,
+                "description": request.description,
+                "amount": PayPalAmount::from_money(&request.amount),
+            }],
+            "payment_source": {
+                "paypal": {
+                    "experience_context": {
+                        "user_action": "PAY_NOW",
+                        "return_url": self.config.return_url,
+                        "cancel_url": self.config.cancel_url,
+                    }
+                }
+            }
+        });
+        let order: OrderResponse = self
+            .post("/v2/checkout/orders", &request.request_id, &body)
+            .await?;
+
+        let approval_url = order
+            .links
+            .iter()
+            .find(|link| link.rel == "payer-action" || link.rel == "approve")
+            .map(|link| link.href.clone());
+        Ok(PaymentIntent {
+            intent_id: order.id,
+            status: order_status(&order.status),
+            amount: request.amount.clone(),
+            approval_url,
+            client_secret: None,
+        })
+    }
+
+    async fn capture(&self, intent_id: &str) -> Result<PaymentCapture, PaymentError> {
+        // A fixed request ID makes retried captures return the first result
+        let order: OrderResponse = self
+            .post(
+                &format!("/v2/checkout/orders/{}/capture", intent_id),
+                &format!("capture-{}", intent_id),
+                &serde_json::json!({}),
+            )
+            .await?;
+
+        let capture = order
+            .purchase_units
+            .into_iter()
+            .filter_map(|unit| unit.payments)
+            .flat_map(|payments| payments.captures)
+            .next()
+            .ok_or_else(|| PaymentError::Parse(format!("No capture in order {}", order.id)))?;
+        Ok(PaymentCapture {
+            status: transaction_status(&capture.status),
+            amount: capture.amount.to_money()?,
+            capture_id: capture.id,
+        })
+    }
+
+    async fn refund(
+        &self,
+        capture_id: &str,
+        amount: Option<&Money>,
+        request_id: &str,
+    ) -> Result<PaymentRefund, PaymentError> {
+        let body = match amount {
+            Some(amount) => serde_json::json!({ "amount": PayPalAmount::from_money(amount) }),
+            None => serde_json::json!({}),
+        };
+        let refund: RefundResponse = self
+            .post(
+                &format!("/v2/payments/captures/{}/refund", capture_id),
+                request_id,
+                &body,
+            )
+            .await?;
+
+        let amount = match (refund.amount, amount) {
+            (Some(refunded), _) => refunded.to_money()?,
+            (None, Some(requested)) => requested.clone(),
+            (None, None) => {
+                return Err(PaymentError::Parse(format!(
+                    "No amount in refund {}",
+                    refund.id
+                )));
+            }
+        };
+        Ok(PaymentRefund {
+            refund_id: refund.id,
+            status: transaction_status(&refund.status),
+            amount,
+        })
+    }
+
+    async fn status(&self, intent_id: &str) -> Result<PaymentStatus, PaymentError> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/v2/checkout/orders/{}",
+                self.config.api_url, intent_id
+            ))
+            .bearer_auth(self.access_token().await?)
+            .send()
+            .await?;
+        let order: OrderResponse = Self::parse_response(response).await?;
+
+        // A completed order stays COMPLETED; what happened since is on the capture
+        let capture = order
+            .purchase_units
+            .iter()
+            .filter_map(|unit| unit.payments.as_ref())
+            .flat_map(|payments| &payments.captures)
+            .next();
+        Ok(match (order_status(&order.status), capture) {
+            (PaymentStatus::Completed, Some(capture)) => transaction_status(&capture.status),
+            (status, _) => status,
+        })
+    }
+
+    async fn verify_webhook(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<Option<WebhookEvent>, PaymentError> {
+        if !headers.contains_key("PayPal-Transmission-Id") {
+            return Ok(None);
+        }
+        let webhook_id = self
+            .config
+            .webhook_id
+            .as_deref()
+            .ok_or_else(|| PaymentError::Config("PAYPAL_WEBHOOK_ID is not set".to_string()))?;
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| PaymentError::Signature(format!("Missing {} header", name)))
+        };
+        let webhook_event: serde_json::Value =
+            serde_json::from_slice(body).map_err(|e| PaymentError::Parse(e.to_string()))?;
+
+        let request = serde_json::json!({
+            "auth_algo": header("PayPal-Auth-Algo")?,
+            "cert_url": header("PayPal-Cert-Url")?,
+            "transmission_id": header("PayPal-Transmission-Id")?,
+            "transmission_sig": header("PayPal-Transmission-Sig")?,
+            "transmission_time": header("PayPal-Transmission-Time")?,
+            "webhook_id": webhook_id,
+            "webhook_event": webhook_event,
+        });
+        let verification: VerifySignatureResponse = self
+            .post(
+                "/v1/notifications/verify-webhook-signature",
+                &Uuid::new_v4().to_string(),
+                &request,
+            )
+            .await?;
+        if verification.verification_status != "SUCCESS" {
+            return Err(PaymentError::Signature(format!(
+                "PayPal reported {}",
+                verification.verification_status
+            )));
+        }
+
+        let event: PayPalEvent = serde_json::from_value(request["webhook_event"].clone())
+            .map_err(|e| PaymentError::Parse(e.to_string()))?;
+        parse_event(event).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::spawn_stub_server;
+    use axum::{
+        Json, Router,
+        extract::{Path, State},
+        http::{HeaderMap, StatusCode},
+        routing::{get, post},
+    };
+    use serde_json::{Value, json};
+    use std::{
+        collections::HashMap,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+    };
+
+    /// Stand-in for the PayPal API, holding order ID -> (status, amount)
+    #[derive(Debug, Default)]
+    struct StubPayPal {
+        orders: std::sync::Mutex<HashMap<String, (String, Value)>>,
+        /// First response to each `PayPal-Request-Id`, returned on retries
+        responses: std::sync::Mutex<HashMap<String, Value>>,
+        refunds: AtomicUsize,
+        token_requests: AtomicUsize,
+    }
+
+    impl StubPayPal {
+        /// Respond as PayPal does to a request ID it has already seen
+        fn replay(&self, headers: &HeaderMap) -> Option<Json<Value>> {
+            let request_id = headers.get("paypal-request-id")?.to_str().ok()?;
+            self.responses
+                .lock()
+                .unwrap()
+                .get(request_id)
+                .cloned()
+                .map(Json)
+        }
+
+        fn remember(&self, headers: &HeaderMap, response: Value) -> Json<Value> {
+            if let Some(request_id) = headers
+                .get("paypal-request-id")
+                .and_then(|v| v.to_str().ok())
+            {
+                self.responses
+                    .lock()
+                    .unwrap()
+                    .insert(request_id.to_string(), response.clone());
+            }
+            Json(response)
+        }
+    }
+
+    type Stub = State<Arc<StubPayPal>>;
+    type StubResult = Result<Json<Value>, (StatusCode, Json<Value>)>;
+
+    fn unprocessable(issue: &str) -> (StatusCode, Json<Value>) {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "name": "UNPROCESSABLE_ENTITY",
+                "message": "The requested action could not be performed.",
+                "details": [{ "issue": issue }]
+            })),
+        )
+    }
+
+    fn authorized(headers: &HeaderMap) -> Result<(), (StatusCode, Json<Value>)> {
+        match headers.get("authorization").and_then(|v| v.to_str().ok()) {
+            Some("Bearer A21AA-test") => Ok(()),
+            _ => Err((
+                StatusCode::UNAUTHORIZED,
+                Json(json!({ "error": "invalid_token" })),
+            )),
+        }
+    }
+
+    async fn token(State(stub): Stub) -> Json<Value> {
+        stub.token_requests.fetch_add(1, Ordering::SeqCst);
+        Json(json!({ "access_token": "A21AA-test", "token_type": "Bearer", "expires_in": 32400 }))
+    }
+
+    async fn create_order(
+        State(stub): Stub,
+        headers: HeaderMap,
+        Json(body): Json<Value>,
+    ) -> StubResult {
+        authorized(&headers)?;
+        if let Some(response) = stub.replay(&headers) {
+            return Ok(response);
+        }
+        let id = format!("5O{}", stub.orders.lock().unwrap().len() + 190127);
+        let amount = body["purchase_units"][0]["amount"].clone();
+        stub.orders
+            .lock()
+            .unwrap()
+            .insert(id.clone(), ("PAYER_ACTION_REQUIRED".to_string(), amount));
+        Ok(stub.remember(
+            &headers,
+            json!({
+                "id": id,
+                "status": "PAYER_ACTION_REQUIRED",
+                "links": [
+                    { "href": format!("https://api-m.sandbox.paypal.com/v2/checkout/orders/{}", id), "rel": "self" },
+                    { "href": format!("https://www.sandbox.paypal.com/checkoutnow?token={}", id), "rel": "payer-action" }
+                ]
+            }),
+        ))
+    }
+
+    fn order_body(id: &str, status: &str, amount: &Value) -> Value {
+        let captures = if status == "COMPLETED" {
+            json!([{ "id": format!("CAP-{}", id), "status": "COMPLETED", "amount": amount }])
+        } else {
+            json!([])
+        };
+        json!({
+            "id": id,
+            "status": status,
+            "purchase_units": [{ "amount": amount, "payments": { "captures": captures } }]
+        })
+    }
+
+    async fn get_order(
+        State(stub): Stub,
+        headers: HeaderMap,
+        Path(id): Path<String>,
+    ) -> StubResult {
+        authorized(&headers)?;
+        let orders = stub.orders.lock().unwrap();
+        let (status, amount) = orders.get(&id).ok_or((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "name": "RESOURCE_NOT_FOUND", "details": [{ "issue": "INVALID_RESOURCE_ID" }] })),
+        ))?;
+        Ok(Json(order_body(&id, status, amount)))
+    }
+
+    async fn capture_order(
+        State(stub): Stub,
+        headers: HeaderMap,
+        Path(id): Path<String>,
+    ) -> StubResult {
+        authorized(&headers)?;
+        let mut orders = stub.orders.lock().unwrap();
+        let (status, amount) = orders
+            .get_mut(&id)
+            .ok_or_else(|| unprocessable("INVALID_RESOURCE_ID"))?;
+        if status != "APPROVED" {
+            return Err(unprocessable("ORDER_NOT_APPROVED"));
+        }
+        *status = "COMPLETED".to_string();
+        Ok(Json(order_body(&id, status, amount)))
+    }
+
+    async fn refund_capture(
+        State(stub): Stub,
+        headers: HeaderMap,
+        Path(id): Path<String>,
+        Json(body): Json<Value>,
+    ) -> StubResult {
+        authorized(&headers)?;
+        if let Some(response) = stub.replay(&headers) {
+            return Ok(response);
+        }
+        let amount = if body["amount"].is_null() {
+            json!({ "currency_code": "USD", "value": "37.98" })
+        } else {
+            body["amount"].clone()
+        };
+        let refund = stub.refunds.fetch_add(1, Ordering::SeqCst) + 1;
+        Ok(stub.remember(
+            &headers,
+            json!({ "id": format!("REF-{}-{}", id, refund), "status": "COMPLETED", "amount": amount }),
+        ))
+    }
+
+    async fn verify_signature(headers: HeaderMap, Json(body): Json<Value>) -> StubResult {
+        authorized(&headers)?;
+        let valid = body["webhook_id"] == "WH-1TU26016ED4963457-73K03948VJ187920C"
+            && body["transmission_sig"] == "valid-signature"
+            && body["webhook_event"]["id"].is_string();
+        let status = if valid { "SUCCESS" } else { "FAILURE" };
+        Ok(Json(json!({ "verification_status": status })))
+    }
+
+    async fn stub_provider() -> (PayPalProvider, Arc<StubPayPal>) {
+        let stub = Arc::new(StubPayPal::default());
+        let router = Router::new()
+            .route("/v1/oauth2/token", post(token))
+            .route("/v2/checkout/orders", post(create_order))
+            .route("/v2/checkout/orders/{id}", get(get_order))
+            .route("/v2/checkout/orders/{id}/capture", post(capture_order))
+            .route("/v2/payments/captures/{id}/refund", post(refund_capture))
+            .route(
+                "/v1/notifications/verify-webhook-signature",
+                post(verify_signature),
+            )
+            .with_state(stub.clone());
+        let api_url = spawn_stub_server(router).await;
+
+        let provider = PayPalProvider::new(PayPalConfig {
+            api_url,
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            return_url: "https://example.com/checkout/complete".to_string(),
+            cancel_url: "https://example.com/checkout/cancelled".to_string(),
+            webhook_id: Some("WH-1TU26016ED4963457-73K03948VJ187920C".to_string()),
+        });
+        (provider, stub)
+    }
+
+    #[tokio::test]
+    async fn test_paypal_payment_flow() {
+        let (provider, stub) = stub_provider().await;
+
+        let request = CreateIntentRequest {
+            order_id: "ord_20250812_0001".to_string(),
+            amount: Money::from_minor(3798, "USD"),
+            description: Some("Print order ord_20250812_0001".to_string()),
+            request_id: "order-ord_20250812_0001".to_string(),
+        };
+        let intent = provider.create_intent(&request).await.unwrap();
+        assert_eq!(intent.status, PaymentStatus::Pending);
+        // Retrying the same checkout doesn't open a second PayPal order
+        let retried = provider.create_intent(&request).await.unwrap();
+        assert_eq!(retried.intent_id, intent.intent_id);
+        assert_eq!(stub.orders.lock().unwrap().len(), 1);
+        assert_eq!(
+            intent.approval_url.as_deref(),
+            Some(
+                format!(
+                    "https://www.sandbox.paypal.com/checkoutnow?token={}",
+                    intent.intent_id
+                )
+                .as_str()
+            )
+        );
+        // The amount PayPal was asked for is the server-side total
+        assert_eq!(
+            stub.orders.lock().unwrap()[&intent.intent_id].1,
+            json!({ "currency_code": "USD", "value": "37.98" })
+        );
+
+        let err = provider.capture(&intent.intent_id).await.unwrap_err();
+        assert!(
+            matches!(err, PaymentError::InvalidState(ref msg) if msg.contains("ORDER_NOT_APPROVED"))
+        );
+
+        // The customer approves on PayPal
+        stub.orders
+            .lock()
+            .unwrap()
+            .get_mut(&intent.intent_id)
+            .unwrap()
+            .0 = "APPROVED".to_string();
+        assert_eq!(
+            provider.status(&intent.intent_id).await.unwrap(),
+            PaymentStatus::Approved
+        );
+
+        let capture = provider.capture(&intent.intent_id).await.unwrap();
+        assert_eq!(capture.status, PaymentStatus::Completed);
+        assert_eq!(capture.amount, Money::from_minor(3798, "USD"));
+        assert_eq!(
+            provider.status(&intent.intent_id).await.unwrap(),
+            PaymentStatus::Completed
+        );
+
+        let five_dollars = Money::from_minor(500, "USD");
+        let refund = provider
+            .refund(&capture.capture_id, Some(&five_dollars), "refund-1")
+            .await
+            .unwrap();
+        assert_eq!(refund.amount, five_dollars);
+        // A retry whose first response was lost doesn't refund again
+        let retried = provider
+            .refund(&capture.capture_id, Some(&five_dollars), "refund-1")
+            .await
+            .unwrap();
+        assert_eq!(retried, refund);
+        assert_eq!(stub.refunds.load(Ordering::SeqCst), 1);
+        let refund = provider
+            .refund(&capture.capture_id, None, "refund-2")
+            .await
+            .unwrap();
+        assert_eq!(refund.amount, Money::from_minor(3798, "USD"));
+        assert_eq!(stub.refunds.load(Ordering::SeqCst), 2);
+
+        assert!(matches!(
+            provider.status("missing").await,
+            Err(PaymentError::NotFound(_))
+        ));
+        // The access token is fetched once and reused
+        assert_eq!(stub.token_requests.load(Ordering::SeqCst), 1);
+    }
+
+    fn webhook_headers(signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            ("PayPal-Auth-Algo", "SHA256withRSA"),
+            (
+                "PayPal-Cert-Url",
+                "https://api.sandbox.paypal.com/v1/notifications/certs/CERT-360caa42",
+            ),
+            (
+                "PayPal-Transmission-Id",
+                "69cd13f0-d67a-11e5-baa3-778b53f4ae55",
+            ),
+            ("PayPal-Transmission-Sig", signature),
+            ("PayPal-Transmission-Time", "2025-08-12T03:15:00Z"),
+        ] {
+            headers.insert(name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_paypal_webhook_events() {
+        let (provider, _) = stub_provider().await;
+
+        let completed = json!({
+            "id": "WH-2WR32451HC0233532-67976317FL4543714",
+            "event_type": "PAYMENT.CAPTURE.COMPLETED",
+            "resource": {
+                "id": "CAP-5O190127",
+                "status": "COMPLETED",
+                "amount": { "currency_code": "USD", "value": "37.98" },
+                "supplementary_data": { "related_ids": { "order_id": "5O190127" } }
+            }
+        })
+        .to_string();
+        let event = provider
+            .verify_webhook(&webhook_headers("valid-signature"), completed.as_bytes())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.event_id, "WH-2WR32451HC0233532-67976317FL4543714");
+        assert_eq!(event.intent_id.as_deref(), Some("5O190127"));
+        assert_eq!(event.capture_id.as_deref(), Some("CAP-5O190127"));
+        assert_eq!(event.action, WebhookAction::Paid);
+
+        assert!(matches!(
+            provider
+                .verify_webhook(&webhook_headers("forged"), completed.as_bytes())
+                .await,
+            Err(PaymentError::Signature(_))
+        ));
+
+        let refunded = json!({
+            "id": "WH-1GE84257G0350133W-6RW800890C634293G",
+            "event_type": "PAYMENT.CAPTURE.REFUNDED",
+            "resource": {
+                "id": "REF-1",
+                "status": "COMPLETED",
+                "amount": { "currency_code": "USD", "value": "5.00" },
+                "seller_payable_breakdown": {
+                    "total_refunded_amount": { "currency_code": "USD", "value": "15.00" }
+                },
+                "links": [
+                    { "href": "https://api.paypal.com/v2/payments/refunds/REF-1", "rel": "self" },
+                    { "href": "https://api.paypal.com/v2/payments/captures/CAP-5O190127", "rel": "up" }
+                ]
+            }
+        })
+        .to_string();
+        let event = provider
+            .verify_webhook(&webhook_headers("valid-signature"), refunded.as_bytes())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.capture_id.as_deref(), Some("CAP-5O190127"));
+        assert_eq!(
+            event.action,
+            WebhookAction::Refunded {
+                total_refunded: Money::from_minor(1500, "USD")
+            }
+        );
+
+        // Requests without PayPal's headers are someone else's
+        assert!(
+            provider
+                .verify_webhook(&HeaderMap::new(), completed.as_bytes())
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
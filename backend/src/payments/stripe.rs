@@ -0,0 +1,760 @@
+//! Stripe PaymentIntents integration for card payments
+//!
+//! We create the PaymentIntent for the order total and hand its client
+//! secret to the frontend, which confirms the card payment with Stripe.js.
+//! Intents use automatic capture, so "capturing" mostly means checking with
+//! Stripe that the customer's confirmation went through; manual-capture
+//! intents are captured explicitly.
+//!
+//! Webhook deliveries are signed with the endpoint's signing secret
+//! (HMAC-SHA256 over `{timestamp}.{body}` in the `Stripe-Signature` header).
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, de::DeserializeOwned};
+use sha2::Sha256;
+
+use super::{
+    CreateIntentRequest, PaymentCapture, PaymentError, PaymentIntent, PaymentProvider,
+    PaymentRefund, PaymentStatus, WebhookAction, WebhookEvent,
+};
+use crate::money::Money;
+
+/// Stripe API base URL
+pub const DEFAULT_API_URL: &str = "https://api.stripe.com";
+
+/// Oldest webhook signature we accept, in seconds, as in Stripe's own libraries
+const SIGNATURE_TOLERANCE_SECS: i64 = 300;
+
+/// Stripe API settings
+#[derive(Debug, Clone)]
+pub struct StripeConfig {
+    pub api_url: String,
+    /// Secret API key (`sk_live_...` or `sk_test_...`)
+    pub secret_key: String,
+    /// Webhook signing secret (`whsec_...`); webhooks are rejected without it
+    pub webhook_secret: Option<String>,
+}
+
+/// Payment provider backed by Stripe PaymentIntents
+#[derive(Debug)]
+pub struct StripeProvider {
+    config: StripeConfig,
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct StripePaymentIntent {
+    id: String,
+    status: String,
+    amount: i64,
+    #[serde(default)]
+    amount_received: i64,
+    currency: String,
+    client_secret: Option<String>,
+    latest_charge: Option<LatestCharge>,
+}
+
+/// `latest_charge` is an ID unless the request expanded it
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum LatestCharge {
+    Id(String),
+    Expanded { id: String, refunded: bool },
+}
+
+impl LatestCharge {
+    fn id(&self) -> &str {
+        match self {
+            LatestCharge::Id(id) | LatestCharge::Expanded { id, .. } => id,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct StripeRefund {
+    id: String,
+    status: String,
+    amount: i64,
+    currency: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct StripeEvent {
+    id: String,
+    #[serde(rename = "type")]
+    event_type: String,
+    data: StripeEventData,
+}
+
+#[derive(Debug, Deserialize)]
+struct StripeEventData {
+    object: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventCharge {
+    id: String,
+    payment_intent: Option<String>,
+    amount_refunded: i64,
+    currency: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventDispute {
+    charge: String,
+    payment_intent: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: StripeErrorBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct StripeErrorBody {
+    code: Option<String>,
+    message: Option<String>,
+}
+
+/// Convert an amount in Stripe's minor units and lowercase currency
+fn stripe_money(amount: i64, currency: &str) -> Money {
+    Money::from_minor(amount, &currency.to_uppercase())
+}
+
+/// Map a PaymentIntent status to ours
+fn intent_status(status: &str) -> PaymentStatus {
+    match status {
+        "requires_capture" => PaymentStatus::Approved,
+        "succeeded" => PaymentStatus::Completed,
+        "canceled" => PaymentStatus::Cancelled,
+        // requires_payment_method, requires_confirmation, requires_action, processing
+        _ => PaymentStatus::Pending,
+    }
+}
+
+/// Check a `Stripe-Signature` header against the request body
+///
+/// The header looks like `t=1492774577,v1=5257a869...`, with one `v1` entry
+/// per active signing secret.
+fn verify_signature(secret: &str, header: &str, body: &[u8], now: i64) -> Result<(), PaymentError> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.push(value),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or_else(|| PaymentError::Signature("No timestamp".to_string()))?;
+    if (now - timestamp).abs() > SIGNATURE_TOLERANCE_SECS {
+        return Err(PaymentError::Signature(
+            "Timestamp outside the tolerance zone".to_string(),
+        ));
+    }
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|e| PaymentError::Config(e.to_string()))?;
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    let matches = signatures.iter().any(|signature| {
+        hex::decode(signature).is_ok_and(|signature| mac.clone().verify_slice(&signature).is_ok())
+    });
+    if !matches {
+        return Err(PaymentError::Signature(
+            "No signature matches the payload".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Turn a Stripe event into the action it calls for
+fn parse_event(body: &[u8]) -> Result<WebhookEvent, PaymentError> {
+    let event: StripeEvent =
+        serde_json::from_slice(body).map_err(|e| PaymentError::Parse(e.to_string()))?;
+    let object = event.data.object;
+    let parse_err = |e: serde_json::Error| PaymentError::Parse(e.to_string());
+
+    let (intent_id, capture_id, action) = match event.event_type.as_str() {
+        "payment_intent.succeeded" | "payment_intent.payment_failed" => {
+            let intent: StripePaymentIntent = serde_json::from_value(object).map_err(parse_err)?;
+            let action = if event.event_type == "payment_intent.succeeded" {
+                WebhookAction::Paid
+            } else {
+                WebhookAction::Failed
+            };
+            let charge = intent.latest_charge.map(|charge| charge.id().to_string());
+            (Some(intent.id), charge, action)
+        }
+        "charge.refunded" => {
+            let charge: EventCharge = serde_json::from_value(object).map_err(parse_err)?;
+            let total_refunded = stripe_money(charge.amount_refunded, &charge.currency);
+            (
+                charge.payment_intent,
+                Some(charge.id),
+                WebhookAction::Refunded { total_refunded },
+            )
+        }
+        "charge.dispute.created" => {
+            let dispute: EventDispute = serde_json::from_value(object).map_err(parse_err)?;
+            (
+                dispute.payment_intent,
+                Some(dispute.charge),
+                WebhookAction::Disputed,
+            )
+        }
+        _ => (None, None, WebhookAction::Ignored),
+    };
+    Ok(WebhookEvent {
+        event_id: event.id,
+        event_type: event.event_type,
+        intent_id,
+        capture_id,
+        action,
+    })
+}
+
+impl StripeProvider {
+    pub fn new(config: StripeConfig) -> Self {
+        StripeProvider {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Send a form-encoded POST with an idempotency key
+    async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        idempotency_key: &str,
+        form: &[(&str, String)],
+    ) -> Result<T, PaymentError> {
+        let response = self
+            .client
+            .post(format!("{}{}", self.config.api_url, path))
+            .bearer_auth(&self.config.secret_key)
+            .header("Idempotency-Key", idempotency_key)
+            .form(form)
+            .send()
+            .await?;
+        Self::parse_response(response).await
+    }
+
+    async fn retrieve(
+        &self,
+        intent_id: &str,
+        expand_charge: bool,
+    ) -> Result<StripePaymentIntent, PaymentError> {
+        let mut request = self
+            .client
+            .get(format!(
+                "{}/v1/payment_intents/{}",
+                self.config.api_url, intent_id
+            ))
+            .bearer_auth(&self.config.secret_key);
+        if expand_charge {
+            request = request.query(&[("expand[]", "latest_charge")]);
+        }
+        Self::parse_response(request.send().await?).await
+    }
+
+    /// Decode a successful response, or turn a Stripe error body into a `PaymentError`
+    async fn parse_response<T: DeserializeOwned>(
+        response: reqwest::Response,
+    ) -> Result<T, PaymentError> {
+        let status = response.status();
+        let text = response.text().await?;
+        if status.is_success() {
+            return serde_json::from_str(&text).map_err(|e| PaymentError::Parse(e.to_string()));
+        }
+
+        let error = serde_json::from_str::<ErrorResponse>(&text)
+            .ok()
+            .map(|e| e.error);
+        let code = error.as_ref().and_then(|e| e.code.clone());
+        let message = error.and_then(|e| e.message).unwrap_or(text);
+        Err(match (status.as_u16(), code.as_deref()) {
+            (404, _) => PaymentError::NotFound(message),
+            (_, Some("payment_intent_unexpected_state" | "charge_already_refunded")) => {
+                PaymentError::InvalidState(message)
+            }
+            (status, _) => PaymentError::Api { status, message },
+        })
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for StripeProvider {
+    fn method(&self) -> &str {
+        "credit_card"
+    }
+
+    async fn create_intent(
+        &self,
+        request: &CreateIntentRequest,
+    ) -> Result<PaymentIntent, PaymentError> {
+        let mut form = vec![
+            ("amount", request.amount.amount_minor().to_string()),
+            ("currency", request.amount.currency().to_lowercase()),
+            ("automatic_payment_methods[enabled]", "true".to_string()),
+            ("metadata[order_id]", request.order_id.clone()),
+        ];
+        if let Some(description) = &request.description {
+            form.push(("description", description.clone()));
+        }
+        let intent: StripePaymentIntent = self
+            .post("/v1/payment_intents", &request.request_id, &form)
+            .await?;
+
+        Ok(PaymentIntent {
+            status: intent_status(&intent.status),
+            amount: stripe_money(intent.amount, &intent.currency),
+            approval_url: None,
+            client_secret: intent.client_secret,
+            intent_id: intent.id,
+        })
+    }
+
+    async fn capture(&self, intent_id: &str) -> Result<PaymentCapture, PaymentError> {
+        let mut intent = self.retrieve(intent_id, false).await?;
+        if intent.status == "requires_capture" {
+            // A fixed key makes retried captures return the first result
+            intent = self
+                .post(
+                    &format!("/v1/payment_intents/{}/capture", intent_id),
+                    &format!("capture-{}", intent_id),
+                    &[],
+                )
+                .await?;
+        }
+
+        let status = match intent.status.as_str() {
+            "succeeded" => PaymentStatus::Completed,
+            "processing" => PaymentStatus::Pending,
+            other => {
+                return Err(PaymentError::InvalidState(format!(
+                    "Payment {} has not been confirmed ({})",
+                    intent_id, other
+                )));
+            }
+        };
+        let charge = intent
+            .latest_charge
+            .as_ref()
+            .ok_or_else(|| PaymentError::Parse(format!("No charge on payment {}", intent_id)))?;
+        let amount = if intent.amount_received > 0 {
+            intent.amount_received
+        } else {
+            intent.amount
+        };
+        Ok(PaymentCapture {
+            capture_id: charge.id().to_string(),
+            status,
+            amount: stripe_money(amount, &intent.currency),
+        })
+    }
+
+    async fn refund(
+        &self,
+        capture_id: &str,
+        amount: Option<&Money>,
+        request_id: &str,
+    ) -> Result<PaymentRefund, PaymentError> {
+        let mut form = vec![("charge", capture_id.to_string())];
+        if let Some(amount) = amount {
+            form.push(("amount", amount.amount_minor().to_string()));
+        }
+        let refund: StripeRefund = self.post("/v1/refunds", request_id, &form).await?;
+
+        let status = match refund.status.as_str() {
+            "succeeded" => PaymentStatus::Completed,
+            "failed" => PaymentStatus::Failed,
+            "canceled" => PaymentStatus::Cancelled,
+            _ => PaymentStatus::Pending,
+        };
+        Ok(PaymentRefund {
+            refund_id: refund.id,
+            status,
+            amount: stripe_money(refund.amount, &refund.currency),
+        })
+    }
+
+    async fn status(&self, intent_id: &str) -> Result<PaymentStatus, PaymentError> {
+        let intent = self.retrieve(intent_id, true).await?;
+        Ok(
+            match (intent_status(&intent.status), intent.latest_charge) {
+                (PaymentStatus::Completed, Some(LatestCharge::Expanded { refunded: true, .. })) => {
+                    PaymentStatus::Refunded
+                }
+                (status, _) => status,
+            },
+        )
+    }
+
+    async fn verify_webhook(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<Option<WebhookEvent>, PaymentError> {
+        let Some(header) = headers.get("Stripe-Signature") else {
+            return Ok(None);
+        };
+        let secret =
+            self.config.webhook_secret.as_deref().ok_or_else(|| {
+                PaymentError::Config("STRIPE_WEBHOOK_SECRET is not set".to_string())
+            })?;
+        let header = header
+            .to_str()
+            .map_err(|_| PaymentError::Signature("Malformed Stripe-Signature".to_string()))?;
+        verify_signature(secret, header, body, chrono::Utc::now().timestamp())?;
+        parse_event(body).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::spawn_stub_server;
+    use axum::{
+        Form, Json, Router,
+        extract::{Path, State},
+        http::{HeaderMap, StatusCode},
+        routing::{get, post},
+    };
+    use serde_json::{Value, json};
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    /// Stand-in for the Stripe API, holding intent ID -> PaymentIntent JSON
+    #[derive(Debug, Default)]
+    struct StubStripe {
+        intents: Mutex<HashMap<String, Value>>,
+        created_with: Mutex<Option<HashMap<String, String>>>,
+    }
+
+    type Stub = State<Arc<StubStripe>>;
+    type StubResult = Result<Json<Value>, (StatusCode, Json<Value>)>;
+
+    fn stripe_error(status: StatusCode, code: &str) -> (StatusCode, Json<Value>) {
+        (
+            status,
+            Json(json!({
+                "error": { "type": "invalid_request_error", "code": code, "message": code }
+            })),
+        )
+    }
+
+    fn authorized(headers: &HeaderMap) -> Result<(), (StatusCode, Json<Value>)> {
+        match headers.get("authorization").and_then(|v| v.to_str().ok()) {
+            Some("Bearer sk_test_123") => Ok(()),
+            _ => Err(stripe_error(StatusCode::UNAUTHORIZED, "api_key_invalid")),
+        }
+    }
+
+    async fn create_intent(
+        State(stub): Stub,
+        headers: HeaderMap,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> StubResult {
+        authorized(&headers)?;
+        let mut intents = stub.intents.lock().unwrap();
+        let id = format!("pi_3Mtw{}", intents.len());
+        let intent = json!({
+            "id": id,
+            "object": "payment_intent",
+            "amount": form["amount"].parse::<i64>().unwrap(),
+            "amount_received": 0,
+            "currency": form["currency"],
+            "status": "requires_payment_method",
+            "client_secret": format!("{}_secret_YrKJUKribcBjcG8HVhfZluoGH", id),
+            "latest_charge": null,
+        });
+        intents.insert(id, intent.clone());
+        *stub.created_with.lock().unwrap() = Some(form);
+        Ok(Json(intent))
+    }
+
+    async fn get_intent(
+        State(stub): Stub,
+        headers: HeaderMap,
+        Path(id): Path<String>,
+    ) -> StubResult {
+        authorized(&headers)?;
+        let intents = stub.intents.lock().unwrap();
+        let intent = intents
+            .get(&id)
+            .ok_or_else(|| stripe_error(StatusCode::NOT_FOUND, "resource_missing"))?;
+        let mut intent = intent.clone();
+        // Every test request expands the charge
+        if let Some(charge) = intent["latest_charge"].as_str() {
+            intent["latest_charge"] =
+                json!({ "id": charge, "refunded": charge.ends_with("_refunded") });
+        }
+        Ok(Json(intent))
+    }
+
+    async fn capture_intent(
+        State(stub): Stub,
+        headers: HeaderMap,
+        Path(id): Path<String>,
+    ) -> StubResult {
+        authorized(&headers)?;
+        let mut intents = stub.intents.lock().unwrap();
+        let intent = intents
+            .get_mut(&id)
+            .ok_or_else(|| stripe_error(StatusCode::NOT_FOUND, "resource_missing"))?;
+        if intent["status"] != "requires_capture" {
+            return Err(stripe_error(
+                StatusCode::BAD_REQUEST,
+                "payment_intent_unexpected_state",
+            ));
+        }
+        intent["status"] = json!("succeeded");
+        intent["amount_received"] = intent["amount"].clone();
+        Ok(Json(intent.clone()))
+    }
+
+    async fn create_refund(
+        headers: HeaderMap,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> StubResult {
+        authorized(&headers)?;
+        let amount = form
+            .get("amount")
+            .map_or(3798, |a| a.parse::<i64>().unwrap());
+        Ok(Json(json!({
+            "id": format!("re_{}", form["charge"]),
+            "object": "refund",
+            "amount": amount,
+            "currency": "usd",
+            "status": "succeeded",
+        })))
+    }
+
+    async fn stub_provider() -> (StripeProvider, Arc<StubStripe>) {
+        let stub = Arc::new(StubStripe::default());
+        let router = Router::new()
+            .route("/v1/payment_intents", post(create_intent))
+            .route("/v1/payment_intents/{id}", get(get_intent))
+            .route("/v1/payment_intents/{id}/capture", post(capture_intent))
+            .route("/v1/refunds", post(create_refund))
+            .with_state(stub.clone());
+        let provider = StripeProvider::new(StripeConfig {
+            api_url: spawn_stub_server(router).await,
+            secret_key: "sk_test_123".to_string(),
+            webhook_secret: Some("whsec_test".to_string()),
+        });
+        (provider, stub)
+    }
+
+    /// Act as Stripe.js confirming the card payment
+    fn confirm(stub: &StubStripe, intent_id: &str, status: &str) {
+        let mut intents = stub.intents.lock().unwrap();
+        let intent = intents.get_mut(intent_id).unwrap();
+        intent["status"] = json!(status);
+        intent["latest_charge"] = json!("ch_3MtwBwLkdIwHu7ix");
+        if status == "succeeded" {
+            intent["amount_received"] = intent["amount"].clone();
+        }
+    }
+
+    fn order_request() -> CreateIntentRequest {
+        CreateIntentRequest {
+            order_id: "ord_20250812_0001".to_string(),
+            amount: Money::from_minor(3798, "USD"),
+            description: Some("Print order ord_20250812_0001".to_string()),
+            request_id: "order-ord_20250812_0001".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stripe_payment_flow() {
+        let (provider, stub) = stub_provider().await;
+
+        let intent = provider.create_intent(&order_request()).await.unwrap();
+        assert_eq!(intent.status, PaymentStatus::Pending);
+        assert_eq!(intent.amount, Money::from_minor(3798, "USD"));
+        assert!(
+            intent
+                .client_secret
+                .as_deref()
+                .unwrap()
+                .starts_with(&intent.intent_id)
+        );
+        let form = stub.created_with.lock().unwrap().clone().unwrap();
+        assert_eq!(form["amount"], "3798");
+        assert_eq!(form["currency"], "usd");
+        assert_eq!(form["metadata[order_id]"], "ord_20250812_0001");
+
+        // Nothing is paid until the customer confirms
+        assert!(matches!(
+            provider.capture(&intent.intent_id).await,
+            Err(PaymentError::InvalidState(_))
+        ));
+
+        confirm(&stub, &intent.intent_id, "succeeded");
+        let capture = provider.capture(&intent.intent_id).await.unwrap();
+        assert_eq!(capture.status, PaymentStatus::Completed);
+        assert_eq!(capture.capture_id, "ch_3MtwBwLkdIwHu7ix");
+        assert_eq!(capture.amount, Money::from_minor(3798, "USD"));
+        assert_eq!(
+            provider.status(&intent.intent_id).await.unwrap(),
+            PaymentStatus::Completed
+        );
+
+        let refund = provider
+            .refund(
+                &capture.capture_id,
+                Some(&Money::from_minor(500, "USD")),
+                "refund-1",
+            )
+            .await
+            .unwrap();
+        assert_eq!(refund.amount, Money::from_minor(500, "USD"));
+        assert_eq!(refund.status, PaymentStatus::Completed);
+
+        stub.intents
+            .lock()
+            .unwrap()
+            .get_mut(&intent.intent_id)
+            .unwrap()["latest_charge"] = json!("ch_3MtwBwLkdIwHu7ix_refunded");
+        assert_eq!(
+            provider.status(&intent.intent_id).await.unwrap(),
+            PaymentStatus::Refunded
+        );
+        assert!(matches!(
+            provider.status("pi_missing").await,
+            Err(PaymentError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stripe_capture_states() {
+        let (provider, stub) = stub_provider().await;
+
+        // Manual-capture intents are captured once confirmed
+        let intent = provider.create_intent(&order_request()).await.unwrap();
+        confirm(&stub, &intent.intent_id, "requires_capture");
+        assert_eq!(
+            provider.status(&intent.intent_id).await.unwrap(),
+            PaymentStatus::Approved
+        );
+        let capture = provider.capture(&intent.intent_id).await.unwrap();
+        assert_eq!(capture.status, PaymentStatus::Completed);
+        assert_eq!(
+            stub.intents.lock().unwrap()[&intent.intent_id]["status"],
+            "succeeded"
+        );
+
+        // Some payment methods take a while to settle
+        let intent = provider.create_intent(&order_request()).await.unwrap();
+        confirm(&stub, &intent.intent_id, "processing");
+        let capture = provider.capture(&intent.intent_id).await.unwrap();
+        assert_eq!(capture.status, PaymentStatus::Pending);
+    }
+
+    fn sign(body: &str, timestamp: i64) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"whsec_test").unwrap();
+        mac.update(format!("{}.{}", timestamp, body).as_bytes());
+        format!(
+            "t={},v1={}",
+            timestamp,
+            hex::encode(mac.finalize().into_bytes())
+        )
+    }
+
+    #[test]
+    fn test_stripe_webhook_signature() {
+        let body = r#"{"id":"evt_1","type":"payment_intent.succeeded"}"#;
+        let now = 1_757_000_000;
+        let header = sign(body, now);
+        assert!(verify_signature("whsec_test", &header, body.as_bytes(), now + 10).is_ok());
+        // A second secret during rotation adds another v1 entry
+        let rotated = format!("{},v1=deadbeef", header);
+        assert!(verify_signature("whsec_test", &rotated, body.as_bytes(), now).is_ok());
+
+        let tampered = body.replace("evt_1", "evt_2");
+        assert!(matches!(
+            verify_signature("whsec_test", &header, tampered.as_bytes(), now),
+            Err(PaymentError::Signature(_))
+        ));
+        assert!(verify_signature("whsec_other", &header, body.as_bytes(), now).is_err());
+        // Replays of old deliveries are rejected
+        assert!(verify_signature("whsec_test", &header, body.as_bytes(), now + 301).is_err());
+        assert!(verify_signature("whsec_test", "v1=abc", body.as_bytes(), now).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stripe_webhook_events() {
+        let (provider, _) = stub_provider().await;
+        let verify = |body: Value| {
+            let body = body.to_string();
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                "Stripe-Signature",
+                sign(&body, chrono::Utc::now().timestamp()).parse().unwrap(),
+            );
+            let provider = &provider;
+            async move { provider.verify_webhook(&headers, body.as_bytes()).await }
+        };
+
+        let event = verify(json!({
+            "id": "evt_1",
+            "type": "payment_intent.succeeded",
+            "data": { "object": {
+                "id": "pi_3Mtw0", "object": "payment_intent", "status": "succeeded",
+                "amount": 3798, "amount_received": 3798, "currency": "usd",
+                "client_secret": "pi_3Mtw0_secret", "latest_charge": "ch_3MtwBwLkdIwHu7ix"
+            } }
+        }))
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(event.event_id, "evt_1");
+        assert_eq!(event.intent_id.as_deref(), Some("pi_3Mtw0"));
+        assert_eq!(event.capture_id.as_deref(), Some("ch_3MtwBwLkdIwHu7ix"));
+        assert_eq!(event.action, WebhookAction::Paid);
+
+        let event = verify(json!({
+            "id": "evt_2",
+            "type": "charge.refunded",
+            "data": { "object": {
+                "id": "ch_3MtwBwLkdIwHu7ix", "object": "charge", "payment_intent": "pi_3Mtw0",
+                "amount": 3798, "amount_refunded": 500, "currency": "usd", "refunded": false
+            } }
+        }))
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            event.action,
+            WebhookAction::Refunded {
+                total_refunded: Money::from_minor(500, "USD")
+            }
+        );
+
+        let event = verify(json!({
+            "id": "evt_3",
+            "type": "customer.created",
+            "data": { "object": { "id": "cus_1" } }
+        }))
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(event.action, WebhookAction::Ignored);
+
+        // Requests without a Stripe signature are someone else's
+        assert!(
+            provider
+                .verify_webhook(&HeaderMap::new(), b"{}")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
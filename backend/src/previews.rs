@@ -0,0 +1,137 @@
+//! Thumbnails and print crop previews for uploaded images
+//!
+//! Derivatives are rendered in the background so uploads and order creation
+//! don't wait on image decoding. They're stored as JPEGs next to the
+//! original (`uploads/<user_id>/<image_id>/thumb.jpg`,
+//! `.../preview_<size_id>.jpg`). HEIC can't be decoded, so HEIC images
+//! stored before those uploads were rejected get none.
+
+use chrono::Utc;
+
+use crate::{
+    AppState,
+    imaging::{ImagingError, decode_upright, render_crop_preview, render_thumbnail},
+    models::{
+        image::{DerivativeKind, ImageDerivative, StoredImage},
+        print_catalog::PrintSize,
+    },
+};
+
+/// Storage key for a derivative, in the same folder as the original
+pub fn derivative_key(original_key: &str, kind: DerivativeKind, size_id: Option<&str>) -> String {
+    let folder = original_key
+        .rsplit_once('/')
+        .map_or("", |(folder, _)| folder);
+    let name = match kind {
+        DerivativeKind::Thumbnail => "thumb".to_string(),
+        DerivativeKind::Preview => {
+            // Size IDs are admin-defined, so keep them safe as a file name
+            let size: String = size_id
+                .unwrap_or_default()
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            format!("preview_{}", size)
+        }
+    };
+    if folder.is_empty() {
+        format!("{}.jpg", name)
+    } else {
+        format!("{}/{}.jpg", folder, name)
+    }
+}
+
+/// Render, store and record a thumbnail and/or crop previews for `sizes`
+///
+/// Images in formats that can't be decoded are skipped without error.
+pub async fn render_derivatives(
+    state: &AppState,
+    image: &StoredImage,
+    thumbnail: bool,
+    sizes: &[PrintSize],
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let original = state.storage.get(&image.storage_key).await?;
+
+    let content_type = image.content_type.clone();
+    let targets: Vec<(String, f32, f32)> = sizes
+        .iter()
+        .map(|size| (size.size_id.clone(), size.width_in, size.height_in))
+        .collect();
+    let rendered = tokio::task::spawn_blocking(move || {
+        let decoded = decode_upright(&original, &content_type)?;
+        let mut rendered = Vec::new();
+        if thumbnail {
+            rendered.push((DerivativeKind::Thumbnail, None, render_thumbnail(&decoded)?));
+        }
+        for (size_id, width_in, height_in) in targets {
+            let preview = render_crop_preview(&decoded, width_in, height_in)?;
+            rendered.push((DerivativeKind::Preview, Some(size_id), preview));
+        }
+        Ok::<_, ImagingError>(rendered)
+    })
+    .await?;
+
+    let rendered = match rendered {
+        Ok(rendered) => rendered,
+        Err(ImagingError::UnsupportedFormat(format)) => {
+            tracing::debug!("No previews for {} ({})", image.image_id, format);
+            return Ok(());
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    for (kind, size_id, output) in rendered {
+        let derivative = ImageDerivative {
+            image_id: image.image_id.clone(),
+            kind,
+            storage_key: derivative_key(&image.storage_key, kind, size_id.as_deref()),
+            size_id,
+            width_px: i32::try_from(output.dimensions.width)?,
+            height_px: i32::try_from(output.dimensions.height)?,
+            created_at: Utc::now(),
+        };
+        state
+            .storage
+            .put(&derivative.storage_key, output.data, "image/jpeg")
+            .await?;
+        state.images.save_derivative(&derivative).await?;
+    }
+    Ok(())
+}
+
+/// Run `render_derivatives` in the background, logging any failure
+pub fn spawn_render(state: AppState, image: StoredImage, thumbnail: bool, sizes: Vec<PrintSize>) {
+    tokio::spawn(async move {
+        if let Err(err) = render_derivatives(&state, &image, thumbnail, &sizes).await {
+            tracing::warn!("Failed to render previews for {}: {}", image.image_id, err);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derivative_key() {
+        let original = "uploads/user/img_1/original.jpg";
+        assert_eq!(
+            derivative_key(original, DerivativeKind::Thumbnail, None),
+            "uploads/user/img_1/thumb.jpg"
+        );
+        assert_eq!(
+            derivative_key(original, DerivativeKind::Preview, Some("8x10")),
+            "uploads/user/img_1/preview_8x10.jpg"
+        );
+        assert_eq!(
+            derivative_key(original, DerivativeKind::Preview, Some("../a b")),
+            "uploads/user/img_1/preview____a_b.jpg"
+        );
+    }
+}
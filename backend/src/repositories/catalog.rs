@@ -0,0 +1,313 @@
+//! Print size and finish catalog storage
+
+use crate::{
+    models::print_catalog::{PrintFinish, PrintSize},
+    money::Money,
+    repositories::RepositoryError,
+};
+use async_trait::async_trait;
+use sqlx::postgres::PgPool;
+
+/// Columns selected for a `PrintSize`
+const SIZE_COLUMNS: &str = "id AS size_id, description, price, currency, width_in, height_in, \
+     weight_lbs, production_days, hs_code, active, sort_order, created_at, updated_at";
+
+/// Columns selected for a `PrintFinish`
+const FINISH_COLUMNS: &str = "id AS finish_id, description, premium, currency, active, sort_order";
+
+/// A print size to add to the catalog
+#[derive(Debug, Clone)]
+pub struct NewPrintSize {
+    pub size_id: String,
+    pub description: String,
+    pub price: Money,
+    pub width_in: f32,
+    pub height_in: f32,
+    pub weight_lbs: f32,
+    pub production_days: i32,
+    pub hs_code: String,
+    pub active: bool,
+    pub sort_order: i32,
+}
+
+/// Changes to an existing print size; `None` fields are left untouched
+#[derive(Debug, Clone, Default)]
+pub struct PrintSizeUpdate {
+    pub description: Option<String>,
+    pub price: Option<Money>,
+    pub width_in: Option<f32>,
+    pub height_in: Option<f32>,
+    pub weight_lbs: Option<f32>,
+    pub production_days: Option<i32>,
+    pub hs_code: Option<String>,
+    pub active: Option<bool>,
+    pub sort_order: Option<i32>,
+}
+
+/// Storage operations for the print catalog
+#[async_trait]
+pub trait CatalogRepository: Send + Sync + std::fmt::Debug {
+    /// List print sizes in display order, optionally including inactive ones
+    async fn list_sizes(&self, include_inactive: bool) -> Result<Vec<PrintSize>, RepositoryError>;
+
+    /// Look up a print size by ID, whether active or not
+    async fn find_size(&self, size_id: &str) -> Result<Option<PrintSize>, RepositoryError>;
+
+    /// Add a print size; fails with `Conflict` if the ID is taken
+    async fn create_size(&self, size: &NewPrintSize) -> Result<PrintSize, RepositoryError>;
+
+    /// Apply changes to a print size, returning `None` if it doesn't exist
+    async fn update_size(
+        &self,
+        size_id: &str,
+        update: &PrintSizeUpdate,
+    ) -> Result<Option<PrintSize>, RepositoryError>;
+
+    /// Remove a print size, returning whether it existed
+    async fn delete_size(&self, size_id: &str) -> Result<bool, RepositoryError>;
+
+    /// List finishes in display order, optionally including inactive ones
+    async fn list_finishes(
+        &self,
+        include_inactive: bool,
+    ) -> Result<Vec<PrintFinish>, RepositoryError>;
+
+    /// Look up a finish by ID, whether active or not
+    async fn find_finish(&self, finish_id: &str) -> Result<Option<PrintFinish>, RepositoryError>;
+}
+
+/// PostgreSQL-backed catalog repository
+#[derive(Debug, Clone)]
+pub struct PgCatalogRepository {
+    pool: PgPool,
+}
+
+impl PgCatalogRepository {
+    pub fn new(pool: PgPool) -> Self {
+        PgCatalogRepository { pool }
+    }
+}
+
+#[async_trait]
+impl CatalogRepository for PgCatalogRepository {
+    async fn list_sizes(&self, include_inactive: bool) -> Result<Vec<PrintSize>, RepositoryError> {
+        let sizes = sqlx::query_as::<_, PrintSize>(&format!(
+            "SELECT {} FROM print_sizes WHERE active OR $1 ORDER BY sort_order, id",
+            SIZE_COLUMNS
+        ))
+        .bind(include_inactive)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(sizes)
+    }
+
+    async fn find_size(&self, size_id: &str) -> Result<Option<PrintSize>, RepositoryError> {
+        let size = sqlx::query_as::<_, PrintSize>(&format!(
+            "SELECT {} FROM print_sizes WHERE id = $1",
+            SIZE_COLUMNS
+        ))
+        .bind(size_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(size)
+    }
+
+    async fn create_size(&self, size: &NewPrintSize) -> Result<PrintSize, RepositoryError> {
+        let created = sqlx::query_as::<_, PrintSize>(&format!(
+            "INSERT INTO print_sizes
+                (id, description, price, currency, width_in, height_in, weight_lbs,
+                 production_days, hs_code, active, sort_order)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+             RETURNING {}",
+            SIZE_COLUMNS
+        ))
+        .bind(&size.size_id)
+        .bind(&size.description)
+        .bind(size.price.to_decimal())
+        .bind(size.price.currency())
+        .bind(size.width_in)
+        .bind(size.height_in)
+        .bind(size.weight_lbs)
+        .bind(size.production_days)
+        .bind(&size.hs_code)
+        .bind(size.active)
+        .bind(size.sort_order)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(created)
+    }
+
+    async fn update_size(
+        &self,
+        size_id: &str,
+        update: &PrintSizeUpdate,
+    ) -> Result<Option<PrintSize>, RepositoryError> {
+        let updated = sqlx::query_as::<_, PrintSize>(&format!(
+            "UPDATE print_sizes SET
+                description = COALESCE($2, description),
+                price = COALESCE($3, price),
+                currency = COALESCE($4, currency),
+                width_in = COALESCE($5, width_in),
+                height_in = COALESCE($6, height_in),
+                weight_lbs = COALESCE($7, weight_lbs),
+                production_days = COALESCE($8, production_days),
+                hs_code = COALESCE($9, hs_code),
+                active = COALESCE($10, active),
+                sort_order = COALESCE($11, sort_order),
+                updated_at = now()
+             WHERE id = $1
+             RETURNING {}",
+            SIZE_COLUMNS
+        ))
+        .bind(size_id)
+        .bind(&update.description)
+        .bind(update.price.as_ref().map(Money::to_decimal))
+        .bind(
+            update
+                .price
+                .as_ref()
+                .map(|price| price.currency().to_string()),
+        )
+        .bind(update.width_in)
+        .bind(update.height_in)
+        .bind(update.weight_lbs)
+        .bind(update.production_days)
+        .bind(&update.hs_code)
+        .bind(update.active)
+        .bind(update.sort_order)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(updated)
+    }
+
+    async fn delete_size(&self, size_id: &str) -> Result<bool, RepositoryError> {
+        // Order items keep their own copy of the size and price, so removing
+        // a size doesn't affect existing orders
+        let result = sqlx::query("DELETE FROM print_sizes WHERE id = $1")
+            .bind(size_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_finishes(
+        &self,
+        include_inactive: bool,
+    ) -> Result<Vec<PrintFinish>, RepositoryError> {
+        let finishes = sqlx::query_as::<_, PrintFinish>(&format!(
+            "SELECT {} FROM print_finishes WHERE active OR $1 ORDER BY sort_order, id",
+            FINISH_COLUMNS
+        ))
+        .bind(include_inactive)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(finishes)
+    }
+
+    async fn find_finish(&self, finish_id: &str) -> Result<Option<PrintFinish>, RepositoryError> {
+        let finish = sqlx::query_as::<_, PrintFinish>(&format!(
+            "SELECT {} FROM print_finishes WHERE id = $1",
+            FINISH_COLUMNS
+        ))
+        .bind(finish_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(finish)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_size(size_id: &str) -> NewPrintSize {
+        NewPrintSize {
+            size_id: size_id.to_string(),
+            description: "12\" x 18\" print".to_string(),
+            price: Money::from_minor(1200, "USD"),
+            width_in: 12.0,
+            height_in: 18.0,
+            weight_lbs: 0.15,
+            production_days: 3,
+            hs_code: "4911910000".to_string(),
+            active: true,
+            sort_order: 50,
+        }
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_seeded_catalog(pool: PgPool) {
+        let repo = PgCatalogRepository::new(pool);
+
+        let sizes = repo.list_sizes(false).await.expect("List should succeed");
+        let ids: Vec<&str> = sizes.iter().map(|s| s.size_id.as_str()).collect();
+        assert_eq!(ids, vec!["4x6", "5x7", "8x10", "11x14"]);
+        let days: Vec<i32> = sizes.iter().map(|s| s.production_days).collect();
+        assert_eq!(days, vec![1, 1, 2, 2]);
+        assert!(sizes.iter().all(|s| s.hs_code == "491191"));
+
+        let matte = repo
+            .find_finish("matte")
+            .await
+            .expect("Lookup should succeed")
+            .expect("Matte should be seeded");
+        assert_eq!(matte.premium, Money::from_minor(25, "USD"));
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_size_lifecycle(pool: PgPool) {
+        let repo = PgCatalogRepository::new(pool);
+
+        let created = repo
+            .create_size(&new_size("12x18"))
+            .await
+            .expect("Create should succeed");
+        assert_eq!(created.price, Money::from_minor(1200, "USD"));
+        assert!(matches!(
+            repo.create_size(&new_size("12x18")).await,
+            Err(RepositoryError::Conflict(_))
+        ));
+
+        let update = PrintSizeUpdate {
+            price: Some(Money::from_minor(1350, "USD")),
+            hs_code: Some("49119100".to_string()),
+            active: Some(false),
+            ..Default::default()
+        };
+        let updated = repo
+            .update_size("12x18", &update)
+            .await
+            .expect("Update should succeed")
+            .expect("Size should exist");
+        assert_eq!(updated.price, Money::from_minor(1350, "USD"));
+        assert_eq!(updated.width_in, 12.0);
+        assert_eq!(updated.production_days, 3);
+        assert_eq!(updated.hs_code, "49119100");
+        assert!(!updated.active);
+
+        let active = repo.list_sizes(false).await.expect("List should succeed");
+        assert!(active.iter().all(|s| s.size_id != "12x18"));
+        let all = repo.list_sizes(true).await.expect("List should succeed");
+        assert!(all.iter().any(|s| s.size_id == "12x18"));
+
+        assert!(
+            repo.delete_size("12x18")
+                .await
+                .expect("Delete should succeed")
+        );
+        assert!(
+            !repo
+                .delete_size("12x18")
+                .await
+                .expect("Delete should succeed")
+        );
+        assert!(
+            repo.update_size("12x18", &update)
+                .await
+                .expect("Update should succeed")
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! Idempotency key storage
+
+use crate::repositories::RepositoryError;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{FromRow, postgres::PgPool};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+/// Hours a key is remembered before it can be reused
+pub const KEY_TTL_HOURS: i32 = 24;
+
+/// Minutes a claim without a response is held before it's taken to be
+/// abandoned, e.g. by a server that crashed mid-request, and can be claimed
+/// again
+pub const CLAIM_TIMEOUT_MINUTES: i32 = 5;
+
+/// A stored idempotency key and the response it produced
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct IdempotencyRecord {
+    /// Hash of the first request made with the key
+    pub request_hash: String,
+    /// `None` while the first request is still being handled
+    pub response_status: Option<i32>,
+    pub response_body: Option<Vec<u8>>,
+}
+
+/// Storage operations for idempotency keys
+#[async_trait]
+pub trait IdempotencyRepository: Send + Sync + std::fmt::Debug {
+    /// Claim a key for a request
+    ///
+    /// Returns `None` if the key is new, expired or abandoned and now belongs
+    /// to this request, otherwise the stored record.
+    async fn claim(
+        &self,
+        user_id: &str,
+        key: &str,
+        request_hash: &str,
+    ) -> Result<Option<IdempotencyRecord>, RepositoryError>;
+
+    /// Store the response for a claimed key
+    async fn complete(
+        &self,
+        user_id: &str,
+        key: &str,
+        status: u16,
+        body: &[u8],
+        response_hash: &str,
+    ) -> Result<(), RepositoryError>;
+
+    /// Forget a claimed key so the request can be retried
+    async fn release(&self, user_id: &str, key: &str) -> Result<(), RepositoryError>;
+}
+
+/// PostgreSQL-backed idempotency key repository
+#[derive(Debug, Clone)]
+pub struct PgIdempotencyRepository {
+    pool: PgPool,
+}
+
+impl PgIdempotencyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        PgIdempotencyRepository { pool }
+    }
+}
+
+#[async_trait]
+impl IdempotencyRepository for PgIdempotencyRepository {
+    async fn claim(
+        &self,
+        user_id: &str,
+        key: &str,
+        request_hash: &str,
+    ) -> Result<Option<IdempotencyRecord>, RepositoryError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "DELETE FROM idempotency_keys
+             WHERE user_id = $1 AND key = $2
+               AND (created_at < now() - make_interval(hours => $3)
+                    OR (response_status IS NULL
+                        AND created_at < now() - make_interval(mins => $4)))",
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(KEY_TTL_HOURS)
+        .bind(CLAIM_TIMEOUT_MINUTES)
+        .execute(&mut *tx)
+        .await?;
+
+        let claimed = sqlx::query(
+            "INSERT INTO idempotency_keys (user_id, key, request_hash) VALUES ($1, $2, $3)
+             ON CONFLICT (user_id, key) DO NOTHING",
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(request_hash)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            == 1;
+
+        let existing = if claimed {
+            None
+        } else {
+            Some(
+                sqlx::query_as::<_, IdempotencyRecord>(
+                    "SELECT request_hash, response_status, response_body FROM idempotency_keys
+                     WHERE user_id = $1 AND key = $2",
+                )
+                .bind(user_id)
+                .bind(key)
+                .fetch_one(&mut *tx)
+                .await?,
+            )
+        };
+
+        tx.commit().await?;
+        Ok(existing)
+    }
+
+    async fn complete(
+        &self,
+        user_id: &str,
+        key: &str,
+        status: u16,
+        body: &[u8],
+        response_hash: &str,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "UPDATE idempotency_keys
+             SET response_status = $3, response_body = $4, response_hash = $5
+             WHERE user_id = $1 AND key = $2",
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(status as i32)
+        .bind(body)
+        .bind(response_hash)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn release(&self, user_id: &str, key: &str) -> Result<(), RepositoryError> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE user_id = $1 AND key = $2")
+            .bind(user_id)
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// A key held by `IdempotencyStore`
+#[derive(Debug, Clone)]
+struct StoredKey {
+    record: IdempotencyRecord,
+    created_at: DateTime<Utc>,
+}
+
+/// Simple in-memory idempotency key store, used by tests and local experiments
+#[derive(Debug, Default)]
+pub struct IdempotencyStore {
+    keys: RwLock<HashMap<(String, String), StoredKey>>, // (user ID, key) -> key
+}
+
+impl IdempotencyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl IdempotencyRepository for IdempotencyStore {
+    async fn claim(
+        &self,
+        user_id: &str,
+        key: &str,
+        request_hash: &str,
+    ) -> Result<Option<IdempotencyRecord>, RepositoryError> {
+        let mut keys = self.keys.write().await;
+        let id = (user_id.to_string(), key.to_string());
+        let now = Utc::now();
+
+        if let Some(stored) = keys.get(&id) {
+            let age = now - stored.created_at;
+            let abandoned = stored.record.response_status.is_none()
+                && age > Duration::minutes(CLAIM_TIMEOUT_MINUTES.into());
+            if age <= Duration::hours(KEY_TTL_HOURS.into()) && !abandoned {
+                return Ok(Some(stored.record.clone()));
+            }
+        }
+
+        keys.insert(
+            id,
+            StoredKey {
+                record: IdempotencyRecord {
+                    request_hash: request_hash.to_string(),
+                    response_status: None,
+                    response_body: None,
+                },
+                created_at: now,
+            },
+        );
+        Ok(None)
+    }
+
+    async fn complete(
+        &self,
+        user_id: &str,
+        key: &str,
+        status: u16,
+        body: &[u8],
+        _response_hash: &str,
+    ) -> Result<(), RepositoryError> {
+        let mut keys = self.keys.write().await;
+        if let Some(stored) = keys.get_mut(&(user_id.to_string(), key.to_string())) {
+            stored.record.response_status = Some(status.into());
+            stored.record.response_body = Some(body.to_vec());
+        }
+        Ok(())
+    }
+
+    async fn release(&self, user_id: &str, key: &str) -> Result<(), RepositoryError> {
+        self.keys
+            .write()
+            .await
+            .remove(&(user_id.to_string(), key.to_string()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_idempotency_keys(pool: PgPool) {
+        let repo = PgIdempotencyRepository::new(pool.clone());
+
+        assert_eq!(repo.claim("user-1", "key-1", "hash-a").await.unwrap(), None);
+        // A retry while the first request is in flight sees no response yet
+        let in_flight = repo.claim("user-1", "key-1", "hash-a").await.unwrap();
+        assert_eq!(
+            in_flight,
+            Some(IdempotencyRecord {
+                request_hash: "hash-a".to_string(),
+                response_status: None,
+                response_body: None,
+            })
+        );
+
+        repo.complete("user-1", "key-1", 201, b"{\"ok\":true}", "hash-r")
+            .await
+            .unwrap();
+        let done = repo
+            .claim("user-1", "key-1", "hash-b")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(done.request_hash, "hash-a");
+        assert_eq!(done.response_status, Some(201));
+        assert_eq!(done.response_body.as_deref(), Some(&b"{\"ok\":true}"[..]));
+
+        // Keys belong to one user
+        assert_eq!(repo.claim("user-2", "key-1", "hash-a").await.unwrap(), None);
+
+        repo.release("user-1", "key-1").await.unwrap();
+        assert_eq!(repo.claim("user-1", "key-1", "hash-b").await.unwrap(), None);
+
+        // Claims left without a response are abandoned after a few minutes
+        sqlx::query("UPDATE idempotency_keys SET created_at = now() - interval '6 minutes'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(repo.claim("user-1", "key-1", "hash-c").await.unwrap(), None);
+        // but completed keys are kept
+        assert!(
+            repo.claim("user-2", "key-1", "hash-a")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        repo.complete("user-2", "key-1", 201, b"{}", "hash-r")
+            .await
+            .unwrap();
+        sqlx::query("UPDATE idempotency_keys SET created_at = now() - interval '6 minutes'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(
+            repo.claim("user-2", "key-1", "hash-a")
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        // Expired keys can be used again
+        sqlx::query("UPDATE idempotency_keys SET created_at = now() - interval '25 hours'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(repo.claim("user-2", "key-1", "hash-c").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,225 @@
+//! Uploaded image metadata storage
+
+use crate::{
+    models::image::{DerivativeKind, ImageDerivative, StoredImage},
+    repositories::RepositoryError,
+};
+use async_trait::async_trait;
+use sqlx::postgres::PgPool;
+
+/// Columns selected for a `StoredImage`
+const IMAGE_COLUMNS: &str = "id AS image_id, user_id, order_id, storage_key, original_filename, \
+     content_type, size_bytes, width_px, height_px, created_at";
+
+/// Columns selected for an `ImageDerivative`
+const DERIVATIVE_COLUMNS: &str =
+    "image_id, kind, size_id, storage_key, width_px, height_px, created_at";
+
+/// Storage operations for uploaded image metadata
+#[async_trait]
+pub trait ImageRepository: Send + Sync + std::fmt::Debug {
+    /// Record a newly uploaded image
+    async fn create(&self, image: &StoredImage) -> Result<(), RepositoryError>;
+
+    /// Look up images by ID; unknown IDs are left out of the result
+    async fn find_by_ids(&self, image_ids: &[String]) -> Result<Vec<StoredImage>, RepositoryError>;
+
+    /// Record a thumbnail or preview, replacing an earlier one of the same kind and size
+    async fn save_derivative(&self, derivative: &ImageDerivative) -> Result<(), RepositoryError>;
+
+    /// Thumbnails and previews of a set of images
+    async fn list_derivatives(
+        &self,
+        image_ids: &[String],
+    ) -> Result<Vec<ImageDerivative>, RepositoryError>;
+
+    /// Look up one thumbnail (`size_id` of `None`) or preview
+    async fn find_derivative(
+        &self,
+        image_id: &str,
+        kind: DerivativeKind,
+        size_id: Option<&str>,
+    ) -> Result<Option<ImageDerivative>, RepositoryError>;
+}
+
+/// PostgreSQL-backed image repository
+#[derive(Debug, Clone)]
+pub struct PgImageRepository {
+    pool: PgPool,
+}
+
+impl PgImageRepository {
+    pub fn new(pool: PgPool) -> Self {
+        PgImageRepository { pool }
+    }
+}
+
+#[async_trait]
+impl ImageRepository for PgImageRepository {
+    async fn create(&self, image: &StoredImage) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO images
+                (id, user_id, order_id, storage_key, original_filename, content_type, size_bytes,
+                 width_px, height_px, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+        )
+        .bind(&image.image_id)
+        .bind(image.user_id)
+        .bind(&image.order_id)
+        .bind(&image.storage_key)
+        .bind(&image.original_filename)
+        .bind(&image.content_type)
+        .bind(image.size_bytes)
+        .bind(image.width_px)
+        .bind(image.height_px)
+        .bind(image.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn find_by_ids(&self, image_ids: &[String]) -> Result<Vec<StoredImage>, RepositoryError> {
+        let images = sqlx::query_as::<_, StoredImage>(&format!(
+            "SELECT {} FROM images WHERE id = ANY($1)",
+            IMAGE_COLUMNS
+        ))
+        .bind(image_ids)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(images)
+    }
+
+    async fn save_derivative(&self, derivative: &ImageDerivative) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO image_derivatives
+                (image_id, kind, size_id, storage_key, width_px, height_px, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             ON CONFLICT (image_id, kind, (COALESCE(size_id, ''))) DO UPDATE
+             SET storage_key = EXCLUDED.storage_key,
+                 width_px = EXCLUDED.width_px,
+                 height_px = EXCLUDED.height_px,
+                 created_at = EXCLUDED.created_at",
+        )
+        .bind(&derivative.image_id)
+        .bind(derivative.kind)
+        .bind(&derivative.size_id)
+        .bind(&derivative.storage_key)
+        .bind(derivative.width_px)
+        .bind(derivative.height_px)
+        .bind(derivative.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_derivatives(
+        &self,
+        image_ids: &[String],
+    ) -> Result<Vec<ImageDerivative>, RepositoryError> {
+        let derivatives = sqlx::query_as::<_, ImageDerivative>(&format!(
+            "SELECT {} FROM image_derivatives WHERE image_id = ANY($1) ORDER BY image_id, kind, size_id",
+            DERIVATIVE_COLUMNS
+        ))
+        .bind(image_ids)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(derivatives)
+    }
+
+    async fn find_derivative(
+        &self,
+        image_id: &str,
+        kind: DerivativeKind,
+        size_id: Option<&str>,
+    ) -> Result<Option<ImageDerivative>, RepositoryError> {
+        let derivative = sqlx::query_as::<_, ImageDerivative>(&format!(
+            "SELECT {} FROM image_derivatives
+             WHERE image_id = $1 AND kind = $2 AND size_id IS NOT DISTINCT FROM $3",
+            DERIVATIVE_COLUMNS
+        ))
+        .bind(image_id)
+        .bind(kind)
+        .bind(size_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(derivative)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::stored_image;
+    use chrono::Utc;
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_image_round_trip(pool: PgPool) {
+        let repo = PgImageRepository::new(pool);
+        repo.create(&stored_image("img_a"))
+            .await
+            .expect("Create should succeed");
+        repo.create(&stored_image("img_b"))
+            .await
+            .expect("Create should succeed");
+
+        let ids = vec!["img_a".to_string(), "img_missing".to_string()];
+        let images = repo.find_by_ids(&ids).await.expect("Lookup should succeed");
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].image_id, "img_a");
+        assert_eq!(images[0].order_id, None);
+        assert_eq!(images[0].width_px, Some(4032));
+
+        assert!(matches!(
+            repo.create(&stored_image("img_a")).await,
+            Err(RepositoryError::Conflict(_))
+        ));
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_derivatives_upsert(pool: PgPool) {
+        let repo = PgImageRepository::new(pool);
+        repo.create(&stored_image("img_a"))
+            .await
+            .expect("Create should succeed");
+
+        let mut thumbnail = ImageDerivative {
+            image_id: "img_a".to_string(),
+            kind: DerivativeKind::Thumbnail,
+            size_id: None,
+            storage_key: "uploads/test/img_a/thumb.jpg".to_string(),
+            width_px: 256,
+            height_px: 192,
+            created_at: Utc::now(),
+        };
+        repo.save_derivative(&thumbnail).await.unwrap();
+        // Re-rendering replaces the existing row
+        thumbnail.width_px = 255;
+        repo.save_derivative(&thumbnail).await.unwrap();
+        repo.save_derivative(&ImageDerivative {
+            kind: DerivativeKind::Preview,
+            size_id: Some("4x6".to_string()),
+            storage_key: "uploads/test/img_a/preview_4x6.jpg".to_string(),
+            ..thumbnail.clone()
+        })
+        .await
+        .unwrap();
+
+        let all = repo.list_derivatives(&["img_a".to_string()]).await.unwrap();
+        assert_eq!(all.len(), 2);
+
+        let found = repo
+            .find_derivative("img_a", DerivativeKind::Thumbnail, None)
+            .await
+            .unwrap()
+            .expect("Thumbnail should exist");
+        assert_eq!(found.width_px, 255);
+        assert!(
+            repo.find_derivative("img_a", DerivativeKind::Preview, Some("8x10"))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
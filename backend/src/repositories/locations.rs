@@ -0,0 +1,333 @@
+//! Fulfillment location storage
+
+use crate::{models::fulfillment_location::FulfillmentLocation, repositories::RepositoryError};
+use async_trait::async_trait;
+use sqlx::postgres::PgPool;
+
+/// Columns selected for a `FulfillmentLocation`
+const LOCATION_COLUMNS: &str = "id AS location_id, name, contact_name, company, phone, line1, \
+     line2, city, state, postal_code, country, regions, size_ids, priority, pickup, pickup_hours, \
+     active, created_at, updated_at";
+
+/// A fulfillment location to add
+#[derive(Debug, Clone)]
+pub struct NewLocation {
+    pub location_id: String,
+    pub name: String,
+    pub contact_name: String,
+    pub company: Option<String>,
+    pub phone: Option<String>,
+    pub line1: String,
+    pub line2: Option<String>,
+    pub city: String,
+    pub state: String,
+    pub postal_code: String,
+    pub country: String,
+    pub regions: Vec<String>,
+    pub size_ids: Vec<String>,
+    pub priority: i32,
+    pub pickup: bool,
+    pub pickup_hours: Option<String>,
+    pub active: bool,
+}
+
+/// Changes to an existing location; `None` fields are left untouched
+#[derive(Debug, Clone, Default)]
+pub struct LocationUpdate {
+    pub name: Option<String>,
+    pub contact_name: Option<String>,
+    pub company: Option<String>,
+    pub phone: Option<String>,
+    pub line1: Option<String>,
+    pub line2: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+    pub regions: Option<Vec<String>>,
+    pub size_ids: Option<Vec<String>>,
+    pub priority: Option<i32>,
+    pub pickup: Option<bool>,
+    pub pickup_hours: Option<String>,
+    pub active: Option<bool>,
+}
+
+/// Storage operations for fulfillment locations
+#[async_trait]
+pub trait LocationRepository: Send + Sync + std::fmt::Debug {
+    /// List locations by priority, optionally including inactive ones
+    async fn list(
+        &self,
+        include_inactive: bool,
+    ) -> Result<Vec<FulfillmentLocation>, RepositoryError>;
+
+    /// Look up a location by ID, whether active or not
+    async fn find(&self, location_id: &str)
+    -> Result<Option<FulfillmentLocation>, RepositoryError>;
+
+    /// Add a location; fails with `Conflict` if the ID is taken
+    async fn create(&self, location: &NewLocation) -> Result<FulfillmentLocation, RepositoryError>;
+
+    /// Apply changes to a location, returning `None` if it doesn't exist
+    async fn update(
+        &self,
+        location_id: &str,
+        update: &LocationUpdate,
+    ) -> Result<Option<FulfillmentLocation>, RepositoryError>;
+
+    /// Remove a location, returning whether it existed
+    ///
+    /// Fails with `Conflict` if orders were routed to it; deactivate it
+    /// instead.
+    async fn delete(&self, location_id: &str) -> Result<bool, RepositoryError>;
+}
+
+/// PostgreSQL-backed fulfillment location repository
+#[derive(Debug, Clone)]
+pub struct PgLocationRepository {
+    pool: PgPool,
+}
+
+impl PgLocationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        PgLocationRepository { pool }
+    }
+}
+
+#[async_trait]
+impl LocationRepository for PgLocationRepository {
+    async fn list(
+        &self,
+        include_inactive: bool,
+    ) -> Result<Vec<FulfillmentLocation>, RepositoryError> {
+        let locations = sqlx::query_as::<_, FulfillmentLocation>(&format!(
+            "SELECT {} FROM fulfillment_locations WHERE active OR $1 ORDER BY priority, id",
+            LOCATION_COLUMNS
+        ))
+        .bind(include_inactive)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(locations)
+    }
+
+    async fn find(
+        &self,
+        location_id: &str,
+    ) -> Result<Option<FulfillmentLocation>, RepositoryError> {
+        let location = sqlx::query_as::<_, FulfillmentLocation>(&format!(
+            "SELECT {} FROM fulfillment_locations WHERE id = $1",
+            LOCATION_COLUMNS
+        ))
+        .bind(location_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(location)
+    }
+
+    async fn create(&self, location: &NewLocation) -> Result<FulfillmentLocation, RepositoryError> {
+        let created = sqlx::query_as::<_, FulfillmentLocation>(&format!(
+            "INSERT INTO fulfillment_locations
+                (id, name, contact_name, company, phone, line1, line2, city, state, postal_code,
+                 country, regions, size_ids, priority, active, pickup, pickup_hours)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+             RETURNING {}",
+            LOCATION_COLUMNS
+        ))
+        .bind(&location.location_id)
+        .bind(&location.name)
+        .bind(&location.contact_name)
+        .bind(&location.company)
+        .bind(&location.phone)
+        .bind(&location.line1)
+        .bind(&location.line2)
+        .bind(&location.city)
+        .bind(&location.state)
+        .bind(&location.postal_code)
+        .bind(&location.country)
+        .bind(&location.regions)
+        .bind(&location.size_ids)
+        .bind(location.priority)
+        .bind(location.active)
+        .bind(location.pickup)
+        .bind(&location.pickup_hours)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(created)
+    }
+
+    async fn update(
+        &self,
+        location_id: &str,
+        update: &LocationUpdate,
+    ) -> Result<Option<FulfillmentLocation>, RepositoryError> {
+        let updated = sqlx::query_as::<_, FulfillmentLocation>(&format!(
+            "UPDATE fulfillment_locations SET
+                name = COALESCE($2, name),
+                contact_name = COALESCE($3, contact_name),
+                company = COALESCE($4, company),
+                phone = COALESCE($5, phone),
+                line1 = COALESCE($6, line1),
+                line2 = COALESCE($7, line2),
+                city = COALESCE($8, city),
+                state = COALESCE($9, state),
+                postal_code = COALESCE($10, postal_code),
+                country = COALESCE($11, country),
+                regions = COALESCE($12, regions),
+                size_ids = COALESCE($13, size_ids),
+                priority = COALESCE($14, priority),
+                active = COALESCE($15, active),
+                pickup = COALESCE($16, pickup),
+                pickup_hours = COALESCE($17, pickup_hours),
+                updated_at = now()
+             WHERE id = $1
+             RETURNING {}",
+            LOCATION_COLUMNS
+        ))
+        .bind(location_id)
+        .bind(&update.name)
+        .bind(&update.contact_name)
+        .bind(&update.company)
+        .bind(&update.phone)
+        .bind(&update.line1)
+        .bind(&update.line2)
+        .bind(&update.city)
+        .bind(&update.state)
+        .bind(&update.postal_code)
+        .bind(&update.country)
+        .bind(&update.regions)
+        .bind(&update.size_ids)
+        .bind(update.priority)
+        .bind(update.active)
+        .bind(update.pickup)
+        .bind(&update.pickup_hours)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(updated)
+    }
+
+    async fn delete(&self, location_id: &str) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM fulfillment_locations WHERE id = $1")
+            .bind(location_id)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(sqlx::Error::Database(err)) if err.is_foreign_key_violation() => {
+                Err(RepositoryError::Conflict(format!(
+                    "Orders were routed to location {}",
+                    location_id
+                )))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_location(location_id: &str, state: &str) -> NewLocation {
+        NewLocation {
+            location_id: location_id.to_string(),
+            name: format!("{} lab", location_id),
+            contact_name: "Shipping Desk".to_string(),
+            company: Some("Sushi Prints".to_string()),
+            phone: Some("5551234567".to_string()),
+            line1: "1600 Market Street".to_string(),
+            line2: None,
+            city: "Denver".to_string(),
+            state: state.to_string(),
+            postal_code: "80202".to_string(),
+            country: "US".to_string(),
+            regions: vec![format!("US-{}", state)],
+            size_ids: Vec::new(),
+            priority: 10,
+            pickup: false,
+            pickup_hours: None,
+            active: true,
+        }
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_location_lifecycle(pool: PgPool) {
+        let repo = PgLocationRepository::new(pool.clone());
+
+        let created = repo
+            .create(&new_location("denver", "CO"))
+            .await
+            .expect("Create should succeed");
+        assert_eq!(created.regions, ["US-CO"]);
+        assert!(created.size_ids.is_empty());
+        assert!(matches!(
+            repo.create(&new_location("denver", "CO")).await,
+            Err(RepositoryError::Conflict(_))
+        ));
+        repo.create(&NewLocation {
+            priority: 5,
+            ..new_location("oakland", "CA")
+        })
+        .await
+        .expect("Create should succeed");
+
+        let ids: Vec<String> = repo
+            .list(false)
+            .await
+            .expect("List should succeed")
+            .into_iter()
+            .map(|location| location.location_id)
+            .collect();
+        assert_eq!(ids, ["oakland", "denver"]);
+
+        let update = LocationUpdate {
+            size_ids: Some(vec!["4x6".to_string(), "5x7".to_string()]),
+            pickup: Some(true),
+            pickup_hours: Some("Mon-Fri 9:00-18:00".to_string()),
+            active: Some(false),
+            ..Default::default()
+        };
+        let updated = repo
+            .update("oakland", &update)
+            .await
+            .expect("Update should succeed")
+            .expect("Location should exist");
+        assert_eq!(updated.size_ids, ["4x6", "5x7"]);
+        assert_eq!(updated.regions, ["US-CA"]);
+        assert!(updated.pickup);
+        assert_eq!(updated.pickup_hours.as_deref(), Some("Mon-Fri 9:00-18:00"));
+        assert!(!updated.active);
+        assert_eq!(
+            repo.list(false).await.expect("List should succeed").len(),
+            1
+        );
+        assert_eq!(repo.list(true).await.expect("List should succeed").len(), 2);
+
+        // Locations orders were routed to can only be deactivated
+        sqlx::query(
+            "INSERT INTO orders (id, status, customer_name, customer_email, customer_phone,
+                shipping_line1, shipping_city, shipping_state, shipping_postal_code,
+                shipping_country, shipping_option, payment_method, items_subtotal, shipping,
+                tax, grand_total, currency, estimated_delivery_min, estimated_delivery_max,
+                fulfillment_location_id)
+             VALUES ('ord_1', 'paid', 'Jane Doe', 'jane@example.com', '555', '1 Main St', 'Austin',
+                'TX', '78701', 'US', 'UPS_Ground', 'paypal', 1, 1, 0, 2, 'USD', CURRENT_DATE,
+                CURRENT_DATE, 'denver')",
+        )
+        .execute(&pool)
+        .await
+        .expect("Order insert should succeed");
+        assert!(matches!(
+            repo.delete("denver").await,
+            Err(RepositoryError::Conflict(_))
+        ));
+        assert!(repo.delete("oakland").await.expect("Delete should succeed"));
+        assert!(!repo.delete("oakland").await.expect("Delete should succeed"));
+        assert!(
+            repo.update("oakland", &update)
+                .await
+                .expect("Update should succeed")
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,52 @@
+//! Persistence layer
+//!
+//! Every repository is a trait with a PostgreSQL implementation, so handlers
+//! only depend on storage behaviour. `users::UserStore` is an in-memory
+//! implementation used by tests.
+
+pub mod catalog;
+pub mod idempotency;
+pub mod images;
+pub mod locations;
+pub mod orders;
+pub mod payments;
+pub mod shipping_labels;
+pub mod shipping_rules;
+pub mod users;
+
+use std::fmt;
+
+/// Errors returned by repository implementations
+#[derive(Debug)]
+pub enum RepositoryError {
+    /// The requested record does not exist
+    NotFound(String),
+    /// A uniqueness constraint was violated
+    Conflict(String),
+    /// Database error (connection, query or decoding failures)
+    Database(String),
+}
+
+impl fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepositoryError::NotFound(msg) => write!(f, "{}", msg),
+            RepositoryError::Conflict(msg) => write!(f, "{}", msg),
+            RepositoryError::Database(msg) => write!(f, "Database error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for RepositoryError {}
+
+impl From<sqlx::Error> for RepositoryError {
+    fn from(err: sqlx::Error) -> Self {
+        match &err {
+            sqlx::Error::RowNotFound => RepositoryError::NotFound("Record not found".to_string()),
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                RepositoryError::Conflict(db_err.message().to_string())
+            }
+            _ => RepositoryError::Database(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,641 @@
+//! Print order storage
+
+use crate::{
+    models::{
+        order_status::{OrderStatus, OrderStatusChange},
+        print_order::{PrintOrder, PrintOrderItem},
+    },
+    money::Money,
+    repositories::RepositoryError,
+};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use sqlx::{Postgres, QueryBuilder, postgres::PgPool};
+use uuid::Uuid;
+
+/// Columns selected for a `PrintOrder`
+const ORDER_COLUMNS: &str = "id AS order_id, user_id, status, customer_name, customer_email, \
+     customer_phone, shipping_line1, shipping_line2, shipping_city, shipping_state, \
+     shipping_postal_code, shipping_country, special_instructions, shipping_option, \
+     fulfillment_method, fulfillment_location_id, tracking_number, pickup_code, payment_method, \
+     payment_reference, items_subtotal, shipping, tax, grand_total, amount_refunded, currency, estimated_delivery_min, estimated_delivery_max, \
+     created_at, updated_at";
+
+/// Columns selected for a `PrintOrderItem`, joined with `orders` for the currency
+const ITEM_COLUMNS: &str = "i.id AS item_id, i.order_id, i.size, i.finish, i.quantity, i.image_ids, \
+     i.unit_price, i.line_total, i.hs_code, o.currency";
+
+/// Filters for listing orders
+#[derive(Debug, Clone, Default)]
+pub struct OrderFilter {
+    /// Only orders placed by this user
+    pub user_id: Option<Uuid>,
+    /// Only orders in this status
+    pub status: Option<OrderStatus>,
+    /// Only orders created on or after this date (UTC)
+    pub from: Option<NaiveDate>,
+    /// Only orders created on or before this date (UTC)
+    pub to: Option<NaiveDate>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Storage operations for print orders
+#[async_trait]
+pub trait OrderRepository: Send + Sync + std::fmt::Debug {
+    /// Insert an order together with its line items and initial history entry
+    ///
+    /// Staged images referenced by the items are attached to the order in the
+    /// same transaction.
+    async fn create(&self, order: &PrintOrder) -> Result<(), RepositoryError>;
+
+    /// Look up an order and its line items by ID
+    async fn find_by_id(&self, order_id: &str) -> Result<Option<PrintOrder>, RepositoryError>;
+
+    /// List orders matching a filter, newest first
+    async fn list(&self, filter: &OrderFilter) -> Result<Vec<PrintOrder>, RepositoryError>;
+
+    /// Number of orders matching a filter, ignoring its limit and offset
+    async fn count(&self, filter: &OrderFilter) -> Result<i64, RepositoryError>;
+
+    /// Atomically claim the next order number for a day, starting at 1
+    async fn next_order_number(&self, day: NaiveDate) -> Result<i32, RepositoryError>;
+
+    /// Move an order from `from` to `to` and record the change in its history
+    ///
+    /// Fails with `Conflict` if the order is no longer in `from`, so two
+    /// concurrent updates can't both apply. Transition rules are checked by
+    /// the caller.
+    async fn update_status(
+        &self,
+        order_id: &str,
+        from: OrderStatus,
+        to: OrderStatus,
+        changed_by: Option<Uuid>,
+        note: Option<String>,
+    ) -> Result<(), RepositoryError>;
+
+    /// Point an order at the provider's ID for its current payment
+    async fn set_payment_reference(
+        &self,
+        order_id: &str,
+        payment_reference: &str,
+    ) -> Result<(), RepositoryError>;
+
+    /// Raise an order's refunded amount to at least `total`
+    ///
+    /// Used for refunds the provider reports that weren't issued through us.
+    async fn raise_amount_refunded(
+        &self,
+        order_id: &str,
+        total: &Money,
+    ) -> Result<(), RepositoryError>;
+
+    /// Add a note to an order's status history without changing its status
+    async fn add_status_note(&self, order_id: &str, note: &str) -> Result<(), RepositoryError>;
+
+    /// Status history of an order, oldest first
+    async fn status_history(
+        &self,
+        order_id: &str,
+    ) -> Result<Vec<OrderStatusChange>, RepositoryError>;
+}
+
+/// PostgreSQL-backed order repository
+#[derive(Debug, Clone)]
+pub struct PgOrderRepository {
+    pool: PgPool,
+}
+
+impl PgOrderRepository {
+    pub fn new(pool: PgPool) -> Self {
+        PgOrderRepository { pool }
+    }
+
+    /// Load line items for a set of orders and attach them
+    async fn attach_items(&self, orders: &mut [PrintOrder]) -> Result<(), RepositoryError> {
+        if orders.is_empty() {
+            return Ok(());
+        }
+
+        let order_ids: Vec<String> = orders.iter().map(|o| o.order_id.clone()).collect();
+        let items = sqlx::query_as::<_, PrintOrderItem>(&format!(
+            "SELECT {} FROM order_items i JOIN orders o ON o.id = i.order_id \
+             WHERE i.order_id = ANY($1) ORDER BY i.id",
+            ITEM_COLUMNS
+        ))
+        .bind(&order_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for item in items {
+            if let Some(order) = orders.iter_mut().find(|o| o.order_id == item.order_id) {
+                order.items.push(item);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OrderRepository for PgOrderRepository {
+    async fn create(&self, order: &PrintOrder) -> Result<(), RepositoryError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO orders (
+                id, user_id, status, customer_name, customer_email, customer_phone,
+                shipping_line1, shipping_line2, shipping_city, shipping_state,
+                shipping_postal_code, shipping_country, special_instructions, shipping_option,
+                payment_method, payment_reference, items_subtotal, shipping, tax, grand_total,
+                currency, estimated_delivery_min, estimated_delivery_max, created_at, updated_at,
+                fulfillment_location_id, fulfillment_method, pickup_code
+             ) VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+                $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28
+             )",
+        )
+        .bind(&order.order_id)
+        .bind(order.user_id)
+        .bind(order.status)
+        .bind(&order.customer_name)
+        .bind(&order.customer_email)
+        .bind(&order.customer_phone)
+        .bind(&order.shipping_line1)
+        .bind(&order.shipping_line2)
+        .bind(&order.shipping_city)
+        .bind(&order.shipping_state)
+        .bind(&order.shipping_postal_code)
+        .bind(&order.shipping_country)
+        .bind(&order.special_instructions)
+        .bind(&order.shipping_option)
+        .bind(&order.payment_method)
+        .bind(&order.payment_reference)
+        .bind(order.items_subtotal.to_decimal())
+        .bind(order.shipping.to_decimal())
+        .bind(order.tax.to_decimal())
+        .bind(order.grand_total.to_decimal())
+        .bind(&order.currency)
+        .bind(order.estimated_delivery_min)
+        .bind(order.estimated_delivery_max)
+        .bind(order.created_at)
+        .bind(order.updated_at)
+        .bind(&order.fulfillment_location_id)
+        .bind(order.fulfillment_method)
+        .bind(&order.pickup_code)
+        .execute(&mut *tx)
+        .await?;
+
+        for item in &order.items {
+            sqlx::query(
+                "INSERT INTO order_items
+                    (order_id, size, finish, quantity, image_ids, unit_price, line_total, hs_code)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            )
+            .bind(&order.order_id)
+            .bind(&item.size)
+            .bind(&item.finish)
+            .bind(item.quantity)
+            .bind(&item.image_ids)
+            .bind(item.unit_price.to_decimal())
+            .bind(item.line_total.to_decimal())
+            .bind(&item.hs_code)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Claim staged uploads referenced by the items; images already
+        // attached to an earlier order stay with it
+        let image_ids: Vec<&String> = order.items.iter().flat_map(|i| &i.image_ids).collect();
+        sqlx::query("UPDATE images SET order_id = $1 WHERE id = ANY($2) AND order_id IS NULL")
+            .bind(&order.order_id)
+            .bind(&image_ids)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            "INSERT INTO order_status_history (order_id, from_status, to_status, changed_by, changed_at)
+             VALUES ($1, NULL, $2, $3, $4)",
+        )
+        .bind(&order.order_id)
+        .bind(order.status)
+        .bind(order.user_id)
+        .bind(order.created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn find_by_id(&self, order_id: &str) -> Result<Option<PrintOrder>, RepositoryError> {
+        let order = sqlx::query_as::<_, PrintOrder>(&format!(
+            "SELECT {} FROM orders WHERE id = $1",
+            ORDER_COLUMNS
+        ))
+        .bind(order_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match order {
+            Some(order) => {
+                let mut orders = [order];
+                self.attach_items(&mut orders).await?;
+                let [order] = orders;
+                Ok(Some(order))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn list(&self, filter: &OrderFilter) -> Result<Vec<PrintOrder>, RepositoryError> {
+        let mut query: QueryBuilder<Postgres> =
+            QueryBuilder::new(format!("SELECT {} FROM orders WHERE TRUE", ORDER_COLUMNS));
+        push_filter(&mut query, filter);
+
+        query.push(" ORDER BY created_at DESC");
+        query.push(" LIMIT ").push_bind(filter.limit.unwrap_or(100));
+        query.push(" OFFSET ").push_bind(filter.offset.unwrap_or(0));
+
+        let mut orders = query
+            .build_query_as::<PrintOrder>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        self.attach_items(&mut orders).await?;
+        Ok(orders)
+    }
+
+    async fn count(&self, filter: &OrderFilter) -> Result<i64, RepositoryError> {
+        let mut query: QueryBuilder<Postgres> =
+            QueryBuilder::new("SELECT COUNT(*) FROM orders WHERE TRUE");
+        push_filter(&mut query, filter);
+
+        let count = query
+            .build_query_scalar::<i64>()
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count)
+    }
+
+    async fn next_order_number(&self, day: NaiveDate) -> Result<i32, RepositoryError> {
+        // The upsert takes a row lock, so concurrent callers are serialized
+        // and each one sees a distinct value
+        let number = sqlx::query_scalar::<_, i32>(
+            "INSERT INTO order_number_sequences (day, last_value) VALUES ($1, 1)
+             ON CONFLICT (day) DO UPDATE
+             SET last_value = order_number_sequences.last_value + 1
+             RETURNING last_value",
+        )
+        .bind(day)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(number)
+    }
+
+    async fn update_status(
+        &self,
+        order_id: &str,
+        from: OrderStatus,
+        to: OrderStatus,
+        changed_by: Option<Uuid>,
+        note: Option<String>,
+    ) -> Result<(), RepositoryError> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "UPDATE orders SET status = $3, updated_at = now() WHERE id = $1 AND status = $2",
+        )
+        .bind(order_id)
+        .bind(from)
+        .bind(to)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::Conflict(format!(
+                "Order {} is no longer {}",
+                order_id, from
+            )));
+        }
+
+        sqlx::query(
+            "INSERT INTO order_status_history (order_id, from_status, to_status, changed_by, note)
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(order_id)
+        .bind(from)
+        .bind(to)
+        .bind(changed_by)
+        .bind(note)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn set_payment_reference(
+        &self,
+        order_id: &str,
+        payment_reference: &str,
+    ) -> Result<(), RepositoryError> {
+        let result = sqlx::query(
+            "UPDATE orders SET payment_reference = $2, updated_at = now() WHERE id = $1",
+        )
+        .bind(order_id)
+        .bind(payment_reference)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(format!(
+                "Order {} not found",
+                order_id
+            )));
+        }
+        Ok(())
+    }
+
+    async fn raise_amount_refunded(
+        &self,
+        order_id: &str,
+        total: &Money,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "UPDATE orders SET amount_refunded = $2, updated_at = now()
+             WHERE id = $1 AND amount_refunded < $2",
+        )
+        .bind(order_id)
+        .bind(total.to_decimal())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn add_status_note(&self, order_id: &str, note: &str) -> Result<(), RepositoryError> {
+        let result = sqlx::query(
+            "INSERT INTO order_status_history (order_id, from_status, to_status, note)
+             SELECT id, status, status, $2 FROM orders WHERE id = $1",
+        )
+        .bind(order_id)
+        .bind(note)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(format!(
+                "Order {} not found",
+                order_id
+            )));
+        }
+        Ok(())
+    }
+
+    async fn status_history(
+        &self,
+        order_id: &str,
+    ) -> Result<Vec<OrderStatusChange>, RepositoryError> {
+        let history = sqlx::query_as::<_, OrderStatusChange>(
+            "SELECT from_status, to_status, changed_by, note, changed_at
+             FROM order_status_history WHERE order_id = $1 ORDER BY id",
+        )
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(history)
+    }
+}
+
+/// Append the conditions of an order filter to a `WHERE TRUE` query
+fn push_filter(query: &mut QueryBuilder<Postgres>, filter: &OrderFilter) {
+    if let Some(user_id) = filter.user_id {
+        query.push(" AND user_id = ").push_bind(user_id);
+    }
+    if let Some(status) = filter.status {
+        query.push(" AND status = ").push_bind(status);
+    }
+    if let Some(from) = filter.from {
+        query
+            .push(" AND created_at >= ")
+            .push_bind(from.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+    if let Some(to) = filter.to {
+        // Inclusive end date: everything before midnight of the following day
+        let end = to.succ_opt().unwrap_or(to).and_hms_opt(0, 0, 0).unwrap();
+        query.push(" AND created_at < ").push_bind(end.and_utc());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::print_order::FulfillmentMethod, test_support::print_order};
+    use chrono::Utc;
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_order_round_trip(pool: PgPool) {
+        let repo = PgOrderRepository::new(pool);
+        repo.create(&print_order("ord_1", OrderStatus::PendingPayment))
+            .await
+            .expect("Create should succeed");
+
+        let order = repo
+            .find_by_id("ord_1")
+            .await
+            .expect("Lookup should succeed")
+            .expect("Order should exist");
+        assert_eq!(order.grand_total, Money::from_minor(1017, "USD"));
+        assert_eq!(order.items[0].unit_price, Money::from_minor(150, "USD"));
+        assert_eq!(order.items.len(), 1);
+        assert_eq!(order.items[0].image_ids, vec!["img_001".to_string()]);
+        assert_eq!(order.items[0].hs_code, "49119100");
+        assert_eq!(order.fulfillment_method, FulfillmentMethod::Ship);
+        assert!(order.pickup_code.is_none());
+
+        repo.create(&PrintOrder {
+            shipping_option: "pickup".to_string(),
+            fulfillment_method: FulfillmentMethod::Pickup,
+            pickup_code: Some("042917".to_string()),
+            ..print_order("ord_2", OrderStatus::PendingPayment)
+        })
+        .await
+        .expect("Create should succeed");
+        let order = repo
+            .find_by_id("ord_2")
+            .await
+            .expect("Lookup should succeed")
+            .expect("Order should exist");
+        assert!(order.is_pickup());
+        assert_eq!(order.pickup_code.as_deref(), Some("042917"));
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_order_list_filters(pool: PgPool) {
+        let repo = PgOrderRepository::new(pool);
+        repo.create(&print_order("ord_1", OrderStatus::PendingPayment))
+            .await
+            .unwrap();
+        repo.create(&print_order("ord_2", OrderStatus::Paid))
+            .await
+            .unwrap();
+
+        let filter = OrderFilter {
+            status: Some(OrderStatus::Paid),
+            from: Some(Utc::now().date_naive()),
+            to: Some(Utc::now().date_naive()),
+            ..Default::default()
+        };
+        let orders = repo.list(&filter).await.expect("List should succeed");
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, "ord_2");
+        assert_eq!(orders[0].items.len(), 1);
+
+        // The count ignores paging
+        let filter = OrderFilter {
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(repo.list(&filter).await.unwrap().len(), 1);
+        assert_eq!(repo.count(&filter).await.unwrap(), 2);
+
+        let filter = OrderFilter {
+            to: Utc::now().date_naive().pred_opt(),
+            ..Default::default()
+        };
+        assert!(repo.list(&filter).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_order_create_is_atomic(pool: PgPool) {
+        let repo = PgOrderRepository::new(pool);
+        let mut order = print_order("ord_1", OrderStatus::PendingPayment);
+        // Violates the quantity check, so the whole order must be rolled back
+        order.items[0].quantity = 0;
+
+        assert!(repo.create(&order).await.is_err());
+        assert!(repo.find_by_id("ord_1").await.unwrap().is_none());
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_next_order_number_is_unique_under_concurrency(pool: PgPool) {
+        let repo = std::sync::Arc::new(PgOrderRepository::new(pool));
+        let day = NaiveDate::from_ymd_opt(2025, 8, 12).unwrap();
+
+        let handles: Vec<_> = (0..50)
+            .map(|_| {
+                let repo = repo.clone();
+                tokio::spawn(async move { repo.next_order_number(day).await })
+            })
+            .collect();
+
+        let mut numbers = Vec::new();
+        for handle in handles {
+            numbers.push(handle.await.unwrap().expect("Sequence should advance"));
+        }
+        numbers.sort();
+        assert_eq!(numbers, (1..=50).collect::<Vec<i32>>());
+
+        // Each day has its own counter
+        let next_day = day.succ_opt().unwrap();
+        assert_eq!(repo.next_order_number(next_day).await.unwrap(), 1);
+        assert_eq!(repo.next_order_number(day).await.unwrap(), 51);
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_update_status_records_history(pool: PgPool) {
+        let repo = PgOrderRepository::new(pool);
+        repo.create(&print_order("ord_1", OrderStatus::PendingPayment))
+            .await
+            .unwrap();
+
+        repo.update_status(
+            "ord_1",
+            OrderStatus::PendingPayment,
+            OrderStatus::Paid,
+            None,
+            Some("Paid by phone".to_string()),
+        )
+        .await
+        .expect("Update should succeed");
+
+        // A stale `from` status must not overwrite the newer one
+        let stale = repo
+            .update_status(
+                "ord_1",
+                OrderStatus::PendingPayment,
+                OrderStatus::Cancelled,
+                None,
+                None,
+            )
+            .await;
+        assert!(matches!(stale, Err(RepositoryError::Conflict(_))));
+
+        let order = repo.find_by_id("ord_1").await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::Paid);
+
+        let history = repo.status_history("ord_1").await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].from_status, None);
+        assert_eq!(history[0].to_status, OrderStatus::PendingPayment);
+        assert_eq!(history[1].from_status, Some(OrderStatus::PendingPayment));
+        assert_eq!(history[1].to_status, OrderStatus::Paid);
+        assert_eq!(history[1].note.as_deref(), Some("Paid by phone"));
+
+        repo.add_status_note("ord_1", "Payment disputed")
+            .await
+            .unwrap();
+        let history = repo.status_history("ord_1").await.unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[2].from_status, Some(OrderStatus::Paid));
+        assert_eq!(history[2].to_status, OrderStatus::Paid);
+        assert_eq!(history[2].note.as_deref(), Some("Payment disputed"));
+        assert!(matches!(
+            repo.add_status_note("ord_missing", "Payment disputed")
+                .await,
+            Err(RepositoryError::NotFound(_))
+        ));
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_create_claims_staged_images(pool: PgPool) {
+        sqlx::query(
+            "INSERT INTO images (id, storage_key, content_type, size_bytes)
+             VALUES ('img_001', 'uploads/x/img_001/original.jpg', 'image/jpeg', 10)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let repo = PgOrderRepository::new(pool.clone());
+        repo.create(&print_order("ord_1", OrderStatus::PendingPayment))
+            .await
+            .expect("Create should succeed");
+
+        let order_id: Option<String> =
+            sqlx::query_scalar("SELECT order_id FROM images WHERE id = 'img_001'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(order_id.as_deref(), Some("ord_1"));
+
+        // A later order reusing the image doesn't take it over
+        repo.create(&print_order("ord_2", OrderStatus::PendingPayment))
+            .await
+            .expect("Create should succeed");
+        let order_id: Option<String> =
+            sqlx::query_scalar("SELECT order_id FROM images WHERE id = 'img_001'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(order_id.as_deref(), Some("ord_1"));
+    }
+}
@@ -0,0 +1,544 @@
+//! Payment attempt storage
+
+use crate::{
+    models::payment::{Payment, PaymentEvent, Refund, RefundItem},
+    money::Money,
+    payments::PaymentStatus,
+    repositories::RepositoryError,
+};
+use async_trait::async_trait;
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+/// Columns selected for a `Payment`
+const PAYMENT_COLUMNS: &str = "id AS payment_id, order_id, provider, intent_id, capture_id, \
+     status, amount, currency, created_at, updated_at";
+
+/// Columns selected for a `PaymentEvent`
+const EVENT_COLUMNS: &str =
+    "id, provider, event_id, event_type, payload, received_at, processed_at";
+
+/// Columns selected for a `Refund`
+const REFUND_COLUMNS: &str = "id AS refund_id, order_id, payment_id, provider_refund_id, status, \
+     amount, currency, reason, refunded_by, created_at";
+
+/// A payment created with a provider, to be recorded
+#[derive(Debug, Clone)]
+pub struct NewPayment {
+    pub order_id: String,
+    pub provider: String,
+    pub intent_id: String,
+    pub status: PaymentStatus,
+    pub amount: Money,
+}
+
+/// A refund issued with a provider, to be recorded
+#[derive(Debug, Clone)]
+pub struct NewRefund {
+    pub order_id: String,
+    pub payment_id: i64,
+    pub provider_refund_id: String,
+    pub status: PaymentStatus,
+    pub amount: Money,
+    pub reason: String,
+    pub refunded_by: Option<Uuid>,
+    pub items: Vec<NewRefundItem>,
+}
+
+/// Prints of a line item covered by a new refund
+#[derive(Debug, Clone)]
+pub struct NewRefundItem {
+    pub item_id: i64,
+    pub quantity: i32,
+    pub amount: Money,
+}
+
+/// Storage operations for payments
+#[async_trait]
+pub trait PaymentRepository: Send + Sync + std::fmt::Debug {
+    /// Record a new payment attempt
+    async fn create(&self, payment: &NewPayment) -> Result<Payment, RepositoryError>;
+
+    /// Look up a payment by the provider's ID for it
+    async fn find_by_intent(
+        &self,
+        provider: &str,
+        intent_id: &str,
+    ) -> Result<Option<Payment>, RepositoryError>;
+
+    /// Look up a payment by the provider's capture ID
+    async fn find_by_capture(
+        &self,
+        provider: &str,
+        capture_id: &str,
+    ) -> Result<Option<Payment>, RepositoryError>;
+
+    /// The most recent payment attempt for an order
+    async fn latest_for_order(&self, order_id: &str) -> Result<Option<Payment>, RepositoryError>;
+
+    /// Update a payment's status, and its capture ID once it has one
+    async fn update_status(
+        &self,
+        payment_id: i64,
+        status: PaymentStatus,
+        capture_id: Option<&str>,
+    ) -> Result<Payment, RepositoryError>;
+
+    /// Store a webhook event, or return the stored copy if it was received before
+    async fn record_event(
+        &self,
+        provider: &str,
+        event_id: &str,
+        event_type: &str,
+        payload: &str,
+    ) -> Result<PaymentEvent, RepositoryError>;
+
+    /// Mark a stored webhook event as applied
+    async fn mark_event_processed(&self, id: i64) -> Result<(), RepositoryError>;
+
+    /// Record a refund and, unless it failed, make sure the order's refunded
+    /// amount covers it and every earlier refund
+    async fn create_refund(&self, refund: &NewRefund) -> Result<Refund, RepositoryError>;
+
+    /// Refunds of an order with their line items, oldest first
+    async fn list_refunds(&self, order_id: &str) -> Result<Vec<Refund>, RepositoryError>;
+}
+
+/// PostgreSQL-backed payment repository
+#[derive(Debug, Clone)]
+pub struct PgPaymentRepository {
+    pool: PgPool,
+}
+
+impl PgPaymentRepository {
+    pub fn new(pool: PgPool) -> Self {
+        PgPaymentRepository { pool }
+    }
+}
+
+#[async_trait]
+impl PaymentRepository for PgPaymentRepository {
+    async fn create(&self, payment: &NewPayment) -> Result<Payment, RepositoryError> {
+        let payment = sqlx::query_as::<_, Payment>(&format!(
+            "INSERT INTO payments (order_id, provider, intent_id, status, amount, currency)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             RETURNING {}",
+            PAYMENT_COLUMNS
+        ))
+        .bind(&payment.order_id)
+        .bind(&payment.provider)
+        .bind(&payment.intent_id)
+        .bind(payment.status)
+        .bind(payment.amount.to_decimal())
+        .bind(payment.amount.currency())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(payment)
+    }
+
+    async fn find_by_intent(
+        &self,
+        provider: &str,
+        intent_id: &str,
+    ) -> Result<Option<Payment>, RepositoryError> {
+        let payment = sqlx::query_as::<_, Payment>(&format!(
+            "SELECT {} FROM payments WHERE provider = $1 AND intent_id = $2",
+            PAYMENT_COLUMNS
+        ))
+        .bind(provider)
+        .bind(intent_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(payment)
+    }
+
+    async fn find_by_capture(
+        &self,
+        provider: &str,
+        capture_id: &str,
+    ) -> Result<Option<Payment>, RepositoryError> {
+        let payment = sqlx::query_as::<_, Payment>(&format!(
+            "SELECT {} FROM payments WHERE provider = $1 AND capture_id = $2",
+            PAYMENT_COLUMNS
+        ))
+        .bind(provider)
+        .bind(capture_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(payment)
+    }
+
+    async fn latest_for_order(&self, order_id: &str) -> Result<Option<Payment>, RepositoryError> {
+        let payment = sqlx::query_as::<_, Payment>(&format!(
+            "SELECT {} FROM payments WHERE order_id = $1 ORDER BY created_at DESC, id DESC LIMIT 1",
+            PAYMENT_COLUMNS
+        ))
+        .bind(order_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(payment)
+    }
+
+    async fn update_status(
+        &self,
+        payment_id: i64,
+        status: PaymentStatus,
+        capture_id: Option<&str>,
+    ) -> Result<Payment, RepositoryError> {
+        let payment = sqlx::query_as::<_, Payment>(&format!(
+            "UPDATE payments
+             SET status = $2, capture_id = COALESCE($3, capture_id), updated_at = now()
+             WHERE id = $1
+             RETURNING {}",
+            PAYMENT_COLUMNS
+        ))
+        .bind(payment_id)
+        .bind(status)
+        .bind(capture_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        payment
+            .ok_or_else(|| RepositoryError::NotFound(format!("Payment {} not found", payment_id)))
+    }
+
+    async fn record_event(
+        &self,
+        provider: &str,
+        event_id: &str,
+        event_type: &str,
+        payload: &str,
+    ) -> Result<PaymentEvent, RepositoryError> {
+        // The no-op update makes RETURNING yield the existing row on redelivery
+        let event = sqlx::query_as::<_, PaymentEvent>(&format!(
+            "INSERT INTO payment_events (provider, event_id, event_type, payload)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (provider, event_id) DO UPDATE SET provider = EXCLUDED.provider
+             RETURNING {}",
+            EVENT_COLUMNS
+        ))
+        .bind(provider)
+        .bind(event_id)
+        .bind(event_type)
+        .bind(payload)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(event)
+    }
+
+    async fn mark_event_processed(&self, id: i64) -> Result<(), RepositoryError> {
+        sqlx::query("UPDATE payment_events SET processed_at = now() WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn create_refund(&self, refund: &NewRefund) -> Result<Refund, RepositoryError> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut stored = sqlx::query_as::<_, Refund>(&format!(
+            "INSERT INTO refunds (order_id, payment_id, provider_refund_id, status, amount,
+                currency, reason, refunded_by)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+             RETURNING {}",
+            REFUND_COLUMNS
+        ))
+        .bind(&refund.order_id)
+        .bind(refund.payment_id)
+        .bind(&refund.provider_refund_id)
+        .bind(refund.status)
+        .bind(refund.amount.to_decimal())
+        .bind(refund.amount.currency())
+        .bind(&refund.reason)
+        .bind(refund.refunded_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        for item in &refund.items {
+            sqlx::query(
+                "INSERT INTO refund_items (refund_id, order_item_id, quantity, amount)
+                 VALUES ($1, $2, $3, $4)",
+            )
+            .bind(stored.refund_id)
+            .bind(item.item_id)
+            .bind(item.quantity)
+            .bind(item.amount.to_decimal())
+            .execute(&mut *tx)
+            .await?;
+            stored.items.push(RefundItem {
+                refund_id: stored.refund_id,
+                item_id: item.item_id,
+                quantity: item.quantity,
+                amount: item.amount.clone(),
+            });
+        }
+
+        // Recomputed from the refund rows rather than incremented: a refund webhook may
+        // already have raised the total to include this refund
+        if refund.status != PaymentStatus::Failed {
+            sqlx::query(
+                "UPDATE orders SET amount_refunded = GREATEST(amount_refunded,
+                    (SELECT COALESCE(SUM(amount), 0) FROM refunds
+                     WHERE order_id = $1 AND status <> 'failed')), updated_at = now()
+                 WHERE id = $1",
+            )
+            .bind(&refund.order_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(stored)
+    }
+
+    async fn list_refunds(&self, order_id: &str) -> Result<Vec<Refund>, RepositoryError> {
+        let mut refunds = sqlx::query_as::<_, Refund>(&format!(
+            "SELECT {} FROM refunds WHERE order_id = $1 ORDER BY created_at, id",
+            REFUND_COLUMNS
+        ))
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let items = sqlx::query_as::<_, RefundItem>(
+            "SELECT ri.refund_id, ri.order_item_id AS item_id, ri.quantity, ri.amount, r.currency
+             FROM refund_items ri JOIN refunds r ON r.id = ri.refund_id
+             WHERE r.order_id = $1
+             ORDER BY ri.order_item_id",
+        )
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+        for item in items {
+            if let Some(refund) = refunds.iter_mut().find(|r| r.refund_id == item.refund_id) {
+                refund.items.push(item);
+            }
+        }
+
+        Ok(refunds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::orders::{OrderRepository, PgOrderRepository};
+
+    async fn insert_order(pool: &PgPool, order_id: &str) {
+        sqlx::query(
+            "INSERT INTO orders (id, status, customer_name, customer_email, customer_phone,
+                shipping_line1, shipping_city, shipping_state, shipping_postal_code,
+                shipping_country, shipping_option, payment_method, items_subtotal, shipping,
+                tax, grand_total, currency, estimated_delivery_min, estimated_delivery_max)
+             VALUES ($1, 'pending_payment', 'Jane Doe', 'jane@example.com', '555-0100',
+                '123 Main St', 'Denver', 'CO', '80202', 'US', 'UPS_Ground', 'paypal',
+                10.00, 0, 0, 10.00, 'USD', CURRENT_DATE, CURRENT_DATE)",
+        )
+        .bind(order_id)
+        .execute(pool)
+        .await
+        .expect("Order insert should succeed");
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_payment_lifecycle(pool: PgPool) {
+        insert_order(&pool, "ord_1").await;
+        let repo = PgPaymentRepository::new(pool);
+
+        let new_payment = NewPayment {
+            order_id: "ord_1".to_string(),
+            provider: "paypal".to_string(),
+            intent_id: "5O190127TN364715T".to_string(),
+            status: PaymentStatus::Pending,
+            amount: Money::from_minor(1000, "USD"),
+        };
+        let payment = repo.create(&new_payment).await.unwrap();
+        assert_eq!(payment.amount, Money::from_minor(1000, "USD"));
+        assert!(matches!(
+            repo.create(&new_payment).await,
+            Err(RepositoryError::Conflict(_))
+        ));
+
+        let captured = repo
+            .update_status(payment.payment_id, PaymentStatus::Completed, Some("CAP-1"))
+            .await
+            .unwrap();
+        assert_eq!(captured.capture_id.as_deref(), Some("CAP-1"));
+
+        // Later updates keep the capture ID
+        repo.update_status(payment.payment_id, PaymentStatus::Refunded, None)
+            .await
+            .unwrap();
+        let latest = repo.latest_for_order("ord_1").await.unwrap().unwrap();
+        assert_eq!(latest.status, PaymentStatus::Refunded);
+        assert_eq!(latest.capture_id.as_deref(), Some("CAP-1"));
+        assert!(
+            repo.find_by_intent("paypal", "5O190127TN364715T")
+                .await
+                .unwrap()
+                .is_some()
+        );
+        let by_capture = repo.find_by_capture("paypal", "CAP-1").await.unwrap();
+        assert_eq!(by_capture.map(|p| p.payment_id), Some(payment.payment_id));
+
+        repo.update_status(payment.payment_id, PaymentStatus::Disputed, None)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_refunds(pool: PgPool) {
+        insert_order(&pool, "ord_1").await;
+        let (item_id,): (i64,) = sqlx::query_as(
+            "INSERT INTO order_items (order_id, size, finish, quantity, image_ids, unit_price,
+                line_total)
+             VALUES ('ord_1', '4x6', 'glossy', 2, ARRAY['img_1'], 5.00, 10.00)
+             RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let repo = PgPaymentRepository::new(pool.clone());
+        let payment = repo
+            .create(&NewPayment {
+                order_id: "ord_1".to_string(),
+                provider: "paypal".to_string(),
+                intent_id: "5O190127TN364715T".to_string(),
+                status: PaymentStatus::Completed,
+                amount: Money::from_minor(1000, "USD"),
+            })
+            .await
+            .unwrap();
+
+        let refund = NewRefund {
+            order_id: "ord_1".to_string(),
+            payment_id: payment.payment_id,
+            provider_refund_id: "REF-1".to_string(),
+            status: PaymentStatus::Completed,
+            amount: Money::from_minor(500, "USD"),
+            reason: "Print arrived damaged".to_string(),
+            refunded_by: None,
+            items: vec![NewRefundItem {
+                item_id,
+                quantity: 1,
+                amount: Money::from_minor(500, "USD"),
+            }],
+        };
+        let stored = repo.create_refund(&refund).await.unwrap();
+        assert_eq!(stored.items.len(), 1);
+
+        // Failed refunds are recorded but don't count
+        repo.create_refund(&NewRefund {
+            provider_refund_id: "REF-2".to_string(),
+            status: PaymentStatus::Failed,
+            items: Vec::new(),
+            ..refund
+        })
+        .await
+        .unwrap();
+
+        let refunds = repo.list_refunds("ord_1").await.unwrap();
+        assert_eq!(refunds.len(), 2);
+        assert_eq!(refunds[0].items[0].item_id, item_id);
+        assert_eq!(refunds[0].items[0].amount, Money::from_minor(500, "USD"));
+        assert!(refunds[1].items.is_empty());
+
+        let (refunded,): (rust_decimal::Decimal,) =
+            sqlx::query_as("SELECT amount_refunded FROM orders WHERE id = 'ord_1'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(refunded, rust_decimal::Decimal::new(500, 2));
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_refund_after_webhook(pool: PgPool) {
+        insert_order(&pool, "ord_1").await;
+        let repo = PgPaymentRepository::new(pool.clone());
+        let orders = PgOrderRepository::new(pool.clone());
+        let payment = repo
+            .create(&NewPayment {
+                order_id: "ord_1".to_string(),
+                provider: "paypal".to_string(),
+                intent_id: "5O190127TN364715T".to_string(),
+                status: PaymentStatus::Completed,
+                amount: Money::from_minor(1000, "USD"),
+            })
+            .await
+            .unwrap();
+        let refund = NewRefund {
+            order_id: "ord_1".to_string(),
+            payment_id: payment.payment_id,
+            provider_refund_id: "REF-1".to_string(),
+            status: PaymentStatus::Completed,
+            amount: Money::from_minor(400, "USD"),
+            reason: "Print arrived damaged".to_string(),
+            refunded_by: None,
+            items: Vec::new(),
+        };
+        let amount_refunded = || async {
+            let (refunded,): (rust_decimal::Decimal,) =
+                sqlx::query_as("SELECT amount_refunded FROM orders WHERE id = 'ord_1'")
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap();
+            refunded
+        };
+
+        // The provider's webhook arrives before the refund is recorded
+        orders
+            .raise_amount_refunded("ord_1", &Money::from_minor(400, "USD"))
+            .await
+            .unwrap();
+        repo.create_refund(&refund).await.unwrap();
+        assert_eq!(amount_refunded().await, rust_decimal::Decimal::new(400, 2));
+
+        // A second refund recorded first, then its webhook
+        repo.create_refund(&NewRefund {
+            provider_refund_id: "REF-2".to_string(),
+            amount: Money::from_minor(300, "USD"),
+            ..refund
+        })
+        .await
+        .unwrap();
+        assert_eq!(amount_refunded().await, rust_decimal::Decimal::new(700, 2));
+        orders
+            .raise_amount_refunded("ord_1", &Money::from_minor(700, "USD"))
+            .await
+            .unwrap();
+        assert_eq!(amount_refunded().await, rust_decimal::Decimal::new(700, 2));
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_payment_events_dedup(pool: PgPool) {
+        let repo = PgPaymentRepository::new(pool);
+        let payload = r#"{"id":"evt_1","type":"payment_intent.succeeded"}"#;
+
+        let event = repo
+            .record_event("credit_card", "evt_1", "payment_intent.succeeded", payload)
+            .await
+            .unwrap();
+        assert_eq!(event.payload, payload);
+        assert!(event.processed_at.is_none());
+        repo.mark_event_processed(event.id).await.unwrap();
+
+        // A redelivery returns the stored event instead of a new one
+        let again = repo
+            .record_event("credit_card", "evt_1", "payment_intent.succeeded", payload)
+            .await
+            .unwrap();
+        assert_eq!(again.id, event.id);
+        assert!(again.processed_at.is_some());
+
+        // Event IDs are only unique per provider
+        let other = repo
+            .record_event("paypal", "evt_1", "PAYMENT.CAPTURE.COMPLETED", "{}")
+            .await
+            .unwrap();
+        assert_ne!(other.id, event.id);
+    }
+}
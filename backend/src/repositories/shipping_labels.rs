@@ -0,0 +1,266 @@
+//! Shipping label storage
+
+use crate::{
+    carriers::LabelFormat,
+    models::{order_status::OrderStatus, shipping_label::StoredLabel},
+    money::Money,
+    repositories::RepositoryError,
+};
+use async_trait::async_trait;
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+/// Columns selected for a `StoredLabel`
+const LABEL_COLUMNS: &str = "id AS label_id, order_id, carrier, service_code, tracking_number, \
+     format, storage_key, price, currency, created_by, created_at, voided_at";
+
+/// A label bought from a carrier, to be recorded
+#[derive(Debug, Clone)]
+pub struct NewShippingLabel {
+    pub order_id: String,
+    pub carrier: String,
+    pub service_code: String,
+    pub tracking_number: String,
+    pub format: LabelFormat,
+    pub storage_key: String,
+    pub price: Option<Money>,
+    pub created_by: Option<Uuid>,
+}
+
+/// Storage operations for shipping labels
+#[async_trait]
+pub trait ShippingLabelRepository: Send + Sync + std::fmt::Debug {
+    /// Record a label and save its tracking number on the order
+    ///
+    /// Fails with `Conflict` if the order already has a label that hasn't
+    /// been voided.
+    async fn create(&self, label: &NewShippingLabel) -> Result<StoredLabel, RepositoryError>;
+
+    /// The order's label that hasn't been voided, if any
+    async fn find_active(&self, order_id: &str) -> Result<Option<StoredLabel>, RepositoryError>;
+
+    /// Labels of an order, voided ones included, oldest first
+    async fn list_for_order(&self, order_id: &str) -> Result<Vec<StoredLabel>, RepositoryError>;
+
+    /// Labels that haven't been voided on orders in the given status
+    async fn list_active_for_order_status(
+        &self,
+        status: OrderStatus,
+    ) -> Result<Vec<StoredLabel>, RepositoryError>;
+
+    /// Mark a label voided and clear its tracking number from the order
+    async fn mark_voided(&self, label_id: i64) -> Result<StoredLabel, RepositoryError>;
+}
+
+/// PostgreSQL-backed shipping label repository
+#[derive(Debug, Clone)]
+pub struct PgShippingLabelRepository {
+    pool: PgPool,
+}
+
+impl PgShippingLabelRepository {
+    pub fn new(pool: PgPool) -> Self {
+        PgShippingLabelRepository { pool }
+    }
+}
+
+#[async_trait]
+impl ShippingLabelRepository for PgShippingLabelRepository {
+    async fn create(&self, label: &NewShippingLabel) -> Result<StoredLabel, RepositoryError> {
+        let mut tx = self.pool.begin().await?;
+
+        let stored = sqlx::query_as::<_, StoredLabel>(&format!(
+            "INSERT INTO shipping_labels (order_id, carrier, service_code, tracking_number,
+                format, storage_key, price, currency, created_by)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+             RETURNING {}",
+            LABEL_COLUMNS
+        ))
+        .bind(&label.order_id)
+        .bind(&label.carrier)
+        .bind(&label.service_code)
+        .bind(&label.tracking_number)
+        .bind(label.format)
+        .bind(&label.storage_key)
+        .bind(label.price.as_ref().map(Money::to_decimal))
+        .bind(label.price.as_ref().map(Money::currency))
+        .bind(label.created_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE orders SET tracking_number = $2, updated_at = now() WHERE id = $1")
+            .bind(&label.order_id)
+            .bind(&label.tracking_number)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(stored)
+    }
+
+    async fn find_active(&self, order_id: &str) -> Result<Option<StoredLabel>, RepositoryError> {
+        let label = sqlx::query_as::<_, StoredLabel>(&format!(
+            "SELECT {} FROM shipping_labels WHERE order_id = $1 AND voided_at IS NULL",
+            LABEL_COLUMNS
+        ))
+        .bind(order_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(label)
+    }
+
+    async fn list_for_order(&self, order_id: &str) -> Result<Vec<StoredLabel>, RepositoryError> {
+        let labels = sqlx::query_as::<_, StoredLabel>(&format!(
+            "SELECT {} FROM shipping_labels WHERE order_id = $1 ORDER BY created_at, id",
+            LABEL_COLUMNS
+        ))
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(labels)
+    }
+
+    async fn list_active_for_order_status(
+        &self,
+        status: OrderStatus,
+    ) -> Result<Vec<StoredLabel>, RepositoryError> {
+        let labels = sqlx::query_as::<_, StoredLabel>(&format!(
+            "SELECT {} FROM shipping_labels
+             WHERE voided_at IS NULL
+               AND order_id IN (SELECT id FROM orders WHERE status = $1)
+             ORDER BY created_at, id",
+            LABEL_COLUMNS
+        ))
+        .bind(status)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(labels)
+    }
+
+    async fn mark_voided(&self, label_id: i64) -> Result<StoredLabel, RepositoryError> {
+        let mut tx = self.pool.begin().await?;
+
+        let label = sqlx::query_as::<_, StoredLabel>(&format!(
+            "UPDATE shipping_labels SET voided_at = now()
+             WHERE id = $1 AND voided_at IS NULL
+             RETURNING {}",
+            LABEL_COLUMNS
+        ))
+        .bind(label_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| {
+            RepositoryError::NotFound(format!("No active shipping label {}", label_id))
+        })?;
+
+        sqlx::query(
+            "UPDATE orders SET tracking_number = NULL, updated_at = now()
+             WHERE id = $1 AND tracking_number = $2",
+        )
+        .bind(&label.order_id)
+        .bind(&label.tracking_number)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn insert_order(pool: &PgPool, order_id: &str) {
+        sqlx::query(
+            "INSERT INTO orders (id, status, customer_name, customer_email, customer_phone,
+                shipping_line1, shipping_city, shipping_state, shipping_postal_code,
+                shipping_country, shipping_option, payment_method, items_subtotal, shipping,
+                tax, grand_total, currency, estimated_delivery_min, estimated_delivery_max)
+             VALUES ($1, 'printed', 'Jane Doe', 'jane@example.com', '555-0100',
+                '123 Main St', 'Denver', 'CO', '80202', 'US', 'UPS_Ground', 'paypal',
+                10.00, 0, 0, 10.00, 'USD', CURRENT_DATE, CURRENT_DATE)",
+        )
+        .bind(order_id)
+        .execute(pool)
+        .await
+        .expect("Order insert should succeed");
+    }
+
+    async fn order_tracking_number(pool: &PgPool, order_id: &str) -> Option<String> {
+        sqlx::query_scalar("SELECT tracking_number FROM orders WHERE id = $1")
+            .bind(order_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    fn new_label(tracking_number: &str) -> NewShippingLabel {
+        NewShippingLabel {
+            order_id: "ord_1".to_string(),
+            carrier: "ups".to_string(),
+            service_code: "03".to_string(),
+            tracking_number: tracking_number.to_string(),
+            format: LabelFormat::Gif,
+            storage_key: format!("labels/ord_1/{}.gif", tracking_number),
+            price: Some(Money::from_minor(1187, "USD")),
+            created_by: None,
+        }
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_label_lifecycle(pool: PgPool) {
+        insert_order(&pool, "ord_1").await;
+        let repo = PgShippingLabelRepository::new(pool.clone());
+
+        let label = repo.create(&new_label("1Z001")).await.unwrap();
+        assert_eq!(label.price, Some(Money::from_minor(1187, "USD")));
+        assert_eq!(label.format, LabelFormat::Gif);
+        assert_eq!(
+            order_tracking_number(&pool, "ord_1").await.as_deref(),
+            Some("1Z001")
+        );
+
+        // Only one label at a time
+        assert!(matches!(
+            repo.create(&new_label("1Z002")).await,
+            Err(RepositoryError::Conflict(_))
+        ));
+
+        let voided = repo.mark_voided(label.label_id).await.unwrap();
+        assert!(voided.voided_at.is_some());
+        assert_eq!(order_tracking_number(&pool, "ord_1").await, None);
+        assert!(repo.find_active("ord_1").await.unwrap().is_none());
+        assert!(matches!(
+            repo.mark_voided(label.label_id).await,
+            Err(RepositoryError::NotFound(_))
+        ));
+
+        // A replacement label can be bought once the first is voided
+        let mut replacement = new_label("1Z002");
+        replacement.price = None;
+        repo.create(&replacement).await.unwrap();
+        let active = repo.find_active("ord_1").await.unwrap().unwrap();
+        assert_eq!(active.tracking_number, "1Z002");
+        assert_eq!(active.price, None);
+        assert_eq!(repo.list_for_order("ord_1").await.unwrap().len(), 2);
+
+        // Only active labels of orders in the requested status are listed
+        let printed = repo
+            .list_active_for_order_status(OrderStatus::Printed)
+            .await
+            .unwrap();
+        assert_eq!(printed.len(), 1);
+        assert_eq!(printed[0].tracking_number, "1Z002");
+        assert!(
+            repo.list_active_for_order_status(OrderStatus::Shipped)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Shipping rule storage
+
+use crate::{
+    models::shipping_rule::{ShippingRule, ShippingRuleKind},
+    money::Money,
+    repositories::RepositoryError,
+};
+use async_trait::async_trait;
+use sqlx::postgres::PgPool;
+
+/// Columns selected for a `ShippingRule`
+const RULE_COLUMNS: &str = "id AS rule_id, name, position, kind, shipping_options, regions, \
+     min_subtotal, amount, basis_points, currency, active, created_at, updated_at";
+
+/// A shipping rule to add, or to replace an existing one with
+#[derive(Debug, Clone)]
+pub struct NewShippingRule {
+    pub name: String,
+    pub position: i32,
+    pub kind: ShippingRuleKind,
+    pub shipping_options: Vec<String>,
+    pub regions: Vec<String>,
+    pub min_subtotal: Option<Money>,
+    pub amount: Option<Money>,
+    pub basis_points: Option<i32>,
+    /// Currency of `min_subtotal` and `amount`
+    pub currency: String,
+    pub active: bool,
+}
+
+/// Storage operations for shipping rules
+#[async_trait]
+pub trait ShippingRuleRepository: Send + Sync + std::fmt::Debug {
+    /// List rules in the order they apply, optionally including inactive ones
+    async fn list(&self, include_inactive: bool) -> Result<Vec<ShippingRule>, RepositoryError>;
+
+    /// Add a rule
+    async fn create(&self, rule: &NewShippingRule) -> Result<ShippingRule, RepositoryError>;
+
+    /// Replace a rule, returning `None` if it doesn't exist
+    async fn replace(
+        &self,
+        rule_id: i64,
+        rule: &NewShippingRule,
+    ) -> Result<Option<ShippingRule>, RepositoryError>;
+
+    /// Remove a rule, returning whether it existed
+    async fn delete(&self, rule_id: i64) -> Result<bool, RepositoryError>;
+}
+
+/// PostgreSQL-backed shipping rule repository
+#[derive(Debug, Clone)]
+pub struct PgShippingRuleRepository {
+    pool: PgPool,
+}
+
+impl PgShippingRuleRepository {
+    pub fn new(pool: PgPool) -> Self {
+        PgShippingRuleRepository { pool }
+    }
+}
+
+#[async_trait]
+impl ShippingRuleRepository for PgShippingRuleRepository {
+    async fn list(&self, include_inactive: bool) -> Result<Vec<ShippingRule>, RepositoryError> {
+        let rules = sqlx::query_as::<_, ShippingRule>(&format!(
+            "SELECT {} FROM shipping_rules WHERE active OR $1 ORDER BY position, id",
+            RULE_COLUMNS
+        ))
+        .bind(include_inactive)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rules)
+    }
+
+    async fn create(&self, rule: &NewShippingRule) -> Result<ShippingRule, RepositoryError> {
+        let created = sqlx::query_as::<_, ShippingRule>(&format!(
+            "INSERT INTO shipping_rules
+                (name, position, kind, shipping_options, regions, min_subtotal, amount,
+                 basis_points, currency, active)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+             RETURNING {}",
+            RULE_COLUMNS
+        ))
+        .bind(&rule.name)
+        .bind(rule.position)
+        .bind(rule.kind)
+        .bind(&rule.shipping_options)
+        .bind(&rule.regions)
+        .bind(rule.min_subtotal.as_ref().map(Money::to_decimal))
+        .bind(rule.amount.as_ref().map(Money::to_decimal))
+        .bind(rule.basis_points)
+        .bind(&rule.currency)
+        .bind(rule.active)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(created)
+    }
+
+    async fn replace(
+        &self,
+        rule_id: i64,
+        rule: &NewShippingRule,
+    ) -> Result<Option<ShippingRule>, RepositoryError> {
+        let replaced = sqlx::query_as::<_, ShippingRule>(&format!(
+            "UPDATE shipping_rules SET
+                name = $2,
+                position = $3,
+                kind = $4,
+                shipping_options = $5,
+                regions = $6,
+                min_subtotal = $7,
+                amount = $8,
+                basis_points = $9,
+                currency = $10,
+                active = $11,
+                updated_at = now()
+             WHERE id = $1
+             RETURNING {}",
+            RULE_COLUMNS
+        ))
+        .bind(rule_id)
+        .bind(&rule.name)
+        .bind(rule.position)
+        .bind(rule.kind)
+        .bind(&rule.shipping_options)
+        .bind(&rule.regions)
+        .bind(rule.min_subtotal.as_ref().map(Money::to_decimal))
+        .bind(rule.amount.as_ref().map(Money::to_decimal))
+        .bind(rule.basis_points)
+        .bind(&rule.currency)
+        .bind(rule.active)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(replaced)
+    }
+
+    async fn delete(&self, rule_id: i64) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM shipping_rules WHERE id = $1")
+            .bind(rule_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_rule(name: &str, position: i32) -> NewShippingRule {
+        NewShippingRule {
+            name: name.to_string(),
+            position,
+            kind: ShippingRuleKind::FreeShipping,
+            shipping_options: vec!["UPS_Ground".to_string()],
+            regions: vec!["US".to_string()],
+            min_subtotal: Some(Money::from_minor(5000, "USD")),
+            amount: None,
+            basis_points: None,
+            currency: "USD".to_string(),
+            active: true,
+        }
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_shipping_rule_lifecycle(pool: PgPool) {
+        let repo = PgShippingRuleRepository::new(pool);
+
+        let free = repo
+            .create(&new_rule("Free ground over $50", 20))
+            .await
+            .expect("Create should succeed");
+        assert_eq!(free.min_subtotal, Some(Money::from_minor(5000, "USD")));
+        assert!(free.amount.is_none());
+        let markup = repo
+            .create(&NewShippingRule {
+                kind: ShippingRuleKind::Adjust,
+                shipping_options: Vec::new(),
+                min_subtotal: None,
+                amount: Some(Money::from_minor(100, "USD")),
+                basis_points: Some(1000),
+                ..new_rule("Handling", 10)
+            })
+            .await
+            .expect("Create should succeed");
+
+        let names: Vec<String> = repo
+            .list(false)
+            .await
+            .expect("List should succeed")
+            .into_iter()
+            .map(|rule| rule.name)
+            .collect();
+        assert_eq!(names, ["Handling", "Free ground over $50"]);
+
+        let replaced = repo
+            .replace(
+                free.rule_id,
+                &NewShippingRule {
+                    min_subtotal: None,
+                    active: false,
+                    ..new_rule("Free ground", 20)
+                },
+            )
+            .await
+            .expect("Replace should succeed")
+            .expect("Rule should exist");
+        assert_eq!(replaced.name, "Free ground");
+        assert!(replaced.min_subtotal.is_none());
+        assert!(!replaced.active);
+        assert_eq!(
+            repo.list(false).await.expect("List should succeed").len(),
+            1
+        );
+        assert_eq!(repo.list(true).await.expect("List should succeed").len(), 2);
+
+        assert!(
+            repo.delete(markup.rule_id)
+                .await
+                .expect("Delete should succeed")
+        );
+        assert!(
+            !repo
+                .delete(markup.rule_id)
+                .await
+                .expect("Delete should succeed")
+        );
+        assert!(
+            repo.replace(markup.rule_id, &new_rule("Gone", 0))
+                .await
+                .expect("Replace should succeed")
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,376 @@
+//! User and password-reset token storage
+
+use crate::{models::user::User, repositories::RepositoryError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgPool;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Storage operations for user accounts
+#[async_trait]
+pub trait UserRepository: Send + Sync + std::fmt::Debug {
+    /// Insert a new user, failing with `Conflict` if the email is taken
+    async fn create(&self, user: &User) -> Result<(), RepositoryError>;
+
+    /// Look up a user by ID
+    async fn find_by_id(&self, user_id: &Uuid) -> Result<Option<User>, RepositoryError>;
+
+    /// Look up a user by email
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepositoryError>;
+
+    /// Persist changes to an existing user (email, name, password hash and role)
+    async fn update(&self, user: &User) -> Result<(), RepositoryError>;
+
+    /// Delete a user, returning `false` if no such user existed
+    async fn delete(&self, user_id: &Uuid) -> Result<bool, RepositoryError>;
+
+    /// List every user, oldest first
+    async fn list(&self) -> Result<Vec<User>, RepositoryError>;
+
+    /// Store a password reset token for a user
+    async fn store_reset_token(
+        &self,
+        token: &str,
+        user_id: &Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError>;
+
+    /// Remove a password reset token and return its owner and expiry
+    async fn take_reset_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<(Uuid, DateTime<Utc>)>, RepositoryError>;
+}
+
+/// PostgreSQL-backed user repository
+#[derive(Debug, Clone)]
+pub struct PgUserRepository {
+    pool: PgPool,
+}
+
+impl PgUserRepository {
+    pub fn new(pool: PgPool) -> Self {
+        PgUserRepository { pool }
+    }
+}
+
+#[async_trait]
+impl UserRepository for PgUserRepository {
+    async fn create(&self, user: &User) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO users (id, email, name, password_hash, is_admin, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        )
+        .bind(user.id)
+        .bind(&user.email)
+        .bind(&user.name)
+        .bind(&user.password_hash)
+        .bind(user.is_admin)
+        .bind(user.created_at)
+        .bind(user.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match RepositoryError::from(e) {
+            RepositoryError::Conflict(_) => {
+                RepositoryError::Conflict("User with this email already exists".to_string())
+            }
+            other => other,
+        })?;
+
+        Ok(())
+    }
+
+    async fn find_by_id(&self, user_id: &Uuid) -> Result<Option<User>, RepositoryError> {
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(user)
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepositoryError> {
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
+            .bind(email)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(user)
+    }
+
+    async fn update(&self, user: &User) -> Result<(), RepositoryError> {
+        let result = sqlx::query(
+            "UPDATE users
+             SET email = $2, name = $3, password_hash = $4, is_admin = $5, updated_at = $6
+             WHERE id = $1",
+        )
+        .bind(user.id)
+        .bind(&user.email)
+        .bind(&user.name)
+        .bind(&user.password_hash)
+        .bind(user.is_admin)
+        .bind(user.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match RepositoryError::from(e) {
+            RepositoryError::Conflict(_) => {
+                RepositoryError::Conflict("Email already in use".to_string())
+            }
+            other => other,
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound("User not found".to_string()));
+        }
+
+        Ok(())
+    }
+
+    async fn delete(&self, user_id: &Uuid) -> Result<bool, RepositoryError> {
+        let result = sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list(&self) -> Result<Vec<User>, RepositoryError> {
+        let users = sqlx::query_as::<_, User>("SELECT * FROM users ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(users)
+    }
+
+    async fn store_reset_token(
+        &self,
+        token: &str,
+        user_id: &Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        sqlx::query(
+            "INSERT INTO password_reset_tokens (token, user_id, expires_at) VALUES ($1, $2, $3)",
+        )
+        .bind(token)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn take_reset_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<(Uuid, DateTime<Utc>)>, RepositoryError> {
+        // Deleting and returning in one statement keeps a token single-use
+        // even when two reset requests race each other
+        let row = sqlx::query_as::<_, (Uuid, DateTime<Utc>)>(
+            "DELETE FROM password_reset_tokens WHERE token = $1 RETURNING user_id, expires_at",
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row)
+    }
+}
+
+/// Simple in-memory user store, used by tests and local experiments
+#[derive(Debug, Default)]
+pub struct UserStore {
+    users: RwLock<HashMap<String, User>>, // email -> user
+    password_reset_tokens: RwLock<HashMap<String, (Uuid, DateTime<Utc>)>>, // token -> (user ID, expiry)
+}
+
+impl UserStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl UserRepository for UserStore {
+    async fn create(&self, user: &User) -> Result<(), RepositoryError> {
+        let mut users = self.users.write().await;
+        if users.contains_key(&user.email) {
+            return Err(RepositoryError::Conflict(
+                "User with this email already exists".to_string(),
+            ));
+        }
+
+        users.insert(user.email.clone(), user.clone());
+        Ok(())
+    }
+
+    async fn find_by_id(&self, user_id: &Uuid) -> Result<Option<User>, RepositoryError> {
+        let users = self.users.read().await;
+        Ok(users.values().find(|user| &user.id == user_id).cloned())
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepositoryError> {
+        let users = self.users.read().await;
+        Ok(users.get(email).cloned())
+    }
+
+    async fn update(&self, user: &User) -> Result<(), RepositoryError> {
+        let mut users = self.users.write().await;
+
+        let old_email = users
+            .iter()
+            .find(|(_, existing)| existing.id == user.id)
+            .map(|(email, _)| email.clone())
+            .ok_or_else(|| RepositoryError::NotFound("User not found".to_string()))?;
+
+        if old_email != user.email && users.contains_key(&user.email) {
+            return Err(RepositoryError::Conflict(
+                "Email already in use".to_string(),
+            ));
+        }
+
+        // Re-key the map in case the email changed
+        users.remove(&old_email);
+        users.insert(user.email.clone(), user.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, user_id: &Uuid) -> Result<bool, RepositoryError> {
+        let mut users = self.users.write().await;
+        let email = users
+            .iter()
+            .find(|(_, user)| &user.id == user_id)
+            .map(|(email, _)| email.clone());
+
+        match email {
+            Some(email) => {
+                users.remove(&email);
+                self.password_reset_tokens
+                    .write()
+                    .await
+                    .retain(|_, (owner, _)| owner != user_id);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<User>, RepositoryError> {
+        let users = self.users.read().await;
+        let mut users: Vec<User> = users.values().cloned().collect();
+        users.sort_by_key(|user| user.created_at);
+        Ok(users)
+    }
+
+    async fn store_reset_token(
+        &self,
+        token: &str,
+        user_id: &Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), RepositoryError> {
+        self.password_reset_tokens
+            .write()
+            .await
+            .insert(token.to_string(), (*user_id, expires_at));
+        Ok(())
+    }
+
+    async fn take_reset_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<(Uuid, DateTime<Utc>)>, RepositoryError> {
+        Ok(self.password_reset_tokens.write().await.remove(token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_user(email: &str) -> User {
+        User::new(email.to_string(), "Test User".to_string(), "SecurePass123!")
+            .expect("Failed to create user")
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_user_round_trip(pool: PgPool) {
+        let repo = PgUserRepository::new(pool);
+        let mut user = test_user("test@example.com");
+
+        repo.create(&user).await.expect("Create should succeed");
+        assert!(matches!(
+            repo.create(&test_user("test@example.com")).await,
+            Err(RepositoryError::Conflict(_))
+        ));
+
+        user.update(Some("renamed@example.com".to_string()), None);
+        user.set_admin(true);
+        repo.update(&user).await.expect("Update should succeed");
+
+        let stored = repo
+            .find_by_email("renamed@example.com")
+            .await
+            .expect("Lookup should succeed")
+            .expect("User should exist");
+        assert_eq!(stored.id, user.id);
+        assert!(stored.is_admin);
+        assert!(
+            stored
+                .verify_password("SecurePass123!")
+                .expect("Failed to verify password")
+        );
+
+        assert!(repo.delete(&user.id).await.expect("Delete should succeed"));
+        assert!(repo.find_by_id(&user.id).await.unwrap().is_none());
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_pg_reset_token_is_single_use(pool: PgPool) {
+        let repo = PgUserRepository::new(pool);
+        let user = test_user("test@example.com");
+        repo.create(&user).await.expect("Create should succeed");
+
+        let expiry = Utc::now() + chrono::Duration::hours(1);
+        repo.store_reset_token("token", &user.id, expiry)
+            .await
+            .expect("Storing token should succeed");
+
+        let (owner, _) = repo
+            .take_reset_token("token")
+            .await
+            .expect("Lookup should succeed")
+            .expect("Token should exist");
+        assert_eq!(owner, user.id);
+        assert!(repo.take_reset_token("token").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_update_rekeys_email() {
+        let store = UserStore::new();
+        let mut user = test_user("old@example.com");
+        store.create(&user).await.expect("Create should succeed");
+
+        user.update(Some("new@example.com".to_string()), None);
+        store.update(&user).await.expect("Update should succeed");
+
+        assert!(
+            store
+                .find_by_email("old@example.com")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            store
+                .find_by_email("new@example.com")
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+}
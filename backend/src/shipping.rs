@@ -0,0 +1,280 @@
+//! Shipping quotes
+//!
+//! Orders are packed into mailers, boxes or tubes by `packing`, so packages
+//! are derived from the print catalog rather than entered by the customer.
+//! Quotes come from every configured carrier, see `carriers`.
+//!
+//! Orders ship once their slowest print size is produced, counted in business
+//! days from the day the order is placed, from the lab `fulfillment` routes
+//! them to.
+
+use chrono::NaiveDate;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+
+use crate::{
+    calendar::BusinessCalendar,
+    carriers::{CarrierError, Carriers, Shipment, ShipmentAddress, ShippingQuote},
+    customs::{self, CommercialInvoice, CustomsItem},
+    models::{fulfillment_location::FulfillmentLocation, print_catalog::PrintSize},
+    money::MoneyError,
+    packing::Package,
+    types::PackageDimensions,
+};
+
+/// Business day an order of these sizes, placed on `ordered`, is handed to
+/// the carrier
+pub fn ship_date<'a>(
+    calendar: &BusinessCalendar,
+    ordered: NaiveDate,
+    sizes: impl IntoIterator<Item = &'a PrintSize>,
+) -> NaiveDate {
+    let production_days = sizes
+        .into_iter()
+        .map(|size| size.production_days.max(0) as u32)
+        .max()
+        .unwrap_or(0);
+    calendar.add_business_days(ordered, production_days)
+}
+
+/// Dimensions and weight of packed packages, as carriers rate them
+pub fn package_dimensions(packages: &[Package]) -> Vec<PackageDimensions> {
+    packages
+        .iter()
+        .map(|package| package.dimensions.clone())
+        .collect()
+}
+
+/// Shipment of packed prints from a lab, to be quoted
+///
+/// Prints crossing a border are declared as `customs_items`, made in the
+/// lab's country.
+pub fn quote_shipment(
+    location: &FulfillmentLocation,
+    ship_to: &ShipmentAddress,
+    packages: &[Package],
+    ship_date: NaiveDate,
+    currency: &str,
+    customs_items: &[CustomsItem],
+) -> Result<Shipment, MoneyError> {
+    let ship_from = location.address();
+    let customs = if customs::crosses_border(&ship_from, ship_to) {
+        Some(CommercialInvoice::for_quote(
+            ship_date,
+            currency,
+            &location.country,
+            customs_items.to_vec(),
+        )?)
+    } else {
+        None
+    };
+    Ok(Shipment {
+        ship_from,
+        ship_to: ship_to.clone(),
+        packages: package_dimensions(packages),
+        ship_date,
+        customs,
+    })
+}
+
+/// Quote every service of every carrier for a shipment, cheapest first
+///
+/// Carriers are asked concurrently. One that fails is logged and left out,
+/// so checkout keeps working while a carrier is down; only when every
+/// carrier fails is the first error returned.
+pub async fn quote_all(
+    carriers: &Carriers,
+    shipment: &Shipment,
+) -> Result<Vec<ShippingQuote>, CarrierError> {
+    let mut requests = JoinSet::new();
+    for carrier in carriers.iter() {
+        let carrier = Arc::clone(carrier);
+        let shipment = shipment.clone();
+        requests.spawn(async move {
+            let quotes = carrier.rate(&shipment).await;
+            (carrier.name().to_string(), quotes)
+        });
+    }
+
+    let mut quotes = Vec::new();
+    let mut first_error = None;
+    let mut any_succeeded = false;
+    while let Some(result) = requests.join_next().await {
+        let (name, result) = result.map_err(|e| CarrierError::Api(e.to_string()))?;
+        match result {
+            Ok(carrier_quotes) => {
+                any_succeeded = true;
+                quotes.extend(carrier_quotes);
+            }
+            Err(err) => {
+                tracing::warn!("Failed to get {} shipping rates: {}", name, err);
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+
+    if let (false, Some(err)) = (any_succeeded, first_error) {
+        return Err(err);
+    }
+    quotes.sort_by_key(|quote| quote.price.amount_minor());
+    Ok(quotes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        carriers::{
+            AddressValidation, Carrier, CarrierService, LabelRequest, ShipmentAddress,
+            ShippingLabel, TrackingInfo,
+        },
+        money::Money,
+        test_support::{self, shipment_address},
+    };
+    use async_trait::async_trait;
+
+    const SERVICE: CarrierService = CarrierService {
+        shipping_option: "Test_Ground",
+        code: "GROUND",
+        description: "Test Ground",
+        transit_days: (2, 4),
+    };
+
+    /// Carrier quoting a fixed price, or failing when it has none; it supports
+    /// nothing else
+    #[derive(Debug)]
+    struct FixedRateCarrier {
+        name: &'static str,
+        price: Option<i64>,
+    }
+
+    #[async_trait]
+    impl Carrier for FixedRateCarrier {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn services(&self) -> &[CarrierService] {
+            std::slice::from_ref(&SERVICE)
+        }
+
+        async fn validate_address(
+            &self,
+            _address: &ShipmentAddress,
+        ) -> Result<AddressValidation, CarrierError> {
+            Err(CarrierError::Unsupported(format!(
+                "{} doesn't validate addresses",
+                self.name
+            )))
+        }
+
+        async fn rate(&self, _shipment: &Shipment) -> Result<Vec<ShippingQuote>, CarrierError> {
+            match self.price {
+                Some(price) => Ok(vec![ShippingQuote::new(
+                    self.name,
+                    &SERVICE,
+                    Money::from_minor(price, "USD"),
+                    None,
+                )]),
+                None => Err(CarrierError::Network("connection refused".to_string())),
+            }
+        }
+
+        async fn create_label(
+            &self,
+            _request: &LabelRequest,
+        ) -> Result<ShippingLabel, CarrierError> {
+            Err(CarrierError::Unsupported(format!(
+                "{} doesn't create labels",
+                self.name
+            )))
+        }
+
+        async fn track(&self, _tracking_number: &str) -> Result<TrackingInfo, CarrierError> {
+            Err(CarrierError::Unsupported(format!(
+                "{} doesn't track shipments",
+                self.name
+            )))
+        }
+
+        async fn void_label(&self, _tracking_number: &str) -> Result<(), CarrierError> {
+            Err(CarrierError::Unsupported(format!(
+                "{} doesn't void labels",
+                self.name
+            )))
+        }
+    }
+
+    fn carriers(prices: &[(&'static str, Option<i64>)]) -> Carriers {
+        Carriers::new(
+            prices
+                .iter()
+                .map(|&(name, price)| {
+                    Arc::new(FixedRateCarrier { name, price }) as Arc<dyn Carrier>
+                })
+                .collect(),
+        )
+    }
+
+    fn shipment() -> Shipment {
+        let address = shipment_address();
+        Shipment {
+            ship_from: address.clone(),
+            ship_to: address,
+            packages: vec![PackageDimensions {
+                length: 9.0,
+                width: 7.0,
+                height: 1.0,
+                weight: 0.5,
+            }],
+            ship_date: NaiveDate::from_ymd_opt(2025, 11, 24).unwrap(),
+            customs: None,
+        }
+    }
+
+    fn print_size(size_id: &str, production_days: i32) -> PrintSize {
+        PrintSize {
+            size_id: size_id.to_string(),
+            description: format!("{} print", size_id),
+            production_days,
+            ..test_support::print_size()
+        }
+    }
+
+    #[test]
+    fn test_ship_date_waits_for_slowest_size() {
+        let calendar = BusinessCalendar::default();
+        let friday = NaiveDate::from_ymd_opt(2025, 11, 21).unwrap();
+        let sizes = [print_size("4x6", 1), print_size("11x14", 2)];
+
+        // Two production days from Friday is Tuesday
+        assert_eq!(
+            ship_date(&calendar, friday, &sizes),
+            NaiveDate::from_ymd_opt(2025, 11, 25).unwrap()
+        );
+        // Ordered on Saturday, production starts Monday
+        let saturday = NaiveDate::from_ymd_opt(2025, 11, 22).unwrap();
+        assert_eq!(
+            ship_date(&calendar, saturday, &sizes[..1]),
+            NaiveDate::from_ymd_opt(2025, 11, 25).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_quote_all_merges_carriers() {
+        let carriers = carriers(&[("ups", Some(1187)), ("usps", None), ("fedex", Some(950))]);
+
+        // The failing carrier is skipped and the rest sorted by price
+        let quotes = quote_all(&carriers, &shipment()).await.unwrap();
+        let names: Vec<&str> = quotes.iter().map(|q| q.carrier.as_str()).collect();
+        assert_eq!(names, ["fedex", "ups"]);
+    }
+
+    #[tokio::test]
+    async fn test_quote_all_fails_when_every_carrier_fails() {
+        let carriers = carriers(&[("ups", None), ("usps", None)]);
+
+        let err = quote_all(&carriers, &shipment()).await.unwrap_err();
+        assert!(matches!(err, CarrierError::Network(_)));
+    }
+}
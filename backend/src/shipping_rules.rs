@@ -0,0 +1,271 @@
+//! Shipping rules
+//!
+//! Carrier quotes are what shipping costs us. Before a quote is offered to a
+//! customer, the active rules in `shipping_rules` are applied to it in
+//! position order. A rule applies when the quote's shipping option, the
+//! destination and the prints' subtotal all match it, and then:
+//!
+//! - `free_shipping` makes the quote free
+//! - `flat_rate` sets its price to the rule's amount
+//! - `adjust` adds a percentage of the price and a fixed amount, either of
+//!   which can be negative; the price never goes below zero
+//! - `hide` drops the quote
+//!
+//! Each rule sees the price the rules before it set, so a free shipping rule
+//! placed after a markup still ships for free.
+
+use crate::{
+    carriers::{ShipmentAddress, ShippingQuote},
+    models::shipping_rule::{ShippingRule, ShippingRuleKind},
+    money::{Money, MoneyError},
+};
+
+/// Apply rules to carrier quotes for prints costing `subtotal`, shipped to
+/// `destination`
+///
+/// `rules` must be in the order they apply. Returns the quotes left,
+/// cheapest first. Fails if a rule's amounts aren't in the quotes' currency.
+pub fn apply_rules(
+    rules: &[ShippingRule],
+    subtotal: &Money,
+    destination: &ShipmentAddress,
+    quotes: Vec<ShippingQuote>,
+) -> Result<Vec<ShippingQuote>, MoneyError> {
+    let mut priced = Vec::with_capacity(quotes.len());
+    'quotes: for mut quote in quotes {
+        for rule in rules {
+            if !matches(rule, &quote, subtotal, destination)? {
+                continue;
+            }
+            quote.price = match rule.kind {
+                ShippingRuleKind::FreeShipping => Money::zero(quote.price.currency()),
+                ShippingRuleKind::FlatRate => match &rule.amount {
+                    Some(amount) => Money::zero(quote.price.currency()).checked_add(amount)?,
+                    None => quote.price,
+                },
+                ShippingRuleKind::Adjust => adjust(rule, &quote.price)?,
+                ShippingRuleKind::Hide => continue 'quotes,
+            };
+        }
+        priced.push(quote);
+    }
+
+    priced.sort_by_key(|quote| quote.price.amount_minor());
+    Ok(priced)
+}
+
+/// Whether a rule applies to a quote
+fn matches(
+    rule: &ShippingRule,
+    quote: &ShippingQuote,
+    subtotal: &Money,
+    destination: &ShipmentAddress,
+) -> Result<bool, MoneyError> {
+    if !rule.active {
+        return Ok(false);
+    }
+    if !rule.shipping_options.is_empty() && !rule.shipping_options.contains(&quote.shipping_option)
+    {
+        return Ok(false);
+    }
+    if !rule.regions.is_empty()
+        && !rule
+            .regions
+            .iter()
+            .any(|region| destination.region_match(region) > 0)
+    {
+        return Ok(false);
+    }
+    match &rule.min_subtotal {
+        Some(min_subtotal) => Ok(!subtotal.checked_sub(min_subtotal)?.is_negative()),
+        None => Ok(true),
+    }
+}
+
+/// Add a rule's percentage and fixed amount to a price, stopping at zero
+fn adjust(rule: &ShippingRule, price: &Money) -> Result<Money, MoneyError> {
+    let mut adjusted = price.clone();
+    if let Some(basis_points) = rule.basis_points {
+        adjusted = adjusted.checked_add(&price.apply_basis_points(basis_points as i64)?)?;
+    }
+    if let Some(amount) = &rule.amount {
+        adjusted = adjusted.checked_add(amount)?;
+    }
+    if adjusted.is_negative() {
+        return Ok(Money::zero(price.currency()));
+    }
+    Ok(adjusted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{carriers::CarrierService, test_support::shipment_address};
+    use chrono::Utc;
+
+    const GROUND: CarrierService = CarrierService {
+        shipping_option: "UPS_Ground",
+        code: "03",
+        description: "UPS Ground",
+        transit_days: (1, 5),
+    };
+    const NEXT_DAY: CarrierService = CarrierService {
+        shipping_option: "UPS_Next_Day_Air",
+        code: "01",
+        description: "UPS Next Day Air",
+        transit_days: (1, 1),
+    };
+
+    fn quotes() -> Vec<ShippingQuote> {
+        vec![
+            ShippingQuote::new("ups", &GROUND, Money::from_minor(1187, "USD"), None),
+            ShippingQuote::new("ups", &NEXT_DAY, Money::from_minor(4250, "USD"), None),
+        ]
+    }
+
+    fn rule(kind: ShippingRuleKind) -> ShippingRule {
+        ShippingRule {
+            rule_id: 1,
+            name: "Test rule".to_string(),
+            position: 0,
+            kind,
+            shipping_options: Vec::new(),
+            regions: Vec::new(),
+            min_subtotal: None,
+            amount: None,
+            basis_points: None,
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn address(state: &str, country: &str) -> ShipmentAddress {
+        ShipmentAddress {
+            state: state.to_string(),
+            country: country.to_string(),
+            ..shipment_address()
+        }
+    }
+
+    fn prices(quotes: &[ShippingQuote]) -> Vec<(&str, i64)> {
+        quotes
+            .iter()
+            .map(|quote| (quote.shipping_option.as_str(), quote.price.amount_minor()))
+            .collect()
+    }
+
+    #[test]
+    fn test_free_shipping_over_threshold() {
+        let rules = [ShippingRule {
+            shipping_options: vec!["UPS_Ground".to_string()],
+            min_subtotal: Some(Money::from_minor(5000, "USD")),
+            ..rule(ShippingRuleKind::FreeShipping)
+        }];
+        let colorado = address("CO", "US");
+
+        let below = Money::from_minor(4999, "USD");
+        let priced = apply_rules(&rules, &below, &colorado, quotes()).unwrap();
+        assert_eq!(
+            prices(&priced),
+            [("UPS_Ground", 1187), ("UPS_Next_Day_Air", 4250)]
+        );
+
+        let at = Money::from_minor(5000, "USD");
+        let priced = apply_rules(&rules, &at, &colorado, quotes()).unwrap();
+        assert_eq!(
+            prices(&priced),
+            [("UPS_Ground", 0), ("UPS_Next_Day_Air", 4250)]
+        );
+    }
+
+    #[test]
+    fn test_region_flat_rate_and_hidden_service() {
+        let rules = [
+            ShippingRule {
+                regions: vec!["US-HI".to_string(), "US-AK".to_string()],
+                amount: Some(Money::from_minor(2500, "USD")),
+                ..rule(ShippingRuleKind::FlatRate)
+            },
+            ShippingRule {
+                shipping_options: vec!["UPS_Next_Day_Air".to_string()],
+                regions: vec!["CA".to_string()],
+                ..rule(ShippingRuleKind::Hide)
+            },
+        ];
+        let subtotal = Money::from_minor(2000, "USD");
+
+        let priced = apply_rules(&rules, &subtotal, &address("HI", "US"), quotes()).unwrap();
+        assert_eq!(
+            prices(&priced),
+            [("UPS_Ground", 2500), ("UPS_Next_Day_Air", 2500)]
+        );
+
+        let priced = apply_rules(&rules, &subtotal, &address("ON", "CA"), quotes()).unwrap();
+        assert_eq!(prices(&priced), [("UPS_Ground", 1187)]);
+
+        let priced = apply_rules(&rules, &subtotal, &address("CO", "US"), quotes()).unwrap();
+        assert_eq!(priced.len(), 2);
+    }
+
+    #[test]
+    fn test_adjustments_apply_in_order() {
+        let markup = ShippingRule {
+            basis_points: Some(1000),
+            amount: Some(Money::from_minor(100, "USD")),
+            ..rule(ShippingRuleKind::Adjust)
+        };
+        let free_ground = ShippingRule {
+            shipping_options: vec!["UPS_Ground".to_string()],
+            ..rule(ShippingRuleKind::FreeShipping)
+        };
+        let discount = ShippingRule {
+            amount: Some(Money::from_minor(-1000, "USD")),
+            ..rule(ShippingRuleKind::Adjust)
+        };
+        let subtotal = Money::from_minor(2000, "USD");
+        let colorado = address("CO", "US");
+
+        // 10% plus $1.00, then free ground
+        let rules = [markup.clone(), free_ground.clone()];
+        let priced = apply_rules(&rules, &subtotal, &colorado, quotes()).unwrap();
+        assert_eq!(
+            prices(&priced),
+            [("UPS_Ground", 0), ("UPS_Next_Day_Air", 4775)]
+        );
+
+        // A fixed amount added after free shipping still applies
+        let rules = [free_ground, markup];
+        let priced = apply_rules(&rules, &subtotal, &colorado, quotes()).unwrap();
+        assert_eq!(
+            prices(&priced),
+            [("UPS_Ground", 100), ("UPS_Next_Day_Air", 4775)]
+        );
+
+        // Discounts stop at zero
+        let rules = [discount.clone(), discount];
+        let priced = apply_rules(&rules, &subtotal, &colorado, quotes()).unwrap();
+        assert_eq!(
+            prices(&priced),
+            [("UPS_Ground", 0), ("UPS_Next_Day_Air", 2250)]
+        );
+    }
+
+    #[test]
+    fn test_inactive_and_mismatched_rules() {
+        let inactive = ShippingRule {
+            active: false,
+            ..rule(ShippingRuleKind::Hide)
+        };
+        let subtotal = Money::from_minor(2000, "USD");
+        let colorado = address("CO", "US");
+        let priced = apply_rules(&[inactive], &subtotal, &colorado, quotes()).unwrap();
+        assert_eq!(priced.len(), 2);
+
+        let euros = ShippingRule {
+            amount: Some(Money::from_minor(500, "EUR")),
+            ..rule(ShippingRuleKind::FlatRate)
+        };
+        assert!(apply_rules(&[euros], &subtotal, &colorado, quotes()).is_err());
+    }
+}
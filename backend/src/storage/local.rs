@@ -0,0 +1,197 @@
+//! Filesystem storage backend for tests and local development
+
+use super::{ObjectStorage, ObjectUpload, StorageError, validate_key};
+use async_trait::async_trait;
+use std::{io::ErrorKind, path::PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// Stores objects as files under a root directory
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        LocalStorage { root: root.into() }
+    }
+
+    fn path_for(&self, key: &str) -> Result<PathBuf, StorageError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl ObjectStorage for LocalStorage {
+    async fn put(&self, key: &str, data: Vec<u8>, _content_type: &str) -> Result<(), StorageError> {
+        let path = self.path_for(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| StorageError::Backend(e.to_string()))?;
+        }
+        tokio::fs::write(&path, data)
+            .await
+            .map_err(|e| StorageError::Backend(e.to_string()))
+    }
+
+    async fn start_upload(
+        &self,
+        key: &str,
+        _content_type: &str,
+    ) -> Result<Box<dyn ObjectUpload>, StorageError> {
+        let path = self.path_for(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| StorageError::Backend(e.to_string()))?;
+        }
+        // Written next to the object, then renamed into place
+        let mut partial = path.clone().into_os_string();
+        partial.push(".part");
+        let partial = PathBuf::from(partial);
+        let file = tokio::fs::File::create(&partial)
+            .await
+            .map_err(|e| StorageError::Backend(e.to_string()))?;
+        Ok(Box::new(LocalUpload {
+            file,
+            partial,
+            path,
+        }))
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        let path = self.path_for(key)?;
+        tokio::fs::read(&path).await.map_err(|e| match e.kind() {
+            ErrorKind::NotFound => StorageError::NotFound(key.to_string()),
+            _ => StorageError::Backend(e.to_string()),
+        })
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let path = self.path_for(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(StorageError::Backend(e.to_string())),
+        }
+    }
+}
+
+/// A file being written under a temporary name
+struct LocalUpload {
+    file: tokio::fs::File,
+    partial: PathBuf,
+    path: PathBuf,
+}
+
+#[async_trait]
+impl ObjectUpload for LocalUpload {
+    async fn write(&mut self, data: &[u8]) -> Result<(), StorageError> {
+        self.file
+            .write_all(data)
+            .await
+            .map_err(|e| StorageError::Backend(e.to_string()))
+    }
+
+    async fn finish(mut self: Box<Self>) -> Result<(), StorageError> {
+        self.file
+            .flush()
+            .await
+            .map_err(|e| StorageError::Backend(e.to_string()))?;
+        tokio::fs::rename(&self.partial, &self.path)
+            .await
+            .map_err(|e| StorageError::Backend(e.to_string()))
+    }
+
+    async fn abort(self: Box<Self>) -> Result<(), StorageError> {
+        drop(self.file);
+        tokio::fs::remove_file(&self.partial)
+            .await
+            .map_err(|e| StorageError::Backend(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_round_trip() {
+        let root = std::env::temp_dir().join(format!("sushi-storage-{}", uuid::Uuid::new_v4()));
+        let storage = LocalStorage::new(&root);
+
+        storage
+            .put(
+                "uploads/u1/img_1/original.jpg",
+                b"jpeg".to_vec(),
+                "image/jpeg",
+            )
+            .await
+            .expect("Put should succeed");
+        let data = storage
+            .get("uploads/u1/img_1/original.jpg")
+            .await
+            .expect("Get should succeed");
+        assert_eq!(data, b"jpeg");
+
+        storage
+            .delete("uploads/u1/img_1/original.jpg")
+            .await
+            .expect("Delete should succeed");
+        assert!(matches!(
+            storage.get("uploads/u1/img_1/original.jpg").await,
+            Err(StorageError::NotFound(_))
+        ));
+        // Deleting twice is fine
+        storage
+            .delete("uploads/u1/img_1/original.jpg")
+            .await
+            .expect("Second delete should succeed");
+
+        assert!(matches!(
+            storage.put("../escape", Vec::new(), "text/plain").await,
+            Err(StorageError::InvalidKey(_))
+        ));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_local_upload() {
+        let root = std::env::temp_dir().join(format!("sushi-storage-{}", uuid::Uuid::new_v4()));
+        let storage = LocalStorage::new(&root);
+
+        let mut upload = storage
+            .start_upload("uploads/u1/img_1/original.jpg", "image/jpeg")
+            .await
+            .expect("Start should succeed");
+        upload.write(b"jp").await.expect("Write should succeed");
+        upload.write(b"eg").await.expect("Write should succeed");
+        // Not stored until it's finished
+        assert!(matches!(
+            storage.get("uploads/u1/img_1/original.jpg").await,
+            Err(StorageError::NotFound(_))
+        ));
+        upload.finish().await.expect("Finish should succeed");
+        assert_eq!(
+            storage.get("uploads/u1/img_1/original.jpg").await.unwrap(),
+            b"jpeg"
+        );
+
+        let mut upload = storage
+            .start_upload("uploads/u1/img_2/original.jpg", "image/jpeg")
+            .await
+            .expect("Start should succeed");
+        upload.write(b"jpeg").await.expect("Write should succeed");
+        upload.abort().await.expect("Abort should succeed");
+        assert!(matches!(
+            storage.get("uploads/u1/img_2/original.jpg").await,
+            Err(StorageError::NotFound(_))
+        ));
+        assert!(!root.join("uploads/u1/img_2/original.jpg.part").exists());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
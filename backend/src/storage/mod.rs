@@ -0,0 +1,145 @@
+//! Object storage for uploaded files
+//!
+//! Files are addressed by slash-separated keys such as
+//! `uploads/<user_id>/<image_id>/original.jpg`. Production uses an
+//! S3-compatible bucket (AWS S3 or MinIO); tests and local development can
+//! use a directory on disk instead.
+
+pub mod local;
+pub mod s3;
+
+use async_trait::async_trait;
+use std::{env, fmt, sync::Arc};
+
+/// Errors returned by storage backends
+#[derive(Debug)]
+pub enum StorageError {
+    /// No object is stored under the key
+    NotFound(String),
+    /// The key is empty or tries to escape the storage root
+    InvalidKey(String),
+    /// Backend failure (I/O, network, permissions)
+    Backend(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::NotFound(key) => write!(f, "Object not found: {}", key),
+            StorageError::InvalidKey(key) => write!(f, "Invalid object key: {}", key),
+            StorageError::Backend(msg) => write!(f, "Storage error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+/// A place to put uploaded files
+#[async_trait]
+pub trait ObjectStorage: Send + Sync + fmt::Debug {
+    /// Store an object, replacing any existing object under the key
+    async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> Result<(), StorageError>;
+
+    /// Start storing an object that is written piece by piece, for files too
+    /// large to hold in memory
+    async fn start_upload(
+        &self,
+        key: &str,
+        content_type: &str,
+    ) -> Result<Box<dyn ObjectUpload>, StorageError>;
+
+    /// Read an object back
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+
+    /// Delete an object; deleting a missing object is not an error
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+/// An object being written piece by piece
+///
+/// Nothing is stored under the key until `finish` succeeds. An upload that
+/// isn't finished must be aborted, or the pieces written so far are left
+/// behind.
+#[async_trait]
+pub trait ObjectUpload: Send {
+    /// Append the next piece of the object
+    async fn write(&mut self, data: &[u8]) -> Result<(), StorageError>;
+
+    /// Store everything written under the key
+    async fn finish(self: Box<Self>) -> Result<(), StorageError>;
+
+    /// Discard everything written
+    async fn abort(self: Box<Self>) -> Result<(), StorageError>;
+}
+
+/// Storage backend selected by `STORAGE_BACKEND`
+#[derive(Debug, Clone)]
+pub enum StorageConfig {
+    /// `STORAGE_BACKEND=s3` (default)
+    S3 {
+        bucket: String,
+        /// Custom endpoint for MinIO and other S3-compatible services
+        endpoint: Option<String>,
+        region: Option<String>,
+    },
+    /// `STORAGE_BACKEND=local`
+    Local { root: String },
+}
+
+impl StorageConfig {
+    /// Load storage configuration from environment variables
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("STORAGE_BACKEND").as_deref() {
+            Ok("local") => Ok(StorageConfig::Local {
+                root: env::var("LOCAL_STORAGE_DIR").unwrap_or_else(|_| "./uploads".to_string()),
+            }),
+            Ok("s3") | Err(_) => Ok(StorageConfig::S3 {
+                bucket: env::var("S3_BUCKET").map_err(|_| "S3_BUCKET not set")?,
+                endpoint: env::var("S3_ENDPOINT").ok(),
+                region: env::var("S3_REGION").ok(),
+            }),
+            Ok(other) => Err(format!("Unknown STORAGE_BACKEND: {}", other)),
+        }
+    }
+
+    /// Build the configured storage backend
+    pub async fn build(self) -> Arc<dyn ObjectStorage> {
+        match self {
+            StorageConfig::S3 {
+                bucket,
+                endpoint,
+                region,
+            } => Arc::new(s3::S3Storage::from_env(bucket, endpoint, region).await),
+            StorageConfig::Local { root } => Arc::new(local::LocalStorage::new(root)),
+        }
+    }
+}
+
+/// Reject empty keys and keys that could escape the storage root
+pub(crate) fn validate_key(key: &str) -> Result<(), StorageError> {
+    let valid = !key.is_empty()
+        && !key.starts_with('/')
+        && key
+            .split('/')
+            .all(|part| !part.is_empty() && part != "." && part != ".." && !part.contains('\\'));
+    if valid {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidKey(key.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_key() {
+        assert!(validate_key("uploads/user/img_1/original.jpg").is_ok());
+        assert!(validate_key("").is_err());
+        assert!(validate_key("/etc/passwd").is_err());
+        assert!(validate_key("uploads/../../etc/passwd").is_err());
+        assert!(validate_key("uploads//original.jpg").is_err());
+        assert!(validate_key("uploads\\..\\x").is_err());
+    }
+}
@@ -0,0 +1,391 @@
+//! S3-compatible storage backend (AWS S3, MinIO)
+
+use super::{ObjectStorage, ObjectUpload, StorageError, validate_key};
+use async_trait::async_trait;
+use aws_sdk_s3::{
+    Client,
+    config::Region,
+    primitives::ByteStream,
+    types::{CompletedMultipartUpload, CompletedPart},
+};
+
+/// Size of each part of a multipart upload; S3 rejects smaller parts except
+/// the last
+const PART_BYTES: usize = 5 * 1024 * 1024;
+
+/// Stores objects in an S3 bucket
+#[derive(Debug, Clone)]
+pub struct S3Storage {
+    client: Client,
+    bucket: String,
+}
+
+impl S3Storage {
+    pub fn new(client: Client, bucket: String) -> Self {
+        S3Storage { client, bucket }
+    }
+
+    /// Build a client from the standard AWS environment (credentials, region)
+    ///
+    /// A custom `endpoint` switches to path-style addressing, which MinIO
+    /// and most other S3-compatible services expect.
+    pub async fn from_env(
+        bucket: String,
+        endpoint: Option<String>,
+        region: Option<String>,
+    ) -> Self {
+        let mut loader = aws_config::from_env();
+        if let Some(region) = region {
+            loader = loader.region(Region::new(region));
+        }
+        let shared_config = loader.load().await;
+
+        let mut builder = aws_sdk_s3::config::Builder::from(&shared_config);
+        if let Some(endpoint) = endpoint {
+            builder = builder.endpoint_url(endpoint).force_path_style(true);
+        }
+
+        S3Storage::new(Client::from_conf(builder.build()), bucket)
+    }
+}
+
+#[async_trait]
+impl ObjectStorage for S3Storage {
+    async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> Result<(), StorageError> {
+        validate_key(key)?;
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(|e| StorageError::Backend(format!("{:?}", e)))?;
+        Ok(())
+    }
+
+    async fn start_upload(
+        &self,
+        key: &str,
+        content_type: &str,
+    ) -> Result<Box<dyn ObjectUpload>, StorageError> {
+        validate_key(key)?;
+        Ok(Box::new(S3Upload {
+            storage: self.clone(),
+            key: key.to_string(),
+            content_type: content_type.to_string(),
+            buffer: Vec::new(),
+            upload_id: None,
+            parts: Vec::new(),
+        }))
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        validate_key(key)?;
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.as_service_error().is_some_and(|e| e.is_no_such_key()) {
+                    StorageError::NotFound(key.to_string())
+                } else {
+                    StorageError::Backend(format!("{:?}", e))
+                }
+            })?;
+
+        let data = output
+            .body
+            .collect()
+            .await
+            .map_err(|e| StorageError::Backend(e.to_string()))?;
+        Ok(data.into_bytes().to_vec())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        validate_key(key)?;
+        // S3 deletes are idempotent, so a missing key succeeds
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| StorageError::Backend(format!("{:?}", e)))?;
+        Ok(())
+    }
+}
+
+/// An object sent to S3 one part at a time
+///
+/// At most one part is held in memory. Objects smaller than a part are sent
+/// with a single `PutObject` when they're finished instead.
+struct S3Upload {
+    storage: S3Storage,
+    key: String,
+    content_type: String,
+    /// Written but not yet sent
+    buffer: Vec<u8>,
+    /// Set once the first part is sent
+    upload_id: Option<String>,
+    parts: Vec<CompletedPart>,
+}
+
+impl S3Upload {
+    /// Send the buffered bytes as the next part
+    async fn send_part(&mut self) -> Result<(), StorageError> {
+        let client = &self.storage.client;
+        let upload_id = match &self.upload_id {
+            Some(upload_id) => upload_id.clone(),
+            None => {
+                let output = client
+                    .create_multipart_upload()
+                    .bucket(&self.storage.bucket)
+                    .key(&self.key)
+                    .content_type(&self.content_type)
+                    .send()
+                    .await
+                    .map_err(|e| StorageError::Backend(format!("{:?}", e)))?;
+                let upload_id = output.upload_id.ok_or_else(|| {
+                    StorageError::Backend("S3 returned no multipart upload ID".to_string())
+                })?;
+                self.upload_id = Some(upload_id.clone());
+                upload_id
+            }
+        };
+
+        let part_number = self.parts.len() as i32 + 1;
+        let output = client
+            .upload_part()
+            .bucket(&self.storage.bucket)
+            .key(&self.key)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(std::mem::take(&mut self.buffer)))
+            .send()
+            .await
+            .map_err(|e| StorageError::Backend(format!("{:?}", e)))?;
+        self.parts.push(
+            CompletedPart::builder()
+                .part_number(part_number)
+                .set_e_tag(output.e_tag)
+                .build(),
+        );
+        Ok(())
+    }
+
+    /// Send the rest and put the parts together
+    async fn complete(&mut self) -> Result<(), StorageError> {
+        if !self.buffer.is_empty() {
+            self.send_part().await?;
+        }
+        self.storage
+            .client
+            .complete_multipart_upload()
+            .bucket(&self.storage.bucket)
+            .key(&self.key)
+            .set_upload_id(self.upload_id.clone())
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(std::mem::take(&mut self.parts)))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| StorageError::Backend(format!("{:?}", e)))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ObjectUpload for S3Upload {
+    async fn write(&mut self, data: &[u8]) -> Result<(), StorageError> {
+        self.buffer.extend_from_slice(data);
+        while self.buffer.len() >= PART_BYTES {
+            let rest = self.buffer.split_off(PART_BYTES);
+            self.send_part().await?;
+            self.buffer = rest;
+        }
+        Ok(())
+    }
+
+    async fn finish(mut self: Box<Self>) -> Result<(), StorageError> {
+        if self.upload_id.is_none() {
+            let data = std::mem::take(&mut self.buffer);
+            return self.storage.put(&self.key, data, &self.content_type).await;
+        }
+        match self.complete().await {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                if let Err(abort_err) = self.abort().await {
+                    tracing::warn!("Failed to abort upload: {}", abort_err);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    async fn abort(self: Box<Self>) -> Result<(), StorageError> {
+        let Some(upload_id) = self.upload_id else {
+            return Ok(());
+        };
+        self.storage
+            .client
+            .abort_multipart_upload()
+            .bucket(&self.storage.bucket)
+            .key(&self.key)
+            .upload_id(upload_id)
+            .send()
+            .await
+            .map_err(|e| StorageError::Backend(format!("{:?}", e)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::spawn_stub_server;
+    use aws_sdk_s3::config::{
+        BehaviorVersion, Credentials, RequestChecksumCalculation, ResponseChecksumValidation,
+    };
+    use axum::{
+        Router,
+        body::Bytes,
+        extract::{DefaultBodyLimit, Query, State},
+        http::{Method, StatusCode, header},
+        response::{IntoResponse, Response},
+        routing::any,
+    };
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    /// Stand-in for an S3 bucket, recording the requests it gets
+    #[derive(Debug, Default)]
+    struct StubS3 {
+        requests: Mutex<Vec<String>>,
+    }
+
+    async fn handle(
+        State(stub): State<Arc<StubS3>>,
+        method: Method,
+        Query(query): Query<HashMap<String, String>>,
+        body: Bytes,
+    ) -> Response {
+        let (request, response) = match (method, query.get("partNumber")) {
+            (Method::POST, _) if query.contains_key("uploads") => (
+                "create".to_string(),
+                "<InitiateMultipartUploadResult><Bucket>prints</Bucket><Key>k</Key>\
+                 <UploadId>upload-1</UploadId></InitiateMultipartUploadResult>"
+                    .into_response(),
+            ),
+            (Method::PUT, Some(part)) => (
+                format!("part {} {}", part, body.len()),
+                ([(header::ETAG, format!("\"etag-{}\"", part))], "").into_response(),
+            ),
+            (Method::PUT, None) => (format!("put {}", body.len()), "".into_response()),
+            (Method::POST, _) => (
+                format!(
+                    "complete {}",
+                    String::from_utf8_lossy(&body).matches("<Part>").count()
+                ),
+                "<CompleteMultipartUploadResult><ETag>\"etag\"</ETag>\
+                 </CompleteMultipartUploadResult>"
+                    .into_response(),
+            ),
+            (Method::DELETE, _) => ("abort".to_string(), StatusCode::NO_CONTENT.into_response()),
+            (method, _) => (method.to_string(), StatusCode::BAD_REQUEST.into_response()),
+        };
+        stub.requests.lock().unwrap().push(request);
+        response
+    }
+
+    async fn storage() -> (S3Storage, Arc<StubS3>) {
+        let stub = Arc::new(StubS3::default());
+        let base_url = spawn_stub_server(
+            Router::new()
+                .route("/prints/{*key}", any(handle))
+                .layer(DefaultBodyLimit::disable())
+                .with_state(stub.clone()),
+        )
+        .await;
+        let config = aws_sdk_s3::config::Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("key", "secret", None, None, "test"))
+            .endpoint_url(base_url)
+            .force_path_style(true)
+            .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+            .response_checksum_validation(ResponseChecksumValidation::WhenRequired)
+            .build();
+        (
+            S3Storage::new(Client::from_conf(config), "prints".to_string()),
+            stub,
+        )
+    }
+
+    fn requests(stub: &StubS3) -> Vec<String> {
+        std::mem::take(&mut *stub.requests.lock().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_small_upload_is_a_single_put() {
+        let (storage, stub) = storage().await;
+        let mut upload = storage
+            .start_upload("uploads/u1/img_1/original.jpg", "image/jpeg")
+            .await
+            .unwrap();
+        upload.write(b"jpeg").await.unwrap();
+        upload.write(b"data").await.unwrap();
+        upload.finish().await.expect("Finish should succeed");
+        assert_eq!(requests(&stub), ["put 8"]);
+    }
+
+    #[tokio::test]
+    async fn test_large_upload_is_sent_in_parts() {
+        let (storage, stub) = storage().await;
+        let chunk = vec![0u8; 1024 * 1024];
+
+        let mut upload = storage
+            .start_upload("uploads/u1/img_1/original.jpg", "image/jpeg")
+            .await
+            .unwrap();
+        for _ in 0..6 {
+            upload.write(&chunk).await.unwrap();
+        }
+        upload.finish().await.expect("Finish should succeed");
+        assert_eq!(
+            requests(&stub),
+            [
+                "create".to_string(),
+                format!("part 1 {}", PART_BYTES),
+                format!("part 2 {}", 6 * chunk.len() - PART_BYTES),
+                "complete 2".to_string(),
+            ]
+        );
+
+        // Abandoned uploads are aborted
+        let mut upload = storage
+            .start_upload("uploads/u1/img_2/original.jpg", "image/jpeg")
+            .await
+            .unwrap();
+        for _ in 0..6 {
+            upload.write(&chunk).await.unwrap();
+        }
+        upload.abort().await.expect("Abort should succeed");
+        assert_eq!(
+            requests(&stub),
+            [
+                "create".to_string(),
+                format!("part 1 {}", PART_BYTES),
+                "abort".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! Helpers shared by unit tests
+
+use crate::{
+    AppState,
+    calendar::BusinessCalendar,
+    carriers::{Carriers, ShipmentAddress},
+    config::PrintQualityConfig,
+    fulfillment::RoutingConfig,
+    models::{
+        image::StoredImage,
+        order_status::OrderStatus,
+        print_catalog::PrintSize,
+        print_order::{FulfillmentMethod, PrintOrder, PrintOrderItem},
+    },
+    money::Money,
+    packing::PackagingConfig,
+    payments::PaymentProviders,
+    repositories::{
+        catalog::PgCatalogRepository, idempotency::PgIdempotencyRepository,
+        images::PgImageRepository, locations::PgLocationRepository, orders::PgOrderRepository,
+        payments::PgPaymentRepository, shipping_labels::PgShippingLabelRepository,
+        shipping_rules::PgShippingRuleRepository, users::PgUserRepository,
+    },
+    storage::local::LocalStorage,
+};
+use axum::Router;
+use chrono::Utc;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+/// Serve `router` on a random local port, returning its base URL
+///
+/// Used to stand in for third-party HTTP APIs.
+pub(crate) async fn spawn_stub_server(router: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind stub server");
+    let addr = listener.local_addr().expect("Stub server has no address");
+    tokio::spawn(async move {
+        axum::serve(listener, router)
+            .await
+            .expect("Stub server failed");
+    });
+    format!("http://{}", addr)
+}
+
+/// A US address to ship to; tests override the fields they care about
+pub(crate) fn shipment_address() -> ShipmentAddress {
+    ShipmentAddress {
+        name: "Jane Doe".to_string(),
+        company: None,
+        phone: None,
+        line1: "123 Main Street".to_string(),
+        line2: None,
+        city: "Austin".to_string(),
+        state: "TX".to_string(),
+        postal_code: "78701".to_string(),
+        country: "US".to_string(),
+    }
+}
+
+/// An active 4x6 catalog size; tests override the fields they care about
+pub(crate) fn print_size() -> PrintSize {
+    PrintSize {
+        size_id: "4x6".to_string(),
+        description: "4x6 print".to_string(),
+        price: Money::from_minor(100, "USD"),
+        width_in: 4.0,
+        height_in: 6.0,
+        weight_lbs: 0.02,
+        production_days: 1,
+        hs_code: "491191".to_string(),
+        active: true,
+        sort_order: 0,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+/// App state backed by `pool`, with no carriers or payment providers; tests
+/// override the fields they care about
+pub(crate) fn app_state(pool: PgPool) -> AppState {
+    AppState {
+        carriers: Carriers::new(Vec::new()),
+        locations: Arc::new(PgLocationRepository::new(pool.clone())),
+        routing: RoutingConfig::default(),
+        shipping_rules: Arc::new(PgShippingRuleRepository::new(pool.clone())),
+        packaging: PackagingConfig::default(),
+        calendar: BusinessCalendar::default(),
+        users: Arc::new(PgUserRepository::new(pool.clone())),
+        orders: Arc::new(PgOrderRepository::new(pool.clone())),
+        catalog: Arc::new(PgCatalogRepository::new(pool.clone())),
+        images: Arc::new(PgImageRepository::new(pool.clone())),
+        storage: Arc::new(LocalStorage::new(std::env::temp_dir())),
+        print_quality: PrintQualityConfig::default(),
+        payment_providers: PaymentProviders::default(),
+        payments: Arc::new(PgPaymentRepository::new(pool.clone())),
+        shipping_labels: Arc::new(PgShippingLabelRepository::new(pool.clone())),
+        idempotency: Arc::new(PgIdempotencyRepository::new(pool.clone())),
+        db_pool: pool,
+    }
+}
+
+/// A $10.17 order of two 4x6 prints of `img_001`; tests override the fields
+/// they care about
+pub(crate) fn print_order(order_id: &str, status: OrderStatus) -> PrintOrder {
+    let now = Utc::now();
+    PrintOrder {
+        order_id: order_id.to_string(),
+        user_id: None,
+        status,
+        customer_name: "Jane Doe".to_string(),
+        customer_email: "jane.doe@example.com".to_string(),
+        customer_phone: "+1-555-234-5678".to_string(),
+        shipping_line1: "123 Main Street".to_string(),
+        shipping_line2: None,
+        shipping_city: "Denver".to_string(),
+        shipping_state: "CO".to_string(),
+        shipping_postal_code: "80202".to_string(),
+        shipping_country: "US".to_string(),
+        special_instructions: None,
+        shipping_option: "UPS_Ground".to_string(),
+        fulfillment_method: FulfillmentMethod::Ship,
+        fulfillment_location_id: None,
+        tracking_number: None,
+        pickup_code: None,
+        payment_method: "paypal".to_string(),
+        payment_reference: None,
+        items_subtotal: Money::from_minor(300, "USD"),
+        shipping: Money::from_minor(650, "USD"),
+        tax: Money::from_minor(67, "USD"),
+        grand_total: Money::from_minor(1017, "USD"),
+        amount_refunded: Money::zero("USD"),
+        currency: "USD".to_string(),
+        estimated_delivery_min: now.date_naive(),
+        estimated_delivery_max: now.date_naive(),
+        created_at: now,
+        updated_at: now,
+        items: vec![PrintOrderItem {
+            item_id: 0,
+            order_id: order_id.to_string(),
+            size: "4x6".to_string(),
+            finish: "glossy".to_string(),
+            quantity: 2,
+            image_ids: vec!["img_001".to_string()],
+            unit_price: Money::from_minor(150, "USD"),
+            line_total: Money::from_minor(300, "USD"),
+            hs_code: "49119100".to_string(),
+        }],
+    }
+}
+
+/// A staged 4032x3024 JPEG upload; tests override the fields they care about
+pub(crate) fn stored_image(image_id: &str) -> StoredImage {
+    StoredImage {
+        image_id: image_id.to_string(),
+        user_id: None,
+        order_id: None,
+        storage_key: format!("uploads/test/{}/original.jpg", image_id),
+        original_filename: Some("beach.jpg".to_string()),
+        content_type: "image/jpeg".to_string(),
+        size_bytes: 1024,
+        width_px: Some(4032),
+        height_px: Some(3024),
+        created_at: Utc::now(),
+    }
+}
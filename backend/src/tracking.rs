@@ -0,0 +1,313 @@
+//! Background tracking of shipped orders
+//!
+//! Shipped orders are tracked with their carrier on an interval, and moved
+//! to `delivered` once the carrier confirms delivery.
+
+use std::{env, time::Duration};
+
+use crate::{
+    AppState,
+    carriers::{CarrierError, TrackingStatus},
+    models::order_status::OrderStatus,
+    repositories::RepositoryError,
+};
+
+/// How often shipped orders are tracked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackingConfig {
+    /// `None` when polling is disabled
+    pub poll_interval: Option<Duration>,
+}
+
+impl Default for TrackingConfig {
+    fn default() -> Self {
+        TrackingConfig {
+            poll_interval: Some(Duration::from_secs(3600)),
+        }
+    }
+}
+
+impl TrackingConfig {
+    /// Create a TrackingConfig from environment variables
+    ///
+    /// # Environment Variables
+    ///
+    /// - `TRACKING_POLL_INTERVAL_SECS`: Seconds between tracking runs, `0` to
+    ///   disable (optional, defaults to 3600)
+    pub fn from_env() -> Result<Self, String> {
+        let poll_interval = match env::var("TRACKING_POLL_INTERVAL_SECS") {
+            Ok(value) => {
+                let secs = value
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid TRACKING_POLL_INTERVAL_SECS: {}", value))?;
+                (secs > 0).then(|| Duration::from_secs(secs))
+            }
+            Err(_) => TrackingConfig::default().poll_interval,
+        };
+
+        Ok(TrackingConfig { poll_interval })
+    }
+}
+
+/// Track every shipped order and mark the delivered ones, returning how many
+/// were marked
+///
+/// Failures tracking a single order are logged and the order is retried on
+/// the next run.
+pub async fn poll_deliveries(state: &AppState) -> Result<usize, RepositoryError> {
+    let labels = state
+        .shipping_labels
+        .list_active_for_order_status(OrderStatus::Shipped)
+        .await?;
+
+    let mut delivered = 0;
+    for label in labels {
+        let Some(carrier) = state.carriers.get(&label.carrier) else {
+            tracing::warn!(
+                "Can't track order {}: {} is not configured",
+                label.order_id,
+                label.carrier
+            );
+            continue;
+        };
+
+        let tracking = match carrier.track(&label.tracking_number).await {
+            Ok(tracking) => tracking,
+            // Not scanned by the carrier yet
+            Err(CarrierError::NotFound(_)) => continue,
+            Err(err) => {
+                tracing::warn!("Failed to track order {}: {}", label.order_id, err);
+                continue;
+            }
+        };
+        if tracking.status != TrackingStatus::Delivered {
+            continue;
+        }
+
+        let note = format!(
+            "Delivered according to {} tracking {}",
+            label.carrier, label.tracking_number
+        );
+        match state
+            .orders
+            .update_status(
+                &label.order_id,
+                OrderStatus::Shipped,
+                OrderStatus::Delivered,
+                None,
+                Some(note),
+            )
+            .await
+        {
+            Ok(()) => {
+                tracing::info!("Order {} was delivered", label.order_id);
+                delivered += 1;
+            }
+            // Moved on since the labels were listed, e.g. refunded
+            Err(RepositoryError::Conflict(_)) => {}
+            Err(err) => {
+                tracing::error!("Failed to mark order {} delivered: {}", label.order_id, err);
+            }
+        }
+    }
+
+    Ok(delivered)
+}
+
+/// Run `poll_deliveries` in the background every `interval`, logging any failure
+pub fn spawn_delivery_poller(state: AppState, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match poll_deliveries(&state).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("Marked {} orders delivered", count),
+                Err(err) => tracing::warn!("Failed to poll deliveries: {}", err),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        carriers::{
+            AddressValidation, Carrier, CarrierService, Carriers, LabelFormat, LabelRequest,
+            Shipment, ShipmentAddress, ShippingLabel, ShippingQuote, TrackingInfo,
+        },
+        repositories::{
+            orders::{OrderRepository, PgOrderRepository},
+            shipping_labels::{
+                NewShippingLabel, PgShippingLabelRepository, ShippingLabelRepository,
+            },
+        },
+        test_support,
+    };
+    use async_trait::async_trait;
+    use sqlx::postgres::PgPool;
+    use std::{collections::HashMap, sync::Arc};
+
+    /// Carrier reporting a fixed status per tracking number; numbers it
+    /// doesn't know haven't been scanned yet
+    #[derive(Debug)]
+    struct StubCarrier {
+        statuses: HashMap<&'static str, TrackingStatus>,
+        /// Tracking number and order refunded while the order is tracked
+        refunds: Option<(&'static str, &'static str, PgOrderRepository)>,
+    }
+
+    #[async_trait]
+    impl Carrier for StubCarrier {
+        fn name(&self) -> &str {
+            "ups"
+        }
+
+        fn services(&self) -> &[CarrierService] {
+            &[]
+        }
+
+        async fn validate_address(
+            &self,
+            _address: &ShipmentAddress,
+        ) -> Result<AddressValidation, CarrierError> {
+            Err(CarrierError::Unsupported(
+                "ups doesn't validate addresses".to_string(),
+            ))
+        }
+
+        async fn rate(&self, _shipment: &Shipment) -> Result<Vec<ShippingQuote>, CarrierError> {
+            Err(CarrierError::Unsupported("ups doesn't quote".to_string()))
+        }
+
+        async fn create_label(
+            &self,
+            _request: &LabelRequest,
+        ) -> Result<ShippingLabel, CarrierError> {
+            Err(CarrierError::Unsupported(
+                "ups doesn't create labels".to_string(),
+            ))
+        }
+
+        async fn track(&self, tracking_number: &str) -> Result<TrackingInfo, CarrierError> {
+            if let Some((refunded, order_id, orders)) = &self.refunds
+                && *refunded == tracking_number
+            {
+                orders
+                    .update_status(
+                        order_id,
+                        OrderStatus::Shipped,
+                        OrderStatus::Refunded,
+                        None,
+                        None,
+                    )
+                    .await
+                    .expect("Refund should succeed");
+            }
+            let status = self
+                .statuses
+                .get(tracking_number)
+                .copied()
+                .ok_or_else(|| CarrierError::NotFound(tracking_number.to_string()))?;
+            Ok(TrackingInfo {
+                tracking_number: tracking_number.to_string(),
+                status,
+                estimated_delivery: None,
+                events: Vec::new(),
+            })
+        }
+
+        async fn void_label(&self, _tracking_number: &str) -> Result<(), CarrierError> {
+            Err(CarrierError::Unsupported(
+                "ups doesn't void labels".to_string(),
+            ))
+        }
+    }
+
+    fn app_state(pool: PgPool, carrier: StubCarrier) -> AppState {
+        AppState {
+            carriers: Carriers::new(vec![Arc::new(carrier)]),
+            ..test_support::app_state(pool)
+        }
+    }
+
+    /// Add a shipped order with an active label
+    async fn ship_order(pool: &PgPool, order_id: &str, carrier: &str, tracking_number: &str) {
+        sqlx::query(
+            "INSERT INTO orders (id, status, customer_name, customer_email, customer_phone,
+                shipping_line1, shipping_city, shipping_state, shipping_postal_code,
+                shipping_country, shipping_option, payment_method, items_subtotal, shipping,
+                tax, grand_total, currency, estimated_delivery_min, estimated_delivery_max)
+             VALUES ($1, 'shipped', 'Jane Doe', 'jane@example.com', '555-0100',
+                '123 Main St', 'Denver', 'CO', '80202', 'US', 'UPS_Ground', 'paypal',
+                10.00, 0, 0, 10.00, 'USD', CURRENT_DATE, CURRENT_DATE)",
+        )
+        .bind(order_id)
+        .execute(pool)
+        .await
+        .expect("Order insert should succeed");
+        PgShippingLabelRepository::new(pool.clone())
+            .create(&NewShippingLabel {
+                order_id: order_id.to_string(),
+                carrier: carrier.to_string(),
+                service_code: "03".to_string(),
+                tracking_number: tracking_number.to_string(),
+                format: LabelFormat::Gif,
+                storage_key: format!("labels/{}/{}.gif", order_id, tracking_number),
+                price: None,
+                created_by: None,
+            })
+            .await
+            .expect("Label insert should succeed");
+    }
+
+    async fn status(state: &AppState, order_id: &str) -> OrderStatus {
+        state
+            .orders
+            .find_by_id(order_id)
+            .await
+            .unwrap()
+            .expect("Order should exist")
+            .status
+    }
+
+    #[sqlx::test]
+    #[ignore = "requires DATABASE_URL pointing at a PostgreSQL server"]
+    async fn test_poll_deliveries(pool: PgPool) {
+        ship_order(&pool, "ord_1", "ups", "1Z001").await;
+        ship_order(&pool, "ord_2", "ups", "1Z002").await;
+        ship_order(&pool, "ord_3", "ups", "1Z003").await;
+        ship_order(&pool, "ord_4", "ups", "1Z004").await;
+        ship_order(&pool, "ord_5", "fedex", "7946").await;
+
+        let carrier = StubCarrier {
+            statuses: HashMap::from([
+                ("1Z001", TrackingStatus::Delivered),
+                ("1Z003", TrackingStatus::InTransit),
+                ("1Z004", TrackingStatus::Delivered),
+            ]),
+            refunds: Some(("1Z004", "ord_4", PgOrderRepository::new(pool.clone()))),
+        };
+        let state = app_state(pool, carrier);
+
+        assert_eq!(poll_deliveries(&state).await.unwrap(), 1);
+        assert_eq!(status(&state, "ord_1").await, OrderStatus::Delivered);
+        let history = state.orders.status_history("ord_1").await.unwrap();
+        assert_eq!(
+            history.last().unwrap().note.as_deref(),
+            Some("Delivered according to ups tracking 1Z001")
+        );
+        // Not scanned yet
+        assert_eq!(status(&state, "ord_2").await, OrderStatus::Shipped);
+        assert_eq!(status(&state, "ord_3").await, OrderStatus::Shipped);
+        // Refunded while it was tracked, which wins
+        assert_eq!(status(&state, "ord_4").await, OrderStatus::Refunded);
+        // FedEx isn't configured
+        assert_eq!(status(&state, "ord_5").await, OrderStatus::Shipped);
+
+        // Delivered orders aren't tracked again
+        assert_eq!(poll_deliveries(&state).await.unwrap(), 0);
+    }
+}
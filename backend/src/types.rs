@@ -1,7 +1,10 @@
 //! Type definitions and enums
 
+use serde::Serialize;
+
 /// Result of address validation
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum AddressValidationResult {
     /// Address is valid and can be used for shipping
     Valid,
@@ -53,9 +56,30 @@ pub enum UpsServiceCode {
     NextDayAir,
     /// UPS Express
     Express,
+    /// UPS Next Day Air Early
+    NextDayAirEarly,
+    /// UPS 2nd Day Air A.M.
+    SecondDayAirAm,
 }
 
 impl UpsServiceCode {
+    /// Every service, for looking up the codes UPS returns
+    pub const ALL: [UpsServiceCode; 8] = [
+        UpsServiceCode::Ground,
+        UpsServiceCode::ThreeDaySelect,
+        UpsServiceCode::SecondDayAir,
+        UpsServiceCode::NextDayAirSaver,
+        UpsServiceCode::NextDayAir,
+        UpsServiceCode::Express,
+        UpsServiceCode::NextDayAirEarly,
+        UpsServiceCode::SecondDayAirAm,
+    ];
+
+    /// Find the service for a UPS service code
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|service| service.code() == code)
+    }
+
     /// Get the UPS service code as a string
     pub fn code(&self) -> &'static str {
         match self {
@@ -65,6 +89,8 @@ impl UpsServiceCode {
             UpsServiceCode::NextDayAirSaver => "13",
             UpsServiceCode::NextDayAir => "01",
             UpsServiceCode::Express => "07",
+            UpsServiceCode::NextDayAirEarly => "14",
+            UpsServiceCode::SecondDayAirAm => "59",
         }
     }
 
@@ -77,6 +103,8 @@ impl UpsServiceCode {
             UpsServiceCode::NextDayAirSaver => "UPS Next Day Air Saver",
             UpsServiceCode::NextDayAir => "UPS Next Day Air",
             UpsServiceCode::Express => "UPS Express",
+            UpsServiceCode::NextDayAirEarly => "UPS Next Day Air Early",
+            UpsServiceCode::SecondDayAirAm => "UPS 2nd Day Air A.M.",
         }
     }
 }
@@ -96,7 +124,7 @@ impl UpsServiceCode {
 /// - Billing weight: max(2.0, 3.45, 4.0) = **4.0 lbs** (due to minimum)
 ///
 /// This is why you might see a billing weight of 4.0 lbs even for lighter packages.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PackageDimensions {
     /// Length in inches
     pub length: f32,
@@ -139,6 +167,10 @@ pub struct ShippingRateRequest<'a> {
     pub request_option: RateRequestOptions,
     /// UPS service code
     pub service_code: UpsServiceCode,
-    /// Package dimensions and weight
-    pub dimensions: PackageDimensions,
+    /// Dimensions and weight of each package in the shipment
+    pub packages: Vec<PackageDimensions>,
+    /// Date the shipment is handed to UPS, required for time in transit
+    pub pickup_date: Option<chrono::NaiveDate>,
+    /// Customs paperwork, for shipments that cross a border
+    pub customs: Option<&'a crate::customs::CommercialInvoice>,
 }
@@ -77,8 +77,11 @@ pub fn display_order_items(items: &[crate::models::order_item::OrderItem]) {
         tracing::info!("  Product ID: {}", item.product_id);
         tracing::info!("  Name: {}", item.name);
         tracing::info!("  Quantity: {}", item.quantity);
-        tracing::info!("  Unit Price: ${:.2}", item.unit_price);
-        tracing::info!("  Total: ${:.2}", item.unit_price * item.quantity as f64);
+        tracing::info!("  Unit Price: {}", item.unit_price);
+        match item.unit_price.checked_mul(item.quantity as i64) {
+            Ok(total) => tracing::info!("  Total: {}", total),
+            Err(e) => tracing::warn!("  Total: {}", e),
+        }
         tracing::info!("---");
     }
 }
@@ -158,10 +161,9 @@ pub async fn get_and_display_rates(
     client: &UpsClient,
     ship_from: &AddressKeyFormat,
     order: &Order,
-    access_token: &str,
 ) -> UpsResult<()> {
     let customer_name = format!("{} {}", order.customer.first_name, order.customer.last_name);
-    let dimensions = PackageDimensions::default(); // Using default package dimensions
+    let packages = vec![PackageDimensions::default()]; // Using default package dimensions
 
     let shipping_request = ShippingRateRequest {
         ship_from,
@@ -169,12 +171,12 @@ pub async fn get_and_display_rates(
         customer_name: &customer_name,
         request_option: RateRequestOptions::Rate,
         service_code: UpsServiceCode::Ground,
-        dimensions,
+        packages,
+        pickup_date: None,
+        customs: None,
     };
 
-    let rate_response = client
-        .get_shipping_rates(&shipping_request, access_token)
-        .await?;
+    let rate_response = client.get_shipping_rates(&shipping_request).await?;
 
     display_rate_response(&rate_response);
     Ok(())